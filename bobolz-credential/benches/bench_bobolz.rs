@@ -15,11 +15,11 @@ fn bobolz_benchmark(c: &mut Criterion) {
 
     c.bench_function("Setup", |b| {
         b.iter(|| {
-            let pp = ih::par_gen();
+            let pp = ih::par_gen(*message_len.iter().max().unwrap());
             black_box(pp);
         });
     });
-    let pp = ih::par_gen();
+    let pp = ih::par_gen(*message_len.iter().max().unwrap());
 
     c.bench_function("Issuer_Key_Gen", |b| {
         b.iter(|| {
@@ -55,16 +55,16 @@ fn bobolz_benchmark(c: &mut Criterion) {
             group.bench_with_input(BenchmarkId::new("Sign", len), &len, |b, &_| {
                 b.iter(|| {
                     let signature = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
-                    black_box(signature);
+                    let _ = black_box(signature);
                 });
             });
 
             // Verify Credential (署名生成済みデータが必要)
-            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
             group.bench_with_input(BenchmarkId::new("Verify", len), &len, |b, &_| {
                 b.iter(|| {
                     let result = ih::verify(&pp, &cred_temp, &message_fr_temp, &issuer_key_pair.public_key);
-                    black_box(result);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -103,7 +103,7 @@ fn bobolz_benchmark(c: &mut Criterion) {
             group.bench_with_input(BenchmarkId::new("Verify_List", num), &num, |b, &_| {
                 b.iter(|| {
                     let result = ih::verify_list(&pp, &trusted_list);
-                    black_box(result);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -126,7 +126,7 @@ fn bobolz_benchmark(c: &mut Criterion) {
             for _ in 0..mlen {
                 message_fr_temp.push(Fr::rand(&mut rng));
             }
-            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
 
             // 公開する属性の数を決定
             let open_message_6 = mlen * 3 / 5;
@@ -168,19 +168,27 @@ fn bobolz_benchmark(c: &mut Criterion) {
                     // Present Benchmark
                     group.bench_with_input(BenchmarkId::new("Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &trusted_list, &open_temp);
-                            black_box(pt);
+                            let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &trusted_list, &open_temp, b"");
+                            let _ = black_box(pt);
                         });
                     });
 
                     // Verify Present Benchmark
                     // ベンチマーク内で毎回生成すると遅いので、計測外で一度生成
-                    let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &trusted_list, &open_temp);
+                    let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &trusted_list, &open_temp, b"").unwrap();
                     
                     group.bench_with_input(BenchmarkId::new("Verify_Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let result = ih::verify_present(&pp, &trusted_list, &pt);
-                            black_box(result);
+                            let result = ih::verify_present(&pp, &trusted_list, &pt, b"");
+                            let _ = black_box(result);
+                        });
+                    });
+
+                    let context = ih::verifier_context(&pp, &trusted_list);
+                    group.bench_with_input(BenchmarkId::new("Verify_Present_Context", &param_str), &param_str, |b, _| {
+                        b.iter(|| {
+                            let result = ih::verify_present_with_context(&context, &pt, b"");
+                            let _ = black_box(result);
                         });
                     });
                 }
@@ -194,7 +202,7 @@ fn bobolz_mobile_benchmark(c: &mut Criterion) {
     let message_len = [5, 10, 15, 20, 25, 30, 35, 40, 45, 50];
     let issuer_num = [5, 10, 50, 100, 500, 1000];
     let mut rng = thread_rng();
-    let pp = ih::par_gen();
+    let pp = ih::par_gen(*message_len.iter().max().unwrap());
     let issuer_key_pair = ih::issuer_key_gen(&pp);
     let verifier_key_pair = ih::verifier_key_gen(&pp);
 
@@ -212,11 +220,11 @@ fn bobolz_mobile_benchmark(c: &mut Criterion) {
             }
 
             // Verify Credential (署名生成済みデータが必要)
-            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
             group.bench_with_input(BenchmarkId::new("Verify", len), &len, |b, &_| {
                 b.iter(|| {
                     let result = ih::verify(&pp, &cred_temp, &message_fr_temp, &issuer_key_pair.public_key);
-                    black_box(result);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -247,7 +255,7 @@ fn bobolz_mobile_benchmark(c: &mut Criterion) {
             group.bench_with_input(BenchmarkId::new("Verify_List", num), &num, |b, &_| {
                 b.iter(|| {
                     let result = ih::verify_list(&pp, &trusted_list);
-                    black_box(result);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -270,7 +278,7 @@ fn bobolz_mobile_benchmark(c: &mut Criterion) {
             for _ in 0..mlen {
                 message_fr_temp.push(Fr::rand(&mut rng));
             }
-            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
 
             // 公開する属性の数を決定
             let open_message_6 = mlen * 3 / 5;
@@ -312,8 +320,8 @@ fn bobolz_mobile_benchmark(c: &mut Criterion) {
                     // Present Benchmark
                     group.bench_with_input(BenchmarkId::new("Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &trusted_list, &open_temp);
-                            black_box(pt);
+                            let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &trusted_list, &open_temp, b"");
+                            let _ = black_box(pt);
                         });
                     });
                 }
@@ -327,7 +335,7 @@ fn bobolz_pc_benchmark(c: &mut Criterion) {
     let message_len = [5, 10, 15, 20, 25, 30, 35, 40, 45, 50];
     let issuer_num = [5, 10, 50, 100, 500, 1000];
     let mut rng = thread_rng();
-    let pp = ih::par_gen();
+    let pp = ih::par_gen(*message_len.iter().max().unwrap());
 
     c.bench_function("Issuer_Key_Gen", |b| {
         b.iter(|| {
@@ -363,7 +371,7 @@ fn bobolz_pc_benchmark(c: &mut Criterion) {
             group.bench_with_input(BenchmarkId::new("Sign", len), &len, |b, &_| {
                 b.iter(|| {
                     let signature = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
-                    black_box(signature);
+                    let _ = black_box(signature);
                 });
             });
         }
@@ -416,7 +424,7 @@ fn bobolz_pc_benchmark(c: &mut Criterion) {
             for _ in 0..mlen {
                 message_fr_temp.push(Fr::rand(&mut rng));
             }
-            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
 
             // 公開する属性の数を決定
             let open_message_6 = mlen * 3 / 5;
@@ -457,12 +465,20 @@ fn bobolz_pc_benchmark(c: &mut Criterion) {
 
                     // Verify Present Benchmark
                     // ベンチマーク内で毎回生成すると遅いので、計測外で一度生成
-                    let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &trusted_list, &open_temp);
+                    let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &trusted_list, &open_temp, b"").unwrap();
                     
                     group.bench_with_input(BenchmarkId::new("Verify_Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let result = ih::verify_present(&pp, &trusted_list, &pt);
-                            black_box(result);
+                            let result = ih::verify_present(&pp, &trusted_list, &pt, b"");
+                            let _ = black_box(result);
+                        });
+                    });
+
+                    let context = ih::verifier_context(&pp, &trusted_list);
+                    group.bench_with_input(BenchmarkId::new("Verify_Present_Context", &param_str), &param_str, |b, _| {
+                        b.iter(|| {
+                            let result = ih::verify_present_with_context(&context, &pt, b"");
+                            let _ = black_box(result);
                         });
                     });
                 }
@@ -1,12 +1,19 @@
 use ark_bls12_381::{Bls12_381, G1Affine, G2Affine, G1Projective};
 use ark_ff::Field;
-use ark_ec::pairing::Pairing;
+use ark_ec::pairing::{Pairing, PairingOutput};
 use ark_std::{fmt::Debug, UniformRand, vec::Vec};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use zeroize::Zeroizing;
+
+use mybbs::bbs;
+use mybbs::holder::{self, HolderSecretKey, KeyBinding};
+use mybbs::proof;
 
 use crate::groth;
 use crate::groth1;
 use crate::groth2;
+use crate::error::{ProveError, VerifyError};
 
 pub type Fr = <Bls12_381 as Pairing>::ScalarField;
 
@@ -27,6 +34,7 @@ pub struct PiKP{
     pub open: Vec<usize>,
     pub len: usize,
     pub message_list: Vec<Fr>,
+    pub presentation_header: Vec<u8>,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
@@ -45,16 +53,23 @@ pub struct TrustedIssuerCredential{
     pub cred: groth2::Signature
 }
 
-pub fn par_gen() -> PublicParameters{
-    let pp_groth = groth::par_gen();
-    
+// h_i depends only on i, so generators can be added later with extend_generators.
+pub fn h_generator(i: usize) -> G1Affine{
     let h_seed = "MESSAGE_GENERATOR_SEED_";
     let h_dst = b"BLS12381G1_XMD:SHA-256_SSWU_RO_";
-    let h_vec:Vec<G1Affine> = (0..50).map(|i| {
-            let seed = format!("{}{}", h_seed, i);
-            groth::hash_to_g1(seed.as_bytes(), h_dst)
-        })
-        .collect();
+    let seed = format!("{}{}", h_seed, i);
+    return groth::hash_to_g1(seed.as_bytes(), h_dst)
+}
+
+pub fn extend_generators(pp: &mut PublicParameters, message_len: usize){
+    for i in pp.h.len()..message_len{
+        pp.h.push(h_generator(i));
+    }
+}
+
+pub fn par_gen(message_len: usize) -> PublicParameters{
+    let pp_groth = groth::par_gen();
+    let h_vec:Vec<G1Affine> = (0..message_len).map(h_generator).collect();
 
     let pp_bobolz = PublicParameters{
         g1 : pp_groth.g1,
@@ -66,17 +81,27 @@ pub fn par_gen() -> PublicParameters{
     return pp_bobolz
 }
 
-pub fn issuer_key_gen(pp: &PublicParameters) -> groth1::KeyPair{
+pub fn issuer_key_gen_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, rng: &mut R) -> groth1::KeyPair{
     let pp_groth1 = groth1::PublicParameters {
         g1: pp.g1, 
         g2: pp.g2, 
         y1: pp.y1, 
     };
-    let keypair = groth1::key_gen(&pp_groth1);
+    let keypair = groth1::key_gen_with_rng(&pp_groth1, rng);
     return keypair
 }
 
-pub fn issue(pp: &PublicParameters, isk: &groth::SecretKey, message: &Vec<Fr>) -> groth1::Signature{
+pub fn issuer_key_gen(pp: &PublicParameters) -> groth1::KeyPair{
+    return issuer_key_gen_with_rng(pp, &mut OsRng)
+}
+
+pub fn issue_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, isk: &groth::SecretKey, message: &Vec<Fr>, rng: &mut R) -> Result<groth1::Signature, ProveError>{
+    if message.is_empty() {
+        return Err(ProveError::MalformedInput("no messages to sign"));
+    }
+    if message.len() > pp.h.len() {
+        return Err(ProveError::TooManyMessages { messages: message.len(), generators: pp.h.len() });
+    }
     let pp_groth1 = groth1::PublicParameters {
         g1: pp.g1, 
         g2: pp.g2, 
@@ -88,12 +113,64 @@ pub fn issue(pp: &PublicParameters, isk: &groth::SecretKey, message: &Vec<Fr>) -
         message_pro += pp.h[i] * message[i];
     }
     let message_affine = G1Affine::from(message_pro);
-    let signature = groth1::sign(&pp_groth1, isk, &message_affine);
-    return signature
+    let signature = groth1::sign_with_rng(&pp_groth1, isk, &message_affine, rng);
+    return Ok(signature)
+}
 
+pub fn issue(pp: &PublicParameters, isk: &groth::SecretKey, message: &Vec<Fr>) -> Result<groth1::Signature, ProveError>{
+    return issue_with_rng(pp, isk, message, &mut OsRng)
 }
 
-pub fn verify(pp: &PublicParameters, cred: &groth1::Signature, message: &Vec<Fr>, ipk: &groth1::PublicKey) -> bool{
+// issue for a holder that sent binding for its secret key, key = h_{holder_index}^sk, which is
+// signed as the message at holder_index without the issuer learning it. message are the other
+// messages, in order.
+#[allow(clippy::too_many_arguments)]
+pub fn issue_bound_with_rng<R: RngCore + CryptoRng>(
+    pp: &PublicParameters,
+    isk: &groth::SecretKey,
+    binding: &KeyBinding,
+    holder_index: usize,
+    message: &Vec<Fr>,
+    nonce: &[u8],
+    rng: &mut R,
+) -> Result<groth1::Signature, ProveError>{
+    let message_len = message.len() + 1;
+    if message_len > pp.h.len() {
+        return Err(ProveError::TooManyMessages { messages: message_len, generators: pp.h.len() });
+    }
+    if holder_index >= message_len {
+        return Err(ProveError::BadRevealIndex(holder_index));
+    }
+    holder::verify_key_binding(&pp.h[holder_index], binding, nonce).map_err(ProveError::HolderKeyRejected)?;
+    let pp_groth1 = groth1::PublicParameters {
+        g1: pp.g1,
+        g2: pp.g2,
+        y1: pp.y1,
+    };
+    let mut message_pro = G1Projective::from(binding.key);
+    for (k, i) in (0..message_len).filter(|&i| i != holder_index).enumerate(){
+        message_pro += pp.h[i] * message[k];
+    }
+    let message_affine = G1Affine::from(message_pro);
+    let signature = groth1::sign_with_rng(&pp_groth1, isk, &message_affine, rng);
+    return Ok(signature)
+}
+
+pub fn issue_bound(
+    pp: &PublicParameters,
+    isk: &groth::SecretKey,
+    binding: &KeyBinding,
+    holder_index: usize,
+    message: &Vec<Fr>,
+    nonce: &[u8],
+) -> Result<groth1::Signature, ProveError>{
+    return issue_bound_with_rng(pp, isk, binding, holder_index, message, nonce, &mut OsRng)
+}
+
+pub fn verify(pp: &PublicParameters, cred: &groth1::Signature, message: &Vec<Fr>, ipk: &groth1::PublicKey) -> Result<(), VerifyError>{
+    if message.is_empty() || message.len() > pp.h.len() {
+        return Err(VerifyError::MalformedInput("message length out of range"));
+    }
     let mut message_pro = pp.h[0] * message[0];
     for i in 1..message.len(){
         message_pro += pp.h[i] * message[i];
@@ -108,17 +185,21 @@ pub fn verify(pp: &PublicParameters, cred: &groth1::Signature, message: &Vec<Fr>
     return result
 }
 
-pub fn verifier_key_gen(pp: &PublicParameters) -> groth2::KeyPair{
+pub fn verifier_key_gen_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, rng: &mut R) -> groth2::KeyPair{
     let pp_groth2 = groth2::PublicParameters {
         g1: pp.g1, 
         g2: pp.g2, 
         y2: pp.y2,
     };
-    let keypair = groth2::key_gen(&pp_groth2);
+    let keypair = groth2::key_gen_with_rng(&pp_groth2, rng);
     return keypair
 }
 
-pub fn issue_list(pp: &PublicParameters, message: &Vec<groth1::PublicKey>, keypair: &groth2::KeyPair) -> (groth2::PublicKey, Vec<TrustedIssuerCredential>){
+pub fn verifier_key_gen(pp: &PublicParameters) -> groth2::KeyPair{
+    return verifier_key_gen_with_rng(pp, &mut OsRng)
+}
+
+pub fn issue_list_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, message: &Vec<groth1::PublicKey>, keypair: &groth2::KeyPair, rng: &mut R) -> (groth2::PublicKey, Vec<TrustedIssuerCredential>){
     let pp_groth2 = groth2::PublicParameters {
         g1: pp.g1, 
         g2: pp.g2, 
@@ -127,7 +208,7 @@ pub fn issue_list(pp: &PublicParameters, message: &Vec<groth1::PublicKey>, keypa
     let mut result: Vec<TrustedIssuerCredential> = Vec::new();
     for i in 0..message.len(){
         let ipk = &message[i];
-        let signature = groth2::sign(&pp_groth2, &keypair.secret_key, &ipk.0);
+        let signature = groth2::sign_with_rng(&pp_groth2, &keypair.secret_key, &ipk.0, rng);
         let cred = TrustedIssuerCredential{
             ipk: ipk.clone(),
             cred: signature
@@ -138,63 +219,68 @@ pub fn issue_list(pp: &PublicParameters, message: &Vec<groth1::PublicKey>, keypa
     return (pk, result);
 }
 
-pub fn verify_list(pp: &PublicParameters,(vpk, list): &(groth2::PublicKey, Vec<TrustedIssuerCredential>)) -> bool{
+pub fn issue_list(pp: &PublicParameters, message: &Vec<groth1::PublicKey>, keypair: &groth2::KeyPair) -> (groth2::PublicKey, Vec<TrustedIssuerCredential>){
+    return issue_list_with_rng(pp, message, keypair, &mut OsRng)
+}
+
+pub fn verify_list(pp: &PublicParameters,(vpk, list): &(groth2::PublicKey, Vec<TrustedIssuerCredential>)) -> Result<(), VerifyError>{
     let pp_groth2 = groth2::PublicParameters {
         g1: pp.g1, 
         g2: pp.g2, 
         y2: pp.y2,
     };
-    for trusted_cred in list{
+    for (i, trusted_cred) in list.iter().enumerate(){
         let ipk_i = &trusted_cred.ipk;
         let sig_i = &trusted_cred.cred;
-        let is_valid = groth2::verify(&pp_groth2, vpk, &sig_i, &ipk_i.0);
-        if is_valid == false{
-            println!("Groth2 list verification failed");
-            return false
+        if !bbs::valid_points(&[ipk_i.0]) || groth2::verify(&pp_groth2, vpk, sig_i, &ipk_i.0).is_err(){
+            return Err(VerifyError::InvalidListEntry(i))
         }
     }
-    return true
+    return Ok(())
 }
 
-pub fn present(pp: &PublicParameters, cred: &groth1::Signature, ipk: &groth1::PublicKey, message: &Vec<Fr>, (_, list): &(groth2::PublicKey, Vec<TrustedIssuerCredential>),open: &Vec<usize>) -> (PiKP, PiZKP){
-    //make random holder signature
-    let new_cred = groth1::rand_sign(cred);
-    let mut issuer_list = list[0].clone();
-    for i in 0..list.len(){
-        if list[i].ipk == *ipk{
-            issuer_list = list[i].clone();
-        }
+#[allow(clippy::too_many_arguments)]
+pub fn present_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, cred: &groth1::Signature, ipk: &groth1::PublicKey, message: &Vec<Fr>, (_, list): &(groth2::PublicKey, Vec<TrustedIssuerCredential>),open: &Vec<usize>, presentation_header: &[u8], rng: &mut R) -> Result<(PiKP, PiZKP), ProveError>{
+    if message.len() > pp.h.len() {
+        return Err(ProveError::TooManyMessages { messages: message.len(), generators: pp.h.len() });
     }
+    proof::check_reveal_index(message.len(), open).map_err(ProveError::BadRevealIndex)?;
+    let issuer_list = match list.iter().find(|entry| entry.ipk == *ipk) {
+        Some(entry) => entry.clone(),
+        None => return Err(ProveError::IssuerNotInList),
+    };
+
+    //make random holder signature
+    let new_cred = groth1::rand_sign_with_rng(cred, rng);
     let h_generators : Vec<G1Affine> = pp.h[0..message.len()].to_vec();
 
     //make random issuer public key signature
-    let new_issuer_sig = groth2::rand_sign( &issuer_list.cred);
-
-    //make random blind values
-    let mut rng = rand::thread_rng();
-    let alpha = Fr::rand(&mut rng);
-    let alpha_inverse = alpha.inverse().unwrap();
-    let beta = Fr::rand(&mut rng);
-    let beta_inverse = beta.inverse().unwrap();
-    let gamma = Fr::rand(&mut rng);
-    let gamma_inverse = gamma.inverse().unwrap();
-    let delta = Fr::rand(&mut rng);
-    let delta_inverse = delta.inverse().unwrap();
+    let new_issuer_sig = groth2::rand_sign_with_rng(&issuer_list.cred, rng);
+
+    //make random blind values, wiped once the proof is built
+    let alpha = Zeroizing::new(Fr::rand(rng));
+    let alpha_inverse = Zeroizing::new(alpha.inverse().unwrap());
+    let beta = Zeroizing::new(Fr::rand(rng));
+    let beta_inverse = Zeroizing::new(beta.inverse().unwrap());
+    let gamma = Zeroizing::new(Fr::rand(rng));
+    let gamma_inverse = Zeroizing::new(gamma.inverse().unwrap());
+    let delta = Zeroizing::new(Fr::rand(rng));
+    let delta_inverse = Zeroizing::new(delta.inverse().unwrap());
 
     //make blind holder signature
     let blind_cred = groth1::Signature{
         r2: new_cred.r2,
-        s1: G1Affine::from(new_cred.s1 * (alpha_inverse)),
-        t1: G1Affine::from(new_cred.t1 * (beta_inverse))
+        s1: G1Affine::from(new_cred.s1 * (*alpha_inverse)),
+        t1: G1Affine::from(new_cred.t1 * (*beta_inverse))
     };
 
     //make blind issuer public key
-    let blind_ipk = groth1::PublicKey(G2Affine::from(ipk.0 * (gamma_inverse)));
+    let blind_ipk = groth1::PublicKey(G2Affine::from(ipk.0 * (*gamma_inverse)));
     //make blind issuer public key signature
     let blind_issuer_sig = groth2::Signature{
         r1: new_issuer_sig.r1,
         s2: new_issuer_sig.s2,
-        t2: G2Affine::from(new_issuer_sig.t2 * (delta_inverse))
+        t2: G2Affine::from(new_issuer_sig.t2 * (*delta_inverse))
     };
 
     //make close message number list
@@ -210,19 +296,19 @@ pub fn present(pp: &PublicParameters, cred: &groth1::Signature, ipk: &groth1::Pu
         message_open_list.push(message[*i]);
     }
     //make close message list
-    let mut message_close_list: Vec<Fr> = Vec::new();
+    let mut message_close_list: Zeroizing<Vec<Fr>> = Zeroizing::new(Vec::new());
     for i in &close{
         message_close_list.push(message[*i]);
     }
     
     //make proof of knowledge
-    let r1 = Fr::rand(&mut rng);
-    let r2 = Fr::rand(&mut rng);
-    let r3 = Fr::rand(&mut rng);
-    let r4 = Fr::rand(&mut rng);
-    let mut r5 = Vec::new();
+    let r1 = Zeroizing::new(Fr::rand(rng));
+    let r2 = Zeroizing::new(Fr::rand(rng));
+    let r3 = Zeroizing::new(Fr::rand(rng));
+    let r4 = Zeroizing::new(Fr::rand(rng));
+    let mut r5 = Zeroizing::new(Vec::with_capacity(close.len()));
     for _ in 0..close.len(){
-        r5.push(Fr::rand(&mut rng));
+        r5.push(Fr::rand(rng));
     }
 
     let pi_kp = PiKP{
@@ -232,17 +318,29 @@ pub fn present(pp: &PublicParameters, cred: &groth1::Signature, ipk: &groth1::Pu
         open: open.clone(),
         len: message.len(),
         message_list: message_open_list.clone(),
+        presentation_header: presentation_header.to_vec(),
     };
     
-    let k_ipk = G2Affine::from(blind_ipk.0 * (-r3));
-    let mut message_close_proj_rand = pp.h[close[0]] * -r5[0];
-    for i in 1..close.len(){
+    let k_ipk = G2Affine::from(blind_ipk.0 * (-*r3));
+    let mut message_close_proj_rand = G1Projective::from(G1Affine::identity());
+    for i in 0..close.len(){
         message_close_proj_rand += pp.h[close[i]] * -r5[i];
     }
     let message_close_affine_rand = G1Affine::from(message_close_proj_rand);
-    let u1 = Bls12_381::pairing(G1Affine::from(blind_cred.s1 * r1), blind_cred.r2) + Bls12_381::pairing(pp.g1, k_ipk);
-    let u2 = Bls12_381::pairing(G1Affine::from(blind_cred.t1 * r2), blind_cred.r2) + Bls12_381::pairing(pp.y1, k_ipk) + Bls12_381::pairing(message_close_affine_rand, pp.g2);
-    let u3 = Bls12_381::pairing(G1Affine::from(blind_issuer_sig.r1 * r4), blind_issuer_sig.t2) + Bls12_381::pairing(pp.g1, k_ipk);
+    let r2_prepared = groth::G2Prepared::from(blind_cred.r2);
+    let k_ipk_prepared = groth::G2Prepared::from(k_ipk);
+    let u1 = Bls12_381::multi_pairing(
+        [G1Affine::from(blind_cred.s1 * *r1), pp.g1],
+        [r2_prepared.clone(), k_ipk_prepared.clone()],
+    );
+    let u2 = Bls12_381::multi_pairing(
+        [G1Affine::from(blind_cred.t1 * *r2), pp.y1, message_close_affine_rand],
+        [r2_prepared, k_ipk_prepared.clone(), groth::G2Prepared::from(pp.g2)],
+    );
+    let u3 = Bls12_381::multi_pairing(
+        [G1Affine::from(blind_issuer_sig.r1 * *r4), pp.g1],
+        [groth::G2Prepared::from(blind_issuer_sig.t2), k_ipk_prepared],
+    );
 
     let dst = b"CHALLENGE_GENERATOR_DST_Bobolz_Issuer_Hiding_V1";
     let mut c_inputs_buffer = Vec::new();
@@ -256,13 +354,14 @@ pub fn present(pp: &PublicParameters, cred: &groth1::Signature, ipk: &groth1::Pu
     u2.serialize_compressed(&mut c_inputs_buffer).unwrap();
     u3.serialize_compressed(&mut c_inputs_buffer).unwrap();
     list.serialize_compressed(&mut c_inputs_buffer).unwrap();
+    pi_kp.presentation_header.serialize_compressed(&mut c_inputs_buffer).unwrap();
 
     let c = groth::hash_to_fr(&c_inputs_buffer[..], dst);
 
-    let z1 = r1 + c * alpha;
-    let z2 = r2 + c * beta;
-    let z3 = r3 + c * gamma;
-    let z4 = r4 + c * delta;
+    let z1 = *r1 + c * *alpha;
+    let z2 = *r2 + c * *beta;
+    let z3 = *r3 + c * *gamma;
+    let z4 = *r4 + c * *delta;
     let mut z5 = Vec::new();
     for i in 0..r5.len(){
         z5.push(r5[i] + c * message_close_list[i]);
@@ -275,13 +374,120 @@ pub fn present(pp: &PublicParameters, cred: &groth1::Signature, ipk: &groth1::Pu
         z4,
         z5,
     };
-    return (pi_kp, pi_zkp)
+    return Ok((pi_kp, pi_zkp))
+}
+
+pub fn present(pp: &PublicParameters, cred: &groth1::Signature, ipk: &groth1::PublicKey, message: &Vec<Fr>, list: &(groth2::PublicKey, Vec<TrustedIssuerCredential>),open: &Vec<usize>, presentation_header: &[u8]) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_with_rng(pp, cred, ipk, message, list, open, presentation_header, &mut OsRng)
 }
 
-pub fn verify_present(pp: &PublicParameters, (vpk, list): &(groth2::PublicKey, Vec<TrustedIssuerCredential>), (pi_kp, pi_zkp): &(PiKP, PiZKP)) -> bool{
+// present a credential bound to a holder key. message are the messages without the holder
+// secret, which goes in at holder_index; open counts it.
+#[allow(clippy::too_many_arguments)]
+pub fn present_bound_with_rng<R: RngCore + CryptoRng>(
+    pp: &PublicParameters,
+    cred: &groth1::Signature,
+    ipk: &groth1::PublicKey,
+    holder_secret: &HolderSecretKey,
+    holder_index: usize,
+    message: &Vec<Fr>,
+    list: &(groth2::PublicKey, Vec<TrustedIssuerCredential>),
+    open: &Vec<usize>,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    if open.contains(&holder_index) {
+        return Err(ProveError::BadRevealIndex(holder_index));
+    }
+    let message = holder::with_holder_secret(message, holder_index, holder_secret).map_err(|_| ProveError::BadRevealIndex(holder_index))?;
+    return present_with_rng(pp, cred, ipk, &message, list, open, presentation_header, rng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn present_bound(
+    pp: &PublicParameters,
+    cred: &groth1::Signature,
+    ipk: &groth1::PublicKey,
+    holder_secret: &HolderSecretKey,
+    holder_index: usize,
+    message: &Vec<Fr>,
+    list: &(groth2::PublicKey, Vec<TrustedIssuerCredential>),
+    open: &Vec<usize>,
+    presentation_header: &[u8],
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_bound_with_rng(pp, cred, ipk, holder_secret, holder_index, message, list, open, presentation_header, &mut OsRng)
+}
+
+// g2 and y2 prepared once, and e(g1, y2) * e(vpk, g2) from the issuer-signature check, for a
+// verifier that checks many presentations against the same list. Build it with verifier_context.
+#[derive(Clone, Debug)]
+pub struct VerifierContext{
+    pub pp: PublicParameters,
+    pub list: (groth2::PublicKey, Vec<TrustedIssuerCredential>),
+    pub g2_prepared: groth::G2Prepared,
+    pub y2_prepared: groth::G2Prepared,
+    pub list_target: PairingOutput<Bls12_381>,
+}
+
+fn list_target(pp: &PublicParameters, vpk: &groth2::PublicKey) -> PairingOutput<Bls12_381>{
+    return Bls12_381::multi_pairing([pp.g1, vpk.0], [pp.y2, pp.g2])
+}
+
+pub fn verifier_context(pp: &PublicParameters, list: &(groth2::PublicKey, Vec<TrustedIssuerCredential>)) -> VerifierContext{
+    let context = VerifierContext{
+        pp: pp.clone(),
+        list: list.clone(),
+        g2_prepared: groth::G2Prepared::from(pp.g2),
+        y2_prepared: groth::G2Prepared::from(pp.y2),
+        list_target: list_target(pp, &list.0),
+    };
+    return context
+}
+
+pub fn verify_present(pp: &PublicParameters, list: &(groth2::PublicKey, Vec<TrustedIssuerCredential>), pt: &(PiKP, PiZKP), presentation_header: &[u8]) -> Result<(), VerifyError>{
+    let g2_prepared = groth::G2Prepared::from(pp.g2);
+    let y2_prepared = groth::G2Prepared::from(pp.y2);
+    return present_check(pp, list, pt, presentation_header, &g2_prepared, &y2_prepared, &list_target(pp, &list.0))
+}
+
+// verify_present for a presentation of a credential bound to a holder key at holder_index.
+pub fn verify_present_bound(pp: &PublicParameters, list: &(groth2::PublicKey, Vec<TrustedIssuerCredential>), pt: &(PiKP, PiZKP), holder_index: usize, presentation_header: &[u8]) -> Result<(), VerifyError>{
+    holder::check_bound(pt.0.len, &pt.0.open, holder_index).map_err(|_| VerifyError::HolderSecretNotHidden(holder_index))?;
+    return verify_present(pp, list, pt, presentation_header)
+}
+
+pub fn verify_present_with_context(context: &VerifierContext, pt: &(PiKP, PiZKP), presentation_header: &[u8]) -> Result<(), VerifyError>{
+    return present_check(&context.pp, &context.list, pt, presentation_header, &context.g2_prepared, &context.y2_prepared, &context.list_target)
+}
+
+fn present_check(
+    pp: &PublicParameters,
+    (vpk, list): &(groth2::PublicKey, Vec<TrustedIssuerCredential>),
+    (pi_kp, pi_zkp): &(PiKP, PiZKP),
+    presentation_header: &[u8],
+    g2_prepared: &groth::G2Prepared,
+    y2_prepared: &groth::G2Prepared,
+    list_target: &PairingOutput<Bls12_381>,
+) -> Result<(), VerifyError>{
+    if pi_kp.presentation_header != presentation_header {
+        return Err(VerifyError::PresentationHeaderMismatch);
+    }
+    if pi_kp.len > pp.h.len() {
+        return Err(VerifyError::MalformedInput("message length exceeds generators"));
+    }
+    proof::check_reveal_index(pi_kp.len, &pi_kp.open).map_err(VerifyError::BadRevealIndex)?;
+    if pi_kp.message_list.len() != pi_kp.open.len() {
+        return Err(VerifyError::MalformedInput("revealed messages do not match reveal indices"));
+    }
     let blind_cred = &pi_kp.blind_cred;
     let blind_ipk = &pi_kp.blind_ipk;
     let blind_issuer_sig = &pi_kp.blind_issuer_sig;
+    if !bbs::valid_points(&[blind_cred.s1, blind_cred.t1, blind_issuer_sig.r1]) {
+        return Err(VerifyError::MalformedInput("s1, t1 or r1 is the identity or not in G1"));
+    }
+    if !bbs::valid_points(&[blind_cred.r2, blind_ipk.0, blind_issuer_sig.s2, blind_issuer_sig.t2]) {
+        return Err(VerifyError::MalformedInput("r2, ipk, s2 or t2 is the identity or not in G2"));
+    }
     let h_generators : Vec<G1Affine> = pp.h[0..pi_kp.len].to_vec();
     let mut close_index: Vec<usize> = Vec::new();
     for i in 0..pi_kp.len{
@@ -290,6 +496,9 @@ pub fn verify_present(pp: &PublicParameters, (vpk, list): &(groth2::PublicKey, V
         }
     }
     let close_len = close_index.len();
+    if pi_zkp.z5.len() != close_len {
+        return Err(VerifyError::MalformedInput("response count does not match hidden messages"));
+    }
 
     let k_ipk = G2Affine::from(blind_ipk.0 * (-pi_zkp.z3));
 
@@ -303,9 +512,20 @@ pub fn verify_present(pp: &PublicParameters, (vpk, list): &(groth2::PublicKey, V
         k2_element += h_i * (-pi_zkp.z5[i]);
     }
 
-    let k1 = Bls12_381::pairing(G1Affine::from(blind_cred.s1 * pi_zkp.z1), blind_cred.r2) + Bls12_381::pairing(pp.g1, k_ipk) + Bls12_381::pairing(G1Affine::from(pp.y1 * (-pi_zkp.c)), pp.g2);
-    let k2 = Bls12_381::pairing(G1Affine::from(blind_cred.t1 * pi_zkp.z2), blind_cred.r2) + Bls12_381::pairing(pp.y1, k_ipk) + Bls12_381::pairing(G1Affine::from(k2_element), pp.g2);
-    let k3 = Bls12_381::pairing(G1Affine::from(blind_issuer_sig.r1 * pi_zkp.z4), blind_issuer_sig.t2) + Bls12_381::pairing(pp.g1, k_ipk) + Bls12_381::pairing(G1Affine::from(vpk.0.clone() * (-pi_zkp.c)), pp.y2);
+    let r2_prepared = groth::G2Prepared::from(blind_cred.r2);
+    let k_ipk_prepared = groth::G2Prepared::from(k_ipk);
+    let k1 = Bls12_381::multi_pairing(
+        [G1Affine::from(blind_cred.s1 * pi_zkp.z1), pp.g1, G1Affine::from(pp.y1 * (-pi_zkp.c))],
+        [r2_prepared.clone(), k_ipk_prepared.clone(), g2_prepared.clone()],
+    );
+    let k2 = Bls12_381::multi_pairing(
+        [G1Affine::from(blind_cred.t1 * pi_zkp.z2), pp.y1, G1Affine::from(k2_element)],
+        [r2_prepared, k_ipk_prepared.clone(), g2_prepared.clone()],
+    );
+    let k3 = Bls12_381::multi_pairing(
+        [G1Affine::from(blind_issuer_sig.r1 * pi_zkp.z4), pp.g1, G1Affine::from(vpk.0 * (-pi_zkp.c))],
+        [groth::G2Prepared::from(blind_issuer_sig.t2), k_ipk_prepared, y2_prepared.clone()],
+    );
 
     let dst = b"CHALLENGE_GENERATOR_DST_Bobolz_Issuer_Hiding_V1";
     let mut c_inputs_buffer = Vec::new();
@@ -318,21 +538,20 @@ pub fn verify_present(pp: &PublicParameters, (vpk, list): &(groth2::PublicKey, V
     k2.serialize_compressed(&mut c_inputs_buffer).unwrap();
     k3.serialize_compressed(&mut c_inputs_buffer).unwrap();
     list.serialize_compressed(&mut c_inputs_buffer).unwrap();
+    pi_kp.presentation_header.serialize_compressed(&mut c_inputs_buffer).unwrap();
     let c_calculated = groth::hash_to_fr(&c_inputs_buffer[..], dst);
     if c_calculated != pi_zkp.c{
-        println!("ZKP verification failed");
-        return false
+        return Err(VerifyError::ChallengeMismatch)
     }
-    if Bls12_381::pairing(blind_issuer_sig.r1, blind_issuer_sig.s2) != Bls12_381::pairing(pp.g1, pp.y2) + Bls12_381::pairing(vpk.0, pp.g2){
-        println!("ZKP verification failed at equation 1");
-        return false
+    if Bls12_381::pairing(blind_issuer_sig.r1, blind_issuer_sig.s2) != *list_target{
+        return Err(VerifyError::PairingCheckFailed(1))
     }
-    return true
+    return Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use rand::{thread_rng, Rng};
+    use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
     use super::*;
     #[test]
     // fn test(){
@@ -344,16 +563,16 @@ mod tests {
         let message_len = 10;
         let issuer_num = 5;
         let mut rng = thread_rng();
-        let pp = par_gen();
+        let pp = par_gen(message_len as usize);
         let issuer_keypair = issuer_key_gen(&pp);
         let ipk = &issuer_keypair.public_key;
         let mut message_fr = Vec::new();
         for _ in 0..message_len{
             message_fr.push(Fr::rand(&mut rng));
         }
-        let cred = issue(&pp, &issuer_keypair.secret_key, &message_fr);
+        let cred = issue(&pp, &issuer_keypair.secret_key, &message_fr).unwrap();
         let result1 = verify(&pp, &cred, &message_fr, &issuer_keypair.public_key);
-        assert_eq!(result1, true);
+        assert_eq!(result1, Ok(()));
 
         let verifier_keypair = verifier_key_gen(&pp);
         let mut issuer_list = Vec::new();
@@ -367,7 +586,7 @@ mod tests {
         issuer_list[r] = ipk.clone();
         let trusted_issuer_credential = issue_list(&pp, &issuer_list, &verifier_keypair);
         let result2 = verify_list(&pp, &trusted_issuer_credential);
-        assert_eq!(result2, true);
+        assert_eq!(result2, Ok(()));
         let open_num = rng.gen_range(1..(message_len / 2));
         let mut open = Vec::new();
         for j in 0..open_num{
@@ -386,8 +605,77 @@ mod tests {
             open.push(x);
         }
         open.sort();
-        let pt = present(&pp, &cred, &ipk, &message_fr, &trusted_issuer_credential, &open);
-        let result3 = verify_present(&pp, &trusted_issuer_credential, &pt);
-        assert_eq!(result3, true);
+        let nonce = b"verifier-nonce-1";
+        let pt = present(&pp, &cred, ipk, &message_fr, &trusted_issuer_credential, &open, nonce).unwrap();
+        let result3 = verify_present(&pp, &trusted_issuer_credential, &pt, nonce);
+        assert_eq!(result3, Ok(()));
+        let context = verifier_context(&pp, &trusted_issuer_credential);
+        assert_eq!(verify_present_with_context(&context, &pt, nonce), Ok(()));
+
+        let seeded = present_with_rng(&pp, &cred, ipk, &message_fr, &trusted_issuer_credential, &open, nonce, &mut StdRng::seed_from_u64(7)).unwrap();
+        assert_eq!(present_with_rng(&pp, &cred, ipk, &message_fr, &trusted_issuer_credential, &open, nonce, &mut StdRng::seed_from_u64(7)).unwrap(), seeded);
+
+        let mut tampered = pt.clone();
+        tampered.0.message_list[0] += Fr::from(1u64);
+        assert_eq!(verify_present(&pp, &trusted_issuer_credential, &tampered, nonce), Err(VerifyError::ChallengeMismatch));
+
+        assert_eq!(verify_present(&pp, &trusted_issuer_credential, &pt, b"verifier-nonce-2"), Err(VerifyError::PresentationHeaderMismatch));
+        let mut replayed = pt.clone();
+        replayed.0.presentation_header = b"verifier-nonce-2".to_vec();
+        assert_eq!(verify_present(&pp, &trusted_issuer_credential, &replayed, b"verifier-nonce-2"), Err(VerifyError::ChallengeMismatch));
+
+        // a credential bound to a holder key only presents with the holder secret
+        let holder_secret = holder::holder_key_gen();
+        let holder_index = (0..message_len as usize).find(|i| !open.contains(i)).unwrap();
+        let holder_nonce = b"issuance-nonce";
+        let binding = holder::key_binding(&pp.h[holder_index], &holder_secret, holder_nonce);
+        let bound_message = message_fr[1..].to_vec();
+        let bound_cred = issue_bound(&pp, &issuer_keypair.secret_key, &binding, holder_index, &bound_message, holder_nonce).unwrap();
+        let all = holder::with_holder_secret(&bound_message, holder_index, &holder_secret).unwrap();
+        assert_eq!(verify(&pp, &bound_cred, &all, ipk), Ok(()));
+        assert_eq!(issue_bound(&pp, &issuer_keypair.secret_key, &binding, holder_index, &bound_message, nonce).unwrap_err(), ProveError::HolderKeyRejected(mybbs::error::VerifyError::ChallengeMismatch));
+        let bound_pt = present_bound(&pp, &bound_cred, ipk, &holder_secret, holder_index, &bound_message, &trusted_issuer_credential, &open, nonce).unwrap();
+        assert_eq!(verify_present_bound(&pp, &trusted_issuer_credential, &bound_pt, holder_index, nonce), Ok(()));
+        let stolen_pt = present_bound(&pp, &bound_cred, ipk, &holder::holder_key_gen(), holder_index, &bound_message, &trusted_issuer_credential, &open, nonce).unwrap();
+        assert_eq!(verify_present_bound(&pp, &trusted_issuer_credential, &stolen_pt, holder_index, nonce), Err(VerifyError::ChallengeMismatch));
+        assert_eq!(verify_present_bound(&pp, &trusted_issuer_credential, &pt, open[0], nonce), Err(VerifyError::HolderSecretNotHidden(open[0])));
+        assert_eq!(present_bound(&pp, &bound_cred, ipk, &holder_secret, holder_index, &bound_message, &trusted_issuer_credential, &vec![holder_index], nonce).unwrap_err(), ProveError::BadRevealIndex(holder_index));
+
+        let stranger = issuer_key_gen(&pp);
+        let result4 = present(&pp, &cred, &stranger.public_key, &message_fr, &trusted_issuer_credential, &open, nonce);
+        assert_eq!(result4.unwrap_err(), ProveError::IssuerNotInList);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn rejects_malformed_input() {
+        let pp = par_gen(4);
+        let issuer_keypair = issuer_key_gen(&pp);
+        let ipk = &issuer_keypair.public_key;
+        let message_fr: Vec<Fr> = (0..4u64).map(Fr::from).collect();
+        let cred = issue(&pp, &issuer_keypair.secret_key, &message_fr).unwrap();
+        let verifier_keypair = verifier_key_gen(&pp);
+        let list = issue_list(&pp, &vec![ipk.clone()], &verifier_keypair);
+        let nonce = b"verifier-nonce";
+
+        assert_eq!(present(&pp, &cred, ipk, &message_fr, &list, &vec![2, 1], nonce).unwrap_err(), ProveError::BadRevealIndex(1));
+        assert_eq!(present(&pp, &cred, ipk, &message_fr, &list, &vec![1, 1], nonce).unwrap_err(), ProveError::BadRevealIndex(1));
+
+        let pt = present(&pp, &cred, ipk, &message_fr, &list, &vec![0, 2], nonce).unwrap();
+        let mut reordered = pt.clone();
+        reordered.0.open = vec![2, 0];
+        assert_eq!(verify_present(&pp, &list, &reordered, nonce), Err(VerifyError::BadRevealIndex(0)));
+        let mut identity = pt.clone();
+        identity.0.blind_issuer_sig.r1 = G1Affine::identity();
+        assert_eq!(verify_present(&pp, &list, &identity, nonce), Err(VerifyError::MalformedInput("s1, t1 or r1 is the identity or not in G1")));
+        let mut identity_ipk = pt.clone();
+        identity_ipk.0.blind_ipk = groth1::PublicKey(G2Affine::identity());
+        assert_eq!(verify_present(&pp, &list, &identity_ipk, nonce), Err(VerifyError::MalformedInput("r2, ipk, s2 or t2 is the identity or not in G2")));
+
+        let mut forged_cred = cred.clone();
+        forged_cred.s1 = G1Affine::identity();
+        assert!(matches!(verify(&pp, &forged_cred, &message_fr, ipk), Err(VerifyError::MalformedInput(_))));
+        let mut bad_list = list.clone();
+        bad_list.1[0].ipk = groth1::PublicKey(G2Affine::identity());
+        assert_eq!(verify_list(&pp, &bad_list), Err(VerifyError::InvalidListEntry(0)));
+    }
+}
@@ -0,0 +1,35 @@
+use thiserror::Error;
+
+// Reasons a verifier rejects a Groth signature, a trusted-issuer list or a presentation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum VerifyError {
+    #[error("challenge mismatch")]
+    ChallengeMismatch,
+    #[error("pairing check {0} failed")]
+    PairingCheckFailed(usize),
+    #[error("bad reveal index {0}")]
+    BadRevealIndex(usize),
+    #[error("list entry {0} invalid")]
+    InvalidListEntry(usize),
+    #[error("presentation header does not match the expected one")]
+    PresentationHeaderMismatch,
+    #[error("message {0} is not a hidden holder secret")]
+    HolderSecretNotHidden(usize),
+    #[error("malformed input: {0}")]
+    MalformedInput(&'static str),
+}
+
+// Reasons a holder refuses to build a presentation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum ProveError {
+    #[error("bad reveal index {0}")]
+    BadRevealIndex(usize),
+    #[error("{messages} messages but only {generators} generators")]
+    TooManyMessages { messages: usize, generators: usize },
+    #[error("issuer public key is not in the trusted list")]
+    IssuerNotInList,
+    #[error("holder key binding rejected: {0}")]
+    HolderKeyRejected(mybbs::error::VerifyError),
+    #[error("malformed input: {0}")]
+    MalformedInput(&'static str),
+}
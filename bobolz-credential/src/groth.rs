@@ -2,9 +2,15 @@ use ark_bls12_381::{G1Affine, G2Affine, Bls12_381, G1Projective, G2Projective, g
 use ark_ec::{pairing::Pairing, hashing::{curve_maps::wb::WBMap, map_to_curve_hasher::MapToCurveBasedHasher, HashToCurve}};
 use ark_ff::field_hashers::{DefaultFieldHasher, HashToField};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::fmt;
 use sha2::Sha256;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use mybbs::bbs;
+use mybbs::error::WireError;
 
 pub type Fr = <Bls12_381 as Pairing>::ScalarField;
+pub type G2Prepared = <Bls12_381 as Pairing>::G2Prepared;
 
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
 pub struct PublicParameters {
@@ -38,8 +44,37 @@ pub fn par_gen() -> PublicParameters{
     return pp
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
-pub struct SecretKey(pub Fr);
+// Issuer (groth1) and verifier (groth2) secret key. Outside this crate the scalar is only
+// reachable through secret_key_to_bytes; it is compared in constant time, redacted in Debug
+// and wiped on drop.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct SecretKey(pub(crate) Fr);
+
+impl fmt::Debug for SecretKey{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        return f.write_str("SecretKey(<redacted>)")
+    }
+}
+
+impl PartialEq for SecretKey{
+    fn eq(&self, other: &Self) -> bool{
+        return bbs::ct_eq_scalars(&[self.0], &[other.0]).into()
+    }
+}
+
+impl Eq for SecretKey {}
+
+pub fn secret_key_to_bytes(sk: &SecretKey) -> Zeroizing<Vec<u8>>{
+    return bbs::scalars_to_bytes(&[sk.0])
+}
+
+pub fn secret_key_from_bytes(bytes: &[u8]) -> Result<SecretKey, WireError>{
+    let scalars = bbs::scalars_from_bytes(bytes)?;
+    if scalars.len() != 1 {
+        return Err(WireError::InvalidPayload);
+    }
+    return Ok(SecretKey(scalars[0]))
+}
 
 pub fn hash_to_fr(input: &[u8], dst: &[u8]) -> Fr {
     let hasher = <DefaultFieldHasher<Sha256> as HashToField<Fr>>::new(dst);
@@ -2,9 +2,11 @@ use ark_bls12_381::{G1Affine, G2Affine, Bls12_381};
 use ark_ff::Field;
 use ark_ec::pairing::Pairing;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::{fmt::Debug, UniformRand, vec::Vec};
-use rand::thread_rng;
+use ark_std::{fmt::Debug, UniformRand, vec::Vec, Zero};
+use rand::{rngs::OsRng, CryptoRng, RngCore};
 use crate::groth;
+use crate::error::VerifyError;
+use mybbs::bbs;
 
 pub type Fr = <Bls12_381 as Pairing>::ScalarField;
 
@@ -19,7 +21,7 @@ pub struct PublicParameters {
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
 pub struct PublicKey(pub G2Affine);
 
-#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 #[allow(non_snake_case)]
 pub struct KeyPair {
     pub secret_key: groth::SecretKey,
@@ -52,11 +54,10 @@ pub fn par_gen() -> PublicParameters{
     return pp;
 }
 
-pub fn key_gen(pp: &PublicParameters) -> KeyPair{
+pub fn key_gen_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, rng: &mut R) -> KeyPair{
     // sk \stackrel{\$}{\leftarrow} Z_p^*
     // let sk_bytes = self.0.to_bytes();
-    let mut rng = thread_rng();
-    let sk_element = Fr::rand(&mut rng);
+    let sk_element = Fr::rand(rng);
     let sk = groth::SecretKey(
         sk_element
     );
@@ -77,9 +78,12 @@ pub fn key_gen(pp: &PublicParameters) -> KeyPair{
     return keypair
 }
 
-pub fn sign(pp: &PublicParameters, sk: &groth::SecretKey, message: &G1Affine) -> Signature{
-    let mut rng = thread_rng();
-    let r = Fr::rand(&mut rng);
+pub fn key_gen(pp: &PublicParameters) -> KeyPair{
+    return key_gen_with_rng(pp, &mut OsRng)
+}
+
+pub fn sign_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, sk: &groth::SecretKey, message: &G1Affine, rng: &mut R) -> Signature{
+    let r = Fr::rand(rng);
     let r_inverse = r.inverse().unwrap();
 
     let r2  = pp.g2 * r;
@@ -97,9 +101,12 @@ pub fn sign(pp: &PublicParameters, sk: &groth::SecretKey, message: &G1Affine) ->
     return sig
 }
 
-pub fn rand_sign(sig: &Signature) -> Signature{
-    let mut rng = thread_rng();
-    let r = Fr::rand(&mut rng);
+pub fn sign(pp: &PublicParameters, sk: &groth::SecretKey, message: &G1Affine) -> Signature{
+    return sign_with_rng(pp, sk, message, &mut OsRng)
+}
+
+pub fn rand_sign_with_rng<R: RngCore + CryptoRng>(sig: &Signature, rng: &mut R) -> Signature{
+    let r = Fr::rand(rng);
     let r_inverse = r.inverse().unwrap();
     // let r = Fr::from(r_fq);
     let newr2  = sig.r2 * r;
@@ -118,24 +125,31 @@ pub fn rand_sign(sig: &Signature) -> Signature{
     return newsig
 }
 
-pub fn verify(pp: &PublicParameters, pk: &PublicKey, sig: &Signature, message: &G1Affine) -> bool{
+pub fn rand_sign(sig: &Signature) -> Signature{
+    return rand_sign_with_rng(sig, &mut OsRng)
+}
+
+pub fn verify(pp: &PublicParameters, pk: &PublicKey, sig: &Signature, message: &G1Affine) -> Result<(), VerifyError>{
     let r2 = sig.r2;
     let s1 = sig.s1;
     let t1 = sig.t1;
     let g1 = pp.g1;
     let g2 = pp.g2;
     let y1 = pp.y1;
-    
-    if Bls12_381::pairing(s1,r2) != Bls12_381::pairing(y1,g2) + Bls12_381::pairing(g1,pk.0){
-        println!("Groth1 First pairing check failed");
-        return false;
+    if !bbs::valid_points(&[s1, t1]) || !bbs::valid_points(&[r2]) {
+        return Err(VerifyError::MalformedInput("r2, s1 or t1 is the identity or not in the group"));
+    }
+
+    // e(s1, r2) = e(y1, g2) * e(g1, pk)
+    if !Bls12_381::multi_pairing([s1, -y1, -g1], [r2, g2, pk.0]).is_zero(){
+        return Err(VerifyError::PairingCheckFailed(1));
     }
-    if Bls12_381::pairing(t1,r2) != Bls12_381::pairing(y1,pk.0) + Bls12_381::pairing(message,g2){
-        println!("Groth1 Second pairing check failed");
-        return false;
+    // e(t1, r2) = e(y1, pk) * e(message, g2)
+    if !Bls12_381::multi_pairing([t1, -y1, -*message], [r2, pk.0, g2]).is_zero(){
+        return Err(VerifyError::PairingCheckFailed(2));
     }
 
-    return true;
+    return Ok(());
 }
 
 #[cfg(test)]
@@ -150,6 +164,6 @@ mod tests {
         let sig = super::sign(&pp, &keypair.secret_key, &message);
         let newsig = super::rand_sign(&sig);
         let result = super::verify(&pp, &keypair.public_key, &newsig, &message);
-        assert_eq!(result, true);
+        assert_eq!(result, Ok(()));
     }
 }
\ No newline at end of file
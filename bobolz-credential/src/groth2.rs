@@ -2,9 +2,11 @@ use ark_bls12_381::{G1Affine, G2Affine, Bls12_381};
 use ark_ff::Field;
 use ark_ec::pairing::Pairing;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::{fmt::Debug, UniformRand};
-use rand;
+use ark_std::{fmt::Debug, UniformRand, Zero};
+use rand::{rngs::OsRng, CryptoRng, RngCore};
 use crate::groth;
+use crate::error::VerifyError;
+use mybbs::bbs;
 
 pub type Fr = <Bls12_381 as Pairing>::ScalarField;
 
@@ -19,7 +21,7 @@ pub struct PublicParameters {
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
 pub struct PublicKey(pub G1Affine);
 
-#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 #[allow(non_snake_case)]
 pub struct KeyPair {
     pub secret_key: groth::SecretKey,
@@ -51,11 +53,10 @@ pub fn par_gen() -> PublicParameters{
     return pp;
 }
 
-pub fn key_gen(pp: &PublicParameters) -> KeyPair{
+pub fn key_gen_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, rng: &mut R) -> KeyPair{
     // sk \stackrel{\$}{\leftarrow} Z_p^*
     // let sk_bytes = self.0.to_bytes();
-    let mut rng = ark_std::rand::thread_rng();
-    let sk_element = Fr::rand(&mut rng);
+    let sk_element = Fr::rand(rng);
     let sk = groth::SecretKey(
         sk_element
     );
@@ -76,9 +77,12 @@ pub fn key_gen(pp: &PublicParameters) -> KeyPair{
     return keypair
 }
 
-pub fn sign(pp: &PublicParameters, sk: &groth::SecretKey, message: &G2Affine) -> Signature{
-    let mut rng = rand::thread_rng();
-    let r = Fr::rand(&mut rng);
+pub fn key_gen(pp: &PublicParameters) -> KeyPair{
+    return key_gen_with_rng(pp, &mut OsRng)
+}
+
+pub fn sign_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, sk: &groth::SecretKey, message: &G2Affine, rng: &mut R) -> Signature{
+    let r = Fr::rand(rng);
     let r_inverse = r.inverse().unwrap();
 
     let r1  = pp.g1 * r;
@@ -96,9 +100,12 @@ pub fn sign(pp: &PublicParameters, sk: &groth::SecretKey, message: &G2Affine) ->
     return sig
 }
 
-pub fn rand_sign(sig: &Signature) -> Signature{
-    let mut rng = rand::thread_rng();
-    let r = Fr::rand(&mut rng);
+pub fn sign(pp: &PublicParameters, sk: &groth::SecretKey, message: &G2Affine) -> Signature{
+    return sign_with_rng(pp, sk, message, &mut OsRng)
+}
+
+pub fn rand_sign_with_rng<R: RngCore + CryptoRng>(sig: &Signature, rng: &mut R) -> Signature{
+    let r = Fr::rand(rng);
     let r_inverse = r.inverse().unwrap();
 
     let newr1  = sig.r1 * r;
@@ -117,24 +124,31 @@ pub fn rand_sign(sig: &Signature) -> Signature{
     return newsig
 }
 
-pub fn verify(pp: &PublicParameters, pk: &PublicKey, sig: &Signature, message: &G2Affine) -> bool{
+pub fn rand_sign(sig: &Signature) -> Signature{
+    return rand_sign_with_rng(sig, &mut OsRng)
+}
+
+pub fn verify(pp: &PublicParameters, pk: &PublicKey, sig: &Signature, message: &G2Affine) -> Result<(), VerifyError>{
     let r1 = sig.r1;
     let s2 = sig.s2;
     let t2 = sig.t2;
     let g1 = pp.g1;
     let g2 = pp.g2;
     let y2 = pp.y2;
+    if !bbs::valid_points(&[r1]) || !bbs::valid_points(&[s2, t2]) {
+        return Err(VerifyError::MalformedInput("r1, s2 or t2 is the identity or not in the group"));
+    }
 
-    if Bls12_381::pairing(r1,s2) != Bls12_381::pairing(g1,y2) + Bls12_381::pairing(pk.0,g2){
-        println!("Groth2 First pairing check failed");
-        return false;
+    // e(r1, s2) = e(g1, y2) * e(pk, g2)
+    if !Bls12_381::multi_pairing([r1, -g1, -pk.0], [s2, y2, g2]).is_zero(){
+        return Err(VerifyError::PairingCheckFailed(1));
     }
-    if Bls12_381::pairing(r1,t2) != Bls12_381::pairing(pk.0,y2) + Bls12_381::pairing(g1,message){
-        println!("Groth2 Second pairing check failed");
-        return false;
+    // e(r1, t2) = e(pk, y2) * e(g1, message)
+    if !Bls12_381::multi_pairing([r1, -pk.0, -g1], [t2, y2, *message]).is_zero(){
+        return Err(VerifyError::PairingCheckFailed(2));
     }
 
-    return true;
+    return Ok(());
 }
 
 #[cfg(test)]
@@ -149,6 +163,6 @@ mod tests {
         let sig = super::sign(&pp, &keypair.secret_key, &message);
         let newsig = super::rand_sign(&sig);
         let result = super::verify(&pp, &keypair.public_key, &newsig, &message);
-        assert_eq!(result, true);
+        assert_eq!(result, Ok(()));
     }
 }
\ No newline at end of file
@@ -1,4 +1,6 @@
 pub mod bobolz;
+pub mod error;
 pub mod groth;
 pub mod groth1;
-pub mod groth2;
\ No newline at end of file
+pub mod groth2;
+pub mod wire;
\ No newline at end of file
@@ -0,0 +1,58 @@
+use mybbs::wire::{Ciphersuite, Header, Kind, Scheme};
+
+pub use mybbs::wire::{decode, encode, from_cbor, from_json, to_cbor, to_json};
+
+// Headers of this scheme's objects for the envelopes of mybbs::wire. Issuer (groth1) and
+// verifier (groth2) public keys share a kind; the type passed to decode tells them apart.
+pub const PUBLIC_PARAMETERS: Header = header(Kind::PublicParameters);
+pub const PUBLIC_KEY: Header = header(Kind::PublicKey);
+pub const SIGNATURE: Header = header(Kind::Signature);
+pub const TRUSTED_ISSUER_LIST: Header = header(Kind::TrustedIssuerList);  // (groth2::PublicKey, Vec<TrustedIssuerCredential>)
+pub const PRESENTATION: Header = header(Kind::Presentation);              // (PiKP, PiZKP)
+
+const fn header(kind: Kind) -> Header{
+    return Header{ scheme: Scheme::Bobolz, kind: kind, ciphersuite: Ciphersuite::Bls12381Sha256 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bobolz::*;
+    use crate::{groth, groth1, groth2};
+    use ark_std::{UniformRand, vec::Vec};
+    use mybbs::error::WireError;
+    use rand::thread_rng;
+
+    #[test]
+    fn it_works(){
+        let mut rng = thread_rng();
+        let messages: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+        let pp = par_gen(4);
+        let issuer_keypair = issuer_key_gen(&pp);
+        let cred = issue(&pp, &issuer_keypair.secret_key, &messages).unwrap();
+        let verifier_keypair = verifier_key_gen(&pp);
+        let list = issue_list(&pp, &vec![issuer_key_gen(&pp).public_key, issuer_keypair.public_key.clone()], &verifier_keypair);
+        let pt = present(&pp, &cred, &issuer_keypair.public_key, &messages, &list, &vec![0, 2], b"nonce").unwrap();
+
+        let pp2: PublicParameters = decode(&PUBLIC_PARAMETERS, &encode(&PUBLIC_PARAMETERS, &pp)).unwrap();
+        let list2 = from_cbor(&TRUSTED_ISSUER_LIST, &to_cbor(&TRUSTED_ISSUER_LIST, &list)).unwrap();
+        let pt2 = from_json(&PRESENTATION, &to_json(&PRESENTATION, &pt)).unwrap();
+        let issuer_keypair2 = groth1::KeyPair{
+            secret_key: groth::secret_key_from_bytes(&groth::secret_key_to_bytes(&issuer_keypair.secret_key)).unwrap(),
+            public_key: decode(&PUBLIC_KEY, &encode(&PUBLIC_KEY, &issuer_keypair.public_key)).unwrap(),
+        };
+        assert_eq!(issuer_keypair2, issuer_keypair);
+        assert_eq!(format!("{:?}", issuer_keypair.secret_key), "SecretKey(<redacted>)");
+        assert_eq!(from_json::<groth2::PublicKey>(&PUBLIC_KEY, &to_json(&PUBLIC_KEY, &verifier_keypair.public_key)), Ok(verifier_keypair.public_key.clone()));
+        assert_eq!(decode::<groth1::Signature>(&SIGNATURE, &encode(&SIGNATURE, &cred)), Ok(cred.clone()));
+        assert_eq!(verify_list(&pp2, &list2), Ok(()));
+        assert_eq!(verify_present(&pp2, &list2, &pt2, b"nonce"), Ok(()));
+
+        // envelopes of other schemes and kinds are rejected before the payload is read
+        let bytes = encode(&PRESENTATION, &pt);
+        let bbs_presentation = mybbs::wire::Header{ scheme: mybbs::wire::Scheme::Bbs, ..PRESENTATION };
+        assert_eq!(decode::<(PiKP, PiZKP)>(&bbs_presentation, &bytes), Err(WireError::WrongScheme));
+        assert_eq!(decode::<(groth2::PublicKey, Vec<TrustedIssuerCredential>)>(&TRUSTED_ISSUER_LIST, &bytes), Err(WireError::WrongKind));
+        assert_eq!(decode::<(PiKP, PiZKP)>(&PRESENTATION, &bytes[..bytes.len() - 1]), Err(WireError::InvalidPayload));
+    }
+}
@@ -2,6 +2,7 @@ use criterion::{criterion_group, criterion_main, Criterion, BenchmarkId};
 use std::hint::black_box;
 use ark_bls12_381::Bls12_381;
 use ark_ec::pairing::Pairing;
+use ark_serialize::CanonicalSerialize;
 use ark_std::{vec::Vec, UniformRand};
 use rand::{self, Rng, thread_rng};
 pub type Fr = <Bls12_381 as Pairing>::ScalarField;
@@ -14,12 +15,12 @@ fn katz_ih_benchmark(c: &mut Criterion){
 
     c.bench_function("Setup", |b| {
         b.iter(|| {
-            let pp = ih::par_gen();
+            let pp = ih::par_gen(*message_len.iter().max().unwrap());
             black_box(pp);
         });
     });
 
-    let pp = ih::par_gen();
+    let pp = ih::par_gen(*message_len.iter().max().unwrap());
 
     c.bench_function("Issuer_Key_Gen", |b| {
         b.iter(|| {
@@ -47,16 +48,16 @@ fn katz_ih_benchmark(c: &mut Criterion){
             group.bench_with_input(BenchmarkId::new("Sign", len), &len, |b, &_| {
                 b.iter(|| {
                     let signature = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
-                    black_box(signature);
+                    let _ = black_box(signature);
                 });
             });
 
             // Verify Credential (署名生成済みデータが必要)
-            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
             group.bench_with_input(BenchmarkId::new("Verify", len), &len, |b, &_| {
                 b.iter(|| {
                     let result = ih::verify(&pp, &issuer_key_pair.public_key, &message_fr_temp, &cred_temp);
-                    black_box(result);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -72,31 +73,34 @@ fn katz_ih_benchmark(c: &mut Criterion){
         for &num in issuer_num.iter() {
             // リストの準備
             let mut issuer_list_temp = Vec::new();
+            let mut key_proof_temp = Vec::new();
             for _ in 0..num {
                 let keypair = ih::issuer_key_gen(&pp);
                 issuer_list_temp.push(keypair.public_key.clone());
+                key_proof_temp.push(ih::prove_issuer_key(&pp, &keypair));
             }
             // ランダムな位置にターゲットを挿入
             let r = rng.gen_range(0..num);
             if r < issuer_list_temp.len() {
                  issuer_list_temp[r] = issuer_key_pair.public_key.clone();
+                 key_proof_temp[r] = ih::prove_issuer_key(&pp, &issuer_key_pair);
             }
 
             // Set Policy
             group.bench_with_input(BenchmarkId::new("Set_Policy", num), &num, |b, &_| {
                 b.iter(|| {
-                    let pkp = ih::set_policy(&pp, &issuer_list_temp);
+                    let pkp = ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap();
                 black_box(pkp);
                 });
             });
 
             // Policy (生成済みデータが必要)
-            let policy_key_pair = ih::set_policy(&pp, &issuer_list_temp);
+            let policy_key_pair = ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap();
             let policy_pk = &policy_key_pair.public_key;
             group.bench_with_input(BenchmarkId::new("Verify_List", num), &num, |b, &_| {
                 b.iter(|| {
-                    let result = ih::audit_policy(&pp, &policy_pk);
-                    black_box(result);
+                    let result = ih::audit_policy(&pp, policy_pk);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -119,7 +123,7 @@ fn katz_ih_benchmark(c: &mut Criterion){
             for _ in 0..mlen {
                 message_fr_temp.push(Fr::rand(&mut rng));
             }
-            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
 
             // 公開する属性の数を決定
             let open_message_6 = mlen * 3 / 5;
@@ -146,14 +150,17 @@ fn katz_ih_benchmark(c: &mut Criterion){
                 for &inum in issuer_num.iter() {
                     // Issuerリスト準備
                     let mut issuer_list_temp = Vec::new();
+                    let mut key_proof_temp = Vec::new();
                     for _ in 0..inum {
                         let kp = ih::issuer_key_gen(&pp);
                         issuer_list_temp.push(kp.public_key.clone());
+                        key_proof_temp.push(ih::prove_issuer_key(&pp, &kp));
                     }
                     let r = rng.gen_range(0..inum);
                     issuer_list_temp[r] = issuer_key_pair.public_key.clone();
+                    key_proof_temp[r] = ih::prove_issuer_key(&pp, &issuer_key_pair);
                     
-                    let policy_key_pair = ih::set_policy(&pp, &issuer_list_temp);
+                    let policy_key_pair = ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap();
                     let policy_pk = &policy_key_pair.public_key;
 
                     // パラメータ識別文字列を作成 (例: "m10_o3_i50")
@@ -162,17 +169,25 @@ fn katz_ih_benchmark(c: &mut Criterion){
                     // Present Benchmark
                     group.bench_with_input(BenchmarkId::new("Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, &policy_pk);
-                            black_box(pt);
+                            let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, policy_pk, b"");
+                            let _ = black_box(pt);
                         });
                     });
                     // ベンチマーク内で毎回生成すると遅いので、計測外で一度生成
-                    let (pikp, pizkp) = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, &policy_pk);
+                    let (pikp, pizkp) = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, policy_pk, b"").unwrap();
                     
                     group.bench_with_input(BenchmarkId::new("Verify_Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let result = ih::verify_present(&pp, &policy_key_pair, &pikp, &pizkp);
-                            black_box(result);
+                            let result = ih::verify_present(&pp, &policy_key_pair, &pikp, &pizkp, b"");
+                            let _ = black_box(result);
+                        });
+                    });
+
+                    let context = ih::verifier_context(&pp, &policy_key_pair).unwrap();
+                    group.bench_with_input(BenchmarkId::new("Verify_Present_Context", &param_str), &param_str, |b, _| {
+                        b.iter(|| {
+                            let result = ih::verify_present_with_context(&context, &pikp, &pizkp, b"");
+                            let _ = black_box(result);
                         });
                     });
                 }
@@ -180,6 +195,106 @@ fn katz_ih_benchmark(c: &mut Criterion){
         }
         group.finish();
     }
+
+    // ------------------------------------------------------------------
+    // Group 4: 複数クレデンシャルの一括提示 (クレデンシャル数による変化)
+    // ------------------------------------------------------------------
+    // m10_o3_i10 のクレデンシャルを k 個、それぞれ別の発行者・別のポリシーで1つのチャレンジの下に提示します。
+    {
+        let mut group = c.benchmark_group("Many_Presentation_Ops");
+        let credential_num = [1, 2, 3, 4, 5];
+        let mlen = 10;
+        let open_temp: Vec<usize> = vec![0, 3, 5];
+        let no_predicates = Vec::new();
+
+        for &k in credential_num.iter() {
+            let issuer_key_pairs: Vec<_> = (0..k).map(|_| ih::issuer_key_gen(&pp)).collect();
+            let messages: Vec<Vec<Fr>> = (0..k).map(|_| (0..mlen).map(|_| Fr::rand(&mut rng)).collect()).collect();
+            let creds: Vec<_> = (0..k).map(|j| ih::issue(&pp, &issuer_key_pairs[j].secret_key, &messages[j]).unwrap()).collect();
+            // クレデンシャルごとに別のポリシーを用意
+            let lists: Vec<_> = (0..k).map(|j| {
+                let mut issuer_keys: Vec<_> = (0..9).map(|_| ih::issuer_key_gen(&pp)).collect();
+                issuer_keys.push(issuer_key_pairs[j].clone());
+                let issuer_list_temp: Vec<_> = issuer_keys.iter().map(|kp| kp.public_key.clone()).collect();
+                let key_proof_temp: Vec<_> = issuer_keys.iter().map(|kp| ih::prove_issuer_key(&pp, kp)).collect();
+                ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap()
+            }).collect();
+            let items: Vec<ih::ManyItem> = (0..k).map(|j| ((&creds[j], &issuer_key_pairs[j].public_key, &messages[j], &open_temp, &no_predicates), &lists[j].public_key)).collect();
+            let param_str = format!("k{}", k);
+
+            group.bench_with_input(BenchmarkId::new("Present_Many", &param_str), &param_str, |b, _| {
+                b.iter(|| {
+                    let pt = ih::present_many(&pp, &items, &Vec::new(), b"");
+                    let _ = black_box(pt);
+                });
+            });
+
+            let presentations = ih::present_many(&pp, &items, &Vec::new(), b"").unwrap();
+            let presented: Vec<ih::ManyPresentation> = (0..k).map(|j| (&lists[j], &presentations[j].0, &presentations[j].1)).collect();
+            group.bench_with_input(BenchmarkId::new("Verify_Present_Many", &param_str), &param_str, |b, _| {
+                b.iter(|| {
+                    let result = ih::verify_present_many(&pp, &presented, &Vec::new(), b"");
+                    let _ = black_box(result);
+                });
+            });
+
+            // サイズは計測値ではないので標準出力に出します
+            println!("Many_Presentation_Ops/Size/{}: {} bytes", param_str, presentations.compressed_size());
+        }
+        group.finish();
+    }
+
+    // ------------------------------------------------------------------
+    // Group 5: 準備済み検証器 (Issuer数による変化)
+    // ------------------------------------------------------------------
+    // m10_o3 に固定し、Verify_Present が Issuer 数に比例するのに対して
+    // Verify_Present_Prepared が一定であることを確認します。
+    {
+        let mut group = c.benchmark_group("Prepared_Verifier_Ops");
+        let mlen = 10;
+        let open_temp: Vec<usize> = vec![0, 3, 5];
+        let message_fr_temp: Vec<Fr> = (0..mlen).map(|_| Fr::rand(&mut rng)).collect();
+        let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
+
+        for &inum in issuer_num.iter() {
+            let mut issuer_list_temp = Vec::new();
+            let mut key_proof_temp = Vec::new();
+            for _ in 0..inum {
+                let kp = ih::issuer_key_gen(&pp);
+                issuer_list_temp.push(kp.public_key.clone());
+                key_proof_temp.push(ih::prove_issuer_key(&pp, &kp));
+            }
+            let r = rng.gen_range(0..inum);
+            issuer_list_temp[r] = issuer_key_pair.public_key.clone();
+            key_proof_temp[r] = ih::prove_issuer_key(&pp, &issuer_key_pair);
+            let policy_key_pair = ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap();
+            let (pikp, pizkp) = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, &policy_key_pair.public_key, b"").unwrap();
+
+            // 準備は Issuer 数に比例しますがポリシーごとに一度だけです
+            group.bench_with_input(BenchmarkId::new("Prepare", inum), &inum, |b, &_| {
+                b.iter(|| {
+                    let verifier = ih::verifier_context(&pp, &policy_key_pair);
+                    let _ = black_box(verifier);
+                });
+            });
+
+            group.bench_with_input(BenchmarkId::new("Verify_Present", inum), &inum, |b, &_| {
+                b.iter(|| {
+                    let result = ih::verify_present(&pp, &policy_key_pair, &pikp, &pizkp, b"");
+                    let _ = black_box(result);
+                });
+            });
+
+            let verifier: ih::PreparedPolicyVerifier = ih::verifier_context(&pp, &policy_key_pair).unwrap();
+            group.bench_with_input(BenchmarkId::new("Verify_Present_Prepared", inum), &inum, |b, &_| {
+                b.iter(|| {
+                    let result = ih::verify_present_with_context(&verifier, &pikp, &pizkp, b"");
+                    let _ = black_box(result);
+                });
+            });
+        }
+        group.finish();
+    }
 }
 
 fn katz_ih_benchmark_pc(c: &mut Criterion) {
@@ -189,11 +304,11 @@ fn katz_ih_benchmark_pc(c: &mut Criterion) {
 
     c.bench_function("Setup", |b| {
         b.iter(|| {
-            let pp = ih::par_gen();
+            let pp = ih::par_gen(*message_len.iter().max().unwrap());
             black_box(pp);
         });
     });
-    let pp = ih::par_gen();
+    let pp = ih::par_gen(*message_len.iter().max().unwrap());
 
     c.bench_function("Issuer_Key_Gen", |b| {
         b.iter(|| {
@@ -221,7 +336,7 @@ fn katz_ih_benchmark_pc(c: &mut Criterion) {
             group.bench_with_input(BenchmarkId::new("Sign", len), &len, |b, &_| {
                 b.iter(|| {
                     let signature = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
-                    black_box(signature);
+                    let _ = black_box(signature);
                 });
             });
         }
@@ -237,20 +352,23 @@ fn katz_ih_benchmark_pc(c: &mut Criterion) {
         for &num in issuer_num.iter() {
             // リストの準備
             let mut issuer_list_temp = Vec::new();
+            let mut key_proof_temp = Vec::new();
             for _ in 0..num {
                 let keypair = ih::issuer_key_gen(&pp);
                 issuer_list_temp.push(keypair.public_key.clone());
+                key_proof_temp.push(ih::prove_issuer_key(&pp, &keypair));
             }
             // ランダムな位置にターゲットを挿入
             let r = rng.gen_range(0..num);
             if r < issuer_list_temp.len() {
                  issuer_list_temp[r] = issuer_key_pair.public_key.clone();
+                 key_proof_temp[r] = ih::prove_issuer_key(&pp, &issuer_key_pair);
             }
 
             // Set Policy
             group.bench_with_input(BenchmarkId::new("Set_Policy", num), &num, |b, &_| {
                 b.iter(|| {
-                    let pkp = ih::set_policy(&pp, &issuer_list_temp);
+                    let pkp = ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap();
                 black_box(pkp);
                 });
             });
@@ -274,7 +392,7 @@ fn katz_ih_benchmark_pc(c: &mut Criterion) {
             for _ in 0..mlen {
                 message_fr_temp.push(Fr::rand(&mut rng));
             }
-            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
 
             // 公開する属性の数を決定
             let open_message_6 = mlen * 3 / 5;
@@ -301,14 +419,17 @@ fn katz_ih_benchmark_pc(c: &mut Criterion) {
                 for &inum in issuer_num.iter() {
                     // Issuerリスト準備
                     let mut issuer_list_temp = Vec::new();
+                    let mut key_proof_temp = Vec::new();
                     for _ in 0..inum {
                         let kp = ih::issuer_key_gen(&pp);
                         issuer_list_temp.push(kp.public_key.clone());
+                        key_proof_temp.push(ih::prove_issuer_key(&pp, &kp));
                     }
                     let r = rng.gen_range(0..inum);
                     issuer_list_temp[r] = issuer_key_pair.public_key.clone();
+                    key_proof_temp[r] = ih::prove_issuer_key(&pp, &issuer_key_pair);
 
-                    let policy_key_pair = ih::set_policy(&pp, &issuer_list_temp);
+                    let policy_key_pair = ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap();
                     let policy_pk = &policy_key_pair.public_key;
 
                     // パラメータ識別文字列を作成 (例: "m10_o3_i50")
@@ -316,12 +437,20 @@ fn katz_ih_benchmark_pc(c: &mut Criterion) {
 
                     // Verify Present Benchmark
                     // ベンチマーク内で毎回生成すると遅いので、計測外で一度生成
-                    let (pikp, pizkp) = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, &policy_pk);
+                    let (pikp, pizkp) = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, policy_pk, b"").unwrap();
                     
                     group.bench_with_input(BenchmarkId::new("Verify_Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let result = ih::verify_present(&pp, &policy_key_pair, &pikp, &pizkp);
-                            black_box(result);
+                            let result = ih::verify_present(&pp, &policy_key_pair, &pikp, &pizkp, b"");
+                            let _ = black_box(result);
+                        });
+                    });
+
+                    let context = ih::verifier_context(&pp, &policy_key_pair).unwrap();
+                    group.bench_with_input(BenchmarkId::new("Verify_Present_Context", &param_str), &param_str, |b, _| {
+                        b.iter(|| {
+                            let result = ih::verify_present_with_context(&context, &pikp, &pizkp, b"");
+                            let _ = black_box(result);
                         });
                     });
                 }
@@ -335,7 +464,7 @@ fn katz_ih_benchmark_android(c: &mut Criterion) {
     let message_len = [5, 10, 15, 20, 25, 30, 35, 40, 45, 50];
     let issuer_num = [5, 10, 50, 100, 500, 1000];
     let mut rng = thread_rng();
-    let pp = ih::par_gen();
+    let pp = ih::par_gen(*message_len.iter().max().unwrap());
 
     let issuer_key_pair = ih::issuer_key_gen(&pp);
 
@@ -353,11 +482,11 @@ fn katz_ih_benchmark_android(c: &mut Criterion) {
             }
 
             // Verify Credential (署名生成済みデータが必要)
-            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
             group.bench_with_input(BenchmarkId::new("Verify", len), &len, |b, &_| {
                 b.iter(|| {
                     let result = ih::verify(&pp, &issuer_key_pair.public_key, &message_fr_temp, &cred_temp);
-                    black_box(result);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -373,23 +502,26 @@ fn katz_ih_benchmark_android(c: &mut Criterion) {
         for &num in issuer_num.iter() {
             // リストの準備
             let mut issuer_list_temp = Vec::new();
+            let mut key_proof_temp = Vec::new();
             for _ in 0..num {
                 let keypair = ih::issuer_key_gen(&pp);
                 issuer_list_temp.push(keypair.public_key.clone());
+                key_proof_temp.push(ih::prove_issuer_key(&pp, &keypair));
             }
             // ランダムな位置にターゲットを挿入
             let r = rng.gen_range(0..num); // gen_range(1..num)だとnum=5のときindex 0が選ばれない可能性があるため修正考慮(元ロジック尊重なら戻してください)
             if r < issuer_list_temp.len() {
                  issuer_list_temp[r] = issuer_key_pair.public_key.clone();
+                 key_proof_temp[r] = ih::prove_issuer_key(&pp, &issuer_key_pair);
             }
 
             // Policy (生成済みデータが必要)
-            let policy_key_pair = ih::set_policy(&pp, &issuer_list_temp);
+            let policy_key_pair = ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap();
             let policy_pk = &policy_key_pair.public_key;
             group.bench_with_input(BenchmarkId::new("Verify_List", num), &num, |b, &_| {
                 b.iter(|| {
-                    let result = ih::audit_policy(&pp, &policy_pk);
-                    black_box(result);
+                    let result = ih::audit_policy(&pp, policy_pk);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -412,7 +544,7 @@ fn katz_ih_benchmark_android(c: &mut Criterion) {
             for _ in 0..mlen {
                 message_fr_temp.push(Fr::rand(&mut rng));
             }
-            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
 
             // 公開する属性の数を決定
             let open_message_6 = mlen * 3 / 5;
@@ -439,14 +571,17 @@ fn katz_ih_benchmark_android(c: &mut Criterion) {
                 for &inum in issuer_num.iter() {
                     // Issuerリスト準備
                     let mut issuer_list_temp = Vec::new();
+                    let mut key_proof_temp = Vec::new();
                     for _ in 0..inum {
                         let kp = ih::issuer_key_gen(&pp);
                         issuer_list_temp.push(kp.public_key.clone());
+                        key_proof_temp.push(ih::prove_issuer_key(&pp, &kp));
                     }
                     let r = rng.gen_range(0..inum);
                     issuer_list_temp[r] = issuer_key_pair.public_key.clone();
+                    key_proof_temp[r] = ih::prove_issuer_key(&pp, &issuer_key_pair);
                     
-                    let policy_key_pair = ih::set_policy(&pp, &issuer_list_temp);
+                    let policy_key_pair = ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap();
                     let policy_pk = &policy_key_pair.public_key;
 
                     // パラメータ識別文字列を作成 (例: "m10_o3_i50")
@@ -455,8 +590,8 @@ fn katz_ih_benchmark_android(c: &mut Criterion) {
                     // Present Benchmark
                     group.bench_with_input(BenchmarkId::new("Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, &policy_pk);
-                            black_box(pt);
+                            let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, policy_pk, b"");
+                            let _ = black_box(pt);
                         });
                     });
                 }
@@ -0,0 +1,37 @@
+use thiserror::Error;
+
+// Reasons a verifier rejects a credential, a policy or a presentation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum VerifyError {
+    #[error("challenge mismatch")]
+    ChallengeMismatch,
+    #[error("pairing check {0} failed")]
+    PairingCheckFailed(usize),
+    #[error("bad reveal index {0}")]
+    BadRevealIndex(usize),
+    #[error("presentation header does not match the expected one")]
+    PresentationHeaderMismatch,
+    #[error("key proof of issuer {0} invalid")]
+    InvalidKeyProof(usize),
+    #[error("policy update to version {got}, expected version {expected}")]
+    StalePolicyVersion { got: u64, expected: u64 },
+    #[error("malformed input: {0}")]
+    MalformedInput(&'static str),
+    #[error(transparent)]
+    Credential(#[from] mybbs::error::VerifyError),
+}
+
+// Reasons a holder refuses to build a presentation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum ProveError {
+    #[error("bad reveal index {0}")]
+    BadRevealIndex(usize),
+    #[error("{messages} messages but only {generators} generators")]
+    TooManyMessages { messages: usize, generators: usize },
+    #[error("issuer public key is not in the policy")]
+    IssuerNotInPolicy,
+    #[error(transparent)]
+    Credential(#[from] mybbs::error::ProveError),
+    #[error(transparent)]
+    Schema(#[from] mybbs::error::SchemaError),
+}
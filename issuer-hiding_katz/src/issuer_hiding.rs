@@ -1,12 +1,22 @@
 use ark_bls12_381::{Bls12_381, G1Affine, G1Projective, G2Affine, G2Projective};
-use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ec::{pairing::Pairing, CurveGroup};
 use ark_ff::Field;
-use ark_std::{fmt::Debug, UniformRand, vec::Vec};
+use ark_std::{fmt::{self, Debug}, UniformRand, vec::Vec};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use rand::thread_rng;
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 use mybbs::bbs;
+use mybbs::equality::{self, Equality};
+use mybbs::error::WireError;
+use mybbs::holder::{self, HolderSecretKey, KeyBinding};
 use mybbs::issuer;
+use mybbs::proof;
+use mybbs::pseudonym::{self, ScopedPseudonym};
+use mybbs::range::{self, RangePredicate, RangeProof};
+use mybbs::schema::{self, AttributeValue, CredentialSchema};
+
+use crate::error::{ProveError, VerifyError};
 
 pub type Fr = <Bls12_381 as Pairing>::ScalarField;
 
@@ -17,21 +27,40 @@ pub struct PiPolicy{
     pub t: Fr,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+// Redacted in Debug, compared in constant time and wiped on drop; outside this crate it is
+// only reachable through policy_secret_key_to_bytes.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
 pub struct PolicySecretKey{
-    pub a: Fr,
-    pub b: Fr,
+    pub(crate) a: Fr,
+    pub(crate) b: Fr,
+}
+
+impl Debug for PolicySecretKey{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        return f.write_str("PolicySecretKey(<redacted>)")
+    }
 }
 
+impl PartialEq for PolicySecretKey{
+    fn eq(&self, other: &Self) -> bool{
+        return bbs::ct_eq_scalars(&[self.a, self.b], &[other.a, other.b]).into()
+    }
+}
+
+impl Eq for PolicySecretKey {}
+
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
 pub struct PolicyPublicKey{
     pub ipks: Vec<issuer::PublicKey>,
+    pub key_proofs: Vec<issuer::KeyProof>,
+    pub version: u64,   // 0 from set_policy, one more with every policy_add_issuer or policy_remove_issuer
     pub s: G2Affine,
     pub t: Vec<G2Affine>,
     pub pi: PiPolicy,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+// Not serializable: the secret key leaves only through policy_secret_key_to_bytes.
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct PolicyKeyPair{
     pub secret_key: PolicySecretKey,
     pub public_key: PolicyPublicKey,
@@ -46,6 +75,9 @@ pub struct PiKP{
     pub open: Vec<usize>,
     pub len: usize,
     pub message_list: Vec<Fr>,
+    pub predicates: Vec<RangePredicate>,
+    pub pseudonym: Option<ScopedPseudonym>,
+    pub presentation_header: Vec<u8>,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalSerialize, CanonicalDeserialize)]
@@ -55,37 +87,116 @@ pub struct PiZKP{
     pub t: Fr,
     pub z: Fr,
     pub v: Vec<Fr>,
+    pub range: RangeProof,
 }
 
-pub fn par_gen() -> issuer::PublicParameters{
-    let pp = issuer::par_gen();
+pub fn par_gen(message_len: usize) -> issuer::PublicParameters{
+    let pp = issuer::par_gen(message_len);
     return pp
 }
 
-pub fn issuer_key_gen(pp: &issuer::PublicParameters) -> issuer::KeyPair{
-    let keypair = issuer::key_gen(&pp);
+pub fn issuer_key_gen_with_rng<R: RngCore + CryptoRng>(pp: &issuer::PublicParameters, rng: &mut R) -> issuer::KeyPair{
+    let keypair = issuer::key_gen_with_rng(pp, rng);
     return keypair
 }
 
-pub fn issue(pp: &issuer::PublicParameters, isk: &bbs::SecretKey, messages: &Vec<Fr>) -> issuer::Signature{
-    let signature = issuer::sign(&pp, isk, messages);
-    return signature
+pub fn issuer_key_gen(pp: &issuer::PublicParameters) -> issuer::KeyPair{
+    return issuer_key_gen_with_rng(pp, &mut OsRng)
+}
+
+// The proof of possession set_policy asks of every issuer key.
+pub fn prove_issuer_key_with_rng<R: RngCore + CryptoRng>(pp: &issuer::PublicParameters, keypair: &issuer::KeyPair, rng: &mut R) -> issuer::KeyProof{
+    return issuer::prove_key_with_rng(pp, keypair, rng)
+}
+
+pub fn prove_issuer_key(pp: &issuer::PublicParameters, keypair: &issuer::KeyPair) -> issuer::KeyProof{
+    return prove_issuer_key_with_rng(pp, keypair, &mut OsRng)
+}
+
+pub fn issue_with_rng<R: RngCore + CryptoRng>(pp: &issuer::PublicParameters, isk: &bbs::SecretKey, messages: &Vec<Fr>, rng: &mut R) -> Result<issuer::Signature, ProveError>{
+    let signature = issuer::sign_with_rng(pp, isk, messages, rng)?;
+    return Ok(signature)
+}
+
+pub fn issue(pp: &issuer::PublicParameters, isk: &bbs::SecretKey, messages: &Vec<Fr>) -> Result<issuer::Signature, ProveError>{
+    return issue_with_rng(pp, isk, messages, &mut OsRng)
+}
+
+pub fn verify(pp: &issuer::PublicParameters, ipk: &issuer::PublicKey, messages: &Vec<Fr>, sig: &issuer::Signature) -> Result<(), VerifyError>{
+    issuer::verify(pp, ipk, messages, sig)?;
+    return Ok(())
+}
+
+// a || b as 32-byte little-endian scalars.
+pub fn policy_secret_key_to_bytes(sk: &PolicySecretKey) -> Zeroizing<Vec<u8>>{
+    return bbs::scalars_to_bytes(&[sk.a, sk.b])
 }
 
-pub fn verify(pp: &issuer::PublicParameters, ipk: &issuer::PublicKey, messages: &Vec<Fr>, sig: &issuer::Signature) -> bool{
-    let is_valid = issuer::verify(&pp, ipk, messages, sig);
-    return is_valid
+pub fn policy_secret_key_from_bytes(bytes: &[u8]) -> Result<PolicySecretKey, WireError>{
+    let scalars = bbs::scalars_from_bytes(bytes)?;
+    if scalars.len() != 2 {
+        return Err(WireError::InvalidPayload);
+    }
+    return Ok(PolicySecretKey{ a: scalars[0], b: scalars[1] })
 }
 
-pub fn set_policy(pp: &issuer::PublicParameters, ipk_list: &Vec<issuer::PublicKey>) -> PolicyKeyPair{
+// Every ipk needs a proof of possession: the presentation check sums all ipks, so a key chosen
+// after the others could otherwise cancel them out.
+fn check_key_proofs(pp: &issuer::PublicParameters, ipk_list: &Vec<issuer::PublicKey>, key_proofs: &Vec<issuer::KeyProof>) -> Result<(), VerifyError>{
+    if ipk_list.len() != key_proofs.len() {
+        return Err(VerifyError::MalformedInput("one key proof per issuer"))
+    }
+    for i in 0..ipk_list.len(){
+        if issuer::verify_key(pp, &ipk_list[i], &key_proofs[i]).is_err(){
+            return Err(VerifyError::InvalidKeyProof(i))
+        }
+    }
+    return Ok(())
+}
+
+// An issuer listed twice would survive policy_remove_issuer, which drops only one copy.
+fn check_distinct_issuers(ipk_list: &[issuer::PublicKey]) -> Result<(), VerifyError>{
+    for i in 0..ipk_list.len(){
+        if ipk_list[..i].contains(&ipk_list[i]) {
+            return Err(VerifyError::MalformedInput("issuer is already in the policy"))
+        }
+    }
+    return Ok(())
+}
+
+// The change from one policy version to the next, all a relying party that audited the
+// previous version needs to fetch. An addition keeps s and carries only the new issuers' t.
+// A removal re-keys the policy and carries every t: the removed issuer's t stays public, and
+// under the old key its holders could still present.
+#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+pub struct PolicyUpdate{
+    pub version: u64,
+    pub removed: Vec<usize>,    // indices into the previous ipks, in increasing order
+    pub added: Vec<issuer::PublicKey>,
+    pub key_proofs: Vec<issuer::KeyProof>,  // one per added issuer
+    pub s: G2Affine,
+    pub t: Vec<G2Affine>,
+    pub pi: PiPolicy,
+}
+
+fn policy_challenge(ipk_list: &[issuer::PublicKey], version: u64, c_input: &[G2Affine]) -> Fr{
+    let dst = b"MY_CHALLENGE_GENERATOR_DST_Set_Policy_V1";
+    let mut c_input_buffer = Vec::new();
+    for issuer_pk in ipk_list{
+        issuer_pk.0.serialize_compressed(&mut c_input_buffer).unwrap();
+    }
+    version.serialize_compressed(&mut c_input_buffer).unwrap();
+    for c_input in c_input{
+        c_input.serialize_compressed(&mut c_input_buffer).unwrap();
+    }
+    return bbs::hash_to_fr(&c_input_buffer[..], dst)
+}
+
+// The policy key of sk over ipk_list, with the proof that every t_i is (ipk_i * g2^b)^a for
+// the a behind s.
+fn policy_key_with_rng<R: RngCore + CryptoRng>(pp: &issuer::PublicParameters, sk: PolicySecretKey, ipk_list: &Vec<issuer::PublicKey>, key_proofs: &Vec<issuer::KeyProof>, version: u64, rng: &mut R) -> PolicyKeyPair{
     let ipk_len = ipk_list.len();
-    let mut rng = thread_rng();
-    let a = Fr::rand(&mut rng);
-    let b = Fr::rand(&mut rng);
-    let sk = PolicySecretKey{
-        a: a,
-        b: b,
-    };
+    let (a, b) = (&sk.a, &sk.b);
 
     let s_pro = pp.g2 * a;
     let mut t_pro  = Vec::new();
@@ -94,41 +205,34 @@ pub fn set_policy(pp: &issuer::PublicParameters, ipk_list: &Vec<issuer::PublicKe
         t_pro.push(t_i);
     }
 
-    let alpha = Fr::rand(&mut rng);
-    let beta = Fr::rand(&mut rng);
-    let u_1_pro = s_pro * alpha;
+    let alpha = Zeroizing::new(Fr::rand(rng));
+    let beta = Zeroizing::new(Fr::rand(rng));
+    let u_1_pro = s_pro * *alpha;
     let mut u_2_vec = Vec::new();
     for i in 0..ipk_len{
-        let u_2_i = t_pro[i] * alpha + pp.g2 * beta;
+        let u_2_i = t_pro[i] * *alpha + pp.g2 * *beta;
         u_2_vec.push(u_2_i);
     }
 
-    let dst = b"MY_CHALLENGE_GENERATOR_DST_Set_Policy_V1";
     let mut c_input_pro = vec![s_pro];
     c_input_pro.extend(t_pro.clone());
     c_input_pro.push(u_1_pro);
     c_input_pro.extend(u_2_vec.clone());
     // s, t_i, u1, u2_i
     let c_input = G2Projective::normalize_batch(&c_input_pro);
+    let c = policy_challenge(ipk_list, version, &c_input);
 
-    let mut c_input_buffer = Vec::new();
-    for issuer_pk in ipk_list{
-        issuer_pk.0.serialize_compressed(&mut c_input_buffer).unwrap();
-    }
-    for c_input in &c_input{
-        c_input.serialize_compressed(&mut c_input_buffer).unwrap();
-    }
-    let c = bbs::hash_to_fr(&c_input_buffer[..], dst);
-
-    let a_inv = a.inverse().unwrap();
+    let a_inv = Zeroizing::new(a.inverse().unwrap());
 
     let pi = PiPolicy{
         c: c,
-        s: alpha + c * a_inv,
-        t: beta - c * b,
+        s: *alpha + c * *a_inv,
+        t: *beta - c * b,
     };
     let pk = PolicyPublicKey{
         ipks: ipk_list.clone(),
+        key_proofs: key_proofs.clone(),
+        version: version,
         s: c_input[0],
         t: c_input[1..=ipk_len].to_vec(),
         pi: pi,
@@ -140,61 +244,222 @@ pub fn set_policy(pp: &issuer::PublicParameters, ipk_list: &Vec<issuer::PublicKe
     return keypair
 }
 
-pub fn audit_policy(pp: &issuer::PublicParameters, ppk: &PolicyPublicKey) -> bool{
-    let (ipk_list, s,t,c,pi_s,pi_t) = (ppk.ipks.clone(), ppk.s, ppk.t.clone(), ppk.pi.c, ppk.pi.s, ppk.pi.t);
-    let ipk_len = ipk_list.len();
-    if ipk_len != t.len(){
-        println!("Issuer public key list length and t vector length mismatch");
-        return false
+fn policy_secret_key_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> PolicySecretKey{
+    return PolicySecretKey{
+        a: Fr::rand(rng),
+        b: Fr::rand(rng),
     }
-    if s.is_zero() {
-        println!("s is identity element");
-        return false
+}
+
+pub fn set_policy_with_rng<R: RngCore + CryptoRng>(pp: &issuer::PublicParameters, ipk_list: &Vec<issuer::PublicKey>, key_proofs: &Vec<issuer::KeyProof>, rng: &mut R) -> Result<PolicyKeyPair, VerifyError>{
+    check_distinct_issuers(ipk_list)?;
+    check_key_proofs(pp, ipk_list, key_proofs)?;
+    let sk = policy_secret_key_with_rng(rng);
+    return Ok(policy_key_with_rng(pp, sk, ipk_list, key_proofs, 0, rng))
+}
+
+pub fn set_policy(pp: &issuer::PublicParameters, ipk_list: &Vec<issuer::PublicKey>, key_proofs: &Vec<issuer::KeyProof>) -> Result<PolicyKeyPair, VerifyError>{
+    return set_policy_with_rng(pp, ipk_list, key_proofs, &mut OsRng)
+}
+
+// Adds ipk to the policy under the same key. Only the new t and the proof are recomputed.
+pub fn policy_add_issuer_with_rng<R: RngCore + CryptoRng>(pp: &issuer::PublicParameters, keypair: &PolicyKeyPair, ipk: &issuer::PublicKey, key_proof: &issuer::KeyProof, rng: &mut R) -> Result<(PolicyKeyPair, PolicyUpdate), VerifyError>{
+    let previous = &keypair.public_key;
+    if previous.ipks.contains(ipk) {
+        return Err(VerifyError::MalformedInput("issuer is already in the policy"))
+    }
+    let added = vec![ipk.clone()];
+    let key_proofs = vec![key_proof.clone()];
+    check_key_proofs(pp, &added, &key_proofs)?;
+    let mut ipk_list = previous.ipks.clone();
+    ipk_list.extend(added.clone());
+    let mut all_key_proofs = previous.key_proofs.clone();
+    all_key_proofs.extend(key_proofs.clone());
+    let next = policy_key_with_rng(pp, keypair.secret_key.clone(), &ipk_list, &all_key_proofs, previous.version + 1, rng);
+    let update = PolicyUpdate{
+        version: next.public_key.version,
+        removed: Vec::new(),
+        added: added,
+        key_proofs: key_proofs,
+        s: next.public_key.s,
+        t: next.public_key.t[previous.t.len()..].to_vec(),
+        pi: next.public_key.pi.clone(),
+    };
+    return Ok((next, update))
+}
+
+pub fn policy_add_issuer(pp: &issuer::PublicParameters, keypair: &PolicyKeyPair, ipk: &issuer::PublicKey, key_proof: &issuer::KeyProof) -> Result<(PolicyKeyPair, PolicyUpdate), VerifyError>{
+    return policy_add_issuer_with_rng(pp, keypair, ipk, key_proof, &mut OsRng)
+}
+
+// Removes ipk from the policy under a fresh key, see PolicyUpdate.
+pub fn policy_remove_issuer_with_rng<R: RngCore + CryptoRng>(pp: &issuer::PublicParameters, keypair: &PolicyKeyPair, ipk: &issuer::PublicKey, rng: &mut R) -> Result<(PolicyKeyPair, PolicyUpdate), VerifyError>{
+    let previous = &keypair.public_key;
+    let index = previous.ipks.iter().position(|k| k == ipk).ok_or(VerifyError::MalformedInput("issuer is not in the policy"))?;
+    let mut ipk_list = previous.ipks.clone();
+    ipk_list.remove(index);
+    let mut key_proofs = previous.key_proofs.clone();
+    key_proofs.remove(index);
+    let sk = policy_secret_key_with_rng(rng);
+    let next = policy_key_with_rng(pp, sk, &ipk_list, &key_proofs, previous.version + 1, rng);
+    let update = PolicyUpdate{
+        version: next.public_key.version,
+        removed: vec![index],
+        added: Vec::new(),
+        key_proofs: Vec::new(),
+        s: next.public_key.s,
+        t: next.public_key.t.clone(),
+        pi: next.public_key.pi.clone(),
+    };
+    return Ok((next, update))
+}
+
+pub fn policy_remove_issuer(pp: &issuer::PublicParameters, keypair: &PolicyKeyPair, ipk: &issuer::PublicKey) -> Result<(PolicyKeyPair, PolicyUpdate), VerifyError>{
+    return policy_remove_issuer_with_rng(pp, keypair, ipk, &mut OsRng)
+}
+
+fn check_policy_points(s: &G2Affine, t: &[G2Affine], ipk_list: &[issuer::PublicKey]) -> Result<(), VerifyError>{
+    if !bbs::valid_points(&[*s]) {
+        return Err(VerifyError::MalformedInput("s is the identity or not in G2"))
     }
+    if !bbs::valid_points(t) || !ipk_list.iter().all(|ipk| bbs::valid_points(&[ipk.0])) {
+        return Err(VerifyError::MalformedInput("policy key is the identity or not in G2"))
+    }
+    return Ok(())
+}
+
+fn check_policy_proof(pp: &issuer::PublicParameters, ppk: &PolicyPublicKey) -> Result<(), VerifyError>{
+    let (ipk_list, s,t,c,pi_s,pi_t) = (&ppk.ipks, ppk.s, &ppk.t, ppk.pi.c, ppk.pi.s, ppk.pi.t);
     let mut c_input = vec![s];
     c_input.extend(t.clone());
     let mut u_pro = vec![s * pi_s + pp.g2 * (-c)];
-    for i in 0..ipk_len{
+    for i in 0..ipk_list.len(){
         let ipk = &ipk_list[i];
         u_pro.push((t[i] * pi_s) + (pp.g2 * pi_t) + (ipk.0 * (-c)));
     }
     c_input.extend(G2Projective::normalize_batch(&u_pro));
-    
-    let dst = b"MY_CHALLENGE_GENERATOR_DST_Set_Policy_V1";
-    let mut c_input_buffer = Vec::new();
-    for issuer_pk in ipk_list{
-        issuer_pk.0.serialize_compressed(&mut c_input_buffer).unwrap();
+
+    let c_check = policy_challenge(ipk_list, ppk.version, &c_input);
+    if c != c_check{
+        return Err(VerifyError::ChallengeMismatch)
     }
-    for c_input in &c_input{
-        c_input.serialize_compressed(&mut c_input_buffer).unwrap();
+    return Ok(())
+}
+
+pub fn audit_policy(pp: &issuer::PublicParameters, ppk: &PolicyPublicKey) -> Result<(), VerifyError>{
+    if ppk.ipks.len() != ppk.t.len(){
+        return Err(VerifyError::MalformedInput("issuer list and t vector lengths differ"))
     }
-    let c_check = bbs::hash_to_fr(&c_input_buffer[..], dst);
-    if c != c_check{
-        println!("Policy challenge hash check failed");
-        println!("Computed c: {:?}", c_check);
-        println!("Presented c: {:?}", c);
-        return false
+    check_policy_points(&ppk.s, &ppk.t, &ppk.ipks)?;
+    check_distinct_issuers(&ppk.ipks)?;
+    check_key_proofs(pp, &ppk.ipks, &ppk.key_proofs)?;
+    return check_policy_proof(pp, ppk)
+}
+
+// Checks update against previous, a policy that already passed audit_policy or
+// audit_policy_update, and returns the policy it leads to. Only the added issuers' key proofs
+// are checked, and InvalidKeyProof counts from the first added issuer.
+pub fn audit_policy_update(pp: &issuer::PublicParameters, previous: &PolicyPublicKey, update: &PolicyUpdate) -> Result<PolicyPublicKey, VerifyError>{
+    let expected = previous.version + 1;
+    if update.version != expected {
+        return Err(VerifyError::StalePolicyVersion{ got: update.version, expected: expected })
     }
-    return true
+    proof::check_reveal_index(previous.ipks.len(), &update.removed).map_err(|_| VerifyError::MalformedInput("removed indices out of range or out of order"))?;
+    check_policy_points(&update.s, &update.t, &update.added)?;
+    check_key_proofs(pp, &update.added, &update.key_proofs)?;
+
+    let kept: Vec<usize> = (0..previous.ipks.len()).filter(|i| !update.removed.contains(i)).collect();
+    let mut ipk_list: Vec<issuer::PublicKey> = kept.iter().map(|&i| previous.ipks[i].clone()).collect();
+    ipk_list.extend(update.added.clone());
+    check_distinct_issuers(&ipk_list)?;
+    let mut key_proofs: Vec<issuer::KeyProof> = kept.iter().map(|&i| previous.key_proofs[i].clone()).collect();
+    key_proofs.extend(update.key_proofs.clone());
+    let t = if update.removed.is_empty() {
+        if update.s != previous.s {
+            return Err(VerifyError::MalformedInput("an addition keeps s"))
+        }
+        let mut t = previous.t.clone();
+        t.extend(update.t.clone());
+        t
+    } else {
+        if update.s == previous.s {
+            return Err(VerifyError::MalformedInput("a removal re-keys the policy"))
+        }
+        update.t.clone()
+    };
+    if ipk_list.len() != t.len(){
+        return Err(VerifyError::MalformedInput("issuer list and t vector lengths differ"))
+    }
+    let ppk = PolicyPublicKey{
+        ipks: ipk_list,
+        key_proofs: key_proofs,
+        version: update.version,
+        s: update.s,
+        t: t,
+        pi: update.pi.clone(),
+    };
+    check_policy_proof(pp, &ppk)?;
+    return Ok(ppk)
 }
 
-pub fn present(
+// (credential, issuer key, messages, reveal_index, predicates) of one credential in present_multi.
+pub type MultiItem<'a> = (&'a issuer::Signature, &'a issuer::PublicKey, &'a Vec<Fr>, &'a Vec<usize>, &'a Vec<RangePredicate>);
+
+// A credential of present_many with the policy it is presented against.
+pub type ManyItem<'a> = (MultiItem<'a>, &'a PolicyPublicKey);
+
+// A presentation of verify_present_many with the policy it is checked against.
+pub type ManyPresentation<'a> = (&'a PolicyKeyPair, &'a PiKP, &'a PiZKP);
+
+// The holder's first move, kept until the challenge is known. The randomizers and hidden
+// messages are wiped when it is dropped.
+struct Commitment{
+    pikp: PiKP,
+    challenge_input: Vec<u8>,   // everything hashed into c but the presentation header
+    r: Zeroizing<Fr>,
+    r_1: Zeroizing<Fr>,
+    r_2: Zeroizing<Fr>,
+    e: Fr,
+    alpha: Zeroizing<Fr>,
+    beta: Zeroizing<Fr>,
+    gamma: Zeroizing<Fr>,
+    delta_vec: Zeroizing<Vec<Fr>>,
+    hidden_messages: Zeroizing<Vec<Fr>>,
+    range_proof: RangeProof,
+    range_witness: range::RangeWitness,
+}
+
+// linked[i], when present, is the blinding the i-th hidden message has to take because it
+// is linked to a message of another credential.
+#[allow(clippy::too_many_arguments)]
+fn commit_with_rng<R: RngCore + CryptoRng>(
     pp: &issuer::PublicParameters, 
     cred: &issuer::Signature, 
     ipk: &issuer::PublicKey, 
     message_list: &Vec<Fr>, 
     reveal_index: &Vec<usize>,
-    ppk: &PolicyPublicKey
-) -> (PiKP, PiZKP){
+    predicates: &Vec<RangePredicate>,
+    pseudonym: Option<(usize, &[u8])>,
+    linked: &[Option<Fr>],
+    ppk: &PolicyPublicKey,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<Commitment, ProveError>{
     let message_len = message_list.len();
+    if message_len > pp.h_vec.len() {
+        return Err(ProveError::TooManyMessages { messages: message_len, generators: pp.h_vec.len() });
+    }
+    proof::check_reveal_index(message_len, reveal_index).map_err(ProveError::BadRevealIndex)?;
     let (ipks, s,t_vec) = (ppk.ipks.clone(), ppk.s, ppk.t.clone());
+    if !ipks.contains(ipk) {
+        return Err(ProveError::IssuerNotInPolicy);
+    }
 
-    let mut rng = thread_rng();
     let h_generators : Vec<G1Affine> = pp.h_vec[0..message_len].to_vec();
-    let r = Fr::rand(&mut rng);
+    let r = Zeroizing::new(Fr::rand(rng));
     
     let ipks_len = ipks.len();
-    let mut sigma_tilde_element = s * r;
+    let mut sigma_tilde_element = s * *r;
     for i in 0..ipks_len{
         if ipks[i] != *ipk{
             sigma_tilde_element += t_vec[i];
@@ -202,9 +467,9 @@ pub fn present(
     }
     let sigma_tilde = G2Affine::from(sigma_tilde_element);
 
-    let r_1 = Fr::rand(&mut rng);
-    let r_2 = Fr::rand(&mut rng);
-    let r_2_inv = r_2.inverse().unwrap();
+    let r_1 = Zeroizing::new(Fr::rand(rng));
+    let r_2 = Zeroizing::new(Fr::rand(rng));
+    let r_2_inv = Zeroizing::new(r_2.inverse().unwrap());
 
     let mut d_element = G1Projective::from(pp.g1);
     let mut open_messages: Vec<Fr> = Vec::new();
@@ -217,25 +482,38 @@ pub fn present(
             close_index.push(i);
         }
     }
-    d_element *= r_2_inv;
+    d_element *= *r_2_inv;
     // let d_affine = G1Affine::from(d_element * r_2_inv);
     let close_len = close_index.len();
     // let abar = G1Affine::from(cred.a * (r_1 * r_2_inv));
     // let bbar = G1Affine::from((d_affine * r_1) + (abar * (-cred.e - r)));
-    let abar_pro = cred.a * (r_1 * r_2_inv);
-    let bbar_pro = (d_element * r_1) + (abar_pro * (-cred.e - r));
+    let abar_pro = cred.a * (*r_1 * *r_2_inv);
+    let bbar_pro = (d_element * *r_1) + (abar_pro * (-cred.e - *r));
 
-    let alpha = Fr::rand(&mut rng);
-    let beta = Fr::rand(&mut rng);
-    let gamma = Fr::rand(&mut rng);
-    let delta_vec = (0..close_len).map(|_| Fr::rand(&mut rng)).collect::<Vec<Fr>>();
+    let alpha = Zeroizing::new(Fr::rand(rng));
+    let beta = Zeroizing::new(Fr::rand(rng));
+    let gamma = Zeroizing::new(Fr::rand(rng));
+    let delta_vec = Zeroizing::new((0..close_len).map(|i| linked.get(i).copied().flatten().unwrap_or_else(|| Fr::rand(rng))).collect::<Vec<Fr>>());
 
-    let u1_pro = (d_element * alpha) + (abar_pro * beta);
-    let mut u2_element = d_element * gamma;
+    let u1_pro = (d_element * *alpha) + (abar_pro * *beta);
+    let mut u2_element = d_element * *gamma;
     for i in 0..close_len{
         u2_element += h_generators[close_index[i]] * delta_vec[i];
     }
-    let dst = b"MY_CHALLENGE_GENERATOR_DST_Issuer_Hiding_V1";
+    // the range proofs reuse the blinding of the hidden message they are about
+    let positions = range::predicate_positions(predicates, &close_index).map_err(ProveError::BadRevealIndex)?;
+    let range_messages: Zeroizing<Vec<Fr>> = Zeroizing::new(positions.iter().map(|&k| message_list[close_index[k]]).collect());
+    let range_blindings: Zeroizing<Vec<Fr>> = Zeroizing::new(positions.iter().map(|&k| delta_vec[k]).collect());
+    let (range_proof, range_witness, range_t) = range::range_commit_with_rng(predicates, &range_messages, &range_blindings, rng)?;
+    // so does the pseudonym for the link secret
+    let (pseudonym, pseudonym_t) = match pseudonym{
+        Some((index, scope)) => {
+            let k = close_index.iter().position(|&i| i == index).ok_or(ProveError::BadRevealIndex(index))?;
+            let (pseudonym, t) = pseudonym::pseudonym_commit(index, scope, &message_list[index], &delta_vec[k]);
+            (Some(pseudonym), Some(t))
+        },
+        None => (None, None),
+    };
     let c_inputs1_pro =vec![
         abar_pro,
         bbar_pro,
@@ -250,8 +528,10 @@ pub fn present(
     for c_input in &c_inputs1{
         c_input.serialize_compressed(&mut c_inputs_buffer).unwrap();
     }
-
-    let c = bbs::hash_to_fr(&c_inputs_buffer[..], dst);
+    policy_digest(ppk).serialize_compressed(&mut c_inputs_buffer).unwrap();
+    sigma_tilde.serialize_compressed(&mut c_inputs_buffer).unwrap();
+    range::serialize_challenge_input(predicates, &range_proof, &range_t, &mut c_inputs_buffer);
+    pseudonym::serialize_challenge_input(&pseudonym, &pseudonym_t, &mut c_inputs_buffer);
     let pikp = PiKP{
         a_bar: c_inputs1[0],
         b_bar: c_inputs1[1],
@@ -260,45 +540,333 @@ pub fn present(
         open: reveal_index.clone(),
         len: message_len,
         message_list: open_messages,
+        predicates: predicates.clone(),
+        pseudonym: pseudonym,
+        presentation_header: presentation_header.to_vec(),
     };
+    return Ok(Commitment{
+        pikp: pikp,
+        challenge_input: c_inputs_buffer,
+        r: r,
+        r_1: r_1,
+        r_2: r_2,
+        e: cred.e,
+        alpha: alpha,
+        beta: beta,
+        gamma: gamma,
+        delta_vec: delta_vec,
+        hidden_messages: Zeroizing::new(close_index.iter().map(|&i| message_list[i]).collect()),
+        range_proof: range_proof,
+        range_witness: range_witness,
+    })
+}
 
-    let s = alpha + c * r_1;
-    let t = beta - c * (cred.e + r);
-    let z = gamma + c * r_2;
+fn respond(commitment: Commitment, c: Fr) -> (PiKP, PiZKP){
+    let s = *commitment.alpha + c * *commitment.r_1;
+    let t = *commitment.beta - c * (commitment.e + *commitment.r);
+    let z = *commitment.gamma + c * *commitment.r_2;
     let mut v_vec: Vec<Fr> = Vec::new();
-    for i in 0..close_len{
-        let v1 = delta_vec[i] - c * message_list[close_index[i]];
+    for i in 0..commitment.delta_vec.len(){
+        let v1 = commitment.delta_vec[i] - c * commitment.hidden_messages[i];
         v_vec.push(v1);
     }
+    let mut range_proof = commitment.range_proof;
+    range::range_respond(&mut range_proof, &commitment.range_witness, &(-c));
     let pizkp = PiZKP{
         c: c,
         s: s,
         t: t,
         z: z,
         v: v_vec,
+        range: range_proof,
     };
-    return (pikp, pizkp)
+    return (commitment.pikp, pizkp)
 }
 
-pub fn verify_present(
+#[allow(clippy::too_many_arguments)]
+fn present_single_with_rng<R: RngCore + CryptoRng>(
     pp: &issuer::PublicParameters, 
-    keypair: &PolicyKeyPair, 
+    cred: &issuer::Signature, 
+    ipk: &issuer::PublicKey, 
+    message_list: &Vec<Fr>, 
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    pseudonym: Option<(usize, &[u8])>,
+    ppk: &PolicyPublicKey,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    let commitment = commit_with_rng(pp, cred, ipk, message_list, reveal_index, predicates, pseudonym, &[], ppk, presentation_header, rng)?;
+    let dst = b"MY_CHALLENGE_GENERATOR_DST_Issuer_Hiding_V1";
+    let mut c_inputs_buffer = commitment.challenge_input.clone();
+    presentation_header.to_vec().serialize_compressed(&mut c_inputs_buffer).unwrap();
+
+    let c = bbs::hash_to_fr(&c_inputs_buffer[..], dst);
+    return Ok(respond(commitment, c))
+}
+
+// present that also shows min <= m_i < max for every predicate on a hidden message m_i.
+#[allow(clippy::too_many_arguments)]
+pub fn present_with_predicates_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    ppk: &PolicyPublicKey,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_single_with_rng(pp, cred, ipk, message_list, reveal_index, predicates, None, ppk, presentation_header, rng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn present_with_predicates(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    ppk: &PolicyPublicKey,
+    presentation_header: &[u8],
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_with_predicates_with_rng(pp, cred, ipk, message_list, reveal_index, predicates, ppk, presentation_header, &mut OsRng)
+}
+
+// present_with_predicates that also shows the pseudonym H_G1(scope)^m for the hidden message
+// m at index link_secret.
+#[allow(clippy::too_many_arguments)]
+pub fn present_with_pseudonym_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    link_secret: usize,
+    scope: &[u8],
+    ppk: &PolicyPublicKey,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_single_with_rng(pp, cred, ipk, message_list, reveal_index, predicates, Some((link_secret, scope)), ppk, presentation_header, rng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn present_with_pseudonym(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    link_secret: usize,
+    scope: &[u8],
+    ppk: &PolicyPublicKey,
+    presentation_header: &[u8],
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_with_pseudonym_with_rng(pp, cred, ipk, message_list, reveal_index, predicates, link_secret, scope, ppk, presentation_header, &mut OsRng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn present_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    ppk: &PolicyPublicKey,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_with_predicates_with_rng(pp, cred, ipk, message_list, reveal_index, &Vec::new(), ppk, presentation_header, rng)
+}
+
+pub fn present(
+    pp: &issuer::PublicParameters, 
+    cred: &issuer::Signature, 
+    ipk: &issuer::PublicKey, 
+    message_list: &Vec<Fr>, 
+    reveal_index: &Vec<usize>,
+    ppk: &PolicyPublicKey,
+    presentation_header: &[u8],
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_with_rng(pp, cred, ipk, message_list, reveal_index, ppk, presentation_header, &mut OsRng)
+}
+
+// issue for a holder that sent binding for its secret key, which is signed as the message at
+// holder_index without the issuer learning it. messages are the other messages, in order.
+#[allow(clippy::too_many_arguments)]
+pub fn issue_bound_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    isk: &bbs::SecretKey,
+    binding: &KeyBinding,
+    holder_index: usize,
+    messages: &Vec<Fr>,
+    nonce: &[u8],
+    rng: &mut R,
+) -> Result<issuer::Signature, ProveError>{
+    let signature = holder::sign_bound_with_rng(pp, isk, binding, holder_index, messages, nonce, rng)?;
+    return Ok(signature)
+}
+
+pub fn issue_bound(
+    pp: &issuer::PublicParameters,
+    isk: &bbs::SecretKey,
+    binding: &KeyBinding,
+    holder_index: usize,
+    messages: &Vec<Fr>,
+    nonce: &[u8],
+) -> Result<issuer::Signature, ProveError>{
+    return issue_bound_with_rng(pp, isk, binding, holder_index, messages, nonce, &mut OsRng)
+}
+
+// present a credential bound to a holder key. message_list are the messages without the
+// holder secret, which goes in at holder_index; reveal_index counts it.
+#[allow(clippy::too_many_arguments)]
+pub fn present_bound_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    holder_secret: &HolderSecretKey,
+    holder_index: usize,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    ppk: &PolicyPublicKey,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    if reveal_index.contains(&holder_index) {
+        return Err(ProveError::BadRevealIndex(holder_index));
+    }
+    let messages = holder::with_holder_secret(message_list, holder_index, holder_secret)?;
+    return present_with_rng(pp, cred, ipk, &messages, reveal_index, ppk, presentation_header, rng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn present_bound(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    holder_secret: &HolderSecretKey,
+    holder_index: usize,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    ppk: &PolicyPublicKey,
+    presentation_header: &[u8],
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_bound_with_rng(pp, cred, ipk, holder_secret, holder_index, message_list, reveal_index, ppk, presentation_header, &mut OsRng)
+}
+
+// issue with the messages given as values of a credential schema, in schema order.
+pub fn issue_attributes_with_rng<R: RngCore + CryptoRng>(pp: &issuer::PublicParameters, isk: &bbs::SecretKey, schema: &CredentialSchema, values: &Vec<AttributeValue>, rng: &mut R) -> Result<issuer::Signature, ProveError>{
+    let messages = schema::encode_messages(schema, values)?;
+    return issue_with_rng(pp, isk, &messages, rng)
+}
+
+pub fn issue_attributes(pp: &issuer::PublicParameters, isk: &bbs::SecretKey, schema: &CredentialSchema, values: &Vec<AttributeValue>) -> Result<issuer::Signature, ProveError>{
+    return issue_attributes_with_rng(pp, isk, schema, values, &mut OsRng)
+}
+
+// present with schema-typed values, revealing the attributes named in reveal.
+#[allow(clippy::too_many_arguments)]
+pub fn present_attributes_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    schema: &CredentialSchema,
+    values: &Vec<AttributeValue>,
+    reveal: &[&str],
+    ppk: &PolicyPublicKey,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    let message_list = schema::encode_messages(schema, values)?;
+    let reveal_index = schema::reveal_index(schema, reveal)?;
+    return present_with_rng(pp, cred, ipk, &message_list, &reveal_index, ppk, presentation_header, rng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn present_attributes(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    schema: &CredentialSchema,
+    values: &Vec<AttributeValue>,
+    reveal: &[&str],
+    ppk: &PolicyPublicKey,
+    presentation_header: &[u8],
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_attributes_with_rng(pp, cred, ipk, schema, values, reveal, ppk, presentation_header, &mut OsRng)
+}
+
+// Binds a presentation to the exact policy it was made for: every field of ppk, its version
+// included, goes into the digest, and the digest into the challenge.
+pub fn policy_digest(ppk: &PolicyPublicKey) -> Fr{
+    let dst = b"MY_POLICY_DIGEST_DST_Issuer_Hiding_V1";
+    let mut buffer = Vec::new();
+    ppk.serialize_compressed(&mut buffer).unwrap();
+    return bbs::hash_to_fr(&buffer[..], dst)
+}
+
+// g2^{(n-1)b} * \prod_{i=1}^{n} ipk_i, the part of the pairing's right argument that only
+// depends on the policy, so verify_batch computes it once for all presentations.
+fn policy_base(pp: &issuer::PublicParameters, keypair: &PolicyKeyPair) -> G2Projective{
+    let ipks_num = keypair.public_key.ipks.len();
+    let mut base = pp.g2 * (Fr::from((ipks_num - 1) as u64) * keypair.secret_key.b);
+    for i in 0..ipks_num{
+        base += keypair.public_key.ipks[i].0;
+    }
+    return base
+}
+
+fn check_points(pikp: &PiKP) -> Result<(), VerifyError>{
+    if !bbs::valid_points(&[pikp.a_bar, pikp.b_bar, pikp.d]) {
+        return Err(VerifyError::MalformedInput("A_bar, B_bar or D is the identity or not in G1"));
+    }
+    if !bbs::valid_points(&[pikp.sigma_tilde]) {
+        return Err(VerifyError::MalformedInput("sigma_tilde is the identity or not in G2"));
+    }
+    return Ok(())
+}
+
+// Everything verify_present checks except the pairing: the challenge input the responses of
+// a presentation imply, but the presentation header. Needs no policy secret.
+fn challenge_input(
+    pp: &issuer::PublicParameters, 
+    digest: &Fr,
     pikp: &PiKP, 
-    pizkp: &PiZKP
-) -> bool{
+    pizkp: &PiZKP,
+) -> Result<Vec<u8>, VerifyError>{
     let message_len = pikp.len;
-    let  ipks_num = keypair.public_key.ipks.len();
+    if message_len > pp.h_vec.len() {
+        return Err(VerifyError::MalformedInput("message length exceeds generators"));
+    }
+    proof::check_reveal_index(message_len, &pikp.open).map_err(VerifyError::BadRevealIndex)?;
+    if pikp.message_list.len() != pikp.open.len() {
+        return Err(VerifyError::MalformedInput("revealed messages do not match reveal indices"));
+    }
+    check_points(pikp)?;
 
     let h_generators : Vec<G1Affine> = pp.h_vec[0..message_len].to_vec();
-    let mut close_index: Vec<usize> = Vec::new();
-    for i in 0..message_len{
-        if !pikp.open.contains(&i){
-            close_index.push(i);
-        }
-    }
+    let close_index = proof::hidden_index(message_len, &pikp.open);
     let close_len = close_index.len();
+    if pizkp.v.len() != close_len {
+        return Err(VerifyError::MalformedInput("response count does not match hidden messages"));
+    }
+    let positions = range::predicate_positions(&pikp.predicates, &close_index).map_err(VerifyError::BadRevealIndex)?;
+    let range_responses: Vec<Fr> = positions.iter().map(|&k| pizkp.v[k]).collect();
+    let range_t = range::range_verify(&pikp.predicates, &pizkp.range, &range_responses, &(-pizkp.c))?;
+    let pseudonym_t = match &pikp.pseudonym{
+        Some(pseudonym) => {
+            let k = close_index.iter().position(|&i| i == pseudonym.index).ok_or(VerifyError::BadRevealIndex(pseudonym.index))?;
+            Some(pseudonym::pseudonym_verify(pseudonym, &pizkp.v[k], &(-pizkp.c))?)
+        },
+        None => None,
+    };
 
-    let dst = b"MY_CHALLENGE_GENERATOR_DST_Issuer_Hiding_V1";
     let mut u2_element = pikp.d * pizkp.z + pp.g1 * (-pizkp.c);
     for i in 0..pikp.open.len(){
         let h_i = h_generators[pikp.open[i]];
@@ -325,33 +893,385 @@ pub fn verify_present(
     for u_i in &u{
         u_i.serialize_compressed(&mut c_inputs_buffer).unwrap();
     }
+    digest.serialize_compressed(&mut c_inputs_buffer).unwrap();
+    pikp.sigma_tilde.serialize_compressed(&mut c_inputs_buffer).unwrap();
+    range::serialize_challenge_input(&pikp.predicates, &pizkp.range, &range_t, &mut c_inputs_buffer);
+    pseudonym::serialize_challenge_input(&pikp.pseudonym, &pseudonym_t, &mut c_inputs_buffer);
+    return Ok(c_inputs_buffer);
+}
+
+// e(A_bar, sigma_tilde^{-1/a} * base) = e(B_bar, g2), the one check that needs the policy
+// secret a.
+fn policy_equation(
+    pp: &issuer::PublicParameters, 
+    a_inv: &Fr,
+    base: &G2Projective,
+    pikp: &PiKP, 
+) -> bbs::PairingEquation{
+    let pairing_right = pikp.sigma_tilde * (-*a_inv) + base;
+
+    return vec![
+        (G1Projective::from(pikp.a_bar), G2Affine::from(pairing_right)),
+        (-G1Projective::from(pikp.b_bar), pp.g2),
+    ]
+}
+
+#[allow(clippy::too_many_arguments)]
+fn present_equation(
+    pp: &issuer::PublicParameters, 
+    a_inv: &Fr,
+    base: &G2Projective,
+    digest: &Fr,
+    pikp: &PiKP, 
+    pizkp: &PiZKP,
+    presentation_header: &[u8],
+) -> Result<bbs::PairingEquation, VerifyError>{
+    check_transcript(pp, digest, pikp, pizkp, presentation_header)?;
+    return Ok(policy_equation(pp, a_inv, base, pikp));
+}
+
+fn check_transcript(
+    pp: &issuer::PublicParameters, 
+    digest: &Fr,
+    pikp: &PiKP, 
+    pizkp: &PiZKP,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    if pikp.presentation_header != presentation_header {
+        return Err(VerifyError::PresentationHeaderMismatch);
+    }
+    let dst = b"MY_CHALLENGE_GENERATOR_DST_Issuer_Hiding_V1";
+    let mut c_inputs_buffer = challenge_input(pp, digest, pikp, pizkp)?;
+    pikp.presentation_header.serialize_compressed(&mut c_inputs_buffer).unwrap();
     let c = bbs::hash_to_fr(&c_inputs_buffer[..], dst);
 
     if c != pizkp.c{
-        println!("Challenge hash check failed");
-        println!("Computed c: {:?}", c);
-        println!("Presented c: {:?}", pizkp.c);
-        return false
+        return Err(VerifyError::ChallengeMismatch)
     }
-    let a_inv = keypair.secret_key.a.inverse().unwrap();
-    let mut pairing_right = pikp.sigma_tilde * (-a_inv) + pp.g2 * (Fr::from((ipks_num - 1) as u64) * keypair.secret_key.b);
-    for i in 0..ipks_num{
-        pairing_right += keypair.public_key.ipks[i].0;
+    return Ok(());
+}
+
+// The public half of verify_present: the presentation is well formed, bound to ppk and
+// presentation_header, and its proof of knowledge, range proofs and pseudonym verify. It
+// does not show that the credential comes from an issuer of the policy, which is what
+// verify_present_designated checks.
+//
+// That half cannot be made public. It needs sigma_tilde^{1/a}, and the only public
+// equivalent, g2^{ab}, would let anyone holding s = g2^a sign a credential under a key x of
+// their own and set sigma_tilde = (base^a) * s^{r - x}, which passes the pairing for any
+// policy. So a third party can audit the proof on its own, but whether the issuer was on the
+// policy stays the policy owner's word.
+pub fn verify_present_public(
+    pp: &issuer::PublicParameters, 
+    ppk: &PolicyPublicKey,
+    pikp: &PiKP, 
+    pizkp: &PiZKP,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    return check_transcript(pp, &policy_digest(ppk), pikp, pizkp, presentation_header)
+}
+
+// The designated-verifier half of verify_present: the pairing that ties the presentation to
+// an issuer of the policy. Only sound together with verify_present_public on the same
+// presentation, and only the holder of the policy secret key can run it.
+pub fn verify_present_designated(
+    pp: &issuer::PublicParameters, 
+    keypair: &PolicyKeyPair, 
+    pikp: &PiKP, 
+) -> Result<(), VerifyError>{
+    if keypair.public_key.ipks.is_empty() {
+        return Err(VerifyError::MalformedInput("policy has no issuers"));
+    }
+    check_points(pikp)?;
+    let base = policy_base(pp, keypair);
+    let a_inv = Zeroizing::new(keypair.secret_key.a.inverse().unwrap());
+    if !bbs::pairing_check(&policy_equation(pp, &a_inv, &base, pikp)) {
+        return Err(VerifyError::PairingCheckFailed(1))
+    }
+    return Ok(());
+}
+
+pub fn verify_present(
+    pp: &issuer::PublicParameters, 
+    keypair: &PolicyKeyPair, 
+    pikp: &PiKP, 
+    pizkp: &PiZKP,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    if keypair.public_key.ipks.is_empty() {
+        return Err(VerifyError::MalformedInput("policy has no issuers"));
+    }
+    let base = policy_base(pp, keypair);
+    let a_inv = Zeroizing::new(keypair.secret_key.a.inverse().unwrap());
+    let digest = policy_digest(&keypair.public_key);
+    let equation = present_equation(pp, &a_inv, &base, &digest, pikp, pizkp, presentation_header)?;
+    if !bbs::pairing_check(&equation) {
+        return Err(VerifyError::PairingCheckFailed(1))
+    }
+    return Ok(());
+}
+
+// verify_present for a presentation that has to carry a pseudonym for scope derived from the
+// message at index link_secret. Returns the pseudonym.
+pub fn verify_present_with_pseudonym(
+    pp: &issuer::PublicParameters,
+    keypair: &PolicyKeyPair,
+    pikp: &PiKP,
+    pizkp: &PiZKP,
+    link_secret: usize,
+    scope: &[u8],
+    presentation_header: &[u8],
+) -> Result<G1Affine, VerifyError>{
+    let nym = pseudonym::expect_pseudonym(&pikp.pseudonym, link_secret, scope)?;
+    verify_present(pp, keypair, pikp, pizkp, presentation_header)?;
+    return Ok(nym)
+}
+
+// verify_present for a presentation of a credential bound to a holder key at holder_index.
+pub fn verify_present_bound(
+    pp: &issuer::PublicParameters,
+    keypair: &PolicyKeyPair,
+    pikp: &PiKP,
+    pizkp: &PiZKP,
+    holder_index: usize,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    holder::check_bound(pikp.len, &pikp.open, holder_index)?;
+    return verify_present(pp, keypair, pikp, pizkp, presentation_header)
+}
+
+// Everything of a policy that verify_present recomputes per presentation: a^{-1}, the
+// issuer-key aggregate g2^{(n-1)b} * \prod ipk_i, the policy digest and g2 prepared for the
+// pairing. Built once with verifier_context, it verifies in time independent of the number
+// of issuers. Debug only shows the policy version, and a^{-1} is wiped on drop.
+#[derive(Clone)]
+pub struct PreparedPolicyVerifier{
+    pub pp: issuer::PublicParameters,
+    pub version: u64,
+    pub base: G2Projective,
+    pub digest: Fr,
+    pub g2_prepared: bbs::G2Prepared,
+    a_inv: Zeroizing<Fr>,
+}
+
+// The name the other schemes give their prepared verifier.
+pub type VerifierContext = PreparedPolicyVerifier;
+
+impl Debug for PreparedPolicyVerifier{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        return f.debug_struct("PreparedPolicyVerifier")
+            .field("version", &self.version)
+            .finish_non_exhaustive()
+    }
+}
+
+pub fn verifier_context(pp: &issuer::PublicParameters, keypair: &PolicyKeyPair) -> Result<PreparedPolicyVerifier, VerifyError>{
+    if keypair.public_key.ipks.is_empty() {
+        return Err(VerifyError::MalformedInput("policy has no issuers"));
+    }
+    let context = PreparedPolicyVerifier{
+        pp: pp.clone(),
+        version: keypair.public_key.version,
+        base: policy_base(pp, keypair),
+        digest: policy_digest(&keypair.public_key),
+        g2_prepared: bbs::G2Prepared::from(pp.g2),
+        a_inv: Zeroizing::new(keypair.secret_key.a.inverse().unwrap()),
+    };
+    return Ok(context)
+}
+
+pub fn verify_present_with_context(
+    context: &PreparedPolicyVerifier,
+    pikp: &PiKP, 
+    pizkp: &PiZKP,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    let equation = present_equation(&context.pp, &context.a_inv, &context.base, &context.digest, pikp, pizkp, presentation_header)?;
+    let right_prepared = bbs::G2Prepared::from(equation[0].1);
+    let pairs = [
+        (equation[0].0, &right_prepared),
+        (equation[1].0, &context.g2_prepared),
+    ];
+    if !bbs::prepared_pairing_check(&pairs) {
+        return Err(VerifyError::PairingCheckFailed(1))
+    }
+    return Ok(());
+}
+
+// Verifies many (pikp, pizkp, presentation_header) presentations under the same policy with
+// one final exponentiation and returns the index and error of every one that does not verify.
+pub fn verify_batch(
+    pp: &issuer::PublicParameters, 
+    keypair: &PolicyKeyPair, 
+    items: &[(&PiKP, &PiZKP, &[u8])],
+) -> Result<(), Vec<(usize, VerifyError)>>{
+    if keypair.public_key.ipks.is_empty() {
+        return Err((0..items.len()).map(|j| (j, VerifyError::MalformedInput("policy has no issuers"))).collect());
+    }
+    let base = policy_base(pp, keypair);
+    let a_inv = Zeroizing::new(keypair.secret_key.a.inverse().unwrap());
+    let digest = policy_digest(&keypair.public_key);
+    let equations = items.iter()
+        .map(|(pikp, pizkp, presentation_header)| present_equation(pp, &a_inv, &base, &digest, pikp, pizkp, presentation_header).map(|eq| vec![eq]))
+        .collect();
+    return bbs::verify_batch_equations(equations, VerifyError::PairingCheckFailed)
+}
+
+// Presents several credentials, each against its own policy, under a single challenge. Each
+// equality asserts that two hidden messages, named by (item, message index), are equal without
+// revealing them.
+pub fn present_many_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    items: &[ManyItem],
+    equalities: &Vec<Equality>,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<Vec<(PiKP, PiZKP)>, ProveError>{
+    let messages: Vec<&Vec<Fr>> = items.iter().map(|(item, _)| item.2).collect();
+    let hidden: Vec<Vec<usize>> = items.iter().map(|(item, _)| proof::hidden_index(item.2.len(), item.3)).collect();
+    let linked = equality::linked_blindings_with_rng(equalities, &messages, &hidden, rng)?;
+    let mut commitments = Vec::new();
+    for j in 0..items.len(){
+        let ((cred, ipk, message_list, reveal_index, predicates), ppk) = items[j];
+        commitments.push(commit_with_rng(pp, cred, ipk, message_list, reveal_index, predicates, None, &linked[j], ppk, presentation_header, rng)?);
     }
 
-    if Bls12_381::pairing(pikp.a_bar, pairing_right) != Bls12_381::pairing(pikp.b_bar, pp.g2) {
-        println!("Pairing check 1 failed");
-        return false
+    let dst = b"MY_MULTI_CHALLENGE_DST_Issuer_Hiding_V1";
+    let mut c_inputs_buffer = Vec::new();
+    for commitment in &commitments{
+        commitment.challenge_input.serialize_compressed(&mut c_inputs_buffer).unwrap();
+    }
+    equality::serialize_challenge_input(equalities, &mut c_inputs_buffer);
+    presentation_header.to_vec().serialize_compressed(&mut c_inputs_buffer).unwrap();
+    let c = bbs::hash_to_fr(&c_inputs_buffer[..], dst);
+    return Ok(commitments.into_iter().map(|commitment| respond(commitment, c)).collect())
+}
+
+pub fn present_many(
+    pp: &issuer::PublicParameters,
+    items: &[ManyItem],
+    equalities: &Vec<Equality>,
+    presentation_header: &[u8],
+) -> Result<Vec<(PiKP, PiZKP)>, ProveError>{
+    return present_many_with_rng(pp, items, equalities, presentation_header, &mut OsRng)
+}
+
+// present_many with every credential against the same policy.
+pub fn present_multi_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    items: &[MultiItem],
+    equalities: &Vec<Equality>,
+    ppk: &PolicyPublicKey,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<Vec<(PiKP, PiZKP)>, ProveError>{
+    let items: Vec<ManyItem> = items.iter().map(|&item| (item, ppk)).collect();
+    return present_many_with_rng(pp, &items, equalities, presentation_header, rng)
+}
+
+pub fn present_multi(
+    pp: &issuer::PublicParameters,
+    items: &[MultiItem],
+    equalities: &Vec<Equality>,
+    ppk: &PolicyPublicKey,
+    presentation_header: &[u8],
+) -> Result<Vec<(PiKP, PiZKP)>, ProveError>{
+    return present_multi_with_rng(pp, items, equalities, ppk, presentation_header, &mut OsRng)
+}
+
+// Verifies the output of present_many, each presentation against its policy. A failing
+// pairing is reported as PairingCheckFailed(j + 1) for the j-th presentation.
+pub fn verify_present_many(
+    pp: &issuer::PublicParameters,
+    items: &[ManyPresentation],
+    equalities: &Vec<Equality>,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    if items.is_empty() {
+        return Err(VerifyError::MalformedInput("no presentations"));
     }
-    return true;
+    let dst = b"MY_MULTI_CHALLENGE_DST_Issuer_Hiding_V1";
+    let c = items[0].2.c;
+    let mut c_inputs_buffer = Vec::new();
+    let mut equations = Vec::new();
+    // policy_base and policy_digest are the costly part, so consecutive items of one policy share them
+    let mut base: Option<(&PolicyKeyPair, G2Projective, Zeroizing<Fr>, Fr)> = None;
+    for &(keypair, pikp, pizkp) in items{
+        if keypair.public_key.ipks.is_empty() {
+            return Err(VerifyError::MalformedInput("policy has no issuers"));
+        }
+        if pikp.presentation_header != presentation_header {
+            return Err(VerifyError::PresentationHeaderMismatch);
+        }
+        if pizkp.c != c {
+            return Err(VerifyError::ChallengeMismatch);
+        }
+        if !matches!(base, Some((last, ..)) if std::ptr::eq(last, keypair)) {
+            base = Some((keypair, policy_base(pp, keypair), Zeroizing::new(keypair.secret_key.a.inverse().unwrap()), policy_digest(&keypair.public_key)));
+        }
+        let (_, policy, a_inv, digest) = base.as_ref().unwrap();
+        let input = challenge_input(pp, digest, pikp, pizkp)?;
+        input.serialize_compressed(&mut c_inputs_buffer).unwrap();
+        equations.push(policy_equation(pp, a_inv, policy, pikp));
+    }
+    let hidden: Vec<Vec<usize>> = items.iter().map(|(_, pikp, _)| proof::hidden_index(pikp.len, &pikp.open)).collect();
+    let responses: Vec<&Vec<Fr>> = items.iter().map(|(_, _, pizkp)| &pizkp.v).collect();
+    equality::check_linked_responses(equalities, &hidden, &responses)?;
+    equality::serialize_challenge_input(equalities, &mut c_inputs_buffer);
+    presentation_header.to_vec().serialize_compressed(&mut c_inputs_buffer).unwrap();
+    if bbs::hash_to_fr(&c_inputs_buffer[..], dst) != c {
+        return Err(VerifyError::ChallengeMismatch);
+    }
+
+    if !bbs::batch_pairing_check(&equations) {
+        let j = equations.iter().position(|eq| !bbs::pairing_check(eq)).unwrap_or(0);
+        return Err(VerifyError::PairingCheckFailed(j + 1));
+    }
+    return Ok(())
+}
+
+// Verifies the output of present_multi.
+pub fn verify_present_multi(
+    pp: &issuer::PublicParameters,
+    keypair: &PolicyKeyPair,
+    items: &[(&PiKP, &PiZKP)],
+    equalities: &Vec<Equality>,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    let items: Vec<ManyPresentation> = items.iter().map(|&(pikp, pizkp)| (keypair, pikp, pizkp)).collect();
+    return verify_present_many(pp, &items, equalities, presentation_header)
 }
 
 #[cfg(test)]
 mod tests {
-    use rand::Rng;
+    use ark_ec::AffineRepr;
+    use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
+
+    use mybbs::error::SchemaError;
 
     use super::*;
 
+    // Three issuers with key proofs and a credential on 5 messages from issuers[signer].
+    struct Issuers{
+        pp: issuer::PublicParameters,
+        messages: Vec<Fr>,
+        ipks: Vec<issuer::PublicKey>,
+        key_proofs: Vec<issuer::KeyProof>,
+        signature: issuer::Signature,
+    }
+
+    fn three_issuers(signer: usize) -> Issuers{
+        let message_len = 5;
+        let mut rng = thread_rng();
+        let messages: Vec<Fr> = (0..message_len).map(|_| Fr::rand(&mut rng)).collect();
+        let pp = par_gen(message_len);
+        let issuers: Vec<issuer::KeyPair> = (0..3).map(|_| issuer_key_gen(&pp)).collect();
+        let key_proofs = issuers.iter().map(|keypair| prove_issuer_key(&pp, keypair)).collect();
+        let ipks = issuers.iter().map(|keypair| keypair.public_key.clone()).collect();
+        let signature = issue(&pp, &issuers[signer].secret_key, &messages).unwrap();
+        return Issuers{ pp, messages, ipks, key_proofs, signature }
+    }
+
     #[test]
     fn test_issuer_hiding() {
         let message_len = 10;
@@ -359,14 +1279,14 @@ mod tests {
         let mut rng = thread_rng();
         let messages: Vec<Fr> = (0..message_len).map(|_| Fr::rand(&mut rng)).collect();
 
-        let pp = par_gen();
+        let pp = par_gen(message_len);
         let issuer_keypair = issuer_key_gen(&pp);
         let issuer_pk = &issuer_keypair.public_key;
 
-        let signature = issue(&pp, &issuer_keypair.secret_key, &messages);
+        let signature = issue(&pp, &issuer_keypair.secret_key, &messages).unwrap();
 
-        let is_valid_cred = verify(&pp, &issuer_pk, &messages, &signature);
-        assert_eq!(is_valid_cred, true);
+        let is_valid_cred = verify(&pp, issuer_pk, &messages, &signature);
+        assert_eq!(is_valid_cred, Ok(()));
 
         let mut issuer_keypairs: Vec<issuer::KeyPair> = Vec::new();
         let mut issuer_pubkeys: Vec<issuer::PublicKey> = Vec::new();
@@ -381,16 +1301,320 @@ mod tests {
         issuer_keypairs[r] = issuer_keypair.clone();
         issuer_pubkeys[r] = issuer_pk.clone();
 
-        let policy_key_pair = set_policy(&pp, &issuer_pubkeys);
+        let key_proofs: Vec<issuer::KeyProof> = issuer_keypairs.iter().map(|keypair| prove_issuer_key(&pp, keypair)).collect();
+        let policy_key_pair = set_policy(&pp, &issuer_pubkeys, &key_proofs).unwrap();
         let policy_pk = &policy_key_pair.public_key;
 
-        let is_valid_list = audit_policy(&pp, &policy_pk);
-        assert_eq!(is_valid_list, true);
+        let is_valid_list = audit_policy(&pp, policy_pk);
+        assert_eq!(is_valid_list, Ok(()));
 
         let reveal_index = vec![0, 3, 5, 6];
-        let (pikp, pizkp) = present(&pp, &signature, &issuer_pk, &messages, &reveal_index, &policy_pk);
+        let nonce = b"verifier-nonce-1";
+        let (pikp, pizkp) = present(&pp, &signature, issuer_pk, &messages, &reveal_index, policy_pk, nonce).unwrap();
+
+        let is_valid_present = verify_present(&pp, &policy_key_pair, &pikp, &pizkp, nonce);
+        assert_eq!(is_valid_present, Ok(()));
+
+        let context = verifier_context(&pp, &policy_key_pair).unwrap();
+        assert_eq!(verify_present_with_context(&context, &pikp, &pizkp, nonce), Ok(()));
+        assert_eq!(format!("{:?}", context), "PreparedPolicyVerifier { version: 0, .. }");
+
+        let seeded = set_policy_with_rng(&pp, &issuer_pubkeys, &key_proofs, &mut StdRng::seed_from_u64(7));
+        assert_eq!(set_policy_with_rng(&pp, &issuer_pubkeys, &key_proofs, &mut StdRng::seed_from_u64(7)), seeded);
+
+        // a key chosen as pk - \sum_{j != i} ipk_j cancels the others out, but its owner cannot prove possession
+        let mut rogue_keys = issuer_pubkeys.clone();
+        rogue_keys[0] = issuer::PublicKey((issuer_keypair.public_key.0 - issuer_pubkeys[1..].iter().map(|ipk| ipk.0).sum::<G2Projective>()).into());
+        assert_eq!(set_policy(&pp, &rogue_keys, &key_proofs), Err(VerifyError::InvalidKeyProof(0)));
+        assert_eq!(set_policy(&pp, &issuer_pubkeys, &key_proofs[1..].to_vec()), Err(VerifyError::MalformedInput("one key proof per issuer")));
+        let mut swapped = policy_pk.clone();
+        swapped.key_proofs.swap(0, 1);
+        assert_eq!(audit_policy(&pp, &swapped), Err(VerifyError::InvalidKeyProof(0)));
+
+        let mut tampered = pikp.clone();
+        tampered.message_list[0] += Fr::from(1u64);
+        assert_eq!(verify_present(&pp, &policy_key_pair, &tampered, &pizkp, nonce), Err(VerifyError::ChallengeMismatch));
+
+        assert_eq!(verify_present(&pp, &policy_key_pair, &pikp, &pizkp, b"verifier-nonce-2"), Err(VerifyError::PresentationHeaderMismatch));
+        let mut replayed = pikp.clone();
+        replayed.presentation_header = b"verifier-nonce-2".to_vec();
+        assert_eq!(verify_present(&pp, &policy_key_pair, &replayed, &pizkp, b"verifier-nonce-2"), Err(VerifyError::ChallengeMismatch));
+
+        let (other_pikp, other_pizkp) = present(&pp, &signature, issuer_pk, &messages, &reveal_index, policy_pk, b"").unwrap();
+        let mut forged = other_pikp.clone();
+        forged.sigma_tilde = pikp.sigma_tilde;
+        let batch: Vec<(&PiKP, &PiZKP, &[u8])> = vec![
+            (&pikp, &pizkp, nonce),
+            (&other_pikp, &other_pizkp, b""),
+            (&tampered, &pizkp, nonce),
+            (&forged, &other_pizkp, b""),
+        ];
+        assert_eq!(verify_batch(&pp, &policy_key_pair, &batch[0..2]), Ok(()));
+        assert_eq!(verify_batch(&pp, &policy_key_pair, &batch), Err(vec![(2, VerifyError::ChallengeMismatch), (3, VerifyError::ChallengeMismatch)]));
+
+        let schema = CredentialSchema{
+            id: "employee".to_string(),
+            attributes: vec![
+                schema::Attribute{ name: "name".to_string(), attribute_type: schema::AttributeType::Utf8String },
+                schema::Attribute{ name: "age".to_string(), attribute_type: schema::AttributeType::Integer },
+            ],
+        };
+        let values = vec![AttributeValue::Utf8String("Alice".to_string()), AttributeValue::Integer(30)];
+        let typed = issue_attributes(&pp, &issuer_keypair.secret_key, &schema, &values).unwrap();
+        let typed_pt = present_attributes(&pp, &typed, issuer_pk, &schema, &values, &["age"], policy_pk, nonce).unwrap();
+        assert_eq!(verify_present(&pp, &policy_key_pair, &typed_pt.0, &typed_pt.1, nonce), Ok(()));
+        assert_eq!(typed_pt.0.message_list, vec![schema::encode_value(&values[1]).unwrap()]);
+        assert_eq!(present_attributes(&pp, &typed, issuer_pk, &schema, &values, &["salary"], policy_pk, nonce).unwrap_err(), ProveError::Schema(SchemaError::UnknownAttribute(0)));
+
+        let typed_messages = schema::encode_messages(&schema, &values).unwrap();
+        let adult = vec![range::integer_range(1, 18, i64::MAX)];
+        let adult_pt = present_with_predicates(&pp, &typed, issuer_pk, &typed_messages, &vec![0], &adult, policy_pk, nonce).unwrap();
+        assert_eq!(verify_present(&pp, &policy_key_pair, &adult_pt.0, &adult_pt.1, nonce), Ok(()));
+        let mut widened = adult_pt.clone();
+        widened.0.predicates[0].min = 0;
+        assert_eq!(verify_present(&pp, &policy_key_pair, &widened.0, &widened.1, nonce), Err(VerifyError::ChallengeMismatch));
+        let senior = vec![range::integer_range(1, 65, i64::MAX)];
+        assert_eq!(present_with_predicates(&pp, &typed, issuer_pk, &typed_messages, &vec![0], &senior, policy_pk, nonce).unwrap_err(), ProveError::Credential(mybbs::error::ProveError::PredicateNotSatisfied(0)));
+
+        // a second credential from another issuer in the policy, bound to the first by message 9
+        let other_issuer = &issuer_keypairs[(r + 1) % issuer_num];
+        let mut other_messages: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+        other_messages[2] = messages[9];
+        let other_signature = issue(&pp, &other_issuer.secret_key, &other_messages).unwrap();
+        let no_predicates = Vec::new();
+        let other_reveal = vec![0];
+        let items: Vec<MultiItem> = vec![
+            (&signature, issuer_pk, &messages, &reveal_index, &no_predicates),
+            (&other_signature, &other_issuer.public_key, &other_messages, &other_reveal, &no_predicates),
+        ];
+        let linked = vec![Equality{ left: (0, 9), right: (1, 2) }];
+        let multi = present_multi(&pp, &items, &linked, policy_pk, nonce).unwrap();
+        let presented: Vec<(&PiKP, &PiZKP)> = multi.iter().map(|(pikp, pizkp)| (pikp, pizkp)).collect();
+        assert_eq!(verify_present_multi(&pp, &policy_key_pair, &presented, &linked, nonce), Ok(()));
+        assert_eq!(verify_present_multi(&pp, &policy_key_pair, &presented, &Vec::new(), nonce), Err(VerifyError::ChallengeMismatch));
+        let unrelated = vec![Equality{ left: (0, 8), right: (1, 2) }];
+        assert_eq!(verify_present_multi(&pp, &policy_key_pair, &presented, &unrelated, nonce), Err(VerifyError::Credential(mybbs::error::VerifyError::EqualityMismatch(0))));
+        assert_eq!(present_multi(&pp, &items, &unrelated, policy_pk, nonce).unwrap_err(), ProveError::Credential(mybbs::error::ProveError::UnequalMessages(0)));
+
+        // an insurance credential from an issuer that only a second policy accepts
+        let insurer = issuer_key_gen(&pp);
+        let insurance_messages: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let insurance = issue(&pp, &insurer.secret_key, &insurance_messages).unwrap();
+        let other_insurer = issuer_key_gen(&pp);
+        let insurer_policy = set_policy(&pp, &vec![insurer.public_key.clone(), other_insurer.public_key.clone()], &vec![prove_issuer_key(&pp, &insurer), prove_issuer_key(&pp, &other_insurer)]).unwrap();
+        let insurance_item: MultiItem = (&insurance, &insurer.public_key, &insurance_messages, &other_reveal, &no_predicates);
+        let many = present_many(&pp, &[(items[0], policy_pk), (insurance_item, &insurer_policy.public_key)], &Vec::new(), nonce).unwrap();
+        assert_eq!(verify_present_many(&pp, &[(&policy_key_pair, &many[0].0, &many[0].1), (&insurer_policy, &many[1].0, &many[1].1)], &Vec::new(), nonce), Ok(()));
+        assert_eq!(verify_present_many(&pp, &[(&policy_key_pair, &many[0].0, &many[0].1), (&policy_key_pair, &many[1].0, &many[1].1)], &Vec::new(), nonce), Err(VerifyError::ChallengeMismatch));
+        assert_eq!(verify_present(&pp, &policy_key_pair, &many[0].0, &many[0].1, nonce), Err(VerifyError::ChallengeMismatch));
+        assert_eq!(present_many(&pp, &[(items[0], policy_pk), (insurance_item, policy_pk)], &Vec::new(), nonce).unwrap_err(), ProveError::IssuerNotInPolicy);
+
+        // the same hidden message 9 gives the same pseudonym in a scope through either credential
+        let scope = b"shop.example";
+        let nym_pt = present_with_pseudonym(&pp, &signature, issuer_pk, &messages, &reveal_index, &no_predicates, 9, scope, policy_pk, nonce).unwrap();
+        let nym = verify_present_with_pseudonym(&pp, &policy_key_pair, &nym_pt.0, &nym_pt.1, 9, scope, nonce).unwrap();
+        assert_eq!(nym, pseudonym::pseudonym(scope, &messages[9]));
+        let other_nym_pt = present_with_pseudonym(&pp, &other_signature, &other_issuer.public_key, &other_messages, &other_reveal, &no_predicates, 2, scope, policy_pk, nonce).unwrap();
+        assert_eq!(verify_present_with_pseudonym(&pp, &policy_key_pair, &other_nym_pt.0, &other_nym_pt.1, 2, scope, nonce), Ok(nym));
+        assert_eq!(verify_present_with_pseudonym(&pp, &policy_key_pair, &nym_pt.0, &nym_pt.1, 9, b"bank.example", nonce), Err(VerifyError::Credential(mybbs::error::VerifyError::PseudonymMismatch)));
+        let mut swapped = nym_pt.clone();
+        swapped.0.pseudonym.as_mut().unwrap().nym = pseudonym::pseudonym(scope, &messages[8]);
+        assert_eq!(verify_present(&pp, &policy_key_pair, &swapped.0, &swapped.1, nonce), Err(VerifyError::ChallengeMismatch));
+
+        // a credential bound to a holder key only presents with the holder secret
+        let holder_secret = holder::holder_key_gen();
+        let holder_nonce = b"issuance-nonce";
+        let binding = holder::key_binding(&pp.h_vec[4], &holder_secret, holder_nonce);
+        let bound_messages = messages[1..].to_vec();
+        let bound_signature = issue_bound(&pp, &issuer_keypair.secret_key, &binding, 4, &bound_messages, holder_nonce).unwrap();
+        assert_eq!(issue_bound(&pp, &issuer_keypair.secret_key, &binding, 4, &bound_messages, nonce).unwrap_err(), ProveError::Credential(mybbs::error::ProveError::HolderKeyRejected(mybbs::error::VerifyError::ChallengeMismatch)));
+        let bound_pt = present_bound(&pp, &bound_signature, issuer_pk, &holder_secret, 4, &bound_messages, &reveal_index, policy_pk, nonce).unwrap();
+        assert_eq!(verify_present_bound(&pp, &policy_key_pair, &bound_pt.0, &bound_pt.1, 4, nonce), Ok(()));
+        let stolen_pt = present_bound(&pp, &bound_signature, issuer_pk, &holder::holder_key_gen(), 4, &bound_messages, &reveal_index, policy_pk, nonce).unwrap();
+        assert_eq!(verify_present_bound(&pp, &policy_key_pair, &stolen_pt.0, &stolen_pt.1, 4, nonce), Err(VerifyError::PairingCheckFailed(1)));
+        assert_eq!(verify_present_bound(&pp, &policy_key_pair, &pikp, &pizkp, 3, nonce), Err(VerifyError::Credential(mybbs::error::VerifyError::HolderSecretNotHidden(3))));
+        assert_eq!(present_bound(&pp, &bound_signature, issuer_pk, &holder_secret, 4, &bound_messages, &vec![4], policy_pk, nonce).unwrap_err(), ProveError::BadRevealIndex(4));
 
-        let is_valid_present = verify_present(&pp, &policy_key_pair, &pikp, &pizkp);
-        assert_eq!(is_valid_present, true);
+        let stranger = issuer_key_gen(&pp);
+        let result = present(&pp, &signature, &stranger.public_key, &messages, &reveal_index, policy_pk, nonce);
+        assert_eq!(result.unwrap_err(), ProveError::IssuerNotInPolicy);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn rejects_malformed_input() {
+        let message_len = 10;
+        let mut rng = thread_rng();
+        let messages: Vec<Fr> = (0..message_len).map(|_| Fr::rand(&mut rng)).collect();
+        let pp = par_gen(message_len);
+        let issuer_keypair = issuer_key_gen(&pp);
+        let issuer_pk = &issuer_keypair.public_key;
+        let signature = issue(&pp, &issuer_keypair.secret_key, &messages).unwrap();
+        let other_issuer = issuer_key_gen(&pp);
+        let key_proofs = vec![prove_issuer_key(&pp, &other_issuer), prove_issuer_key(&pp, &issuer_keypair)];
+        let policy_key_pair = set_policy(&pp, &vec![other_issuer.public_key.clone(), issuer_pk.clone()], &key_proofs).unwrap();
+        let policy_pk = &policy_key_pair.public_key;
+        let (pikp, pizkp) = present(&pp, &signature, issuer_pk, &messages, &vec![0, 3, 5], policy_pk, b"").unwrap();
+        let g1_points = VerifyError::MalformedInput("A_bar, B_bar or D is the identity or not in G1");
+
+        // A_bar = B_bar = 1 would satisfy the pairing equation for any policy
+        let mut bad = pikp.clone();
+        bad.a_bar = G1Affine::zero();
+        bad.b_bar = G1Affine::zero();
+        assert_eq!(verify_present(&pp, &policy_key_pair, &bad, &pizkp, b""), Err(g1_points));
+        let mut bad = pikp.clone();
+        bad.b_bar = G1Affine::new_unchecked(pikp.b_bar.x, -pikp.b_bar.x);
+        assert_eq!(verify_present(&pp, &policy_key_pair, &bad, &pizkp, b""), Err(g1_points));
+        let context = verifier_context(&pp, &policy_key_pair).unwrap();
+        assert_eq!(verify_present_with_context(&context, &bad, &pizkp, b""), Err(g1_points));
+        let mut bad = pikp.clone();
+        bad.sigma_tilde = G2Affine::zero();
+        assert_eq!(verify_present(&pp, &policy_key_pair, &bad, &pizkp, b""), Err(VerifyError::MalformedInput("sigma_tilde is the identity or not in G2")));
+
+        // reveal indices out of range, repeated or out of order
+        for (open, idx) in [(vec![0, 3, 10], 10), (vec![0, 3, 3], 3), (vec![3, 0, 5], 0)]{
+            let mut bad = pikp.clone();
+            bad.open = open;
+            assert_eq!(verify_present(&pp, &policy_key_pair, &bad, &pizkp, b""), Err(VerifyError::BadRevealIndex(idx)));
+        }
+        assert_eq!(present(&pp, &signature, issuer_pk, &messages, &vec![5, 3], policy_pk, b"").unwrap_err(), ProveError::BadRevealIndex(3));
+
+        // lengths that do not add up
+        let mut bad = pikp.clone();
+        bad.len = usize::MAX;
+        assert_eq!(verify_present(&pp, &policy_key_pair, &bad, &pizkp, b""), Err(VerifyError::MalformedInput("message length exceeds generators")));
+        let mut bad = pikp.clone();
+        bad.message_list.push(messages[0]);
+        assert_eq!(verify_present(&pp, &policy_key_pair, &bad, &pizkp, b""), Err(VerifyError::MalformedInput("revealed messages do not match reveal indices")));
+        let mut bad = pizkp.clone();
+        bad.v.pop();
+        assert_eq!(verify_present(&pp, &policy_key_pair, &pikp, &bad, b""), Err(VerifyError::MalformedInput("response count does not match hidden messages")));
+
+        // an identity pseudonym and identity policy keys
+        let nym_pt = present_with_pseudonym(&pp, &signature, issuer_pk, &messages, &vec![0], &Vec::new(), 9, b"shop.example", policy_pk, b"").unwrap();
+        let mut bad = nym_pt.0.clone();
+        bad.pseudonym.as_mut().unwrap().nym = G1Affine::zero();
+        assert_eq!(verify_present(&pp, &policy_key_pair, &bad, &nym_pt.1, b""), Err(VerifyError::Credential(mybbs::error::VerifyError::MalformedInput("pseudonym is the identity or not in G1"))));
+        let mut bad = policy_pk.clone();
+        bad.s = G2Affine::zero();
+        assert_eq!(audit_policy(&pp, &bad), Err(VerifyError::MalformedInput("s is the identity or not in G2")));
+        let mut bad = policy_pk.clone();
+        bad.t[1] = G2Affine::zero();
+        assert_eq!(audit_policy(&pp, &bad), Err(VerifyError::MalformedInput("policy key is the identity or not in G2")));
+        assert_eq!(set_policy(&pp, &vec![other_issuer.public_key.clone(), issuer::PublicKey(G2Affine::zero())], &key_proofs), Err(VerifyError::InvalidKeyProof(1)));
+        let mut bad = policy_pk.clone();
+        bad.ipks[1] = issuer::PublicKey(G2Affine::zero());
+        assert_eq!(audit_policy(&pp, &bad), Err(VerifyError::MalformedInput("policy key is the identity or not in G2")));
+    }
+
+    #[test]
+    fn splits_public_and_designated_verification() {
+        let message_len = 5;
+        let mut rng = thread_rng();
+        let messages: Vec<Fr> = (0..message_len).map(|_| Fr::rand(&mut rng)).collect();
+        let pp = par_gen(message_len);
+        let issuer_keypair = issuer_key_gen(&pp);
+        let issuer_pk = &issuer_keypair.public_key;
+        let signature = issue(&pp, &issuer_keypair.secret_key, &messages).unwrap();
+        let other_issuer = issuer_key_gen(&pp);
+        let ipks = vec![other_issuer.public_key.clone(), issuer_pk.clone()];
+        let key_proofs = vec![prove_issuer_key(&pp, &other_issuer), prove_issuer_key(&pp, &issuer_keypair)];
+        let policy_key_pair = set_policy(&pp, &ipks, &key_proofs).unwrap();
+        let nonce = b"audit-nonce";
+        let (pikp, pizkp) = present(&pp, &signature, issuer_pk, &messages, &vec![1], &policy_key_pair.public_key, nonce).unwrap();
+
+        assert_eq!(verify_present_public(&pp, &policy_key_pair.public_key, &pikp, &pizkp, nonce), Ok(()));
+        assert_eq!(verify_present_designated(&pp, &policy_key_pair, &pikp), Ok(()));
+        assert_eq!(verify_present_public(&pp, &policy_key_pair.public_key, &pikp, &pizkp, b"other"), Err(VerifyError::PresentationHeaderMismatch));
+
+        // a policy over the same issuers under another key
+        let other_policy = set_policy(&pp, &ipks, &key_proofs).unwrap();
+        assert_eq!(verify_present_public(&pp, &other_policy.public_key, &pikp, &pizkp, nonce), Err(VerifyError::ChallengeMismatch));
+        assert_eq!(verify_present_designated(&pp, &other_policy, &pikp), Err(VerifyError::PairingCheckFailed(1)));
+        assert_eq!(audit_policy(&pp, &policy_key_pair.public_key), Ok(()));
+    }
+
+    #[test]
+    fn updates_policy_incrementally() {
+        let Issuers{ pp, messages, ipks, key_proofs, signature } = three_issuers(2);
+        let policy = set_policy(&pp, &ipks[..2].to_vec(), &key_proofs[..2].to_vec()).unwrap();
+        let audited = &policy.public_key;
+        assert_eq!(audited.version, 0);
+
+        // an addition sends one t and keeps everything relying parties already hold
+        let (added, update) = policy_add_issuer(&pp, &policy, &ipks[2], &key_proofs[2]).unwrap();
+        assert_eq!((update.version, update.t.len(), update.s), (1, 1, audited.s));
+        let audited = audit_policy_update(&pp, audited, &update).unwrap();
+        assert_eq!(audited, added.public_key);
+        assert_eq!(audit_policy(&pp, &audited), Ok(()));
+        let (pikp, pizkp) = present(&pp, &signature, &ipks[2], &messages, &vec![0], &audited, b"").unwrap();
+        assert_eq!(verify_present(&pp, &added, &pikp, &pizkp, b""), Ok(()));
+        assert_eq!(audit_policy_update(&pp, &audited, &update), Err(VerifyError::StalePolicyVersion{ got: 1, expected: 2 }));
+
+        // a removal re-keys, so presentations for the old key stop verifying
+        let (removed, update) = policy_remove_issuer(&pp, &added, &ipks[2]).unwrap();
+        assert_eq!((update.removed.clone(), update.t.len()), (vec![2], 2));
+        let audited = audit_policy_update(&pp, &audited, &update).unwrap();
+        assert_eq!(audited, removed.public_key);
+        assert_eq!(verify_present(&pp, &removed, &pikp, &pizkp, b""), Err(VerifyError::ChallengeMismatch));
+        assert_eq!(verify_present_designated(&pp, &removed, &pikp), Err(VerifyError::PairingCheckFailed(1)));
+        assert_eq!(present(&pp, &signature, &ipks[2], &messages, &vec![0], &audited, b"").unwrap_err(), ProveError::IssuerNotInPolicy);
+
+        // the proof is bound to the version, so an update cannot be relabelled to a later one
+        let (_, mut update) = policy_add_issuer(&pp, &removed, &ipks[2], &key_proofs[2]).unwrap();
+        assert!(audit_policy_update(&pp, &audited, &update).is_ok());
+        let mut relabelled = audited.clone();
+        relabelled.version = 3;
+        update.version = 4;
+        assert_eq!(audit_policy_update(&pp, &relabelled, &update), Err(VerifyError::ChallengeMismatch));
+    }
+
+    #[test]
+    fn rejects_duplicate_issuers() {
+        let Issuers{ pp, messages, ipks, key_proofs, signature } = three_issuers(2);
+        let duplicate = Err(VerifyError::MalformedInput("issuer is already in the policy"));
+        let policy = set_policy(&pp, &ipks[..2].to_vec(), &key_proofs[..2].to_vec()).unwrap();
+
+        // adding an issuer twice fails, so a removal really takes it out
+        let (added, update) = policy_add_issuer(&pp, &policy, &ipks[2], &key_proofs[2]).unwrap();
+        assert_eq!(policy_add_issuer(&pp, &added, &ipks[2], &key_proofs[2]).map(|_| ()), duplicate);
+        let (removed, _) = policy_remove_issuer(&pp, &added, &ipks[2]).unwrap();
+        assert_eq!(present(&pp, &signature, &ipks[2], &messages, &vec![0], &removed.public_key, b"").unwrap_err(), ProveError::IssuerNotInPolicy);
+
+        // an update that adds a kept issuer, and a policy that lists one twice, fail the audit
+        let mut readded = update.clone();
+        readded.added = vec![ipks[0].clone()];
+        readded.key_proofs = vec![key_proofs[0].clone()];
+        assert_eq!(audit_policy_update(&pp, &policy.public_key, &readded).map(|_| ()), duplicate);
+        let twice = vec![ipks[0].clone(), ipks[0].clone()];
+        assert_eq!(set_policy(&pp, &twice, &vec![key_proofs[0].clone(), key_proofs[0].clone()]).map(|_| ()), duplicate);
+        let mut listed_twice = added.public_key.clone();
+        listed_twice.ipks[2] = ipks[0].clone();
+        listed_twice.key_proofs[2] = key_proofs[0].clone();
+        assert_eq!(audit_policy(&pp, &listed_twice), duplicate);
+    }
+
+    #[test]
+    fn binds_policy_and_sigma_tilde() {
+        let Issuers{ pp, messages, ipks, key_proofs, signature } = three_issuers(0);
+        let policy = set_policy(&pp, &ipks[..2].to_vec(), &key_proofs[..2].to_vec()).unwrap();
+        let (pikp, pizkp) = present(&pp, &signature, &ipks[0], &messages, &vec![1], &policy.public_key, b"").unwrap();
+        let (other_pikp, _) = present(&pp, &signature, &ipks[0], &messages, &vec![1], &policy.public_key, b"").unwrap();
+        assert_eq!(verify_present(&pp, &policy, &pikp, &pizkp, b""), Ok(()));
+
+        // a sigma_tilde of another presentation, or the own one scaled, fails before the pairing
+        for sigma_tilde in [other_pikp.sigma_tilde, (pikp.sigma_tilde * Fr::from(2u64)).into_affine()]{
+            let mut swapped = pikp.clone();
+            swapped.sigma_tilde = sigma_tilde;
+            assert_eq!(verify_present(&pp, &policy, &swapped, &pizkp, b""), Err(VerifyError::ChallengeMismatch));
+        }
+
+        // so does the same presentation against the next version, or a re-keyed copy, of its policy
+        let (added, _) = policy_add_issuer(&pp, &policy, &ipks[2], &key_proofs[2]).unwrap();
+        assert_eq!(verify_present(&pp, &added, &pikp, &pizkp, b""), Err(VerifyError::ChallengeMismatch));
+        let rekeyed = set_policy(&pp, &ipks[..2].to_vec(), &key_proofs[..2].to_vec()).unwrap();
+        assert_eq!(verify_present(&pp, &rekeyed, &pikp, &pizkp, b""), Err(VerifyError::ChallengeMismatch));
+        let context = verifier_context(&pp, &rekeyed).unwrap();
+        assert_eq!(verify_present_with_context(&context, &pikp, &pizkp, b""), Err(VerifyError::ChallengeMismatch));
+        assert_ne!(policy_digest(&policy.public_key), policy_digest(&rekeyed.public_key));
+    }
+}
@@ -1 +1,3 @@
-pub mod issuer_hiding;
\ No newline at end of file
+pub mod issuer_hiding;
+pub mod error;
+pub mod wire;
\ No newline at end of file
@@ -0,0 +1,63 @@
+use mybbs::wire::{Ciphersuite, Header, Kind, Scheme};
+
+pub use mybbs::wire::{decode, encode, from_cbor, from_json, to_cbor, to_json};
+
+// Headers of this scheme's objects for the envelopes of mybbs::wire.
+pub const PUBLIC_PARAMETERS: Header = header(Kind::PublicParameters);    // issuer::PublicParameters
+pub const PUBLIC_KEY: Header = header(Kind::PublicKey);
+pub const SIGNATURE: Header = header(Kind::Signature);
+pub const POLICY_PUBLIC_KEY: Header = header(Kind::PolicyPublicKey);
+pub const POLICY_UPDATE: Header = header(Kind::PolicyUpdate);
+pub const PRESENTATION: Header = header(Kind::Presentation);              // (PiKP, PiZKP)
+
+const fn header(kind: Kind) -> Header{
+    return Header{ scheme: Scheme::Katz, kind: kind, ciphersuite: Ciphersuite::Bls12381Sha256 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issuer_hiding::*;
+    use ark_std::{UniformRand, vec::Vec};
+    use mybbs::error::WireError;
+    use mybbs::issuer;
+    use rand::thread_rng;
+
+    #[test]
+    fn it_works(){
+        let mut rng = thread_rng();
+        let messages: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+        let pp = par_gen(4);
+        let issuer_keypair = issuer_key_gen(&pp);
+        let signature = issue(&pp, &issuer_keypair.secret_key, &messages).unwrap();
+        let other_issuer = issuer_key_gen(&pp);
+        let key_proofs = vec![prove_issuer_key(&pp, &other_issuer), prove_issuer_key(&pp, &issuer_keypair)];
+        let policy = set_policy(&pp, &vec![other_issuer.public_key, issuer_keypair.public_key.clone()], &key_proofs).unwrap();
+        let pt = present(&pp, &signature, &issuer_keypair.public_key, &messages, &vec![0, 2], &policy.public_key, b"nonce").unwrap();
+
+        let pp2 = decode(&PUBLIC_PARAMETERS, &encode(&PUBLIC_PARAMETERS, &pp)).unwrap();
+        // the verifier keeps its policy secret key as bytes, next to the public key's envelope
+        let policy2 = PolicyKeyPair{
+            secret_key: policy_secret_key_from_bytes(&policy_secret_key_to_bytes(&policy.secret_key)).unwrap(),
+            public_key: from_cbor(&POLICY_PUBLIC_KEY, &to_cbor(&POLICY_PUBLIC_KEY, &policy.public_key)).unwrap(),
+        };
+        assert_eq!(policy2, policy);
+        assert_eq!(format!("{:?}", policy.secret_key), "PolicySecretKey(<redacted>)");
+        assert_eq!(policy_secret_key_from_bytes(&[0u8; 32]), Err(WireError::InvalidPayload));
+        let pt2: (PiKP, PiZKP) = from_json(&PRESENTATION, &to_json(&PRESENTATION, &pt)).unwrap();
+        assert_eq!(from_json::<PolicyPublicKey>(&POLICY_PUBLIC_KEY, &to_json(&POLICY_PUBLIC_KEY, &policy.public_key)), Ok(policy.public_key.clone()));
+        assert_eq!(decode::<issuer::PublicKey>(&PUBLIC_KEY, &encode(&PUBLIC_KEY, &issuer_keypair.public_key)), Ok(issuer_keypair.public_key.clone()));
+        assert_eq!(decode::<issuer::Signature>(&SIGNATURE, &encode(&SIGNATURE, &signature)), Ok(signature.clone()));
+        assert_eq!(audit_policy(&pp2, &policy2.public_key), Ok(()));
+        assert_eq!(verify_present(&pp2, &policy2, &pt2.0, &pt2.1, b"nonce"), Ok(()));
+        let (_, update) = policy_remove_issuer(&pp, &policy, &issuer_keypair.public_key).unwrap();
+        assert_eq!(decode::<PolicyUpdate>(&POLICY_UPDATE, &encode(&POLICY_UPDATE, &update)), Ok(update.clone()));
+
+        // envelopes of other schemes and kinds are rejected before the payload is read
+        let bytes = encode(&mybbs::wire::PUBLIC_PARAMETERS, &pp);
+        assert_eq!(decode::<issuer::PublicParameters>(&PUBLIC_PARAMETERS, &bytes), Err(WireError::WrongScheme));
+        let bytes = encode(&POLICY_PUBLIC_KEY, &policy.public_key);
+        assert_eq!(decode::<issuer::PublicKey>(&PUBLIC_KEY, &bytes), Err(WireError::WrongKind));
+        assert_eq!(decode::<PolicyPublicKey>(&POLICY_PUBLIC_KEY, &bytes[..bytes.len() - 1]), Err(WireError::InvalidPayload));
+    }
+}
@@ -10,6 +10,7 @@ use issuer_hiding_sanders::issuer_hiding as ih;
 fn sanders_ih_benchmark(c: &mut Criterion) {
     let message_len = [5, 10, 15, 20, 25, 30, 35, 40, 45, 50];
     let issuer_num = [5, 10, 50, 100, 500, 1000];
+    let key_len = *message_len.iter().max().unwrap();
     let mut rng = thread_rng();
 
     c.bench_function("Setup", |b| {
@@ -22,12 +23,12 @@ fn sanders_ih_benchmark(c: &mut Criterion) {
 
     c.bench_function("Issuer_Key_Gen", |b| {
         b.iter(|| {
-            let ikp = ih::key_gen(&pp);
+            let ikp = ih::key_gen(&pp, key_len);
             black_box(ikp);
         });
     });
 
-    let issuer_key_pair = ih::key_gen(&pp);
+    let issuer_key_pair = ih::key_gen(&pp, key_len);
 
     // ------------------------------------------------------------------
     // Group 1: 基本的な署名の生成・検証 (メッセージ長による変化)
@@ -46,16 +47,16 @@ fn sanders_ih_benchmark(c: &mut Criterion) {
             group.bench_with_input(BenchmarkId::new("Sign", len), &len, |b, &_| {
                 b.iter(|| {
                     let signature = ih::sign(&pp, &issuer_key_pair.sk, &message_fr_temp);
-                    black_box(signature);
+                    let _ = black_box(signature);
                 });
             });
 
             // Verify Credential (署名生成済みデータが必要)
-            let cred_temp = ih::sign(&pp, &issuer_key_pair.sk, &message_fr_temp);
+            let cred_temp = ih::sign(&pp, &issuer_key_pair.sk, &message_fr_temp).unwrap();
             group.bench_with_input(BenchmarkId::new("Verify", len), &len, |b, &_| {
                 b.iter(|| {
                     let result = ih::verify_sign(&pp, &issuer_key_pair.pk, &cred_temp, &message_fr_temp);
-                    black_box(result);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -71,31 +72,34 @@ fn sanders_ih_benchmark(c: &mut Criterion) {
         for &num in issuer_num.iter() {
             // リストの準備
             let mut issuer_list_temp = Vec::new();
+            let mut key_proof_temp = Vec::new();
             for _ in 0..num {
-                let keypair = ih::key_gen(&pp);
+                let keypair = ih::key_gen(&pp, key_len);
                 issuer_list_temp.push(keypair.pk.clone());
+                key_proof_temp.push(ih::prove_key(&pp, &keypair));
             }
             // ランダムな位置にターゲットを挿入
             let r = rng.gen_range(0..num);
             if r < issuer_list_temp.len() {
                  issuer_list_temp[r] = issuer_key_pair.pk.clone();
+                 key_proof_temp[r] = ih::prove_key(&pp, &issuer_key_pair);
             }
 
             // Set Policy
             group.bench_with_input(BenchmarkId::new("Set_Policy", num), &num, |b, &_| {
                 b.iter(|| {
-                    let pkp = ih::set_policy(&pp, &issuer_list_temp);
+                    let pkp = ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap();
                 black_box(pkp);
                 });
             });
 
             // Policy (生成済みデータが必要)
-            let (policy_key_pair, policy_pi) = ih::set_policy(&pp, &issuer_list_temp);
+            let (policy_key_pair, policy_pi) = ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap();
             let policy_pk = &policy_key_pair.ppk;
             group.bench_with_input(BenchmarkId::new("Verify_List", num), &num, |b, &_| {
                 b.iter(|| {
-                    let result = ih::audit_policy(&pp, &policy_pk, &policy_pi);
-                    black_box(result);
+                    let result = ih::audit_policy(&pp, policy_pk, &policy_pi);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -118,7 +122,7 @@ fn sanders_ih_benchmark(c: &mut Criterion) {
             for _ in 0..mlen {
                 message_fr_temp.push(Fr::rand(&mut rng));
             }
-            let cred_temp = ih::sign(&pp, &issuer_key_pair.sk, &message_fr_temp);
+            let cred_temp = ih::sign(&pp, &issuer_key_pair.sk, &message_fr_temp).unwrap();
 
             // 公開する属性の数を決定
             let open_message_6 = mlen * 3 / 5;
@@ -145,14 +149,17 @@ fn sanders_ih_benchmark(c: &mut Criterion) {
                 for &inum in issuer_num.iter() {
                     // Issuerリスト準備
                     let mut issuer_list_temp = Vec::new();
+                    let mut key_proof_temp = Vec::new();
                     for _ in 0..inum {
-                        let kp = ih::key_gen(&pp);
+                        let kp = ih::key_gen(&pp, key_len);
                         issuer_list_temp.push(kp.pk.clone());
+                        key_proof_temp.push(ih::prove_key(&pp, &kp));
                     }
                     let r = rng.gen_range(0..inum);
                     issuer_list_temp[r] = issuer_key_pair.pk.clone();
+                    key_proof_temp[r] = ih::prove_key(&pp, &issuer_key_pair);
                     
-                    let (policy_key_pair, _) = ih::set_policy(&pp, &issuer_list_temp);
+                    let (policy_key_pair, _) = ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap();
                     let policy_pk = &policy_key_pair.ppk;
 
                     // パラメータ識別文字列を作成 (例: "m10_o3_i50")
@@ -161,19 +168,27 @@ fn sanders_ih_benchmark(c: &mut Criterion) {
                     // Present Benchmark
                     group.bench_with_input(BenchmarkId::new("Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let pt = ih::create_proof(&pp, &issuer_key_pair.pk,&cred_temp, &policy_pk,  &message_fr_temp, &open_temp);
-                            black_box(pt);
+                            let pt = ih::create_proof(&pp, &issuer_key_pair.pk,&cred_temp, policy_pk,  &message_fr_temp, &open_temp, b"");
+                            let _ = black_box(pt);
                         });
                     });
 
                     // Verify Present Benchmark
                     // ベンチマーク内で毎回生成すると遅いので、計測外で一度生成
-                    let pt = ih::create_proof(&pp, &issuer_key_pair.pk,&cred_temp, &policy_pk,  &message_fr_temp, &open_temp);
+                    let pt = ih::create_proof(&pp, &issuer_key_pair.pk,&cred_temp, policy_pk,  &message_fr_temp, &open_temp, b"").unwrap();
                     
                     group.bench_with_input(BenchmarkId::new("Verify_Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let result = ih::verify_proof(&pp, &pt, &policy_key_pair);
-                            black_box(result);
+                            let result = ih::verify_proof(&pp, &pt, &policy_key_pair, b"");
+                            let _ = black_box(result);
+                        });
+                    });
+
+                    let context = ih::verifier_context(&pp, &policy_key_pair).unwrap();
+                    group.bench_with_input(BenchmarkId::new("Verify_Present_Context", &param_str), &param_str, |b, _| {
+                        b.iter(|| {
+                            let result = ih::verify_proof_with_context(&context, &pt, b"");
+                            let _ = black_box(result);
                         });
                     });
                 }
@@ -186,10 +201,11 @@ fn sanders_ih_benchmark(c: &mut Criterion) {
 fn sanders_ih_benchmark_pc(c: &mut Criterion) {
     let message_len = [5, 10, 15, 20, 25, 30, 35, 40, 45, 50];
     let issuer_num = [5, 10, 50, 100, 500, 1000];
+    let key_len = *message_len.iter().max().unwrap();
     let mut rng = thread_rng();
     let pp = ih::par_gen();
 
-    let issuer_key_pair = ih::key_gen(&pp);
+    let issuer_key_pair = ih::key_gen(&pp, key_len);
 
     // ------------------------------------------------------------------
     // Group 1: 基本的な署名の生成 (メッセージ長による変化)
@@ -208,7 +224,7 @@ fn sanders_ih_benchmark_pc(c: &mut Criterion) {
             group.bench_with_input(BenchmarkId::new("Sign", len), &len, |b, &_| {
                 b.iter(|| {
                     let signature = ih::sign(&pp, &issuer_key_pair.sk, &message_fr_temp);
-                    black_box(signature);
+                    let _ = black_box(signature);
                 });
             });
         }
@@ -224,20 +240,23 @@ fn sanders_ih_benchmark_pc(c: &mut Criterion) {
         for &num in issuer_num.iter() {
             // リストの準備
             let mut issuer_list_temp = Vec::new();
+            let mut key_proof_temp = Vec::new();
             for _ in 0..num {
-                let keypair = ih::key_gen(&pp);
+                let keypair = ih::key_gen(&pp, key_len);
                 issuer_list_temp.push(keypair.pk.clone());
+                key_proof_temp.push(ih::prove_key(&pp, &keypair));
             }
             // ランダムな位置にターゲットを挿入
             let r = rng.gen_range(0..num);
             if r < issuer_list_temp.len() {
                  issuer_list_temp[r] = issuer_key_pair.pk.clone();
+                 key_proof_temp[r] = ih::prove_key(&pp, &issuer_key_pair);
             }
 
             // Set Policy
             group.bench_with_input(BenchmarkId::new("Set_Policy", num), &num, |b, &_| {
                 b.iter(|| {
-                    let pkp = ih::set_policy(&pp, &issuer_list_temp);
+                    let pkp = ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap();
                 black_box(pkp);
                 });
             });
@@ -261,7 +280,7 @@ fn sanders_ih_benchmark_pc(c: &mut Criterion) {
             for _ in 0..mlen {
                 message_fr_temp.push(Fr::rand(&mut rng));
             }
-            let cred_temp = ih::sign(&pp, &issuer_key_pair.sk, &message_fr_temp);
+            let cred_temp = ih::sign(&pp, &issuer_key_pair.sk, &message_fr_temp).unwrap();
 
             // 公開する属性の数を決定
             let open_message_6 = mlen * 3 / 5;
@@ -288,13 +307,16 @@ fn sanders_ih_benchmark_pc(c: &mut Criterion) {
                 for &inum in issuer_num.iter() {
                     // Issuerリスト準備
                     let mut issuer_list_temp = Vec::new();
+                    let mut key_proof_temp = Vec::new();
                     for _ in 0..inum {
-                        let kp = ih::key_gen(&pp);
+                        let kp = ih::key_gen(&pp, key_len);
                         issuer_list_temp.push(kp.pk.clone());
+                        key_proof_temp.push(ih::prove_key(&pp, &kp));
                     }
                     let r = rng.gen_range(0..inum);
                     issuer_list_temp[r] = issuer_key_pair.pk.clone();
-                    let (policy_key_pair, _) = ih::set_policy(&pp, &issuer_list_temp);
+                    key_proof_temp[r] = ih::prove_key(&pp, &issuer_key_pair);
+                    let (policy_key_pair, _) = ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap();
                     let policy_pk = &policy_key_pair.ppk;
 
                     // パラメータ識別文字列を作成 (例: "m10_o3_i50")
@@ -302,12 +324,20 @@ fn sanders_ih_benchmark_pc(c: &mut Criterion) {
 
                     // Verify Present Benchmark
                     // ベンチマーク内で毎回生成すると遅いので、計測外で一度生成
-                    let pt = ih::create_proof(&pp, &issuer_key_pair.pk,&cred_temp, &policy_pk,  &message_fr_temp, &open_temp);
+                    let pt = ih::create_proof(&pp, &issuer_key_pair.pk,&cred_temp, policy_pk,  &message_fr_temp, &open_temp, b"").unwrap();
                     
                     group.bench_with_input(BenchmarkId::new("Verify_Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let result = ih::verify_proof(&pp, &pt, &policy_key_pair);
-                            black_box(result);
+                            let result = ih::verify_proof(&pp, &pt, &policy_key_pair, b"");
+                            let _ = black_box(result);
+                        });
+                    });
+
+                    let context = ih::verifier_context(&pp, &policy_key_pair).unwrap();
+                    group.bench_with_input(BenchmarkId::new("Verify_Present_Context", &param_str), &param_str, |b, _| {
+                        b.iter(|| {
+                            let result = ih::verify_proof_with_context(&context, &pt, b"");
+                            let _ = black_box(result);
                         });
                     });
                 }
@@ -320,10 +350,11 @@ fn sanders_ih_benchmark_pc(c: &mut Criterion) {
 fn sanders_ih_benchmark_android(c: &mut Criterion) {
     let message_len = [5, 10, 15, 20, 25, 30, 35, 40, 45, 50];
     let issuer_num = [5, 10, 50, 100, 500, 1000];
+    let key_len = *message_len.iter().max().unwrap();
     let mut rng = thread_rng();
     let pp = ih::par_gen();
 
-    let issuer_key_pair = ih::key_gen(&pp);
+    let issuer_key_pair = ih::key_gen(&pp, key_len);
 
     // ------------------------------------------------------------------
     // Group 1: 基本的な署名の検証 (メッセージ長による変化)
@@ -339,11 +370,11 @@ fn sanders_ih_benchmark_android(c: &mut Criterion) {
             }
 
             // Verify Credential (署名生成済みデータが必要)
-            let cred_temp = ih::sign(&pp, &issuer_key_pair.sk, &message_fr_temp);
+            let cred_temp = ih::sign(&pp, &issuer_key_pair.sk, &message_fr_temp).unwrap();
             group.bench_with_input(BenchmarkId::new("Verify", len), &len, |b, &_| {
                 b.iter(|| {
                     let result = ih::verify_sign(&pp, &issuer_key_pair.pk, &cred_temp, &message_fr_temp);
-                    black_box(result);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -359,23 +390,26 @@ fn sanders_ih_benchmark_android(c: &mut Criterion) {
         for &num in issuer_num.iter() {
             // リストの準備
             let mut issuer_list_temp = Vec::new();
+            let mut key_proof_temp = Vec::new();
             for _ in 0..num {
-                let keypair = ih::key_gen(&pp);
+                let keypair = ih::key_gen(&pp, key_len);
                 issuer_list_temp.push(keypair.pk.clone());
+                key_proof_temp.push(ih::prove_key(&pp, &keypair));
             }
             // ランダムな位置にターゲットを挿入
             let r = rng.gen_range(0..num); // gen_range(1..num)だとnum=5のときindex 0が選ばれない可能性があるため修正考慮(元ロジック尊重なら戻してください)
             if r < issuer_list_temp.len() {
                  issuer_list_temp[r] = issuer_key_pair.pk.clone();
+                 key_proof_temp[r] = ih::prove_key(&pp, &issuer_key_pair);
             }
 
             // Policy (生成済みデータが必要)
-            let (policy_key_pair, policy_pi) = ih::set_policy(&pp, &issuer_list_temp);
+            let (policy_key_pair, policy_pi) = ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap();
             let policy_pk = &policy_key_pair.ppk;
             group.bench_with_input(BenchmarkId::new("Verify_List", num), &num, |b, &_| {
                 b.iter(|| {
-                    let result = ih::audit_policy(&pp, &policy_pk, &policy_pi);
-                    black_box(result);
+                    let result = ih::audit_policy(&pp, policy_pk, &policy_pi);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -398,7 +432,7 @@ fn sanders_ih_benchmark_android(c: &mut Criterion) {
             for _ in 0..mlen {
                 message_fr_temp.push(Fr::rand(&mut rng));
             }
-            let cred_temp = ih::sign(&pp, &issuer_key_pair.sk, &message_fr_temp);
+            let cred_temp = ih::sign(&pp, &issuer_key_pair.sk, &message_fr_temp).unwrap();
 
             // 公開する属性の数を決定
             let open_message_6 = mlen * 3 / 5;
@@ -425,14 +459,17 @@ fn sanders_ih_benchmark_android(c: &mut Criterion) {
                 for &inum in issuer_num.iter() {
                     // Issuerリスト準備
                     let mut issuer_list_temp = Vec::new();
+                    let mut key_proof_temp = Vec::new();
                     for _ in 0..inum {
-                        let kp = ih::key_gen(&pp);
+                        let kp = ih::key_gen(&pp, key_len);
                         issuer_list_temp.push(kp.pk.clone());
+                        key_proof_temp.push(ih::prove_key(&pp, &kp));
                     }
                     let r = rng.gen_range(0..inum);
                     issuer_list_temp[r] = issuer_key_pair.pk.clone();
+                    key_proof_temp[r] = ih::prove_key(&pp, &issuer_key_pair);
                     
-                    let (policy_key_pair, _) = ih::set_policy(&pp, &issuer_list_temp);
+                    let (policy_key_pair, _) = ih::set_policy(&pp, &issuer_list_temp, &key_proof_temp).unwrap();
                     let policy_pk = &policy_key_pair.ppk;
 
                     // パラメータ識別文字列を作成 (例: "m10_o3_i50")
@@ -441,8 +478,8 @@ fn sanders_ih_benchmark_android(c: &mut Criterion) {
                     // Present Benchmark
                     group.bench_with_input(BenchmarkId::new("Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let pt = ih::create_proof(&pp, &issuer_key_pair.pk,&cred_temp, &policy_pk,  &message_fr_temp, &open_temp);
-                            black_box(pt);
+                            let pt = ih::create_proof(&pp, &issuer_key_pair.pk,&cred_temp, policy_pk,  &message_fr_temp, &open_temp, b"");
+                            let _ = black_box(pt);
                         });
                     });
                 }
@@ -455,4 +492,4 @@ fn sanders_ih_benchmark_android(c: &mut Criterion) {
 criterion_group!(benches, sanders_ih_benchmark);
 criterion_group!(benches_pc, sanders_ih_benchmark_pc);
 criterion_group!(benches_mobile, sanders_ih_benchmark_android);
-criterion_main!(benches);
\ No newline at end of file
+criterion_main!(benches);
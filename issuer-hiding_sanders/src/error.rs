@@ -0,0 +1,39 @@
+use thiserror::Error;
+
+// Reasons a verifier rejects a signature, a policy or a presentation token.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum VerifyError {
+    #[error("challenge mismatch")]
+    ChallengeMismatch,
+    #[error("pairing check {0} failed")]
+    PairingCheckFailed(usize),
+    #[error("bad reveal index {0}")]
+    BadRevealIndex(usize),
+    #[error("presentation header does not match the expected one")]
+    PresentationHeaderMismatch,
+    #[error("key proof of issuer {0} invalid")]
+    InvalidKeyProof(usize),
+    #[error("malformed input: {0}")]
+    MalformedInput(&'static str),
+    #[error("message {0} is not a hidden holder secret")]
+    HolderSecretNotHidden(usize),
+    #[error(transparent)]
+    Range(#[from] mybbs::error::VerifyError),
+}
+
+// Reasons a holder refuses to build a presentation token.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum ProveError {
+    #[error("bad reveal index {0}")]
+    BadRevealIndex(usize),
+    #[error("{messages} messages but the key only covers {key_len}")]
+    TooManyMessages { messages: usize, key_len: usize },
+    #[error("issuer public key is not in the policy")]
+    IssuerNotInPolicy,
+    #[error("holder key binding rejected: {0}")]
+    HolderKeyRejected(mybbs::error::VerifyError),
+    #[error(transparent)]
+    Schema(#[from] mybbs::error::SchemaError),
+    #[error(transparent)]
+    Range(#[from] mybbs::error::ProveError),
+}
@@ -3,58 +3,113 @@ use std::vec;
 use ark_bls12_381::{Bls12_381, G1Affine, G1Projective, G2Affine, G2Projective, g1::Config as G1Config, g2::Config as G2Config};
 use ark_ec::{hashing::{curve_maps::wb::WBMap, map_to_curve_hasher::MapToCurveBasedHasher, HashToCurve},pairing::Pairing, CurveGroup};
 use ark_ff::{field_hashers::{DefaultFieldHasher, HashToField}, Field};
-use ark_std::{fmt::Debug, UniformRand, vec::Vec};
+use ark_std::{fmt::{self, Debug}, UniformRand, vec::Vec, Zero};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use sha2::Sha256;
-use rand::thread_rng;
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use mybbs::bbs;
+use mybbs::error::WireError;
+use mybbs::holder::{self, HolderSecretKey, KeyBinding};
+use mybbs::proof;
+use mybbs::range::{self, RangePredicate, RangeProof};
+use mybbs::schema::{self, AttributeValue, CredentialSchema};
+
+use crate::error::{ProveError, VerifyError};
 
 pub type Fr = <Bls12_381 as Pairing>::ScalarField;
+pub type G2Prepared = <Bls12_381 as Pairing>::G2Prepared;
 
+// Only the generators: every issuer draws its own x in key_gen, so there is no shared
+// X = g^x whose discrete log could be public.
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
 pub struct PublicParameters {
     pub g1: G1Affine,
     pub g2: G2Affine,
-    pub x1: G1Affine,
-    pub x2: G2Affine,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+// Secret keys are redacted in Debug, compared in constant time and wiped on drop. Outside
+// this crate they are only reachable through the *_to_bytes functions.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
 pub struct SecretKey {
-    pub y: Vec<Fr>,
+    pub(crate) x: Fr,
+    pub(crate) y: Vec<Fr>,
+}
+
+impl Debug for SecretKey{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        return f.write_str("SecretKey(<redacted>)")
+    }
+}
+
+impl PartialEq for SecretKey{
+    fn eq(&self, other: &Self) -> bool{
+        return (bbs::ct_eq_scalars(&[self.x], &[other.x]) & bbs::ct_eq_scalars(&self.y, &other.y)).into()
+    }
 }
 
+impl Eq for SecretKey {}
+
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
 pub struct PublicKey {
+    pub pk_x: G2Affine,
     pub pk_y: Vec<G2Affine>,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct KeyPair {
     pub sk: SecretKey,
     pub pk: PublicKey,
 }
 
+// Proof of possession of x and every y_i of a PublicKey. set_policy asks for one per issuer:
+// the presentation check adds up pk_x and pk_y[idx] of all issuers, so a key chosen after the
+// others could otherwise cancel them out.
+#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+pub struct KeyProof {
+    pub c: Fr,
+    pub z: Vec<Fr>,
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
 pub struct Signature {
     pub sigma1: G1Affine,
     pub sigma2: G1Affine,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
 pub struct PolicySecretKey {
-    pub a: Fr,
-    pub b: Vec<Fr>,
+    pub(crate) a: Fr,
+    pub(crate) b: Vec<Fr>,
+}
+
+impl Debug for PolicySecretKey{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        return f.write_str("PolicySecretKey(<redacted>)")
+    }
+}
+
+impl PartialEq for PolicySecretKey{
+    fn eq(&self, other: &Self) -> bool{
+        return (bbs::ct_eq_scalars(&[self.a], &[other.a]) & bbs::ct_eq_scalars(&self.b, &other.b)).into()
+    }
 }
 
+impl Eq for PolicySecretKey {}
+
+// b, vec_b and every t_i run over the issuer key elements X, Y_1, ..., Y_n of key_elements:
+// index 0 is for X, index k + 1 for Y_k.
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
 pub struct PolicyPublicKey {
     pub ipks: Vec<PublicKey>,
+    pub key_proofs: Vec<KeyProof>,
     pub s: G2Affine,
     pub vec_b: Vec<G2Affine>,
     pub t: Vec<Vec<G2Affine>>,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct PolicyKeyPair {
     pub psk: PolicySecretKey,
     pub ppk: PolicyPublicKey,
@@ -70,6 +125,7 @@ pub struct PolicyProof{
 pub struct Proof{
     pub z_i: Vec<Fr>,
     pub c: Fr,
+    pub range: RangeProof,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
@@ -81,6 +137,8 @@ pub struct PresentationToken{
     pub len: usize,
     pub open_messages: Vec<Fr>,
     pub reveal_index: Vec<usize>,
+    pub predicates: Vec<RangePredicate>,
+    pub presentation_header: Vec<u8>,
 }
 
 pub fn hash_to_fr(input: &[u8], dst: &[u8]) -> Fr {
@@ -112,35 +170,61 @@ pub fn par_gen() -> PublicParameters{
     let g2_bytes = hex::decode(g2_hex).unwrap();
     let g1 = G1Affine::deserialize_compressed(&g1_bytes[..]).unwrap();
     let g2 = G2Affine::deserialize_compressed(&g2_bytes[..]).unwrap();
-    let x = Fr::from(123456789u64);
-    let x1 = G1Affine::from(g1 * x);
-    let x2 = G2Affine::from(g2 * x);
     let pp = PublicParameters{
         g1,
         g2,
-        x1,
-        x2,
     };
     return pp
 }
 
-pub fn key_gen(pp: &PublicParameters) -> KeyPair{
-    let mut rng = thread_rng();
-    let y_vec : Vec<Fr> = (0..50).map(|_| Fr::rand(&mut rng)).collect();
+// x || y_1 || ... || y_n as 32-byte little-endian scalars.
+pub fn secret_key_to_bytes(sk: &SecretKey) -> Zeroizing<Vec<u8>>{
+    return bbs::scalars_to_bytes(&key_scalars(sk))
+}
+
+pub fn secret_key_from_bytes(bytes: &[u8]) -> Result<SecretKey, WireError>{
+    let scalars = bbs::scalars_from_bytes(bytes)?;
+    if scalars.is_empty() {
+        return Err(WireError::InvalidPayload);
+    }
+    return Ok(SecretKey{ x: scalars[0], y: scalars[1..].to_vec() })
+}
 
-    let mut pk_y_pro : Vec<G2Projective> = Vec::new();
-    for i in 0..50{
+// a || b_0 || ... || b_n as 32-byte little-endian scalars, b_0 for X.
+pub fn policy_secret_key_to_bytes(psk: &PolicySecretKey) -> Zeroizing<Vec<u8>>{
+    let mut scalars = Zeroizing::new(vec![psk.a]);
+    scalars.extend_from_slice(&psk.b);
+    return bbs::scalars_to_bytes(&scalars)
+}
+
+pub fn policy_secret_key_from_bytes(bytes: &[u8]) -> Result<PolicySecretKey, WireError>{
+    let scalars = bbs::scalars_from_bytes(bytes)?;
+    if scalars.is_empty() {
+        return Err(WireError::InvalidPayload);
+    }
+    return Ok(PolicySecretKey{ a: scalars[0], b: scalars[1..].to_vec() })
+}
+
+// A key for credentials of up to message_len messages, with its own secret x.
+pub fn key_gen_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, message_len: usize, rng: &mut R) -> KeyPair{
+    let x = Fr::rand(rng);
+    let y_vec : Vec<Fr> = (0..message_len).map(|_| Fr::rand(rng)).collect();
+
+    let mut pk_pro : Vec<G2Projective> = vec![pp.g2 * x];
+    for i in 0..message_len{
         let pk_y_i = pp.g2 * y_vec[i];
-        pk_y_pro.push(pk_y_i);
+        pk_pro.push(pk_y_i);
     }
 
-    let pk_y = G2Projective::normalize_batch(&pk_y_pro);
+    let pk_elements = G2Projective::normalize_batch(&pk_pro);
 
     let sk = SecretKey{
+        x,
         y: y_vec,
     };
     let pk = PublicKey{
-        pk_y,
+        pk_x: pk_elements[0],
+        pk_y: pk_elements[1..].to_vec(),
     };
     let keypair = KeyPair{
         sk,
@@ -149,79 +233,234 @@ pub fn key_gen(pp: &PublicParameters) -> KeyPair{
     return keypair
 }
 
-pub fn sign(pp: &PublicParameters, sk: &SecretKey, messages: &Vec<Fr>) -> Signature{
-    let mut rng = thread_rng();
-    let r = Fr::rand(&mut rng);
+pub fn key_gen(pp: &PublicParameters, message_len: usize) -> KeyPair{
+    return key_gen_with_rng(pp, message_len, &mut OsRng)
+}
+
+// Appends fresh y_i to an existing key so it covers message_len messages. Signatures
+// made before stay valid, but the new public key has to be published again.
+pub fn extend_key_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, keypair: &mut KeyPair, message_len: usize, rng: &mut R){
+    while keypair.sk.y.len() < message_len{
+        let y_i = Fr::rand(rng);
+        keypair.sk.y.push(y_i);
+        keypair.pk.pk_y.push(G2Affine::from(pp.g2 * y_i));
+    }
+}
+
+pub fn extend_key(pp: &PublicParameters, keypair: &mut KeyPair, message_len: usize){
+    return extend_key_with_rng(pp, keypair, message_len, &mut OsRng)
+}
+
+// X, Y_1, ..., Y_n. The policy treats X as the key of a message fixed to 1, so it is blinded
+// and aggregated over the issuers like every Y_k.
+fn key_elements(pk: &PublicKey) -> Vec<G2Affine>{
+    let mut elements = vec![pk.pk_x];
+    elements.extend_from_slice(&pk.pk_y);
+    return elements
+}
+
+// x, y_1, ..., y_n, the discrete logs of key_elements.
+fn key_scalars(sk: &SecretKey) -> Zeroizing<Vec<Fr>>{
+    let mut scalars = Zeroizing::new(vec![sk.x]);
+    scalars.extend_from_slice(&sk.y);
+    return scalars
+}
+
+fn key_challenge(pp: &PublicParameters, pk: &PublicKey, k: &Vec<G2Affine>) -> Fr{
+    let dst = b"MY_KEY_POSSESSION_CHALLENGE_DST_V1";
+    let mut buffer = Vec::new();
+    pp.g2.serialize_compressed(&mut buffer).unwrap();
+    pk.serialize_compressed(&mut buffer).unwrap();
+    k.serialize_compressed(&mut buffer).unwrap();
+    return hash_to_fr(&buffer, dst)
+}
+
+// A key grown with extend_key needs a new proof.
+pub fn prove_key_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, keypair: &KeyPair, rng: &mut R) -> KeyProof{
+    let scalars = key_scalars(&keypair.sk);
+    let r: Zeroizing<Vec<Fr>> = Zeroizing::new((0..scalars.len()).map(|_| Fr::rand(rng)).collect());
+    let k_pro: Vec<G2Projective> = r.iter().map(|r_i| pp.g2 * r_i).collect();
+    let k = G2Projective::normalize_batch(&k_pro);
+    let c = key_challenge(pp, &keypair.pk, &k);
+    let z = r.iter().zip(scalars.iter()).map(|(r_i, y_i)| *r_i + c * y_i).collect();
+    return KeyProof{ c, z }
+}
+
+pub fn prove_key(pp: &PublicParameters, keypair: &KeyPair) -> KeyProof{
+    return prove_key_with_rng(pp, keypair, &mut OsRng)
+}
+
+pub fn verify_key(pp: &PublicParameters, pk: &PublicKey, proof: &KeyProof) -> Result<(), VerifyError>{
+    let elements = key_elements(pk);
+    if proof.z.len() != elements.len() {
+        return Err(VerifyError::MalformedInput("key proof does not match the key length"));
+    }
+    if !bbs::valid_points(&elements) {
+        return Err(VerifyError::MalformedInput("public key is the identity or not in G2"));
+    }
+    // K_i = g2^{z_i} * pk_i^{-c} over X, Y_1, ..., Y_n
+    let k_pro: Vec<G2Projective> = elements.iter().zip(&proof.z).map(|(pk_i, z_i)| pp.g2 * z_i - *pk_i * proof.c).collect();
+    let k = G2Projective::normalize_batch(&k_pro);
+    if key_challenge(pp, pk, &k) != proof.c {
+        return Err(VerifyError::ChallengeMismatch);
+    }
+    return Ok(())
+}
+
+fn check_key_proofs(pp: &PublicParameters, ipks: &Vec<PublicKey>, key_proofs: &Vec<KeyProof>) -> Result<(), VerifyError>{
+    if ipks.len() != key_proofs.len() {
+        return Err(VerifyError::MalformedInput("one key proof per issuer"));
+    }
+    for i in 0..ipks.len(){
+        if verify_key(pp, &ipks[i], &key_proofs[i]).is_err(){
+            return Err(VerifyError::InvalidKeyProof(i));
+        }
+    }
+    return Ok(())
+}
+
+pub fn sign_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, sk: &SecretKey, messages: &Vec<Fr>, rng: &mut R) -> Result<Signature, ProveError>{
+    let r = Fr::rand(rng);
 
     let message_len = messages.len();
+    if message_len > sk.y.len() {
+        return Err(ProveError::TooManyMessages { messages: message_len, key_len: sk.y.len() });
+    }
 
     let sigma1 = G1Affine::from(pp.g1 * r);
-    let mut temp_element = sk.y[0] * messages[0];
-    for i in 1..message_len{
+    let mut temp_element = Fr::from(0u64);
+    for i in 0..message_len{
         temp_element += sk.y[i] * messages[i];
     }
-    let sigma2 = G1Affine::from(pp.x1 * r + pp.g1 * (r * temp_element));
+    let sigma2 = G1Affine::from(pp.g1 * (r * (sk.x + temp_element)));
+
+    let signature = Signature{
+        sigma1,
+        sigma2,
+    };
+    return Ok(signature)
+}
+
+pub fn sign(pp: &PublicParameters, sk: &SecretKey, messages: &Vec<Fr>) -> Result<Signature, ProveError>{
+    return sign_with_rng(pp, sk, messages, &mut OsRng)
+}
+
+// sign for a holder that sent binding for its secret key sk, key = g1^sk, which is signed as
+// the message at holder_index without the issuer learning it: g1^{r y_k sk} = key^{r y_k}.
+// messages are the other messages, in order.
+#[allow(clippy::too_many_arguments)]
+pub fn sign_bound_with_rng<R: RngCore + CryptoRng>(
+    pp: &PublicParameters,
+    sk: &SecretKey,
+    binding: &KeyBinding,
+    holder_index: usize,
+    messages: &Vec<Fr>,
+    nonce: &[u8],
+    rng: &mut R,
+) -> Result<Signature, ProveError>{
+    let message_len = messages.len() + 1;
+    if message_len > sk.y.len() {
+        return Err(ProveError::TooManyMessages { messages: message_len, key_len: sk.y.len() });
+    }
+    if holder_index >= message_len {
+        return Err(ProveError::BadRevealIndex(holder_index));
+    }
+    holder::verify_key_binding(&pp.g1, binding, nonce).map_err(ProveError::HolderKeyRejected)?;
+
+    let r = Fr::rand(rng);
+    let sigma1 = G1Affine::from(pp.g1 * r);
+    let mut temp_element = Fr::from(0u64);
+    for (k, i) in (0..message_len).filter(|&i| i != holder_index).enumerate(){
+        temp_element += sk.y[i] * messages[k];
+    }
+    let sigma2 = G1Affine::from(pp.g1 * (r * (sk.x + temp_element)) + binding.key * (r * sk.y[holder_index]));
 
     let signature = Signature{
         sigma1,
         sigma2,
     };
-    return signature
+    return Ok(signature)
 }
 
-pub fn verify_sign(pp: &PublicParameters, pk: &PublicKey, signature: &Signature, messages: &Vec<Fr>) -> bool{
+pub fn sign_bound(
+    pp: &PublicParameters,
+    sk: &SecretKey,
+    binding: &KeyBinding,
+    holder_index: usize,
+    messages: &Vec<Fr>,
+    nonce: &[u8],
+) -> Result<Signature, ProveError>{
+    return sign_bound_with_rng(pp, sk, binding, holder_index, messages, nonce, &mut OsRng)
+}
+
+pub fn verify_sign(pp: &PublicParameters, pk: &PublicKey, signature: &Signature, messages: &Vec<Fr>) -> Result<(), VerifyError>{
     let message_len = messages.len();
+    if message_len == 0 || message_len > pk.pk_y.len() {
+        return Err(VerifyError::MalformedInput("message count does not fit the public key"));
+    }
+    // sigma1 = sigma2 = 1 would pass for any messages
+    if !bbs::valid_points(&[signature.sigma1, signature.sigma2]) {
+        return Err(VerifyError::MalformedInput("sigma1 or sigma2 is the identity or not in G1"));
+    }
 
     let mut temp_element = pk.pk_y[0] * messages[0];
     for i in 1..message_len{
         temp_element += pk.pk_y[i] * messages[i];
     }
-    let left = Bls12_381::pairing(signature.sigma2, pp.g2);
-    let right = Bls12_381::pairing(signature.sigma1, G2Affine::from(pp.x2 + temp_element));
-
-    if left != right{
-        println!("Signature verification failed: pairing mismatch");
-        return false
+    // e(sigma2, g2) = e(sigma1, X * \prod Y_i^m_i)
+    let pairing = Bls12_381::multi_pairing([signature.sigma2, -signature.sigma1], [pp.g2, G2Affine::from(temp_element + pk.pk_x)]);
+    if !pairing.is_zero(){
+        return Err(VerifyError::PairingCheckFailed(1))
     }
 
-    return true
+    return Ok(())
 }
 
-pub fn set_policy(
+pub fn set_policy_with_rng<R: RngCore + CryptoRng>(
     pp: &PublicParameters,
     ipks: &Vec<PublicKey>,
-)-> (PolicyKeyPair, PolicyProof){
-    let ipks_len = ipks.len();
-    let ipks_len_1 = Fr::from(ipks_len as u64 - 1);
-    let mut rng = thread_rng();
-    let a = Fr::rand(&mut rng);
-    let a_inv = a.inverse().unwrap();
-    let mut b : Vec<Fr> = Vec::new();
+    key_proofs: &Vec<KeyProof>,
+    rng: &mut R,
+)-> Result<(PolicyKeyPair, PolicyProof), VerifyError>{
+    if ipks.is_empty() {
+        return Err(VerifyError::MalformedInput("policy has no issuers"));
+    }
+    if ipks.iter().any(|ipk| ipk.pk_y.len() != ipks[0].pk_y.len()) {
+        return Err(VerifyError::MalformedInput("policy entries have inconsistent lengths"));
+    }
+    check_key_proofs(pp, ipks, key_proofs)?;
+    let keys: Vec<Vec<G2Affine>> = ipks.iter().map(key_elements).collect();
+    let key_len = keys[0].len();
+    let ipks_len_1 = Fr::from(ipks.len() as u64 - 1);
+    let a = Zeroizing::new(Fr::rand(rng));
+    let a_inv = Zeroizing::new(a.inverse().unwrap());
+    let mut b : Zeroizing<Vec<Fr>> = Zeroizing::new(Vec::new());
     let mut vec_b : Vec<G2Affine> = Vec::new();
-    for _ in 0..ipks[0].pk_y.len(){
-        let b_i = Fr::rand(&mut rng);
+    for _ in 0..key_len{
+        let b_i = Fr::rand(rng);
         let vec_b_i = G2Affine::from(pp.g2 * (b_i * ipks_len_1));
         b.push(b_i);
         vec_b.push(vec_b_i);
     }
 
-    let s = G2Affine::from(pp.g2 * a.clone());
+    let s = G2Affine::from(pp.g2 * *a);
     let mut t : Vec<Vec<G2Affine>> = Vec::new();
     for i in 0..ipks.len(){
         let mut t_i : Vec<G2Affine> = Vec::new();
-        for j in 0..ipks[i].pk_y.len(){
-            let t_ij = G2Affine::from((ipks[i].pk_y[j] + pp.g2 * b[j]) * a);
+        for j in 0..key_len{
+            let t_ij = G2Affine::from((keys[i][j] + pp.g2 * b[j]) * *a);
             t_i.push(t_ij);
         }
         t.push(t_i);
     }
 
     let psk = PolicySecretKey{
-        a,
-        b,
+        a: *a,
+        b: b.to_vec(),
     };
     let ppk = PolicyPublicKey{
         ipks: ipks.clone(),
+        key_proofs: key_proofs.clone(),
         s,
         vec_b: vec_b.clone(),
         t: t.clone(),
@@ -231,11 +470,11 @@ pub fn set_policy(
         ppk: ppk.clone(),
     };
 
-    let mut r: Vec<Fr> = vec![
-        Fr::rand(&mut rng),
-    ];
-    for _ in 0..ipks[0].pk_y.len(){
-        let r_i = Fr::rand(&mut rng);
+    let mut r: Zeroizing<Vec<Fr>> = Zeroizing::new(vec![
+        Fr::rand(rng),
+    ]);
+    for _ in 0..key_len{
+        let r_i = Fr::rand(rng);
         r.push(r_i);
     }
 
@@ -248,7 +487,7 @@ pub fn set_policy(
     let mut k_t: Vec<Vec<G2Projective>> = Vec::new();
     for i in 0..ipks.len(){
         let mut k_t_i : Vec<G2Projective> = Vec::new();
-        for j in 0..ipks[i].pk_y.len(){
+        for j in 0..key_len{
             let k_t_ij = t[i][j] * r[0] + pp.g2 * r[j+1];
             k_t_i.push(k_t_ij);
         }
@@ -264,9 +503,9 @@ pub fn set_policy(
     let c = hash_to_fr(&buffer, dst);
 
     let mut vec_z : Vec<Fr> = vec![
-        r[0] + c * a_inv,
+        r[0] + c * *a_inv,
     ];
-    for i in 0..ipks[0].pk_y.len(){
+    for i in 0..key_len{
         let z_i = r[i+1] - c * psk.b[i];
         vec_z.push(z_i);
     }
@@ -274,14 +513,39 @@ pub fn set_policy(
         vec_z,
         c,
     };
-    return (policy_keypair, policy_proof);
+    return Ok((policy_keypair, policy_proof));
+}
+
+pub fn set_policy(
+    pp: &PublicParameters,
+    ipks: &Vec<PublicKey>,
+    key_proofs: &Vec<KeyProof>,
+)-> Result<(PolicyKeyPair, PolicyProof), VerifyError>{
+    return set_policy_with_rng(pp, ipks, key_proofs, &mut OsRng)
 }
 
 pub fn audit_policy(
     pp: &PublicParameters,
     ppk: &PolicyPublicKey,
     proof: &PolicyProof,
-) -> bool{
+) -> Result<(), VerifyError>{
+    if ppk.ipks.is_empty() {
+        return Err(VerifyError::MalformedInput("policy has no issuers"));
+    }
+    let key_len = ppk.vec_b.len();
+    if proof.vec_z.len() != key_len + 1 || ppk.t.len() != ppk.ipks.len() {
+        return Err(VerifyError::MalformedInput("policy proof does not match the policy size"));
+    }
+    let keys: Vec<Vec<G2Affine>> = ppk.ipks.iter().map(key_elements).collect();
+    for i in 0..ppk.ipks.len(){
+        if keys[i].len() != key_len || ppk.t[i].len() != key_len {
+            return Err(VerifyError::MalformedInput("policy entries have inconsistent lengths"));
+        }
+    }
+    if !bbs::valid_points(&[ppk.s]) || !bbs::valid_points(&ppk.vec_b) || !ppk.t.iter().all(|t_i| bbs::valid_points(t_i)) {
+        return Err(VerifyError::MalformedInput("policy key is the identity or not in G2"));
+    }
+    check_key_proofs(pp, &ppk.ipks, &ppk.key_proofs)?;
     let ipks_len_1 = Fr::from(ppk.ipks.len() as u64 - 1);
 
     let k_s = ppk.s * proof.vec_z[0] + pp.g2 * (-proof.c);
@@ -293,8 +557,8 @@ pub fn audit_policy(
     let mut k_t: Vec<Vec<G2Projective>> = Vec::new();
     for i in 0..ppk.ipks.len(){
         let mut k_t_i : Vec<G2Projective> = Vec::new();
-        for j in 0..ppk.ipks[i].pk_y.len(){
-            let k_t_ij = ppk.t[i][j] * proof.vec_z[0] + pp.g2 * proof.vec_z[j+1] + ppk.ipks[i].pk_y[j] * (-proof.c);
+        for j in 0..key_len{
+            let k_t_ij = ppk.t[i][j] * proof.vec_z[0] + pp.g2 * proof.vec_z[j+1] + keys[i][j] * (-proof.c);
             k_t_i.push(k_t_ij);
         }
         k_t.push(k_t_i);
@@ -309,35 +573,37 @@ pub fn audit_policy(
     let c_calculated = hash_to_fr(&buffer, dst);
 
     if c_calculated != proof.c{
-        println!("Policy proof verification failed: challenge mismatch");
-        return false
+        return Err(VerifyError::ChallengeMismatch)
     }
 
-    return true
+    return Ok(())
 }
 
-pub fn create_proof(
+// create_proof that also shows min <= m_i < max for every predicate on a hidden message m_i.
+#[allow(clippy::too_many_arguments)]
+pub fn create_proof_with_predicates_with_rng<R: RngCore + CryptoRng>(
     _: &PublicParameters,
     pk: &PublicKey,
     cred: &Signature,
     ppk: &PolicyPublicKey,
     message_list: &Vec<Fr>,
     reveal_index: &Vec<usize>,
-) -> PresentationToken{
-    let mut match_flag = false;
+    predicates: &Vec<RangePredicate>,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<PresentationToken, ProveError>{
     let (ipks, s, vec_b, t) = (&ppk.ipks, &ppk.s, &ppk.vec_b, &ppk.t);
-    for ipk in &ipks.clone(){
-        if pk == ipk{
-            match_flag = true;
-        }
-    }
-    if !match_flag{
-        panic!("The provided public key does not match any in the policy public key.");
+    if !ipks.contains(pk){
+        return Err(ProveError::IssuerNotInPolicy);
     }
 
-    let mut rng = thread_rng();
+    // vec_b[0] is for X
     let message_len = message_list.len();
-    let close_len = message_len - reveal_index.len();
+    let key_len = vec_b.len().saturating_sub(1);
+    if message_len > key_len {
+        return Err(ProveError::TooManyMessages { messages: message_len, key_len: key_len });
+    }
+    proof::check_reveal_index(message_len, reveal_index).map_err(ProveError::BadRevealIndex)?;
 
     let mut close_index : Vec<usize> = Vec::new();
     let mut open_messages : Vec<Fr> = Vec::new();
@@ -348,23 +614,31 @@ pub fn create_proof(
             close_index.push(i);
         }
     }
+    let close_len = close_index.len();
 
-    let r_1 = Fr::rand(&mut rng);
-    let r_2 = Fr::rand(&mut rng);
-    let mut k_i = Vec::new();
+    // wiped once the proof is built
+    let r_1 = Zeroizing::new(Fr::rand(rng));
+    let r_2 = Zeroizing::new(Fr::rand(rng));
+    let mut k_i = Zeroizing::new(Vec::with_capacity(close_len));
     for _ in 0..close_len{
-        let k_i_i = Fr::rand(&mut rng);
+        let k_i_i = Fr::rand(rng);
         k_i.push(k_i_i);
     }
 
-    let new_sigma1 = G1Affine::from(cred.sigma1 * r_1);
-    let new_sigma2 = G1Affine::from(cred.sigma2 * r_1 + new_sigma1 * (-r_2));
-    let mut sigma_tilde_pro = s.clone() * r_2;
+    let new_sigma1 = G1Affine::from(cred.sigma1 * *r_1);
+    let new_sigma2 = G1Affine::from(cred.sigma2 * *r_1 + new_sigma1 * (-*r_2));
+    // the other issuers' X enter with the fixed message 1
+    let mut sigma_tilde_pro = *s * *r_2;
+    for j in 0..ipks.len(){
+        if &ipks[j] != pk{
+            sigma_tilde_pro += t[j][0];
+        }
+    }
     for i in 0..message_len{
         let mut sigma_tilde_i = G2Projective::from(G2Affine::identity());
         for j in 0..ipks.len(){
             if &ipks[j] != pk{
-                sigma_tilde_i += t[j][i];
+                sigma_tilde_i += t[j][i + 1];
             }
         }
         sigma_tilde_pro += sigma_tilde_i * message_list[i];
@@ -373,13 +647,18 @@ pub fn create_proof(
 
     let mut k_input = G2Projective::from(G2Affine::identity());
     for i in 0..close_len{
-        let mut k_input_ij = G2Projective::from(vec_b[close_index[i].clone()]);
+        let mut k_input_ij = G2Projective::from(vec_b[close_index[i] + 1]);
         for j in 0..ipks.len(){
-            k_input_ij += ipks[j].pk_y[close_index[i].clone()] ;
+            k_input_ij += ipks[j].pk_y[close_index[i]] ;
         }
         k_input += k_input_ij * k_i[i];
     }
     let k = Bls12_381::pairing(new_sigma1, G2Affine::from(k_input));
+    // the range proofs reuse k_i of the hidden message they are about
+    let positions = range::predicate_positions(predicates, &close_index).map_err(ProveError::BadRevealIndex)?;
+    let range_messages: Zeroizing<Vec<Fr>> = Zeroizing::new(positions.iter().map(|&i| message_list[close_index[i]]).collect());
+    let range_blindings: Zeroizing<Vec<Fr>> = Zeroizing::new(positions.iter().map(|&i| k_i[i]).collect());
+    let (mut range_proof, range_witness, range_t) = range::range_commit_with_rng(predicates, &range_messages, &range_blindings, rng)?;
 
     let dst = b"MY_CHALLENGE_GENERATOR_DST_V1";
     let c_inputs = vec![
@@ -396,6 +675,9 @@ pub fn create_proof(
     for open_msg in &open_messages{
         open_msg.serialize_compressed(&mut buffer).unwrap();
     }
+    range::serialize_challenge_input(predicates, &range_proof, &range_t, &mut buffer);
+    let presentation_header = presentation_header.to_vec();
+    presentation_header.serialize_compressed(&mut buffer).unwrap();
     let c = hash_to_fr(&buffer, dst);
 
     let mut z_i : Vec<Fr> = Vec::new();
@@ -403,9 +685,11 @@ pub fn create_proof(
         let z_i_i = k_i[i] + c * message_list[close_index[i]];
         z_i.push(z_i_i);
     }
+    range::range_respond(&mut range_proof, &range_witness, &c);
     let proof = Proof{
         z_i,
         c,
+        range: range_proof,
     };
     let pt = PresentationToken{
         sigma1: new_sigma1,
@@ -415,23 +699,231 @@ pub fn create_proof(
         len: message_len,
         open_messages,
         reveal_index: reveal_index.clone(),
+        predicates: predicates.clone(),
+        presentation_header,
     };
-    return pt
+    return Ok(pt)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_proof_with_predicates(
+    pp: &PublicParameters,
+    pk: &PublicKey,
+    cred: &Signature,
+    ppk: &PolicyPublicKey,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    presentation_header: &[u8],
+) -> Result<PresentationToken, ProveError>{
+    return create_proof_with_predicates_with_rng(pp, pk, cred, ppk, message_list, reveal_index, predicates, presentation_header, &mut OsRng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_proof_with_rng<R: RngCore + CryptoRng>(
+    pp: &PublicParameters,
+    pk: &PublicKey,
+    cred: &Signature,
+    ppk: &PolicyPublicKey,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<PresentationToken, ProveError>{
+    return create_proof_with_predicates_with_rng(pp, pk, cred, ppk, message_list, reveal_index, &Vec::new(), presentation_header, rng)
+}
+
+pub fn create_proof(
+    pp: &PublicParameters,
+    pk: &PublicKey,
+    cred: &Signature,
+    ppk: &PolicyPublicKey,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    presentation_header: &[u8],
+) -> Result<PresentationToken, ProveError>{
+    return create_proof_with_rng(pp, pk, cred, ppk, message_list, reveal_index, presentation_header, &mut OsRng)
+}
+
+// create_proof for a credential bound to a holder key. message_list are the messages without
+// the holder secret, which goes in at holder_index; reveal_index counts it.
+#[allow(clippy::too_many_arguments)]
+pub fn create_proof_bound_with_rng<R: RngCore + CryptoRng>(
+    pp: &PublicParameters,
+    pk: &PublicKey,
+    cred: &Signature,
+    ppk: &PolicyPublicKey,
+    holder_secret: &HolderSecretKey,
+    holder_index: usize,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<PresentationToken, ProveError>{
+    if reveal_index.contains(&holder_index) {
+        return Err(ProveError::BadRevealIndex(holder_index));
+    }
+    let messages = holder::with_holder_secret(message_list, holder_index, holder_secret)?;
+    return create_proof_with_rng(pp, pk, cred, ppk, &messages, reveal_index, presentation_header, rng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_proof_bound(
+    pp: &PublicParameters,
+    pk: &PublicKey,
+    cred: &Signature,
+    ppk: &PolicyPublicKey,
+    holder_secret: &HolderSecretKey,
+    holder_index: usize,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    presentation_header: &[u8],
+) -> Result<PresentationToken, ProveError>{
+    return create_proof_bound_with_rng(pp, pk, cred, ppk, holder_secret, holder_index, message_list, reveal_index, presentation_header, &mut OsRng)
+}
+
+// sign with the messages given as values of a credential schema, in schema order.
+pub fn sign_attributes_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, sk: &SecretKey, schema: &CredentialSchema, values: &Vec<AttributeValue>, rng: &mut R) -> Result<Signature, ProveError>{
+    let messages = schema::encode_messages(schema, values)?;
+    return sign_with_rng(pp, sk, &messages, rng)
+}
+
+pub fn sign_attributes(pp: &PublicParameters, sk: &SecretKey, schema: &CredentialSchema, values: &Vec<AttributeValue>) -> Result<Signature, ProveError>{
+    return sign_attributes_with_rng(pp, sk, schema, values, &mut OsRng)
+}
+
+// create_proof with schema-typed values, revealing the attributes named in reveal.
+#[allow(clippy::too_many_arguments)]
+pub fn create_proof_attributes_with_rng<R: RngCore + CryptoRng>(
+    pp: &PublicParameters,
+    pk: &PublicKey,
+    cred: &Signature,
+    ppk: &PolicyPublicKey,
+    schema: &CredentialSchema,
+    values: &Vec<AttributeValue>,
+    reveal: &[&str],
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<PresentationToken, ProveError>{
+    let message_list = schema::encode_messages(schema, values)?;
+    let reveal_index = schema::reveal_index(schema, reveal)?;
+    return create_proof_with_rng(pp, pk, cred, ppk, &message_list, &reveal_index, presentation_header, rng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_proof_attributes(
+    pp: &PublicParameters,
+    pk: &PublicKey,
+    cred: &Signature,
+    ppk: &PolicyPublicKey,
+    schema: &CredentialSchema,
+    values: &Vec<AttributeValue>,
+    reveal: &[&str],
+    presentation_header: &[u8],
+) -> Result<PresentationToken, ProveError>{
+    return create_proof_attributes_with_rng(pp, pk, cred, ppk, schema, values, reveal, presentation_header, &mut OsRng)
+}
+
+// g2^{(n-1) b_0} * \prod_{j=1}^{n} X_j, then g2^{(n-1) b_{i+1}} * \prod_{j=1}^{n} Y_{j,i} for
+// i < key_len: the key elements the policy aggregates over all of its issuers.
+fn policy_keys(pp: &PublicParameters, pkp: &PolicyKeyPair, key_len: usize) -> Vec<G2Projective>{
+    let (ppk, psk) = (&pkp.ppk, &pkp.psk);
+    let ipks_len_1 = Fr::from(ppk.ipks.len() as u64 - 1);
+    let mut keys = Vec::new();
+    for idx in 0..(key_len + 1).min(psk.b.len()){
+        let mut key_i = pp.g2 * (psk.b[idx] * ipks_len_1);
+        for j in 0..ppk.ipks.len(){
+            key_i += if idx == 0 { ppk.ipks[j].pk_x } else { ppk.ipks[j].pk_y[idx - 1] };
+        }
+        keys.push(key_i);
+    }
+    return keys
+}
+
+// The longest message vector every issuer key and the policy secret cover.
+fn policy_key_len(pkp: &PolicyKeyPair) -> usize{
+    let ipk_len = pkp.ppk.ipks.iter().map(|ipk| ipk.pk_y.len()).min().unwrap_or(0);
+    return ipk_len.min(pkp.psk.b.len().saturating_sub(1))
+}
+
+// The policy with its aggregated keys and g2 precomputed, for a verifier that checks many
+// presentation tokens under the same policy. Build it once with verifier_context.
+#[derive(Clone, Debug)]
+pub struct VerifierContext{
+    pub pp: PublicParameters,
+    pub pkp: PolicyKeyPair,
+    pub policy_keys: Vec<G2Projective>,
+    pub g2_prepared: G2Prepared,
+}
+
+pub fn verifier_context(pp: &PublicParameters, pkp: &PolicyKeyPair) -> Result<VerifierContext, VerifyError>{
+    if pkp.ppk.ipks.is_empty() {
+        return Err(VerifyError::MalformedInput("policy has no issuers"));
+    }
+    let context = VerifierContext{
+        pp: pp.clone(),
+        pkp: pkp.clone(),
+        policy_keys: policy_keys(pp, pkp, policy_key_len(pkp)),
+        g2_prepared: G2Prepared::from(pp.g2),
+    };
+    return Ok(context)
 }
 
 pub fn verify_proof(
     pp: &PublicParameters,
     pt: &PresentationToken,
     pkp: &PolicyKeyPair,
-) -> bool{
-    if pt.sigma1 == G1Affine::identity(){
-        println!("Proof verification failed: sigma1 is identity");
-        return false
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    if pkp.ppk.ipks.is_empty() {
+        return Err(VerifyError::MalformedInput("policy has no issuers"));
+    }
+    let keys = policy_keys(pp, pkp, pt.len.min(policy_key_len(pkp)));
+    return proof_check(pt, pkp, presentation_header, &keys, &G2Prepared::from(pp.g2))
+}
+
+// verify_proof for a presentation token of a credential bound to a holder key at holder_index.
+pub fn verify_proof_bound(
+    pp: &PublicParameters,
+    pt: &PresentationToken,
+    pkp: &PolicyKeyPair,
+    holder_index: usize,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    holder::check_bound(pt.len, &pt.reveal_index, holder_index).map_err(|_| VerifyError::HolderSecretNotHidden(holder_index))?;
+    return verify_proof(pp, pt, pkp, presentation_header)
+}
+
+pub fn verify_proof_with_context(context: &VerifierContext, pt: &PresentationToken, presentation_header: &[u8]) -> Result<(), VerifyError>{
+    return proof_check(pt, &context.pkp, presentation_header, &context.policy_keys, &context.g2_prepared)
+}
+
+fn proof_check(
+    pt: &PresentationToken,
+    pkp: &PolicyKeyPair,
+    presentation_header: &[u8],
+    policy_keys: &[G2Projective],
+    g2_prepared: &G2Prepared,
+) -> Result<(), VerifyError>{
+    if pt.presentation_header != presentation_header {
+        return Err(VerifyError::PresentationHeaderMismatch);
+    }
+    if !bbs::valid_points(&[pt.sigma1, pt.sigma2]) {
+        return Err(VerifyError::MalformedInput("sigma1 or sigma2 is the identity or not in G1"))
+    }
+    if !bbs::valid_points(&[pt.sigma_tilde]) {
+        return Err(VerifyError::MalformedInput("sigma_tilde is the identity or not in G2"))
     }
     let (ppk, psk) = (&pkp.ppk, &pkp.psk);
+    // policy_keys[0] is the aggregate of X
     let message_len = pt.len;
-    let close_len = message_len - pt.reveal_index.len();
-    let ipks_len_1 = Fr::from(ppk.ipks.len() as u64 - 1);
+    if message_len >= policy_keys.len() {
+        return Err(VerifyError::MalformedInput("message length exceeds the policy keys"));
+    }
+    proof::check_reveal_index(message_len, &pt.reveal_index).map_err(VerifyError::BadRevealIndex)?;
+    if pt.open_messages.len() != pt.reveal_index.len() {
+        return Err(VerifyError::MalformedInput("revealed messages do not match reveal indices"));
+    }
 
     let mut close_index : Vec<usize> = Vec::new();
     for i in 0..message_len{
@@ -439,30 +931,30 @@ pub fn verify_proof(
             close_index.push(i);
         }
     }
+    let close_len = close_index.len();
+    if pt.proof.z_i.len() != close_len {
+        return Err(VerifyError::MalformedInput("response count does not match hidden messages"));
+    }
+    let positions = range::predicate_positions(&pt.predicates, &close_index).map_err(VerifyError::BadRevealIndex)?;
+    let range_responses: Vec<Fr> = positions.iter().map(|&i| pt.proof.z_i[i]).collect();
+    let range_t = range::range_verify(&pt.predicates, &pt.proof.range, &range_responses, &pt.proof.c)?;
 
-    let a_inv = psk.a.clone().inverse().unwrap();
-    let mut t_input = pp.x2 * (-Fr::from(1u64)) + pt.sigma_tilde * (a_inv);
+    let a_inv = Zeroizing::new(psk.a.inverse().unwrap());
+    let mut t_input = -policy_keys[0] + pt.sigma_tilde * *a_inv;
     for i in 0..pt.reveal_index.len(){
-        let idx = pt.reveal_index[i].clone();
-        let mut t_input_i = pp.g2 * (psk.b[idx] * ipks_len_1);
-        for j in 0..ppk.ipks.len(){
-            t_input_i += ppk.ipks[j].pk_y[idx];
-        }
-        t_input += t_input_i * (-pt.open_messages[i]);
+        t_input += policy_keys[pt.reveal_index[i] + 1] * (-pt.open_messages[i]);
     }
     t_input *= -pt.proof.c;
 
-    let t = Bls12_381::pairing(pt.sigma2, pp.g2 * (-pt.proof.c)) + Bls12_381::pairing(pt.sigma1, G2Affine::from(t_input));
     let mut k_input = G2Projective::from(G2Affine::identity());
     for i in 0..close_len{
-        let idx = close_index[i].clone();
-        let mut k_input_i = pp.g2 * (psk.b[idx] * ipks_len_1);
-        for j in 0..ppk.ipks.len(){
-            k_input_i += ppk.ipks[j].pk_y[idx];
-        }
-        k_input += k_input_i * pt.proof.z_i[i];
+        k_input += policy_keys[close_index[i] + 1] * pt.proof.z_i[i];
     }
-    let k = t + Bls12_381::pairing(pt.sigma1, G2Affine::from(k_input));
+    // k = e(sigma2, g2^{-c}) * e(sigma1, t_input) * e(sigma1, k_input)
+    let k = Bls12_381::multi_pairing(
+        [G1Affine::from(pt.sigma2 * (-pt.proof.c)), pt.sigma1],
+        [g2_prepared.clone(), G2Prepared::from(t_input + k_input)],
+    );
 
     let dst = b"MY_CHALLENGE_GENERATOR_DST_V1";
     let c_inputs = vec![
@@ -479,19 +971,21 @@ pub fn verify_proof(
     for open_msg in &pt.open_messages{
         open_msg.serialize_compressed(&mut buffer).unwrap();
     }
+    range::serialize_challenge_input(&pt.predicates, &pt.proof.range, &range_t, &mut buffer);
+    pt.presentation_header.serialize_compressed(&mut buffer).unwrap();
     let c_calculated = hash_to_fr(&buffer, dst);
 
     if c_calculated != pt.proof.c{
-        println!("Proof verification failed: challenge mismatch");
-        return false
+        return Err(VerifyError::ChallengeMismatch)
     }
 
-    return true
+    return Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use rand::Rng;
+    use mybbs::error::SchemaError;
+    use rand::{thread_rng, Rng};
     use super::*;
 
     pub type Fr = <Bls12_381 as Pairing>::ScalarField;
@@ -506,26 +1000,186 @@ mod tests {
 
         let pp = par_gen();
 
-        let keypair = key_gen(&pp);
+        let mut keypair = key_gen(&pp, message_len - 1);
+        assert_eq!(sign(&pp, &keypair.sk, &messages), Err(ProveError::TooManyMessages { messages: message_len, key_len: message_len - 1 }));
+        extend_key(&pp, &mut keypair, message_len);
 
-        let signature = sign(&pp, &keypair.sk, &messages);
-        let bool1 = verify_sign(&pp, &keypair.pk, &signature, &messages);
-        assert_eq!(bool1,true);
+        let signature = sign(&pp, &keypair.sk, &messages).unwrap();
+        let result = verify_sign(&pp, &keypair.pk, &signature, &messages);
+        assert_eq!(result, Ok(()));
 
         let mut ipks = Vec::new();
+        let mut key_proofs = Vec::new();
         for _ in 0..ipks_len{
-            let ipk = key_gen(&pp).pk;
-            ipks.push(ipk);
+            let issuer_keypair = key_gen(&pp, message_len);
+            key_proofs.push(prove_key(&pp, &issuer_keypair));
+            ipks.push(issuer_keypair.pk);
         }
         let r = rng.gen_range(0..ipks_len);
         ipks[r] = keypair.pk.clone();
-        let (policy_keypair, policy_proof) = set_policy(&pp, &ipks);
-        let bool_policy = audit_policy(&pp, &policy_keypair.ppk, &policy_proof);
-        assert_eq!(bool_policy,true);
+        key_proofs[r] = prove_key(&pp, &keypair);
+        let (policy_keypair, policy_proof) = set_policy(&pp, &ipks, &key_proofs).unwrap();
+        let result = audit_policy(&pp, &policy_keypair.ppk, &policy_proof);
+        assert_eq!(result, Ok(()));
+
+        // a key chosen as pk - \sum_{j != i} ipk_j cancels the others out, but its owner cannot prove possession
+        let i = (r + 1) % ipks_len;
+        let mut rogue_keys = ipks.clone();
+        rogue_keys[i].pk_y = (0..message_len).map(|idx| (0..ipks_len).filter(|&j| j != i).fold(G2Projective::from(keypair.pk.pk_y[idx]), |acc, j| acc - ipks[j].pk_y[idx]).into()).collect();
+        assert_eq!(set_policy(&pp, &rogue_keys, &key_proofs).unwrap_err(), VerifyError::InvalidKeyProof(i));
+        let mut extended = keypair.clone();
+        extend_key(&pp, &mut extended, message_len + 1);
+        assert_eq!(verify_key(&pp, &extended.pk, &key_proofs[r]), Err(VerifyError::MalformedInput("key proof does not match the key length")));
+        let mut swapped = policy_keypair.ppk.clone();
+        swapped.key_proofs.swap(r, i);
+        assert_eq!(audit_policy(&pp, &swapped, &policy_proof), Err(VerifyError::InvalidKeyProof(i.min(r))));
 
         let reveal_index = vec![0, 3, 5];
-        let pt = create_proof(&pp, &keypair.pk, &signature, &policy_keypair.ppk, &messages, &reveal_index);
-        let bool2 = verify_proof(&pp, &pt, &policy_keypair);
-        assert_eq!(bool2,true);
+        let nonce = b"verifier-nonce-1";
+        let pt = create_proof(&pp, &keypair.pk, &signature, &policy_keypair.ppk, &messages, &reveal_index, nonce).unwrap();
+        let result = verify_proof(&pp, &pt, &policy_keypair, nonce);
+        assert_eq!(result, Ok(()));
+        let context = verifier_context(&pp, &policy_keypair).unwrap();
+        assert_eq!(verify_proof_with_context(&context, &pt, nonce), Ok(()));
+
+        let mut tampered = pt.clone();
+        tampered.open_messages[0] += Fr::from(1u64);
+        assert_eq!(verify_proof(&pp, &tampered, &policy_keypair, nonce), Err(VerifyError::ChallengeMismatch));
+
+        assert_eq!(verify_proof(&pp, &pt, &policy_keypair, b"verifier-nonce-2"), Err(VerifyError::PresentationHeaderMismatch));
+        let mut replayed = pt.clone();
+        replayed.presentation_header = b"verifier-nonce-2".to_vec();
+        assert_eq!(verify_proof(&pp, &replayed, &policy_keypair, b"verifier-nonce-2"), Err(VerifyError::ChallengeMismatch));
+
+        let schema = CredentialSchema{
+            id: "employee".to_string(),
+            attributes: vec![
+                schema::Attribute{ name: "name".to_string(), attribute_type: schema::AttributeType::Utf8String },
+                schema::Attribute{ name: "age".to_string(), attribute_type: schema::AttributeType::Integer },
+            ],
+        };
+        let values = vec![AttributeValue::Utf8String("Alice".to_string()), AttributeValue::Integer(30)];
+        let typed = sign_attributes(&pp, &keypair.sk, &schema, &values).unwrap();
+        let typed_pt = create_proof_attributes(&pp, &keypair.pk, &typed, &policy_keypair.ppk, &schema, &values, &["age"], nonce).unwrap();
+        assert_eq!(verify_proof(&pp, &typed_pt, &policy_keypair, nonce), Ok(()));
+        assert_eq!(typed_pt.open_messages, vec![schema::encode_value(&values[1]).unwrap()]);
+        assert_eq!(create_proof_attributes(&pp, &keypair.pk, &typed, &policy_keypair.ppk, &schema, &values, &["salary"], nonce).unwrap_err(), ProveError::Schema(SchemaError::UnknownAttribute(0)));
+
+        let typed_messages = schema::encode_messages(&schema, &values).unwrap();
+        let adult = vec![range::integer_range(1, 18, i64::MAX)];
+        let adult_pt = create_proof_with_predicates(&pp, &keypair.pk, &typed, &policy_keypair.ppk, &typed_messages, &vec![0], &adult, nonce).unwrap();
+        assert_eq!(verify_proof(&pp, &adult_pt, &policy_keypair, nonce), Ok(()));
+        let mut widened = adult_pt.clone();
+        widened.predicates[0].min = 0;
+        assert_eq!(verify_proof(&pp, &widened, &policy_keypair, nonce), Err(VerifyError::ChallengeMismatch));
+        let senior = vec![range::integer_range(1, 65, i64::MAX)];
+        assert_eq!(create_proof_with_predicates(&pp, &keypair.pk, &typed, &policy_keypair.ppk, &typed_messages, &vec![0], &senior, nonce).unwrap_err(), ProveError::Range(mybbs::error::ProveError::PredicateNotSatisfied(0)));
+
+        // a credential bound to a holder key only presents with the holder secret
+        let holder_secret = holder::holder_key_gen();
+        let holder_nonce = b"issuance-nonce";
+        let binding = holder::key_binding(&pp.g1, &holder_secret, holder_nonce);
+        let bound_messages = messages[1..].to_vec();
+        let bound_signature = sign_bound(&pp, &keypair.sk, &binding, 4, &bound_messages, holder_nonce).unwrap();
+        let all = holder::with_holder_secret(&bound_messages, 4, &holder_secret).unwrap();
+        assert_eq!(verify_sign(&pp, &keypair.pk, &bound_signature, &all), Ok(()));
+        assert_eq!(sign_bound(&pp, &keypair.sk, &binding, 4, &bound_messages, nonce).unwrap_err(), ProveError::HolderKeyRejected(mybbs::error::VerifyError::ChallengeMismatch));
+        let bound_pt = create_proof_bound(&pp, &keypair.pk, &bound_signature, &policy_keypair.ppk, &holder_secret, 4, &bound_messages, &reveal_index, nonce).unwrap();
+        assert_eq!(verify_proof_bound(&pp, &bound_pt, &policy_keypair, 4, nonce), Ok(()));
+        let stolen_pt = create_proof_bound(&pp, &keypair.pk, &bound_signature, &policy_keypair.ppk, &holder::holder_key_gen(), 4, &bound_messages, &reveal_index, nonce).unwrap();
+        assert_eq!(verify_proof_bound(&pp, &stolen_pt, &policy_keypair, 4, nonce), Err(VerifyError::ChallengeMismatch));
+        assert_eq!(verify_proof_bound(&pp, &pt, &policy_keypair, 3, nonce), Err(VerifyError::HolderSecretNotHidden(3)));
+        assert_eq!(create_proof_bound(&pp, &keypair.pk, &bound_signature, &policy_keypair.ppk, &holder_secret, 4, &bound_messages, &vec![4], nonce).unwrap_err(), ProveError::BadRevealIndex(4));
+
+        let stranger = key_gen(&pp, message_len).pk;
+        let result = create_proof(&pp, &stranger, &signature, &policy_keypair.ppk, &messages, &reveal_index, nonce);
+        assert_eq!(result.unwrap_err(), ProveError::IssuerNotInPolicy);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn rejects_malformed_input() {
+        let message_len = 10;
+        let mut rng = thread_rng();
+        let messages: Vec<Fr> = (0..message_len).map(|_| Fr::rand(&mut rng)).collect();
+        let pp = par_gen();
+        let keypair = key_gen(&pp, message_len);
+        let signature = sign(&pp, &keypair.sk, &messages).unwrap();
+        let other = key_gen(&pp, message_len);
+        let (policy_keypair, policy_proof) = set_policy(&pp, &vec![other.pk.clone(), keypair.pk.clone()], &vec![prove_key(&pp, &other), prove_key(&pp, &keypair)]).unwrap();
+        let pt = create_proof(&pp, &keypair.pk, &signature, &policy_keypair.ppk, &messages, &vec![0, 3, 5], b"").unwrap();
+        let g1_points = VerifyError::MalformedInput("sigma1 or sigma2 is the identity or not in G1");
+
+        // sigma1 = sigma2 = 1 would satisfy the signature equation for any messages
+        let forged = Signature{ sigma1: G1Affine::identity(), sigma2: G1Affine::identity() };
+        assert_eq!(verify_sign(&pp, &keypair.pk, &forged, &messages), Err(g1_points));
+        let mut bad = pt.clone();
+        bad.sigma1 = G1Affine::identity();
+        bad.sigma2 = G1Affine::identity();
+        assert_eq!(verify_proof(&pp, &bad, &policy_keypair, b""), Err(g1_points));
+        let mut bad = pt.clone();
+        bad.sigma2 = G1Affine::new_unchecked(pt.sigma2.x, -pt.sigma2.x);
+        assert_eq!(verify_proof(&pp, &bad, &policy_keypair, b""), Err(g1_points));
+        assert_eq!(verify_proof_with_context(&verifier_context(&pp, &policy_keypair).unwrap(), &bad, b""), Err(g1_points));
+        let mut bad = pt.clone();
+        bad.sigma_tilde = G2Affine::identity();
+        assert_eq!(verify_proof(&pp, &bad, &policy_keypair, b""), Err(VerifyError::MalformedInput("sigma_tilde is the identity or not in G2")));
+
+        // reveal indices out of range, repeated or out of order
+        for (reveal_index, idx) in [(vec![0, 3, 10], 10), (vec![0, 3, 3], 3), (vec![3, 0, 5], 0)]{
+            let mut bad = pt.clone();
+            bad.reveal_index = reveal_index;
+            assert_eq!(verify_proof(&pp, &bad, &policy_keypair, b""), Err(VerifyError::BadRevealIndex(idx)));
+        }
+        assert_eq!(create_proof(&pp, &keypair.pk, &signature, &policy_keypair.ppk, &messages, &vec![5, 3], b"").unwrap_err(), ProveError::BadRevealIndex(3));
+
+        // lengths that do not add up
+        let mut bad = pt.clone();
+        bad.len = usize::MAX;
+        assert_eq!(verify_proof(&pp, &bad, &policy_keypair, b""), Err(VerifyError::MalformedInput("message length exceeds the policy keys")));
+        let mut bad = pt.clone();
+        bad.open_messages.push(messages[0]);
+        assert_eq!(verify_proof(&pp, &bad, &policy_keypair, b""), Err(VerifyError::MalformedInput("revealed messages do not match reveal indices")));
+        let mut bad = pt.clone();
+        bad.proof.z_i.pop();
+        assert_eq!(verify_proof(&pp, &bad, &policy_keypair, b""), Err(VerifyError::MalformedInput("response count does not match hidden messages")));
+
+        // identity policy keys
+        let policy_keys = VerifyError::MalformedInput("policy key is the identity or not in G2");
+        let mut bad = policy_keypair.ppk.clone();
+        bad.s = G2Affine::identity();
+        assert_eq!(audit_policy(&pp, &bad, &policy_proof), Err(policy_keys));
+        let mut bad = policy_keypair.ppk.clone();
+        bad.t[1][2] = G2Affine::identity();
+        assert_eq!(audit_policy(&pp, &bad, &policy_proof), Err(policy_keys));
+    }
+
+    #[test]
+    fn issuers_have_own_x() {
+        let message_len = 4;
+        let mut rng = thread_rng();
+        let messages: Vec<Fr> = (0..message_len).map(|_| Fr::rand(&mut rng)).collect();
+        let pp = par_gen();
+        let keypair = key_gen(&pp, message_len);
+        let other = key_gen(&pp, message_len);
+        assert_ne!(keypair.pk.pk_x, other.pk.pk_x);
+
+        // the same y under another x signs for nobody in the policy
+        let mut outsider = key_gen(&pp, message_len);
+        outsider.sk.y = keypair.sk.y.clone();
+        outsider.pk.pk_y = keypair.pk.pk_y.clone();
+        let signature = sign(&pp, &outsider.sk, &messages).unwrap();
+        assert_eq!(verify_sign(&pp, &outsider.pk, &signature, &messages), Ok(()));
+        assert_eq!(verify_sign(&pp, &keypair.pk, &signature, &messages), Err(VerifyError::PairingCheckFailed(1)));
+        let (policy_keypair, policy_proof) = set_policy(&pp, &vec![other.pk.clone(), keypair.pk.clone()], &vec![prove_key(&pp, &other), prove_key(&pp, &keypair)]).unwrap();
+        assert_eq!(audit_policy(&pp, &policy_keypair.ppk, &policy_proof), Ok(()));
+        assert_eq!(policy_keypair.ppk.vec_b.len(), message_len + 1);
+        let pt = create_proof(&pp, &keypair.pk, &signature, &policy_keypair.ppk, &messages, &vec![0], b"").unwrap();
+        assert_eq!(verify_proof(&pp, &pt, &policy_keypair, b""), Err(VerifyError::ChallengeMismatch));
+
+        // X is covered by the key proof
+        let mut rogue = keypair.pk.clone();
+        rogue.pk_x = other.pk.pk_x;
+        assert_eq!(verify_key(&pp, &rogue, &prove_key(&pp, &keypair)), Err(VerifyError::ChallengeMismatch));
+        assert_eq!(secret_key_from_bytes(&[]), Err(WireError::InvalidPayload));
+    }
+}
@@ -0,0 +1,64 @@
+use mybbs::wire::{Ciphersuite, Header, Kind, Scheme};
+
+pub use mybbs::wire::{decode, encode, from_cbor, from_json, to_cbor, to_json};
+
+// Headers of this scheme's objects for the envelopes of mybbs::wire.
+pub const PUBLIC_PARAMETERS: Header = header(Kind::PublicParameters);
+pub const PUBLIC_KEY: Header = header(Kind::PublicKey);
+pub const SIGNATURE: Header = header(Kind::Signature);
+pub const POLICY_PUBLIC_KEY: Header = header(Kind::PolicyPublicKey);
+pub const POLICY_PROOF: Header = header(Kind::PolicyProof);
+pub const PRESENTATION: Header = header(Kind::Presentation);      // PresentationToken
+
+const fn header(kind: Kind) -> Header{
+    return Header{ scheme: Scheme::Sanders, kind: kind, ciphersuite: Ciphersuite::Bls12381Sha256 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issuer_hiding::*;
+    use ark_std::{UniformRand, vec::Vec};
+    use mybbs::error::WireError;
+    use rand::thread_rng;
+
+    #[test]
+    fn it_works(){
+        let mut rng = thread_rng();
+        let messages: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+        let pp = par_gen();
+        let keypair = key_gen(&pp, 4);
+        let signature = sign(&pp, &keypair.sk, &messages).unwrap();
+        let other = key_gen(&pp, 4);
+        let (policy, policy_proof) = set_policy(&pp, &vec![other.pk.clone(), keypair.pk.clone()], &vec![prove_key(&pp, &other), prove_key(&pp, &keypair)]).unwrap();
+        let pt = create_proof(&pp, &keypair.pk, &signature, &policy.ppk, &messages, &vec![0, 2], b"nonce").unwrap();
+
+        let pp2: PublicParameters = decode(&PUBLIC_PARAMETERS, &encode(&PUBLIC_PARAMETERS, &pp)).unwrap();
+        // secret keys travel as bytes, next to the envelopes of their public keys
+        let policy2 = PolicyKeyPair{
+            psk: policy_secret_key_from_bytes(&policy_secret_key_to_bytes(&policy.psk)).unwrap(),
+            ppk: from_cbor(&POLICY_PUBLIC_KEY, &to_cbor(&POLICY_PUBLIC_KEY, &policy.ppk)).unwrap(),
+        };
+        assert_eq!(policy2, policy);
+        let policy_proof2: PolicyProof = from_json(&POLICY_PROOF, &to_json(&POLICY_PROOF, &policy_proof)).unwrap();
+        let pt2: PresentationToken = from_json(&PRESENTATION, &to_json(&PRESENTATION, &pt)).unwrap();
+        let keypair2 = KeyPair{
+            sk: secret_key_from_bytes(&secret_key_to_bytes(&keypair.sk)).unwrap(),
+            pk: decode(&PUBLIC_KEY, &encode(&PUBLIC_KEY, &keypair.pk)).unwrap(),
+        };
+        assert_eq!(keypair2, keypair);
+        assert_eq!(format!("{:?}", keypair.sk), "SecretKey(<redacted>)");
+        assert_eq!(format!("{:?}", policy.psk), "PolicySecretKey(<redacted>)");
+        assert_eq!(policy_secret_key_from_bytes(&[]), Err(WireError::InvalidPayload));
+        assert_eq!(from_cbor::<Signature>(&SIGNATURE, &to_cbor(&SIGNATURE, &signature)), Ok(signature.clone()));
+        assert_eq!(audit_policy(&pp2, &policy2.ppk, &policy_proof2), Ok(()));
+        assert_eq!(verify_proof(&pp2, &pt2, &policy2, b"nonce"), Ok(()));
+
+        // envelopes of other schemes and kinds are rejected before the payload is read
+        let bytes = encode(&PUBLIC_KEY, &keypair.pk);
+        let bbs_key = mybbs::wire::Header{ scheme: mybbs::wire::Scheme::Bbs, ..PUBLIC_KEY };
+        assert_eq!(decode::<PublicKey>(&bbs_key, &bytes), Err(WireError::WrongScheme));
+        assert_eq!(decode::<PolicyPublicKey>(&POLICY_PUBLIC_KEY, &bytes), Err(WireError::WrongKind));
+        assert_eq!(decode::<PublicKey>(&PUBLIC_KEY, &bytes[..bytes.len() - 1]), Err(WireError::InvalidPayload));
+    }
+}
@@ -2,6 +2,7 @@ use criterion::{criterion_group, criterion_main, Criterion, BenchmarkId}; // Ben
 use std::hint::black_box;
 use ark_bls12_381::Bls12_381;
 use ark_ec::pairing::Pairing;
+use ark_serialize::CanonicalSerialize;
 use ark_std::{vec::Vec, UniformRand};
 use rand::{self, Rng, thread_rng};
 pub type Fr = <Bls12_381 as Pairing>::ScalarField;
@@ -14,11 +15,11 @@ fn myih_benchmark(c: &mut Criterion){
 
     c.bench_function("Setup", |b| {
         b.iter(|| {
-            let pp = ih::par_gen();
+            let pp = ih::par_gen(*message_len.iter().max().unwrap());
             black_box(pp);
         });
     });
-    let pp = ih::par_gen();
+    let pp = ih::par_gen(*message_len.iter().max().unwrap());
 
     c.bench_function("Issuer_Key_Gen", |b| {
         b.iter(|| {
@@ -54,16 +55,16 @@ fn myih_benchmark(c: &mut Criterion){
             group.bench_with_input(BenchmarkId::new("Sign", len), &len, |b, &_| {
                 b.iter(|| {
                     let signature = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
-                    black_box(signature);
+                    let _ = black_box(signature);
                 });
             });
 
             // Verify Credential (署名生成済みデータが必要)
-            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
             group.bench_with_input(BenchmarkId::new("Verify", len), &len, |b, &_| {
                 b.iter(|| {
                     let result = ih::verify(&pp, &issuer_key_pair.public_key, &message_fr_temp, &cred_temp);
-                    black_box(result);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -79,30 +80,33 @@ fn myih_benchmark(c: &mut Criterion){
         for &num in issuer_num.iter() {
             // リストの準備
             let mut issuer_list_temp = Vec::new();
+            let mut key_proof_temp = Vec::new();
             for _ in 0..num {
                 let keypair = ih::issuer_key_gen(&pp);
                 issuer_list_temp.push(keypair.public_key.clone());
+                key_proof_temp.push(ih::prove_issuer_key(&pp, &keypair));
             }
             // ランダムな位置にターゲットを挿入
             let r = rng.gen_range(0..num);
             if r < issuer_list_temp.len() {
                  issuer_list_temp[r] = issuer_key_pair.public_key.clone();
+                 key_proof_temp[r] = ih::prove_issuer_key(&pp, &issuer_key_pair);
             }
 
             // Issue List
             group.bench_with_input(BenchmarkId::new("Set_Policy", num), &num, |b, &_| {
                 b.iter(|| {
-                    let trusted_issuer_credential = ih::issue_list(&pp, &verifier_key_pair, &issuer_list_temp);
+                    let trusted_issuer_credential = ih::issue_list(&pp, &verifier_key_pair, &issuer_list_temp, &key_proof_temp).unwrap();
                     black_box(trusted_issuer_credential);
                 });
             });
 
             // Verify List (生成済みデータが必要)
-            let trusted_issuer_credential = ih::issue_list(&pp, &verifier_key_pair, &issuer_list_temp);
+            let trusted_issuer_credential = ih::issue_list(&pp, &verifier_key_pair, &issuer_list_temp, &key_proof_temp).unwrap();
             group.bench_with_input(BenchmarkId::new("Verify_List", num), &num, |b, &_| {
                 b.iter(|| {
                     let result = ih::verify_list(&pp, &trusted_issuer_credential);
-                    black_box(result);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -125,7 +129,7 @@ fn myih_benchmark(c: &mut Criterion){
             for _ in 0..mlen {
                 message_fr_temp.push(Fr::rand(&mut rng));
             }
-            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
 
             // 公開する属性の数を決定
             let open_message_6 = mlen * 3 / 5;
@@ -152,14 +156,17 @@ fn myih_benchmark(c: &mut Criterion){
                 for &inum in issuer_num.iter() {
                     // Issuerリスト準備
                     let mut issuer_list_temp = Vec::new();
+                    let mut key_proof_temp = Vec::new();
                     for _ in 0..inum {
                         let kp = ih::issuer_key_gen(&pp);
                         issuer_list_temp.push(kp.public_key.clone());
+                        key_proof_temp.push(ih::prove_issuer_key(&pp, &kp));
                     }
                     let r = rng.gen_range(0..inum);
                     issuer_list_temp[r] = issuer_key_pair.public_key.clone();
+                    key_proof_temp[r] = ih::prove_issuer_key(&pp, &issuer_key_pair);
                     
-                    let trusted_cred = ih::issue_list(&pp, &verifier_key_pair, &issuer_list_temp);
+                    let trusted_cred = ih::issue_list(&pp, &verifier_key_pair, &issuer_list_temp, &key_proof_temp).unwrap();
 
                     // パラメータ識別文字列を作成 (例: "m10_o3_i50")
                     let param_str = format!("m{}_o{}_i{}", mlen, olen, inum);
@@ -167,19 +174,27 @@ fn myih_benchmark(c: &mut Criterion){
                     // Present Benchmark
                     group.bench_with_input(BenchmarkId::new("Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, &trusted_cred);
-                            black_box(pt);
+                            let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, &trusted_cred, b"");
+                            let _ = black_box(pt);
                         });
                     });
 
                     // Verify Present Benchmark
                     // ベンチマーク内で毎回生成すると遅いので、計測外で一度生成
-                    let (pikp, pizkp) = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, &trusted_cred);
+                    let (pikp, pizkp) = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, &trusted_cred, b"").unwrap();
                     
                     group.bench_with_input(BenchmarkId::new("Verify_Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let result = ih::verify_present(&pp, &trusted_cred, &pikp, &pizkp);
-                            black_box(result);
+                            let result = ih::verify_present(&pp, &trusted_cred, &pikp, &pizkp, b"");
+                            let _ = black_box(result);
+                        });
+                    });
+
+                    let context = ih::verifier_context(&pp, &trusted_cred);
+                    group.bench_with_input(BenchmarkId::new("Verify_Present_Context", &param_str), &param_str, |b, _| {
+                        b.iter(|| {
+                            let result = ih::verify_present_with_context(&context, &pikp, &pizkp, b"");
+                            let _ = black_box(result);
                         });
                     });
                 }
@@ -187,6 +202,54 @@ fn myih_benchmark(c: &mut Criterion){
         }
         group.finish();
     }
+
+    // ------------------------------------------------------------------
+    // Group 4: 複数クレデンシャルの一括提示 (クレデンシャル数による変化)
+    // ------------------------------------------------------------------
+    // m10_o3_i10 のクレデンシャルを k 個、それぞれ別の発行者・別のリストで1つのチャレンジの下に提示します。
+    {
+        let mut group = c.benchmark_group("Many_Presentation_Ops");
+        let credential_num = [1, 2, 3, 4, 5];
+        let mlen = 10;
+        let open_temp: Vec<usize> = vec![0, 3, 5];
+        let no_predicates = Vec::new();
+
+        for &k in credential_num.iter() {
+            let issuer_key_pairs: Vec<_> = (0..k).map(|_| ih::issuer_key_gen(&pp)).collect();
+            let messages: Vec<Vec<Fr>> = (0..k).map(|_| (0..mlen).map(|_| Fr::rand(&mut rng)).collect()).collect();
+            let creds: Vec<_> = (0..k).map(|j| ih::issue(&pp, &issuer_key_pairs[j].secret_key, &messages[j]).unwrap()).collect();
+            // クレデンシャルごとに別の Verifier のリストを用意
+            let lists: Vec<_> = (0..k).map(|j| {
+                let mut issuer_keys: Vec<_> = (0..9).map(|_| ih::issuer_key_gen(&pp)).collect();
+                issuer_keys.push(issuer_key_pairs[j].clone());
+                let issuer_list_temp: Vec<_> = issuer_keys.iter().map(|kp| kp.public_key.clone()).collect();
+                let key_proof_temp: Vec<_> = issuer_keys.iter().map(|kp| ih::prove_issuer_key(&pp, kp)).collect();
+                ih::issue_list(&pp, &ih::verifier_key_gen(&pp), &issuer_list_temp, &key_proof_temp).unwrap()
+            }).collect();
+            let items: Vec<ih::ManyItem> = (0..k).map(|j| ((&creds[j], &issuer_key_pairs[j].public_key, &messages[j], &open_temp, &no_predicates), &lists[j])).collect();
+            let param_str = format!("k{}", k);
+
+            group.bench_with_input(BenchmarkId::new("Present_Many", &param_str), &param_str, |b, _| {
+                b.iter(|| {
+                    let pt = ih::present_many(&pp, &items, &Vec::new(), b"");
+                    let _ = black_box(pt);
+                });
+            });
+
+            let presentations = ih::present_many(&pp, &items, &Vec::new(), b"").unwrap();
+            let presented: Vec<ih::ManyPresentation> = (0..k).map(|j| (&lists[j], &presentations[j].0, &presentations[j].1)).collect();
+            group.bench_with_input(BenchmarkId::new("Verify_Present_Many", &param_str), &param_str, |b, _| {
+                b.iter(|| {
+                    let result = ih::verify_present_many(&pp, &presented, &Vec::new(), b"");
+                    let _ = black_box(result);
+                });
+            });
+
+            // サイズは計測値ではないので標準出力に出します
+            println!("Many_Presentation_Ops/Size/{}: {} bytes", param_str, presentations.compressed_size());
+        }
+        group.finish();
+    }
 }
 
 fn myih_benchmark_pc(c: &mut Criterion) {
@@ -196,11 +259,11 @@ fn myih_benchmark_pc(c: &mut Criterion) {
 
     c.bench_function("Setup", |b| {
         b.iter(|| {
-            let pp = ih::par_gen();
+            let pp = ih::par_gen(*message_len.iter().max().unwrap());
             black_box(pp);
         });
     });
-    let pp = ih::par_gen();
+    let pp = ih::par_gen(*message_len.iter().max().unwrap());
 
     c.bench_function("Issuer_Key_Gen", |b| {
         b.iter(|| {
@@ -236,7 +299,7 @@ fn myih_benchmark_pc(c: &mut Criterion) {
             group.bench_with_input(BenchmarkId::new("Sign", len), &len, |b, &_| {
                 b.iter(|| {
                     let signature = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
-                    black_box(signature);
+                    let _ = black_box(signature);
                 });
             });
         }
@@ -252,20 +315,23 @@ fn myih_benchmark_pc(c: &mut Criterion) {
         for &num in issuer_num.iter() {
             // リストの準備
             let mut issuer_list_temp = Vec::new();
+            let mut key_proof_temp = Vec::new();
             for _ in 0..num {
                 let keypair = ih::issuer_key_gen(&pp);
                 issuer_list_temp.push(keypair.public_key.clone());
+                key_proof_temp.push(ih::prove_issuer_key(&pp, &keypair));
             }
             // ランダムな位置にターゲットを挿入
             let r = rng.gen_range(0..num);
             if r < issuer_list_temp.len() {
                  issuer_list_temp[r] = issuer_key_pair.public_key.clone();
+                 key_proof_temp[r] = ih::prove_issuer_key(&pp, &issuer_key_pair);
             }
 
             // Issue List
             group.bench_with_input(BenchmarkId::new("Set_Policy", num), &num, |b, &_| {
                 b.iter(|| {
-                    let trusted_issuer_credential = ih::issue_list(&pp, &verifier_key_pair, &issuer_list_temp);
+                    let trusted_issuer_credential = ih::issue_list(&pp, &verifier_key_pair, &issuer_list_temp, &key_proof_temp).unwrap();
                     black_box(trusted_issuer_credential);
                 });
             });
@@ -289,7 +355,7 @@ fn myih_benchmark_pc(c: &mut Criterion) {
             for _ in 0..mlen {
                 message_fr_temp.push(Fr::rand(&mut rng));
             }
-            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
 
             // 公開する属性の数を決定
             let open_message_6 = mlen * 3 / 5;
@@ -316,26 +382,37 @@ fn myih_benchmark_pc(c: &mut Criterion) {
                 for &inum in issuer_num.iter() {
                     // Issuerリスト準備
                     let mut issuer_list_temp = Vec::new();
+                    let mut key_proof_temp = Vec::new();
                     for _ in 0..inum {
                         let kp = ih::issuer_key_gen(&pp);
                         issuer_list_temp.push(kp.public_key.clone());
+                        key_proof_temp.push(ih::prove_issuer_key(&pp, &kp));
                     }
                     let r = rng.gen_range(0..inum);
                     issuer_list_temp[r] = issuer_key_pair.public_key.clone();
+                    key_proof_temp[r] = ih::prove_issuer_key(&pp, &issuer_key_pair);
                     
-                    let trusted_cred = ih::issue_list(&pp, &verifier_key_pair, &issuer_list_temp);
+                    let trusted_cred = ih::issue_list(&pp, &verifier_key_pair, &issuer_list_temp, &key_proof_temp).unwrap();
 
                     // パラメータ識別文字列を作成 (例: "m10_o3_i50")
                     let param_str = format!("m{}_o{}_i{}", mlen, olen, inum);
 
                     // Verify Present Benchmark
                     // ベンチマーク内で毎回生成すると遅いので、計測外で一度生成
-                    let (pikp, pizkp) = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, &trusted_cred);
+                    let (pikp, pizkp) = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, &trusted_cred, b"").unwrap();
                     
                     group.bench_with_input(BenchmarkId::new("Verify_Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let result = ih::verify_present(&pp, &trusted_cred, &pikp, &pizkp);
-                            black_box(result);
+                            let result = ih::verify_present(&pp, &trusted_cred, &pikp, &pizkp, b"");
+                            let _ = black_box(result);
+                        });
+                    });
+
+                    let context = ih::verifier_context(&pp, &trusted_cred);
+                    group.bench_with_input(BenchmarkId::new("Verify_Present_Context", &param_str), &param_str, |b, _| {
+                        b.iter(|| {
+                            let result = ih::verify_present_with_context(&context, &pikp, &pizkp, b"");
+                            let _ = black_box(result);
                         });
                     });
                 }
@@ -349,7 +426,7 @@ fn myih_benchmark_android(c: &mut Criterion) {
     let message_len = [5, 10, 15, 20, 25, 30, 35, 40, 45, 50];
     let issuer_num = [5, 10, 50, 100, 500, 1000];
     let mut rng = thread_rng();
-    let pp = ih::par_gen();
+    let pp = ih::par_gen(*message_len.iter().max().unwrap());
 
     let issuer_key_pair = ih::issuer_key_gen(&pp);
     let verifier_key_pair = ih::verifier_key_gen(&pp);
@@ -368,11 +445,11 @@ fn myih_benchmark_android(c: &mut Criterion) {
             }
 
             // Verify Credential (署名生成済みデータが必要)
-            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
             group.bench_with_input(BenchmarkId::new("Verify", len), &len, |b, &_| {
                 b.iter(|| {
                     let result = ih::verify(&pp, &issuer_key_pair.public_key, &message_fr_temp, &cred_temp);
-                    black_box(result);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -388,22 +465,25 @@ fn myih_benchmark_android(c: &mut Criterion) {
         for &num in issuer_num.iter() {
             // リストの準備
             let mut issuer_list_temp = Vec::new();
+            let mut key_proof_temp = Vec::new();
             for _ in 0..num {
                 let keypair = ih::issuer_key_gen(&pp);
                 issuer_list_temp.push(keypair.public_key.clone());
+                key_proof_temp.push(ih::prove_issuer_key(&pp, &keypair));
             }
             // ランダムな位置にターゲットを挿入
             let r = rng.gen_range(0..num); // gen_range(1..num)だとnum=5のときindex 0が選ばれない可能性があるため修正考慮(元ロジック尊重なら戻してください)
             if r < issuer_list_temp.len() {
                  issuer_list_temp[r] = issuer_key_pair.public_key.clone();
+                 key_proof_temp[r] = ih::prove_issuer_key(&pp, &issuer_key_pair);
             }
 
             // Verify List (生成済みデータが必要)
-            let trusted_issuer_credential = ih::issue_list(&pp, &verifier_key_pair, &issuer_list_temp);
+            let trusted_issuer_credential = ih::issue_list(&pp, &verifier_key_pair, &issuer_list_temp, &key_proof_temp).unwrap();
             group.bench_with_input(BenchmarkId::new("Verify_List", num), &num, |b, &_| {
                 b.iter(|| {
                     let result = ih::verify_list(&pp, &trusted_issuer_credential);
-                    black_box(result);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -426,7 +506,7 @@ fn myih_benchmark_android(c: &mut Criterion) {
             for _ in 0..mlen {
                 message_fr_temp.push(Fr::rand(&mut rng));
             }
-            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = ih::issue(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
 
             // 公開する属性の数を決定
             let open_message_6 = mlen * 3 / 5;
@@ -453,14 +533,17 @@ fn myih_benchmark_android(c: &mut Criterion) {
                 for &inum in issuer_num.iter() {
                     // Issuerリスト準備
                     let mut issuer_list_temp = Vec::new();
+                    let mut key_proof_temp = Vec::new();
                     for _ in 0..inum {
                         let kp = ih::issuer_key_gen(&pp);
                         issuer_list_temp.push(kp.public_key.clone());
+                        key_proof_temp.push(ih::prove_issuer_key(&pp, &kp));
                     }
                     let r = rng.gen_range(0..inum);
                     issuer_list_temp[r] = issuer_key_pair.public_key.clone();
+                    key_proof_temp[r] = ih::prove_issuer_key(&pp, &issuer_key_pair);
                     
-                    let trusted_cred = ih::issue_list(&pp, &verifier_key_pair, &issuer_list_temp);
+                    let trusted_cred = ih::issue_list(&pp, &verifier_key_pair, &issuer_list_temp, &key_proof_temp).unwrap();
 
                     // パラメータ識別文字列を作成 (例: "m10_o3_i50")
                     let param_str = format!("m{}_o{}_i{}", mlen, olen, inum);
@@ -468,8 +551,8 @@ fn myih_benchmark_android(c: &mut Criterion) {
                     // Present Benchmark
                     group.bench_with_input(BenchmarkId::new("Present", &param_str), &param_str, |b, _| {
                         b.iter(|| {
-                            let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, &trusted_cred);
-                            black_box(pt);
+                            let pt = ih::present(&pp, &cred_temp, &issuer_key_pair.public_key, &message_fr_temp, &open_temp, &trusted_cred, b"");
+                            let _ = black_box(pt);
                         });
                     });
                 }
@@ -0,0 +1,41 @@
+use thiserror::Error;
+
+// Reasons a verifier rejects a credential, a trusted-issuer list or a presentation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum VerifyError {
+    #[error("challenge mismatch")]
+    ChallengeMismatch,
+    #[error("pairing check {0} failed")]
+    PairingCheckFailed(usize),
+    #[error("bad reveal index {0}")]
+    BadRevealIndex(usize),
+    #[error("list entry {0} invalid")]
+    InvalidListEntry(usize),
+    #[error("key proof of issuer {0} invalid")]
+    InvalidKeyProof(usize),
+    #[error("presentation is for list epoch {presented} but the current epoch is {current}")]
+    StaleEpoch { presented: u64, current: u64 },
+    #[error("verifier public key is inconsistent")]
+    InvalidVerifierKey,
+    #[error("presentation header does not match the expected one")]
+    PresentationHeaderMismatch,
+    #[error("malformed input: {0}")]
+    MalformedInput(&'static str),
+    #[error(transparent)]
+    Credential(#[from] mybbs::error::VerifyError),
+}
+
+// Reasons a holder refuses to build a presentation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum ProveError {
+    #[error("bad reveal index {0}")]
+    BadRevealIndex(usize),
+    #[error("{messages} messages but only {generators} generators")]
+    TooManyMessages { messages: usize, generators: usize },
+    #[error("issuer public key is not in the trusted list")]
+    IssuerNotInList,
+    #[error(transparent)]
+    Credential(#[from] mybbs::error::ProveError),
+    #[error(transparent)]
+    Schema(#[from] mybbs::error::SchemaError),
+}
@@ -1,19 +1,33 @@
 use ark_bls12_381::{Bls12_381, G1Affine, G1Projective, G2Affine ,G2Projective};
 use ark_ff::Field;
 use ark_ec::{pairing::Pairing, CurveGroup};
-use ark_std::{fmt::Debug, UniformRand, vec::Vec};
+use ark_std::{fmt::Debug, UniformRand, vec::Vec, Zero};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use rand::thread_rng;
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use zeroize::Zeroizing;
 
 use mybbs::bbs as bbs;
+use mybbs::equality::{self, Equality};
+use mybbs::holder::{self, HolderSecretKey, KeyBinding};
 use mybbs::issuer;
+use mybbs::proof;
+use mybbs::pseudonym::{self, ScopedPseudonym};
+use mybbs::range::{self, RangePredicate, RangeProof};
+use mybbs::schema::{self, AttributeValue, CredentialSchema};
 use mybbs::verifier;
 
+use crate::error::{ProveError, VerifyError};
+
 pub type Fr = <Bls12_381 as Pairing>::ScalarField;
 
+// cred is the verifier's signature on ipk * h_epoch^epoch. A list is current for one epoch:
+// next_epoch re-signs the entries it keeps under epoch + 1, which leaves the dropped issuers'
+// old entries useless without rotating vpk.
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct TrustedIssuerCredential{
     pub ipk: issuer::PublicKey,
+    pub key_proof: issuer::KeyProof,
+    pub epoch: u64,
     pub cred: verifier::Signature
 }
 
@@ -29,6 +43,10 @@ pub struct PiKP{
     pub open: Vec<usize>,
     pub len: usize,
     pub message_list: Vec<Fr>,
+    pub predicates: Vec<RangePredicate>,
+    pub pseudonym: Option<ScopedPseudonym>,
+    pub epoch: u64,
+    pub presentation_header: Vec<u8>,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalSerialize, CanonicalDeserialize)]
@@ -41,124 +59,263 @@ pub struct PiZKP{
     pub z2: Fr,
     pub v1: Vec<Fr>,
     pub c: Fr,
+    pub range: RangeProof,
 }
 
-pub fn par_gen() -> bbs::PublicParameters{
-    let pp = bbs::par_gen();
+pub fn par_gen(message_len: usize) -> bbs::PublicParameters{
+    let pp = bbs::par_gen(message_len);
     return pp
 }
 
-pub fn issuer_key_gen(pp: &bbs::PublicParameters) -> issuer::KeyPair{
+pub fn issuer_key_gen_with_rng<R: RngCore + CryptoRng>(pp: &bbs::PublicParameters, rng: &mut R) -> issuer::KeyPair{
     let pp_issuer = issuer::PublicParameters{
         g1: pp.g1,
         g2: pp.g2,
         h_vec: pp.h_vec.clone(),
     };
-    let keypair = issuer::key_gen(&pp_issuer);
+    let keypair = issuer::key_gen_with_rng(&pp_issuer, rng);
     return keypair
 }
 
-pub fn issue(pp: &bbs::PublicParameters, isk: &bbs::SecretKey, messages: &Vec<Fr>) -> issuer::Signature{
+pub fn issuer_key_gen(pp: &bbs::PublicParameters) -> issuer::KeyPair{
+    return issuer_key_gen_with_rng(pp, &mut OsRng)
+}
+
+// The proof of possession issue_list asks of every issuer key.
+pub fn prove_issuer_key_with_rng<R: RngCore + CryptoRng>(pp: &bbs::PublicParameters, keypair: &issuer::KeyPair, rng: &mut R) -> issuer::KeyProof{
     let pp_issuer = issuer::PublicParameters{
         g1: pp.g1,
         g2: pp.g2,
         h_vec: pp.h_vec.clone(),
     };
-    let signature = issuer::sign(&pp_issuer, isk, messages);
-    return signature
+    return issuer::prove_key_with_rng(&pp_issuer, keypair, rng)
+}
+
+pub fn prove_issuer_key(pp: &bbs::PublicParameters, keypair: &issuer::KeyPair) -> issuer::KeyProof{
+    return prove_issuer_key_with_rng(pp, keypair, &mut OsRng)
 }
 
-pub fn verify(pp: &bbs::PublicParameters, ipk: &issuer::PublicKey, messages: &Vec<Fr>, sig: &issuer::Signature) -> bool{
+fn verify_issuer_key(pp: &bbs::PublicParameters, ipk: &issuer::PublicKey, key_proof: &issuer::KeyProof) -> bool{
+    let pp_issuer = issuer::PublicParameters{
+        g1: pp.g1,
+        g2: pp.g2,
+        h_vec: Vec::new(),
+    };
+    return issuer::verify_key(&pp_issuer, ipk, key_proof).is_ok()
+}
+
+pub fn issue_with_rng<R: RngCore + CryptoRng>(pp: &bbs::PublicParameters, isk: &bbs::SecretKey, messages: &Vec<Fr>, rng: &mut R) -> Result<issuer::Signature, ProveError>{
     let pp_issuer = issuer::PublicParameters{
         g1: pp.g1,
         g2: pp.g2,
         h_vec: pp.h_vec.clone(),
     };
-    let is_valid = issuer::verify(&pp_issuer, ipk, messages, sig);
-    return is_valid
+    let signature = issuer::sign_with_rng(&pp_issuer, isk, messages, rng)?;
+    return Ok(signature)
 }
 
-pub fn verifier_key_gen(pp: &bbs::PublicParameters) -> verifier::KeyPair{
+pub fn issue(pp: &bbs::PublicParameters, isk: &bbs::SecretKey, messages: &Vec<Fr>) -> Result<issuer::Signature, ProveError>{
+    return issue_with_rng(pp, isk, messages, &mut OsRng)
+}
+
+pub fn verify(pp: &bbs::PublicParameters, ipk: &issuer::PublicKey, messages: &Vec<Fr>, sig: &issuer::Signature) -> Result<(), VerifyError>{
+    let pp_issuer = issuer::PublicParameters{
+        g1: pp.g1,
+        g2: pp.g2,
+        h_vec: pp.h_vec.clone(),
+    };
+    issuer::verify(&pp_issuer, ipk, messages, sig)?;
+    return Ok(())
+}
+
+pub fn verifier_key_gen_with_rng<R: RngCore + CryptoRng>(pp: &bbs::PublicParameters, rng: &mut R) -> verifier::KeyPair{
     let pp_verifier = verifier::PublicParameters{
         gbar1: pp.gbar1,
         gbar2: pp.gbar2,
     };
-    let keypair = verifier::key_gen(&pp_verifier);
+    let keypair = verifier::key_gen_with_rng(&pp_verifier, rng);
     return keypair
 }
 
-pub fn issue_list(pp: &bbs::PublicParameters, key: &verifier::KeyPair, message_list: &Vec<issuer::PublicKey>) -> (verifier::PublicKey, Vec<TrustedIssuerCredential>){
+pub fn verifier_key_gen(pp: &bbs::PublicParameters) -> verifier::KeyPair{
+    return verifier_key_gen_with_rng(pp, &mut OsRng)
+}
+
+// h_epoch, the G2 generator that binds a list entry to its epoch.
+pub fn epoch_generator() -> G2Affine{
+    let dst = b"BBS-SIG-GENERATOR-DST-V2";
+    let h_epoch_bytes = "Issuer-Hiding BBS Make to h_epoch".to_string().into_bytes();
+    return bbs::hash_to_g2(&h_epoch_bytes[..], dst)
+}
+
+// gbar2 * h_epoch^epoch, which takes the place of gbar2 in the verifier signature of an entry.
+fn epoch_base(pp: &bbs::PublicParameters, epoch: u64) -> G2Projective{
+    return pp.gbar2 + epoch_generator() * Fr::from(epoch)
+}
+
+// The epoch of the list, which every entry shares; an empty list is at epoch 0.
+pub fn list_epoch((_, list): &(verifier::PublicKey, Vec<TrustedIssuerCredential>)) -> u64{
+    return list.first().map_or(0, |entry| entry.epoch)
+}
+
+fn sign_entries<R: RngCore + CryptoRng>(pp: &bbs::PublicParameters, key: &verifier::KeyPair, message_list: &Vec<issuer::PublicKey>, key_proofs: &Vec<issuer::KeyProof>, epoch: u64, rng: &mut R) -> Vec<TrustedIssuerCredential>{
     let pp_verifier = verifier::PublicParameters{
         gbar1: pp.gbar1,
         gbar2: pp.gbar2,
     };
     let vsk = &key.secret_key;
+    let h_epoch = epoch_generator() * Fr::from(epoch);
     let mut credential: Vec<TrustedIssuerCredential> = Vec::new();
     for i in 0..message_list.len(){
         let ipk = &message_list[i];
-        let signature = verifier::sign(&pp_verifier, vsk, &ipk.0);
+        let signature = verifier::sign_with_rng(&pp_verifier, vsk, &G2Affine::from(h_epoch + ipk.0), rng);
         let cred = TrustedIssuerCredential{
             ipk: ipk.clone(),
+            key_proof: key_proofs[i].clone(),
+            epoch: epoch,
             cred: signature,
         };
         credential.push(cred);
     }
+    return credential
+}
+
+// Issues the list at epoch 0.
+pub fn issue_list_with_rng<R: RngCore + CryptoRng>(pp: &bbs::PublicParameters, key: &verifier::KeyPair, message_list: &Vec<issuer::PublicKey>, key_proofs: &Vec<issuer::KeyProof>, rng: &mut R) -> Result<(verifier::PublicKey, Vec<TrustedIssuerCredential>), VerifyError>{
+    if message_list.len() != key_proofs.len() {
+        return Err(VerifyError::MalformedInput("one key proof per issuer"))
+    }
+    for i in 0..message_list.len(){
+        if !verify_issuer_key(pp, &message_list[i], &key_proofs[i]){
+            return Err(VerifyError::InvalidKeyProof(i))
+        }
+    }
+    let credential = sign_entries(pp, key, message_list, key_proofs, 0, rng);
     let vpk = key.public_key.clone();
-    return (vpk, credential)
+    return Ok((vpk, credential))
+}
+
+pub fn issue_list(pp: &bbs::PublicParameters, key: &verifier::KeyPair, message_list: &Vec<issuer::PublicKey>, key_proofs: &Vec<issuer::KeyProof>) -> Result<(verifier::PublicKey, Vec<TrustedIssuerCredential>), VerifyError>{
+    return issue_list_with_rng(pp, key, message_list, key_proofs, &mut OsRng)
+}
+
+// The list for the next epoch, with every issuer in revoked dropped and the others re-signed.
+// Presentations made from the old list stop verifying against the new one.
+pub fn next_epoch_with_rng<R: RngCore + CryptoRng>(pp: &bbs::PublicParameters, key: &verifier::KeyPair, list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>), revoked: &Vec<issuer::PublicKey>, rng: &mut R) -> Result<(verifier::PublicKey, Vec<TrustedIssuerCredential>), VerifyError>{
+    if list.0 != key.public_key {
+        return Err(VerifyError::MalformedInput("list is signed under another verifier key"))
+    }
+    let epoch = match list_epoch(list).checked_add(1) {
+        Some(epoch) => epoch,
+        None => return Err(VerifyError::MalformedInput("no epoch after the list's")),
+    };
+    let kept: Vec<&TrustedIssuerCredential> = list.1.iter().filter(|entry| !revoked.contains(&entry.ipk)).collect();
+    let message_list = kept.iter().map(|entry| entry.ipk.clone()).collect();
+    let key_proofs = kept.iter().map(|entry| entry.key_proof.clone()).collect();
+    let credential = sign_entries(pp, key, &message_list, &key_proofs, epoch, rng);
+    return Ok((list.0.clone(), credential))
+}
+
+pub fn next_epoch(pp: &bbs::PublicParameters, key: &verifier::KeyPair, list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>), revoked: &Vec<issuer::PublicKey>) -> Result<(verifier::PublicKey, Vec<TrustedIssuerCredential>), VerifyError>{
+    return next_epoch_with_rng(pp, key, list, revoked, &mut OsRng)
 }
 
-pub fn verify_list(pp: &bbs::PublicParameters, (vpk, list): &(verifier::PublicKey, Vec<TrustedIssuerCredential>)) -> bool{
+pub fn verify_list(pp: &bbs::PublicParameters, trusted_list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>)) -> Result<(), VerifyError>{
+    let (vpk, list) = trusted_list;
     let pp_verifier = verifier::PublicParameters{
         gbar1: pp.gbar1,
         gbar2: pp.gbar2,
     };
 
-    if Bls12_381::pairing(vpk.0, pp.gbar2) != Bls12_381::pairing(pp.gbar1, vpk.1) {
-        println!("Verifier Public Key check failed");
-        return false
+    if !Bls12_381::multi_pairing([vpk.0, -pp.gbar1], [pp.gbar2, vpk.1]).is_zero() {
+        return Err(VerifyError::InvalidVerifierKey)
     }
 
+    let epoch = list_epoch(trusted_list);
+    let h_epoch = epoch_generator() * Fr::from(epoch);
     for i in 0..list.len(){
         let cred = &list[i];
         let ipk = &cred.ipk;
         let signature = &cred.cred;
-        let is_valid = verifier::verify(&pp_verifier, vpk, &ipk.0, signature);
-        if is_valid == false{
-            println!("Credential list entry {} verification failed", i);
-            return false
+        if cred.epoch != epoch || !bbs::valid_points(&[ipk.0]) || verifier::verify(&pp_verifier, vpk, &G2Affine::from(h_epoch + ipk.0), signature).is_err(){
+            return Err(VerifyError::InvalidListEntry(i))
+        }
+        if !verify_issuer_key(pp, ipk, &cred.key_proof){
+            return Err(VerifyError::InvalidKeyProof(i))
         }
     }
-    return true
+    return Ok(())
 }
 
-pub fn present(
+// (credential, issuer key, messages, reveal_index, predicates) of one credential in present_multi.
+pub type MultiItem<'a> = (&'a issuer::Signature, &'a issuer::PublicKey, &'a Vec<Fr>, &'a Vec<usize>, &'a Vec<RangePredicate>);
+
+// A credential of present_many with the trusted list it is presented against.
+pub type ManyItem<'a> = (MultiItem<'a>, &'a (verifier::PublicKey, Vec<TrustedIssuerCredential>));
+
+// A presentation of verify_present_many with the trusted list it is checked against.
+pub type ManyPresentation<'a> = (&'a (verifier::PublicKey, Vec<TrustedIssuerCredential>), &'a PiKP, &'a PiZKP);
+
+// The holder's first move, kept until the challenge is known. The randomizers and hidden
+// messages are wiped when it is dropped.
+struct Commitment{
+    pikp: PiKP,
+    challenge_input: Vec<u8>,   // everything hashed into c but the presentation header
+    r: Zeroizing<Fr>,
+    r_1: Zeroizing<Fr>,
+    r_2: Zeroizing<Fr>,
+    r_3: Zeroizing<Fr>,
+    e: Fr,
+    verifier_e: Fr,
+    alpha1: Zeroizing<Fr>,
+    alpha2: Zeroizing<Fr>,
+    beta1: Zeroizing<Fr>,
+    beta2: Zeroizing<Fr>,
+    gamma1: Zeroizing<Fr>,
+    gamma2: Zeroizing<Fr>,
+    delta1_vec: Zeroizing<Vec<Fr>>,
+    hidden_messages: Zeroizing<Vec<Fr>>,
+    range_proof: RangeProof,
+    range_witness: range::RangeWitness,
+}
+
+// linked[i], when present, is the blinding the i-th hidden message has to take because it
+// is linked to a message of another credential.
+#[allow(clippy::too_many_arguments)]
+fn commit_with_rng<R: RngCore + CryptoRng>(
     pp: &bbs::PublicParameters, 
     cred: &issuer::Signature, 
     ipk: &issuer::PublicKey, 
     message_list: &Vec<Fr>, 
     reveal_index: &Vec<usize>,
-    (_, list): &(verifier::PublicKey, Vec<TrustedIssuerCredential>)
-) -> (PiKP, PiZKP){
+    predicates: &Vec<RangePredicate>,
+    pseudonym: Option<(usize, &[u8])>,
+    linked: &[Option<Fr>],
+    (_, list): &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<Commitment, ProveError>{
     let message_len = message_list.len();
+    if message_len > pp.h_vec.len() {
+        return Err(ProveError::TooManyMessages { messages: message_len, generators: pp.h_vec.len() });
+    }
+    proof::check_reveal_index(message_len, reveal_index).map_err(ProveError::BadRevealIndex)?;
 
-    let mut rng = thread_rng();
     let h_generators : Vec<G1Affine> = pp.h_vec[0..message_len].to_vec();
-    
-    let list_len = list.len();
-    let mut verifier_sig = list[0].cred.clone();
-    for i in 0..list_len{
-        if list[i].ipk == *ipk{
-            verifier_sig = list[i].cred.clone();
-        }
-    }
 
-    let r = Fr::rand(&mut rng);
-    let r_inv = r.inverse().unwrap();
-    let r_1 = Fr::rand(&mut rng);
-    let r_2 = Fr::rand(&mut rng);
-    let r_2_inv = r_2.inverse().unwrap();
-    let r_3 = Fr::rand(&mut rng);
-    let r_3_inv = r_3.inverse().unwrap();
+    let (verifier_sig, epoch) = match list.iter().find(|entry| entry.ipk == *ipk) {
+        Some(entry) => (entry.cred.clone(), entry.epoch),
+        None => return Err(ProveError::IssuerNotInList),
+    };
+    let base = epoch_base(pp, epoch);
+
+    let r = Zeroizing::new(Fr::rand(rng));
+    let r_inv = Zeroizing::new(r.inverse().unwrap());
+    let r_1 = Zeroizing::new(Fr::rand(rng));
+    let r_2 = Zeroizing::new(Fr::rand(rng));
+    let r_2_inv = Zeroizing::new(r_2.inverse().unwrap());
+    let r_3 = Zeroizing::new(Fr::rand(rng));
+    let r_3_inv = Zeroizing::new(r_3.inverse().unwrap());
 
     let mut d_element = G1Projective::from(pp.g1);
     let mut open_messages: Vec<Fr> = Vec::new();
@@ -171,33 +328,46 @@ pub fn present(
             close_index.push(i);
         }
     }
-    d_element *= r_2_inv;
+    d_element *= *r_2_inv;
     let close_len = close_index.len();
-    let ipk_rand_pro = ipk.0 * r;
-    let abar_pro = cred.a * (r_1 * r_2_inv * r_inv);
-    let bbar_pro = (d_element * r_1) + (abar_pro * (-cred.e * r));
-
-    let d2_pro = (pp.gbar2 + ipk.0) * r_3_inv;
-    let abar2_pro = verifier_sig.a * (r * r_3_inv);
-    let bbar2_pro = (d2_pro * r) + (abar2_pro * (-verifier_sig.e));
-
-    let alpha1 = Fr::rand(&mut rng);
-    let alpha2 = Fr::rand(&mut rng);
-    let beta1 = Fr::rand(&mut rng);
-    let beta2 = Fr::rand(&mut rng); 
-    let gamma1 = Fr::rand(&mut rng);
-    let gamma2 = Fr::rand(&mut rng);
-    let delta1_vec = (0..close_len).map(|_| Fr::rand(&mut rng)).collect::<Vec<Fr>>();
-
-    let u1_pro = (d_element * alpha1) + (abar_pro * beta1);
-    let mut u2_element = d_element * gamma1;
+    let ipk_rand_pro = ipk.0 * *r;
+    let abar_pro = cred.a * (*r_1 * *r_2_inv * *r_inv);
+    let bbar_pro = (d_element * *r_1) + (abar_pro * (-cred.e * *r));
+
+    let d2_pro = (base + ipk.0) * *r_3_inv;
+    let abar2_pro = verifier_sig.a * (*r * *r_3_inv);
+    let bbar2_pro = (d2_pro * *r) + (abar2_pro * (-verifier_sig.e));
+
+    let alpha1 = Zeroizing::new(Fr::rand(rng));
+    let alpha2 = Zeroizing::new(Fr::rand(rng));
+    let beta1 = Zeroizing::new(Fr::rand(rng));
+    let beta2 = Zeroizing::new(Fr::rand(rng));
+    let gamma1 = Zeroizing::new(Fr::rand(rng));
+    let gamma2 = Zeroizing::new(Fr::rand(rng));
+    let delta1_vec = Zeroizing::new((0..close_len).map(|i| linked.get(i).copied().flatten().unwrap_or_else(|| Fr::rand(rng))).collect::<Vec<Fr>>());
+
+    let u1_pro = (d_element * *alpha1) + (abar_pro * *beta1);
+    let mut u2_element = d_element * *gamma1;
     for i in 0..close_len{
         u2_element += h_generators[close_index[i]] * delta1_vec[i];
     }
+    // the range proofs reuse the blinding of the hidden message they are about
+    let positions = range::predicate_positions(predicates, &close_index).map_err(ProveError::BadRevealIndex)?;
+    let range_messages: Zeroizing<Vec<Fr>> = Zeroizing::new(positions.iter().map(|&k| message_list[close_index[k]]).collect());
+    let range_blindings: Zeroizing<Vec<Fr>> = Zeroizing::new(positions.iter().map(|&k| delta1_vec[k]).collect());
+    let (range_proof, range_witness, range_t) = range::range_commit_with_rng(predicates, &range_messages, &range_blindings, rng)?;
+    // so does the pseudonym for the link secret
+    let (pseudonym, pseudonym_t) = match pseudonym{
+        Some((index, scope)) => {
+            let k = close_index.iter().position(|&i| i == index).ok_or(ProveError::BadRevealIndex(index))?;
+            let (pseudonym, t) = pseudonym::pseudonym_commit(index, scope, &message_list[index], &delta1_vec[k]);
+            (Some(pseudonym), Some(t))
+        },
+        None => (None, None),
+    };
     // let u2 = G1Affine::from(u2_element);
-    let u3_pro = (d2_pro * alpha2) + (abar2_pro * beta2);
-    let u4_pro = (d2_pro * gamma2) + (pp.gbar2 * (-alpha2));
-    let dst = b"MY_CHALLENGE_GENERATOR_DST_Issuer_Hiding_V1";
+    let u3_pro = (d2_pro * *alpha2) + (abar2_pro * *beta2);
+    let u4_pro = (d2_pro * *gamma2) + (base * (-*alpha2));
     let c_inputs1_pro = vec![
         abar_pro,
         bbar_pro,
@@ -225,8 +395,8 @@ pub fn present(
     }
     open_messages.serialize_compressed(&mut c_inputs_buffer).unwrap();
     list.serialize_compressed(&mut c_inputs_buffer).unwrap();
-
-    let c = bbs::hash_to_fr(&c_inputs_buffer[..], dst);
+    range::serialize_challenge_input(predicates, &range_proof, &range_t, &mut c_inputs_buffer);
+    pseudonym::serialize_challenge_input(&pseudonym, &pseudonym_t, &mut c_inputs_buffer);
     let pikp = PiKP{
         a_bar1: c_inputs1[0],
         b_bar1: c_inputs1[1],
@@ -238,19 +408,47 @@ pub fn present(
         open: reveal_index.clone(),
         len: message_len,
         message_list: open_messages,
+        predicates: predicates.clone(),
+        pseudonym: pseudonym,
+        epoch: epoch,
+        presentation_header: presentation_header.to_vec(),
     };
+    return Ok(Commitment{
+        pikp: pikp,
+        challenge_input: c_inputs_buffer,
+        r: r,
+        r_1: r_1,
+        r_2: r_2,
+        r_3: r_3,
+        e: cred.e,
+        verifier_e: verifier_sig.e,
+        alpha1: alpha1,
+        alpha2: alpha2,
+        beta1: beta1,
+        beta2: beta2,
+        gamma1: gamma1,
+        gamma2: gamma2,
+        delta1_vec: delta1_vec,
+        hidden_messages: Zeroizing::new(close_index.iter().map(|&i| message_list[i]).collect()),
+        range_proof: range_proof,
+        range_witness: range_witness,
+    })
+}
 
-    let s1 = alpha1 + c * r_1;
-    let s2 = alpha2 + c * r;
-    let t1 = beta1 - c * (cred.e * r);
-    let t2 = beta2 - c * (verifier_sig.e);
-    let z1 = gamma1 + c * r_2;
-    let z2 = gamma2 + c * r * r_3;
+fn respond(commitment: Commitment, c: Fr) -> (PiKP, PiZKP){
+    let s1 = *commitment.alpha1 + c * *commitment.r_1;
+    let s2 = *commitment.alpha2 + c * *commitment.r;
+    let t1 = *commitment.beta1 - c * (commitment.e * *commitment.r);
+    let t2 = *commitment.beta2 - c * (commitment.verifier_e);
+    let z1 = *commitment.gamma1 + c * *commitment.r_2;
+    let z2 = *commitment.gamma2 + c * *commitment.r * *commitment.r_3;
     let mut v1_vec: Vec<Fr> = Vec::new();
-    for i in 0..close_len{
-        let v1 = delta1_vec[i] - c * message_list[close_index[i]];
+    for i in 0..commitment.delta1_vec.len(){
+        let v1 = commitment.delta1_vec[i] - c * commitment.hidden_messages[i];
         v1_vec.push(v1);
     }
+    let mut range_proof = commitment.range_proof;
+    range::range_respond(&mut range_proof, &commitment.range_witness, &(-c));
     let pizkp = PiZKP{
         s1: s1,
         s2: s2,
@@ -259,27 +457,281 @@ pub fn present(
         z1: z1,
         z2: z2,
         v1: v1_vec,
+        range: range_proof,
         c: c,
     };
-    return (pikp, pizkp)
+    return (commitment.pikp, pizkp)
 }
 
-pub fn verify_present(
+#[allow(clippy::too_many_arguments)]
+fn present_single_with_rng<R: RngCore + CryptoRng>(
+    pp: &bbs::PublicParameters, 
+    cred: &issuer::Signature, 
+    ipk: &issuer::PublicKey, 
+    message_list: &Vec<Fr>, 
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    pseudonym: Option<(usize, &[u8])>,
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    let commitment = commit_with_rng(pp, cred, ipk, message_list, reveal_index, predicates, pseudonym, &[], list, presentation_header, rng)?;
+    let dst = b"MY_CHALLENGE_GENERATOR_DST_Issuer_Hiding_V1";
+    let mut c_inputs_buffer = commitment.challenge_input.clone();
+    presentation_header.to_vec().serialize_compressed(&mut c_inputs_buffer).unwrap();
+
+    let c = bbs::hash_to_fr(&c_inputs_buffer[..], dst);
+    return Ok(respond(commitment, c))
+}
+
+// present that also shows min <= m_i < max for every predicate on a hidden message m_i.
+#[allow(clippy::too_many_arguments)]
+pub fn present_with_predicates_with_rng<R: RngCore + CryptoRng>(
+    pp: &bbs::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_single_with_rng(pp, cred, ipk, message_list, reveal_index, predicates, None, list, presentation_header, rng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn present_with_predicates(
+    pp: &bbs::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    presentation_header: &[u8],
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_with_predicates_with_rng(pp, cred, ipk, message_list, reveal_index, predicates, list, presentation_header, &mut OsRng)
+}
+
+// present_with_predicates that also shows the pseudonym H_G1(scope)^m for the hidden message
+// m at index link_secret.
+#[allow(clippy::too_many_arguments)]
+pub fn present_with_pseudonym_with_rng<R: RngCore + CryptoRng>(
+    pp: &bbs::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    link_secret: usize,
+    scope: &[u8],
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_single_with_rng(pp, cred, ipk, message_list, reveal_index, predicates, Some((link_secret, scope)), list, presentation_header, rng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn present_with_pseudonym(
+    pp: &bbs::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    link_secret: usize,
+    scope: &[u8],
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    presentation_header: &[u8],
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_with_pseudonym_with_rng(pp, cred, ipk, message_list, reveal_index, predicates, link_secret, scope, list, presentation_header, &mut OsRng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn present_with_rng<R: RngCore + CryptoRng>(
+    pp: &bbs::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_with_predicates_with_rng(pp, cred, ipk, message_list, reveal_index, &Vec::new(), list, presentation_header, rng)
+}
+
+pub fn present(
+    pp: &bbs::PublicParameters, 
+    cred: &issuer::Signature, 
+    ipk: &issuer::PublicKey, 
+    message_list: &Vec<Fr>, 
+    reveal_index: &Vec<usize>,
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    presentation_header: &[u8],
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_with_rng(pp, cred, ipk, message_list, reveal_index, list, presentation_header, &mut OsRng)
+}
+
+// issue for a holder that sent binding for its secret key, which is signed as the message at
+// holder_index without the issuer learning it. messages are the other messages, in order.
+#[allow(clippy::too_many_arguments)]
+pub fn issue_bound_with_rng<R: RngCore + CryptoRng>(
+    pp: &bbs::PublicParameters,
+    isk: &bbs::SecretKey,
+    binding: &KeyBinding,
+    holder_index: usize,
+    messages: &Vec<Fr>,
+    nonce: &[u8],
+    rng: &mut R,
+) -> Result<issuer::Signature, ProveError>{
+    let pp_issuer = issuer::PublicParameters{
+        g1: pp.g1,
+        g2: pp.g2,
+        h_vec: pp.h_vec.clone(),
+    };
+    let signature = holder::sign_bound_with_rng(&pp_issuer, isk, binding, holder_index, messages, nonce, rng)?;
+    return Ok(signature)
+}
+
+pub fn issue_bound(
+    pp: &bbs::PublicParameters,
+    isk: &bbs::SecretKey,
+    binding: &KeyBinding,
+    holder_index: usize,
+    messages: &Vec<Fr>,
+    nonce: &[u8],
+) -> Result<issuer::Signature, ProveError>{
+    return issue_bound_with_rng(pp, isk, binding, holder_index, messages, nonce, &mut OsRng)
+}
+
+// present a credential bound to a holder key. message_list are the messages without the
+// holder secret, which goes in at holder_index; reveal_index counts it.
+#[allow(clippy::too_many_arguments)]
+pub fn present_bound_with_rng<R: RngCore + CryptoRng>(
+    pp: &bbs::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    holder_secret: &HolderSecretKey,
+    holder_index: usize,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    if reveal_index.contains(&holder_index) {
+        return Err(ProveError::BadRevealIndex(holder_index));
+    }
+    let messages = holder::with_holder_secret(message_list, holder_index, holder_secret)?;
+    return present_with_rng(pp, cred, ipk, &messages, reveal_index, list, presentation_header, rng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn present_bound(
+    pp: &bbs::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    holder_secret: &HolderSecretKey,
+    holder_index: usize,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    presentation_header: &[u8],
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_bound_with_rng(pp, cred, ipk, holder_secret, holder_index, message_list, reveal_index, list, presentation_header, &mut OsRng)
+}
+
+// issue with the messages given as values of a credential schema, in schema order.
+pub fn issue_attributes_with_rng<R: RngCore + CryptoRng>(pp: &bbs::PublicParameters, isk: &bbs::SecretKey, schema: &CredentialSchema, values: &Vec<AttributeValue>, rng: &mut R) -> Result<issuer::Signature, ProveError>{
+    let messages = schema::encode_messages(schema, values)?;
+    return issue_with_rng(pp, isk, &messages, rng)
+}
+
+pub fn issue_attributes(pp: &bbs::PublicParameters, isk: &bbs::SecretKey, schema: &CredentialSchema, values: &Vec<AttributeValue>) -> Result<issuer::Signature, ProveError>{
+    return issue_attributes_with_rng(pp, isk, schema, values, &mut OsRng)
+}
+
+// present with schema-typed values, revealing the attributes named in reveal.
+#[allow(clippy::too_many_arguments)]
+pub fn present_attributes_with_rng<R: RngCore + CryptoRng>(
+    pp: &bbs::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    schema: &CredentialSchema,
+    values: &Vec<AttributeValue>,
+    reveal: &[&str],
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    let message_list = schema::encode_messages(schema, values)?;
+    let reveal_index = schema::reveal_index(schema, reveal)?;
+    return present_with_rng(pp, cred, ipk, &message_list, &reveal_index, list, presentation_header, rng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn present_attributes(
+    pp: &bbs::PublicParameters,
+    cred: &issuer::Signature,
+    ipk: &issuer::PublicKey,
+    schema: &CredentialSchema,
+    values: &Vec<AttributeValue>,
+    reveal: &[&str],
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    presentation_header: &[u8],
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return present_attributes_with_rng(pp, cred, ipk, schema, values, reveal, list, presentation_header, &mut OsRng)
+}
+
+// The challenge input the responses of a presentation imply, but the presentation header,
+// and the two pairing equations left to check.
+fn challenge_input(
     pp: &bbs::PublicParameters, 
-    (vpk, list): &(verifier::PublicKey, Vec<TrustedIssuerCredential>), 
+    trusted_list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>), 
     pikp: &PiKP, 
-    pizkp: &PiZKP
-) -> bool{
+    pizkp: &PiZKP,
+) -> Result<(Vec<u8>, Vec<bbs::PairingEquation>), VerifyError>{
+    let (vpk, list) = trusted_list;
+    // the holder's entry has to be from the list's epoch, so the verifier uses its own
+    let epoch = list_epoch(trusted_list);
+    if pikp.epoch != epoch {
+        return Err(VerifyError::StaleEpoch { presented: pikp.epoch, current: epoch });
+    }
     let message_len = pikp.len;
+    if message_len > pp.h_vec.len() {
+        return Err(VerifyError::MalformedInput("message length exceeds generators"));
+    }
+    proof::check_reveal_index(message_len, &pikp.open).map_err(VerifyError::BadRevealIndex)?;
+    if pikp.message_list.len() != pikp.open.len() {
+        return Err(VerifyError::MalformedInput("revealed messages do not match reveal indices"));
+    }
+    if !bbs::valid_points(&[pikp.a_bar1, pikp.b_bar1, pikp.d_1]) {
+        return Err(VerifyError::MalformedInput("A_bar1, B_bar1 or D_1 is the identity or not in G1"));
+    }
+    if !bbs::valid_points(&[pikp.ipk_rand, pikp.a_bar2, pikp.b_bar2, pikp.d_2]) {
+        return Err(VerifyError::MalformedInput("ipk', A_bar2, B_bar2 or D_2 is the identity or not in G2"));
+    }
 
     let h_generators : Vec<G1Affine> = pp.h_vec[0..message_len].to_vec();
-    let mut close_index: Vec<usize> = Vec::new();
-    for i in 0..message_len{
-        if !pikp.open.contains(&i){
-            close_index.push(i);
-        }
-    }
+    let close_index = proof::hidden_index(message_len, &pikp.open);
     let close_len = close_index.len();
+    if pizkp.v1.len() != close_len {
+        return Err(VerifyError::MalformedInput("response count does not match hidden messages"));
+    }
+    let positions = range::predicate_positions(&pikp.predicates, &close_index).map_err(VerifyError::BadRevealIndex)?;
+    let range_responses: Vec<Fr> = positions.iter().map(|&k| pizkp.v1[k]).collect();
+    let range_t = range::range_verify(&pikp.predicates, &pizkp.range, &range_responses, &(-pizkp.c))?;
+    let pseudonym_t = match &pikp.pseudonym{
+        Some(pseudonym) => {
+            let k = close_index.iter().position(|&i| i == pseudonym.index).ok_or(VerifyError::BadRevealIndex(pseudonym.index))?;
+            Some(pseudonym::pseudonym_verify(pseudonym, &pizkp.v1[k], &(-pizkp.c))?)
+        },
+        None => None,
+    };
 
     let mut u_12_pro = vec![(pikp.d_1 * pizkp.s1) + (pikp.a_bar1 * pizkp.t1) + (pikp.b_bar1 * (-pizkp.c))];
     let mut u2_element = pikp.d_1 * pizkp.z1 + pp.g1 * (-pizkp.c);
@@ -295,11 +747,10 @@ pub fn verify_present(
     let u_12_affine = G1Projective::normalize_batch(&u_12_pro);
     let u_34_pro = vec![
         (pikp.d_2 * pizkp.s2) + (pikp.a_bar2 * pizkp.t2) + (pikp.b_bar2 * (-pizkp.c)),
-        pikp.d_2 * pizkp.z2 + pp.gbar2 * (-pizkp.s2) + pikp.ipk_rand * (-pizkp.c)
+        pikp.d_2 * pizkp.z2 + epoch_base(pp, epoch) * (-pizkp.s2) + pikp.ipk_rand * (-pizkp.c)
     ];
     let u_34_affine = G2Projective::normalize_batch(&u_34_pro);
 
-    let dst = b"MY_CHALLENGE_GENERATOR_DST_Issuer_Hiding_V1";
     let c_inputs1 = vec![
         pikp.a_bar1,
         pikp.b_bar1,
@@ -327,30 +778,260 @@ pub fn verify_present(
     }
     pikp.message_list.serialize_compressed(&mut c_inputs_buffer).unwrap();
     list.serialize_compressed(&mut c_inputs_buffer).unwrap();
+    range::serialize_challenge_input(&pikp.predicates, &pizkp.range, &range_t, &mut c_inputs_buffer);
+    pseudonym::serialize_challenge_input(&pikp.pseudonym, &pseudonym_t, &mut c_inputs_buffer);
+
+    // e(A_bar1, ipk') = e(B_bar1, g2), e(vpk, A_bar2) = e(gbar1, B_bar2)
+    let equations = vec![
+        vec![(G1Projective::from(pikp.a_bar1), pikp.ipk_rand), (-G1Projective::from(pikp.b_bar1), pp.g2)],
+        vec![(G1Projective::from(vpk.0), pikp.a_bar2), (-G1Projective::from(pp.gbar1), pikp.b_bar2)],
+    ];
+    return Ok((c_inputs_buffer, equations));
+}
+
+// Everything verify_present checks except its two pairing equations.
+fn present_equations(
+    pp: &bbs::PublicParameters, 
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>), 
+    pikp: &PiKP, 
+    pizkp: &PiZKP,
+    presentation_header: &[u8],
+) -> Result<Vec<bbs::PairingEquation>, VerifyError>{
+    if pikp.presentation_header != presentation_header {
+        return Err(VerifyError::PresentationHeaderMismatch);
+    }
+    let dst = b"MY_CHALLENGE_GENERATOR_DST_Issuer_Hiding_V1";
+    let (mut c_inputs_buffer, equations) = challenge_input(pp, list, pikp, pizkp)?;
+    pikp.presentation_header.serialize_compressed(&mut c_inputs_buffer).unwrap();
 
     let c = bbs::hash_to_fr(&c_inputs_buffer[..], dst);
 
     if c != pizkp.c{
-        println!("Challenge hash check failed");
-        println!("Computed c: {:?}", c);
-        println!("Presented c: {:?}", pizkp.c);
-        return false
+        return Err(VerifyError::ChallengeMismatch)
+    }
+    return Ok(equations);
+}
+
+pub fn verify_present(
+    pp: &bbs::PublicParameters, 
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>), 
+    pikp: &PiKP, 
+    pizkp: &PiZKP,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    let equations = present_equations(pp, list, pikp, pizkp, presentation_header)?;
+    for (k, equation) in equations.iter().enumerate(){
+        if !bbs::pairing_check(equation) {
+            return Err(VerifyError::PairingCheckFailed(k + 1))
+        }
+    }
+    return Ok(());
+}
+
+// verify_present for a presentation that has to carry a pseudonym for scope derived from the
+// message at index link_secret. Returns the pseudonym.
+pub fn verify_present_with_pseudonym(
+    pp: &bbs::PublicParameters,
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    pikp: &PiKP,
+    pizkp: &PiZKP,
+    link_secret: usize,
+    scope: &[u8],
+    presentation_header: &[u8],
+) -> Result<G1Affine, VerifyError>{
+    let nym = pseudonym::expect_pseudonym(&pikp.pseudonym, link_secret, scope)?;
+    verify_present(pp, list, pikp, pizkp, presentation_header)?;
+    return Ok(nym)
+}
+
+// verify_present for a presentation of a credential bound to a holder key at holder_index.
+pub fn verify_present_bound(
+    pp: &bbs::PublicParameters,
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    pikp: &PiKP,
+    pizkp: &PiZKP,
+    holder_index: usize,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    holder::check_bound(pikp.len, &pikp.open, holder_index)?;
+    return verify_present(pp, list, pikp, pizkp, presentation_header)
+}
+
+// The trusted issuer list and a prepared g2, for a verifier that checks many presentations
+// against the same list. Build it once with verifier_context.
+#[derive(Clone, Debug)]
+pub struct VerifierContext{
+    pub pp: bbs::PublicParameters,
+    pub list: (verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    pub g2_prepared: bbs::G2Prepared,
+}
+
+pub fn verifier_context(pp: &bbs::PublicParameters, list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>)) -> VerifierContext{
+    let context = VerifierContext{
+        pp: pp.clone(),
+        list: list.clone(),
+        g2_prepared: bbs::G2Prepared::from(pp.g2),
+    };
+    return context
+}
+
+pub fn verify_present_with_context(
+    context: &VerifierContext,
+    pikp: &PiKP, 
+    pizkp: &PiZKP,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    let equations = present_equations(&context.pp, &context.list, pikp, pizkp, presentation_header)?;
+    let ipk_rand_prepared = bbs::G2Prepared::from(pikp.ipk_rand);
+    let pairs = [
+        (equations[0][0].0, &ipk_rand_prepared),
+        (equations[0][1].0, &context.g2_prepared),
+    ];
+    if !bbs::prepared_pairing_check(&pairs) {
+        return Err(VerifyError::PairingCheckFailed(1))
+    }
+    if !bbs::pairing_check(&equations[1]) {
+        return Err(VerifyError::PairingCheckFailed(2))
+    }
+    return Ok(());
+}
+
+// Verifies many (pikp, pizkp, presentation_header) presentations against the same trusted
+// issuer list with one final exponentiation and returns the index and error of every one
+// that does not verify.
+pub fn verify_batch(
+    pp: &bbs::PublicParameters, 
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>), 
+    items: &[(&PiKP, &PiZKP, &[u8])],
+) -> Result<(), Vec<(usize, VerifyError)>>{
+    let equations = items.iter()
+        .map(|(pikp, pizkp, presentation_header)| present_equations(pp, list, pikp, pizkp, presentation_header))
+        .collect();
+    return bbs::verify_batch_equations(equations, VerifyError::PairingCheckFailed)
+}
+
+// Presents several credentials, each against its own trusted list, under a single
+// challenge. Each equality asserts that two hidden messages, named by (item, message index),
+// are equal without revealing them.
+pub fn present_many_with_rng<R: RngCore + CryptoRng>(
+    pp: &bbs::PublicParameters,
+    items: &[ManyItem],
+    equalities: &Vec<Equality>,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<Vec<(PiKP, PiZKP)>, ProveError>{
+    let messages: Vec<&Vec<Fr>> = items.iter().map(|(item, _)| item.2).collect();
+    let hidden: Vec<Vec<usize>> = items.iter().map(|(item, _)| proof::hidden_index(item.2.len(), item.3)).collect();
+    let linked = equality::linked_blindings_with_rng(equalities, &messages, &hidden, rng)?;
+    let mut commitments = Vec::new();
+    for j in 0..items.len(){
+        let ((cred, ipk, message_list, reveal_index, predicates), list) = items[j];
+        commitments.push(commit_with_rng(pp, cred, ipk, message_list, reveal_index, predicates, None, &linked[j], list, presentation_header, rng)?);
     }
 
-    if Bls12_381::pairing(pikp.a_bar1, pikp.ipk_rand) != Bls12_381::pairing(pikp.b_bar1, pp.g2) {
-        println!("Pairing check 1 failed");
-        return false
+    let dst = b"MY_MULTI_CHALLENGE_DST_Issuer_Hiding_V1";
+    let mut c_inputs_buffer = Vec::new();
+    for commitment in &commitments{
+        commitment.challenge_input.serialize_compressed(&mut c_inputs_buffer).unwrap();
     }
-    if Bls12_381::pairing(vpk.0, pikp.a_bar2) != Bls12_381::pairing(pp.gbar1, pikp.b_bar2) {
-        println!("Pairing check 2 failed");
-        return false
+    equality::serialize_challenge_input(equalities, &mut c_inputs_buffer);
+    presentation_header.to_vec().serialize_compressed(&mut c_inputs_buffer).unwrap();
+    let c = bbs::hash_to_fr(&c_inputs_buffer[..], dst);
+    return Ok(commitments.into_iter().map(|commitment| respond(commitment, c)).collect())
+}
+
+pub fn present_many(
+    pp: &bbs::PublicParameters,
+    items: &[ManyItem],
+    equalities: &Vec<Equality>,
+    presentation_header: &[u8],
+) -> Result<Vec<(PiKP, PiZKP)>, ProveError>{
+    return present_many_with_rng(pp, items, equalities, presentation_header, &mut OsRng)
+}
+
+// present_many with every credential against the same trusted list.
+pub fn present_multi_with_rng<R: RngCore + CryptoRng>(
+    pp: &bbs::PublicParameters,
+    items: &[MultiItem],
+    equalities: &Vec<Equality>,
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<Vec<(PiKP, PiZKP)>, ProveError>{
+    let items: Vec<ManyItem> = items.iter().map(|&item| (item, list)).collect();
+    return present_many_with_rng(pp, &items, equalities, presentation_header, rng)
+}
+
+pub fn present_multi(
+    pp: &bbs::PublicParameters,
+    items: &[MultiItem],
+    equalities: &Vec<Equality>,
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    presentation_header: &[u8],
+) -> Result<Vec<(PiKP, PiZKP)>, ProveError>{
+    return present_multi_with_rng(pp, items, equalities, list, presentation_header, &mut OsRng)
+}
+
+// Verifies the output of present_many, each presentation against its trusted list. A failing
+// pairing is reported as PairingCheckFailed(2j + k) for equation k of the j-th presentation.
+pub fn verify_present_many(
+    pp: &bbs::PublicParameters,
+    items: &[ManyPresentation],
+    equalities: &Vec<Equality>,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    if items.is_empty() {
+        return Err(VerifyError::MalformedInput("no presentations"));
     }
-    return true;
+    let dst = b"MY_MULTI_CHALLENGE_DST_Issuer_Hiding_V1";
+    let c = items[0].2.c;
+    let mut c_inputs_buffer = Vec::new();
+    let mut equations = Vec::new();
+    for (list, pikp, pizkp) in items{
+        if pikp.presentation_header != presentation_header {
+            return Err(VerifyError::PresentationHeaderMismatch);
+        }
+        if pizkp.c != c {
+            return Err(VerifyError::ChallengeMismatch);
+        }
+        let (input, item_equations) = challenge_input(pp, list, pikp, pizkp)?;
+        input.serialize_compressed(&mut c_inputs_buffer).unwrap();
+        equations.extend(item_equations);
+    }
+    let hidden: Vec<Vec<usize>> = items.iter().map(|(_, pikp, _)| proof::hidden_index(pikp.len, &pikp.open)).collect();
+    let responses: Vec<&Vec<Fr>> = items.iter().map(|(_, _, pizkp)| &pizkp.v1).collect();
+    equality::check_linked_responses(equalities, &hidden, &responses)?;
+    equality::serialize_challenge_input(equalities, &mut c_inputs_buffer);
+    presentation_header.to_vec().serialize_compressed(&mut c_inputs_buffer).unwrap();
+    if bbs::hash_to_fr(&c_inputs_buffer[..], dst) != c {
+        return Err(VerifyError::ChallengeMismatch);
+    }
+
+    if !bbs::batch_pairing_check(&equations) {
+        let k = equations.iter().position(|eq| !bbs::pairing_check(eq)).unwrap_or(0);
+        return Err(VerifyError::PairingCheckFailed(k + 1));
+    }
+    return Ok(())
+}
+
+// Verifies the output of present_multi.
+pub fn verify_present_multi(
+    pp: &bbs::PublicParameters,
+    list: &(verifier::PublicKey, Vec<TrustedIssuerCredential>),
+    items: &[(&PiKP, &PiZKP)],
+    equalities: &Vec<Equality>,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    let items: Vec<ManyPresentation> = items.iter().map(|&(pikp, pizkp)| (list, pikp, pizkp)).collect();
+    return verify_present_many(pp, &items, equalities, presentation_header)
 }
 
 #[cfg(test)]
 mod tests {
-    use rand::Rng;
+    use ark_ec::AffineRepr;
+    use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
+
+    use mybbs::error::SchemaError;
 
     use super::*;
 
@@ -361,14 +1042,14 @@ mod tests {
         let mut rng = thread_rng();
         let messages: Vec<Fr> = (0..message_len).map(|_| Fr::rand(&mut rng)).collect();
 
-        let pp = par_gen();
+        let pp = par_gen(message_len);
         let issuer_keypair = issuer_key_gen(&pp);
         let issuer_pk = &issuer_keypair.public_key;
 
-        let signature = issue(&pp, &issuer_keypair.secret_key, &messages);
+        let signature = issue(&pp, &issuer_keypair.secret_key, &messages).unwrap();
 
-        let is_valid_cred = verify(&pp, &issuer_pk, &messages, &signature);
-        assert_eq!(is_valid_cred, true);
+        let is_valid_cred = verify(&pp, issuer_pk, &messages, &signature);
+        assert_eq!(is_valid_cred, Ok(()));
 
         let mut issuer_keypairs: Vec<issuer::KeyPair> = Vec::new();
         let mut issuer_pubkeys: Vec<issuer::PublicKey> = Vec::new();
@@ -385,15 +1066,237 @@ mod tests {
 
         let verifier_keypair = verifier_key_gen(&pp);
 
-        let list = issue_list(&pp, &verifier_keypair, &issuer_pubkeys);
+        let key_proofs: Vec<issuer::KeyProof> = issuer_keypairs.iter().map(|keypair| prove_issuer_key(&pp, keypair)).collect();
+        let list = issue_list(&pp, &verifier_keypair, &issuer_pubkeys, &key_proofs).unwrap();
 
         let is_valid_list = verify_list(&pp, &list);
-        assert_eq!(is_valid_list, true);
+        assert_eq!(is_valid_list, Ok(()));
+        assert_eq!(issue_list(&pp, &verifier_keypair, &issuer_pubkeys, &key_proofs[1..].to_vec()), Err(VerifyError::MalformedInput("one key proof per issuer")));
+        assert_eq!(issue_list(&pp, &verifier_keypair, &vec![issuer_pubkeys[1].clone()], &vec![key_proofs[0].clone()]), Err(VerifyError::InvalidKeyProof(0)));
+        let mut swapped = list.clone();
+        swapped.1[0].key_proof = list.1[1].key_proof.clone();
+        assert_eq!(verify_list(&pp, &swapped), Err(VerifyError::InvalidKeyProof(0)));
 
         let reveal_index = vec![0, 3, 5, 6];
-        let (pikp, pizkp) = present(&pp, &signature, &issuer_pk, &messages, &reveal_index, &list);
+        let nonce = b"verifier-nonce-1";
+        let (pikp, pizkp) = present(&pp, &signature, issuer_pk, &messages, &reveal_index, &list, nonce).unwrap();
+
+        let is_valid_present = verify_present(&pp, &list, &pikp, &pizkp, nonce);
+        assert_eq!(is_valid_present, Ok(()));
+
+        let seeded = present_with_rng(&pp, &signature, issuer_pk, &messages, &reveal_index, &list, nonce, &mut StdRng::seed_from_u64(7)).unwrap();
+        assert_eq!(present_with_rng(&pp, &signature, issuer_pk, &messages, &reveal_index, &list, nonce, &mut StdRng::seed_from_u64(7)).unwrap(), seeded);
+
+        let mut tampered = pikp.clone();
+        tampered.message_list[0] += Fr::from(1u64);
+        assert_eq!(verify_present(&pp, &list, &tampered, &pizkp, nonce), Err(VerifyError::ChallengeMismatch));
+
+        let context = verifier_context(&pp, &list);
+        assert_eq!(verify_present_with_context(&context, &pikp, &pizkp, nonce), Ok(()));
+        assert_eq!(verify_present_with_context(&context, &tampered, &pizkp, nonce), Err(VerifyError::ChallengeMismatch));
+
+        assert_eq!(verify_present(&pp, &list, &pikp, &pizkp, b"verifier-nonce-2"), Err(VerifyError::PresentationHeaderMismatch));
+        let mut replayed = pikp.clone();
+        replayed.presentation_header = b"verifier-nonce-2".to_vec();
+        assert_eq!(verify_present(&pp, &list, &replayed, &pizkp, b"verifier-nonce-2"), Err(VerifyError::ChallengeMismatch));
+
+        let (other_pikp, other_pizkp) = present(&pp, &signature, issuer_pk, &messages, &reveal_index, &list, b"").unwrap();
+        let batch: Vec<(&PiKP, &PiZKP, &[u8])> = vec![
+            (&pikp, &pizkp, nonce),
+            (&other_pikp, &other_pizkp, b""),
+            (&tampered, &pizkp, nonce),
+        ];
+        assert_eq!(verify_batch(&pp, &list, &batch[0..2]), Ok(()));
+        assert_eq!(verify_batch(&pp, &list, &batch), Err(vec![(2, VerifyError::ChallengeMismatch)]));
 
-        let is_valid_present = verify_present(&pp, &list, &pikp, &pizkp);
-        assert_eq!(is_valid_present, true);
+        let schema = CredentialSchema{
+            id: "employee".to_string(),
+            attributes: vec![
+                schema::Attribute{ name: "name".to_string(), attribute_type: schema::AttributeType::Utf8String },
+                schema::Attribute{ name: "age".to_string(), attribute_type: schema::AttributeType::Integer },
+            ],
+        };
+        let values = vec![AttributeValue::Utf8String("Alice".to_string()), AttributeValue::Integer(30)];
+        let typed = issue_attributes(&pp, &issuer_keypair.secret_key, &schema, &values).unwrap();
+        let typed_pt = present_attributes(&pp, &typed, issuer_pk, &schema, &values, &["age"], &list, nonce).unwrap();
+        assert_eq!(verify_present(&pp, &list, &typed_pt.0, &typed_pt.1, nonce), Ok(()));
+        assert_eq!(typed_pt.0.message_list, vec![schema::encode_value(&values[1]).unwrap()]);
+        assert_eq!(present_attributes(&pp, &typed, issuer_pk, &schema, &values, &["salary"], &list, nonce).unwrap_err(), ProveError::Schema(SchemaError::UnknownAttribute(0)));
+
+        let typed_messages = schema::encode_messages(&schema, &values).unwrap();
+        let adult = vec![range::integer_range(1, 18, i64::MAX)];
+        let adult_pt = present_with_predicates(&pp, &typed, issuer_pk, &typed_messages, &vec![0], &adult, &list, nonce).unwrap();
+        assert_eq!(verify_present(&pp, &list, &adult_pt.0, &adult_pt.1, nonce), Ok(()));
+        let mut widened = adult_pt.clone();
+        widened.0.predicates[0].min = 0;
+        assert_eq!(verify_present(&pp, &list, &widened.0, &widened.1, nonce), Err(VerifyError::ChallengeMismatch));
+        let senior = vec![range::integer_range(1, 65, i64::MAX)];
+        assert_eq!(present_with_predicates(&pp, &typed, issuer_pk, &typed_messages, &vec![0], &senior, &list, nonce).unwrap_err(), ProveError::Credential(mybbs::error::ProveError::PredicateNotSatisfied(0)));
+
+        // a second credential from another trusted issuer, bound to the first by message 9
+        let other_issuer = &issuer_keypairs[(r + 1) % issuer_num];
+        let mut other_messages: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+        other_messages[2] = messages[9];
+        let other_signature = issue(&pp, &other_issuer.secret_key, &other_messages).unwrap();
+        let no_predicates = Vec::new();
+        let other_reveal = vec![0];
+        let items: Vec<MultiItem> = vec![
+            (&signature, issuer_pk, &messages, &reveal_index, &no_predicates),
+            (&other_signature, &other_issuer.public_key, &other_messages, &other_reveal, &no_predicates),
+        ];
+        let linked = vec![Equality{ left: (0, 9), right: (1, 2) }];
+        let multi = present_multi(&pp, &items, &linked, &list, nonce).unwrap();
+        let presented: Vec<(&PiKP, &PiZKP)> = multi.iter().map(|(pikp, pizkp)| (pikp, pizkp)).collect();
+        assert_eq!(verify_present_multi(&pp, &list, &presented, &linked, nonce), Ok(()));
+        assert_eq!(verify_present_multi(&pp, &list, &presented, &Vec::new(), nonce), Err(VerifyError::ChallengeMismatch));
+        let unrelated = vec![Equality{ left: (0, 8), right: (1, 2) }];
+        assert_eq!(verify_present_multi(&pp, &list, &presented, &unrelated, nonce), Err(VerifyError::Credential(mybbs::error::VerifyError::EqualityMismatch(0))));
+        assert_eq!(present_multi(&pp, &items, &unrelated, &list, nonce).unwrap_err(), ProveError::Credential(mybbs::error::ProveError::UnequalMessages(0)));
+
+        // an insurance credential from an issuer that only a second verifier's list trusts
+        let insurer = issuer_key_gen(&pp);
+        let insurance_messages: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let insurance = issue(&pp, &insurer.secret_key, &insurance_messages).unwrap();
+        let other_insurer = issuer_key_gen(&pp);
+        let insurer_list = issue_list(&pp, &verifier_key_gen(&pp), &vec![insurer.public_key.clone(), other_insurer.public_key.clone()], &vec![prove_issuer_key(&pp, &insurer), prove_issuer_key(&pp, &other_insurer)]).unwrap();
+        let insurance_item: MultiItem = (&insurance, &insurer.public_key, &insurance_messages, &other_reveal, &no_predicates);
+        let many = present_many(&pp, &[(items[0], &list), (insurance_item, &insurer_list)], &Vec::new(), nonce).unwrap();
+        assert_eq!(verify_present_many(&pp, &[(&list, &many[0].0, &many[0].1), (&insurer_list, &many[1].0, &many[1].1)], &Vec::new(), nonce), Ok(()));
+        assert_eq!(verify_present_many(&pp, &[(&list, &many[0].0, &many[0].1), (&list, &many[1].0, &many[1].1)], &Vec::new(), nonce), Err(VerifyError::ChallengeMismatch));
+        assert_eq!(verify_present(&pp, &list, &many[0].0, &many[0].1, nonce), Err(VerifyError::ChallengeMismatch));
+        assert_eq!(present_many(&pp, &[(items[0], &list), (insurance_item, &list)], &Vec::new(), nonce).unwrap_err(), ProveError::IssuerNotInList);
+
+        // the same hidden message 9 gives the same pseudonym in a scope through either credential
+        let scope = b"shop.example";
+        let nym_pt = present_with_pseudonym(&pp, &signature, issuer_pk, &messages, &reveal_index, &no_predicates, 9, scope, &list, nonce).unwrap();
+        let nym = verify_present_with_pseudonym(&pp, &list, &nym_pt.0, &nym_pt.1, 9, scope, nonce).unwrap();
+        assert_eq!(nym, pseudonym::pseudonym(scope, &messages[9]));
+        let other_nym_pt = present_with_pseudonym(&pp, &other_signature, &other_issuer.public_key, &other_messages, &other_reveal, &no_predicates, 2, scope, &list, nonce).unwrap();
+        assert_eq!(verify_present_with_pseudonym(&pp, &list, &other_nym_pt.0, &other_nym_pt.1, 2, scope, nonce), Ok(nym));
+        assert_eq!(verify_present_with_pseudonym(&pp, &list, &nym_pt.0, &nym_pt.1, 9, b"bank.example", nonce), Err(VerifyError::Credential(mybbs::error::VerifyError::PseudonymMismatch)));
+        let mut swapped = nym_pt.clone();
+        swapped.0.pseudonym.as_mut().unwrap().nym = pseudonym::pseudonym(scope, &messages[8]);
+        assert_eq!(verify_present(&pp, &list, &swapped.0, &swapped.1, nonce), Err(VerifyError::ChallengeMismatch));
+
+        // a credential bound to a holder key only presents with the holder secret
+        let holder_secret = holder::holder_key_gen();
+        let holder_nonce = b"issuance-nonce";
+        let binding = holder::key_binding(&pp.h_vec[4], &holder_secret, holder_nonce);
+        let bound_messages = messages[1..].to_vec();
+        let bound_signature = issue_bound(&pp, &issuer_keypair.secret_key, &binding, 4, &bound_messages, holder_nonce).unwrap();
+        assert_eq!(issue_bound(&pp, &issuer_keypair.secret_key, &binding, 4, &bound_messages, nonce).unwrap_err(), ProveError::Credential(mybbs::error::ProveError::HolderKeyRejected(mybbs::error::VerifyError::ChallengeMismatch)));
+        let bound_pt = present_bound(&pp, &bound_signature, issuer_pk, &holder_secret, 4, &bound_messages, &reveal_index, &list, nonce).unwrap();
+        assert_eq!(verify_present_bound(&pp, &list, &bound_pt.0, &bound_pt.1, 4, nonce), Ok(()));
+        let stolen_pt = present_bound(&pp, &bound_signature, issuer_pk, &holder::holder_key_gen(), 4, &bound_messages, &reveal_index, &list, nonce).unwrap();
+        assert_eq!(verify_present_bound(&pp, &list, &stolen_pt.0, &stolen_pt.1, 4, nonce), Err(VerifyError::PairingCheckFailed(1)));
+        assert_eq!(verify_present_bound(&pp, &list, &pikp, &pizkp, 3, nonce), Err(VerifyError::Credential(mybbs::error::VerifyError::HolderSecretNotHidden(3))));
+        assert_eq!(present_bound(&pp, &bound_signature, issuer_pk, &holder_secret, 4, &bound_messages, &vec![4], &list, nonce).unwrap_err(), ProveError::BadRevealIndex(4));
+
+        let stranger = issuer_key_gen(&pp);
+        let result = present(&pp, &signature, &stranger.public_key, &messages, &reveal_index, &list, nonce);
+        assert_eq!(result.unwrap_err(), ProveError::IssuerNotInList);
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let message_len = 10;
+        let mut rng = thread_rng();
+        let messages: Vec<Fr> = (0..message_len).map(|_| Fr::rand(&mut rng)).collect();
+        let pp = par_gen(message_len);
+        let issuer_keypair = issuer_key_gen(&pp);
+        let issuer_pk = &issuer_keypair.public_key;
+        let signature = issue(&pp, &issuer_keypair.secret_key, &messages).unwrap();
+        let verifier_keypair = verifier_key_gen(&pp);
+        let other_issuer = issuer_key_gen(&pp);
+        let key_proofs = vec![prove_issuer_key(&pp, &other_issuer), prove_issuer_key(&pp, &issuer_keypair)];
+        let list = issue_list(&pp, &verifier_keypair, &vec![other_issuer.public_key.clone(), issuer_pk.clone()], &key_proofs).unwrap();
+        let (pikp, pizkp) = present(&pp, &signature, issuer_pk, &messages, &vec![0, 3, 5], &list, b"").unwrap();
+        let g1_points = VerifyError::MalformedInput("A_bar1, B_bar1 or D_1 is the identity or not in G1");
+        let g2_points = VerifyError::MalformedInput("ipk', A_bar2, B_bar2 or D_2 is the identity or not in G2");
+
+        // identity A_bar and B_bar would satisfy either pairing equation on their own
+        let mut bad = pikp.clone();
+        bad.a_bar1 = G1Affine::zero();
+        bad.b_bar1 = G1Affine::zero();
+        assert_eq!(verify_present(&pp, &list, &bad, &pizkp, b""), Err(g1_points));
+        let mut bad = pikp.clone();
+        bad.a_bar2 = G2Affine::zero();
+        bad.b_bar2 = G2Affine::zero();
+        assert_eq!(verify_present(&pp, &list, &bad, &pizkp, b""), Err(g2_points));
+        let mut bad = pikp.clone();
+        bad.ipk_rand = G2Affine::zero();
+        assert_eq!(verify_present(&pp, &list, &bad, &pizkp, b""), Err(g2_points));
+        let mut bad = pikp.clone();
+        bad.d_1 = G1Affine::new_unchecked(pikp.d_1.x, -pikp.d_1.x);
+        assert_eq!(verify_present(&pp, &list, &bad, &pizkp, b""), Err(g1_points));
+        assert_eq!(verify_present_with_context(&verifier_context(&pp, &list), &bad, &pizkp, b""), Err(g1_points));
+
+        // reveal indices out of range, repeated or out of order
+        for (open, idx) in [(vec![0, 3, 10], 10), (vec![0, 3, 3], 3), (vec![3, 0, 5], 0)]{
+            let mut bad = pikp.clone();
+            bad.open = open;
+            assert_eq!(verify_present(&pp, &list, &bad, &pizkp, b""), Err(VerifyError::BadRevealIndex(idx)));
+        }
+        assert_eq!(present(&pp, &signature, issuer_pk, &messages, &vec![5, 3], &list, b"").unwrap_err(), ProveError::BadRevealIndex(3));
+
+        // lengths that do not add up
+        let mut bad = pikp.clone();
+        bad.len = usize::MAX;
+        assert_eq!(verify_present(&pp, &list, &bad, &pizkp, b""), Err(VerifyError::MalformedInput("message length exceeds generators")));
+        let mut bad = pikp.clone();
+        bad.message_list.push(messages[0]);
+        assert_eq!(verify_present(&pp, &list, &bad, &pizkp, b""), Err(VerifyError::MalformedInput("revealed messages do not match reveal indices")));
+        let mut bad = pizkp.clone();
+        bad.v1.pop();
+        assert_eq!(verify_present(&pp, &list, &pikp, &bad, b""), Err(VerifyError::MalformedInput("response count does not match hidden messages")));
+        assert_eq!(verify_batch(&pp, &list, &[(&pikp, &bad, b"")]), Err(vec![(0, VerifyError::MalformedInput("response count does not match hidden messages"))]));
+
+        // an identity pseudonym and an identity issuer key in the list
+        let nym_pt = present_with_pseudonym(&pp, &signature, issuer_pk, &messages, &vec![0], &Vec::new(), 9, b"shop.example", &list, b"").unwrap();
+        let mut bad = nym_pt.0.clone();
+        bad.pseudonym.as_mut().unwrap().nym = G1Affine::zero();
+        assert_eq!(verify_present(&pp, &list, &bad, &nym_pt.1, b""), Err(VerifyError::Credential(mybbs::error::VerifyError::MalformedInput("pseudonym is the identity or not in G1"))));
+        let zero_key = issuer::PublicKey(G2Affine::zero());
+        assert_eq!(issue_list(&pp, &verifier_keypair, &vec![other_issuer.public_key.clone(), zero_key.clone()], &key_proofs), Err(VerifyError::InvalidKeyProof(1)));
+        let mut bad_list = list.clone();
+        bad_list.1[1].ipk = zero_key;
+        assert_eq!(verify_list(&pp, &bad_list), Err(VerifyError::InvalidListEntry(1)));
     }
-}
\ No newline at end of file
+    #[test]
+    fn revokes_issuers_by_epoch() {
+        let message_len = 4;
+        let messages: Vec<Fr> = (0..message_len as u64).map(Fr::from).collect();
+        let pp = par_gen(message_len);
+        let kept = issuer_key_gen(&pp);
+        let revoked = issuer_key_gen(&pp);
+        let kept_cred = issue(&pp, &kept.secret_key, &messages).unwrap();
+        let revoked_cred = issue(&pp, &revoked.secret_key, &messages).unwrap();
+        let verifier_keypair = verifier_key_gen(&pp);
+        let key_proofs = vec![prove_issuer_key(&pp, &kept), prove_issuer_key(&pp, &revoked)];
+        let list0 = issue_list(&pp, &verifier_keypair, &vec![kept.public_key.clone(), revoked.public_key.clone()], &key_proofs).unwrap();
+        let nonce = b"verifier-nonce";
+        let old_pt = present(&pp, &revoked_cred, &revoked.public_key, &messages, &vec![0], &list0, nonce).unwrap();
+        assert_eq!(verify_present(&pp, &list0, &old_pt.0, &old_pt.1, nonce), Ok(()));
+
+        // the next epoch drops an issuer under the same vpk
+        let list1 = next_epoch(&pp, &verifier_keypair, &list0, &vec![revoked.public_key.clone()]).unwrap();
+        assert_eq!((list_epoch(&list1), list1.0.clone(), list1.1.len()), (1, list0.0.clone(), 1));
+        assert_eq!(verify_list(&pp, &list1), Ok(()));
+        let pt = present(&pp, &kept_cred, &kept.public_key, &messages, &vec![0], &list1, nonce).unwrap();
+        assert_eq!(pt.0.epoch, 1);
+        assert_eq!(verify_present(&pp, &list1, &pt.0, &pt.1, nonce), Ok(()));
+        assert_eq!(verify_present(&pp, &list1, &old_pt.0, &old_pt.1, nonce), Err(VerifyError::StaleEpoch { presented: 0, current: 1 }));
+        assert_eq!(present(&pp, &revoked_cred, &revoked.public_key, &messages, &vec![0], &list1, nonce).unwrap_err(), ProveError::IssuerNotInList);
+
+        // an old entry relabelled with the new epoch does not verify, in the list or in a presentation
+        let mut relabelled = list0.1[1].clone();
+        relabelled.epoch = 1;
+        let mut forged_list = list1.clone();
+        forged_list.1.push(relabelled);
+        assert_eq!(verify_list(&pp, &forged_list), Err(VerifyError::InvalidListEntry(1)));
+        let forged_pt = present(&pp, &revoked_cred, &revoked.public_key, &messages, &vec![0], &forged_list, nonce).unwrap();
+        assert_eq!(verify_present(&pp, &forged_list, &forged_pt.0, &forged_pt.1, nonce), Err(VerifyError::PairingCheckFailed(2)));
+
+        assert_eq!(next_epoch(&pp, &verifier_key_gen(&pp), &list1, &Vec::new()), Err(VerifyError::MalformedInput("list is signed under another verifier key")));
+    }
+}
@@ -1,5 +1,6 @@
 #[warn(special_module_name)]
-
 // pub mod algorithms;
 // pub mod ciphersuites;
-pub mod issuer_hiding;
\ No newline at end of file
+pub mod issuer_hiding;
+pub mod error;
+pub mod wire;
\ No newline at end of file
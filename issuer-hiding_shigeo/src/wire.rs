@@ -0,0 +1,54 @@
+use mybbs::wire::{Ciphersuite, Header, Kind, Scheme};
+
+pub use mybbs::wire::{decode, encode, from_cbor, from_json, to_cbor, to_json};
+
+// Headers of this scheme's objects for the envelopes of mybbs::wire. Issuer and verifier
+// keys share the key kinds; the type passed to decode tells them apart.
+pub const PUBLIC_PARAMETERS: Header = header(Kind::PublicParameters);    // bbs::PublicParameters
+pub const PUBLIC_KEY: Header = header(Kind::PublicKey);
+pub const SIGNATURE: Header = header(Kind::Signature);
+pub const TRUSTED_ISSUER_LIST: Header = header(Kind::TrustedIssuerList);  // (verifier::PublicKey, Vec<TrustedIssuerCredential>)
+pub const PRESENTATION: Header = header(Kind::Presentation);              // (PiKP, PiZKP)
+
+const fn header(kind: Kind) -> Header{
+    return Header{ scheme: Scheme::Shigeo, kind: kind, ciphersuite: Ciphersuite::Bls12381Sha256 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issuer_hiding::*;
+    use ark_std::{UniformRand, vec::Vec};
+    use mybbs::error::WireError;
+    use mybbs::{issuer, verifier};
+    use rand::thread_rng;
+
+    #[test]
+    fn it_works(){
+        let mut rng = thread_rng();
+        let messages: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+        let pp = par_gen(4);
+        let issuer_keypair = issuer_key_gen(&pp);
+        let signature = issue(&pp, &issuer_keypair.secret_key, &messages).unwrap();
+        let verifier_keypair = verifier_key_gen(&pp);
+        let other_issuer = issuer_key_gen(&pp);
+        let key_proofs = vec![prove_issuer_key(&pp, &other_issuer), prove_issuer_key(&pp, &issuer_keypair)];
+        let list = issue_list(&pp, &verifier_keypair, &vec![other_issuer.public_key, issuer_keypair.public_key.clone()], &key_proofs).unwrap();
+        let pt = present(&pp, &signature, &issuer_keypair.public_key, &messages, &vec![0, 2], &list, b"nonce").unwrap();
+
+        let pp2 = decode(&PUBLIC_PARAMETERS, &encode(&PUBLIC_PARAMETERS, &pp)).unwrap();
+        let list2 = from_cbor(&TRUSTED_ISSUER_LIST, &to_cbor(&TRUSTED_ISSUER_LIST, &list)).unwrap();
+        let pt2: (PiKP, PiZKP) = from_json(&PRESENTATION, &to_json(&PRESENTATION, &pt)).unwrap();
+        assert_eq!(from_json::<issuer::PublicKey>(&PUBLIC_KEY, &to_json(&PUBLIC_KEY, &issuer_keypair.public_key)), Ok(issuer_keypair.public_key.clone()));
+        assert_eq!(decode::<verifier::PublicKey>(&PUBLIC_KEY, &encode(&PUBLIC_KEY, &verifier_keypair.public_key)), Ok(verifier_keypair.public_key.clone()));
+        assert_eq!(decode::<issuer::Signature>(&SIGNATURE, &encode(&SIGNATURE, &signature)), Ok(signature.clone()));
+        assert_eq!(verify_present(&pp2, &list2, &pt2.0, &pt2.1, b"nonce"), Ok(()));
+
+        // envelopes of other schemes and kinds are rejected before the payload is read
+        let bytes = encode(&mybbs::wire::PUBLIC_PARAMETERS, &pp);
+        assert_eq!(decode::<mybbs::bbs::PublicParameters>(&PUBLIC_PARAMETERS, &bytes), Err(WireError::WrongScheme));
+        let bytes = encode(&PRESENTATION, &pt);
+        assert_eq!(decode::<(verifier::PublicKey, Vec<TrustedIssuerCredential>)>(&TRUSTED_ISSUER_LIST, &bytes), Err(WireError::WrongKind));
+        assert_eq!(decode::<(PiKP, PiZKP)>(&PRESENTATION, &bytes[..bytes.len() - 1]), Err(WireError::InvalidPayload));
+    }
+}
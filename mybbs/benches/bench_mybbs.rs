@@ -1,17 +1,20 @@
 use criterion::{criterion_group, criterion_main, Criterion, BenchmarkId}; // BenchmarkIdを追加
 use std::hint::black_box;
-use ark_bls12_381::Bls12_381;
+use ark_bls12_381::{Bls12_381, G1Affine};
 use ark_ec::pairing::Pairing;
-use ark_std::{vec::Vec, UniformRand};
+use ark_std::{vec::Vec, UniformRand, Zero};
 use rand::{self, Rng, thread_rng};
 pub type Fr = <Bls12_381 as Pairing>::ScalarField;
+use mybbs::bbs as bbs;
 use mybbs::issuer as issuer;
 use mybbs::proof as proof;
+use mybbs::range as range;
+use mybbs::schema as schema;
 
 fn mybbs_benchmark_pc(c: &mut Criterion) {
     let message_len = [5, 10, 15, 20, 25, 30, 35, 40, 45, 50];
     let mut rng = thread_rng();
-    let pp = issuer::par_gen();
+    let pp = issuer::par_gen(*message_len.iter().max().unwrap());
     let issuer_key_pair = issuer::key_gen(&pp);
     let isk = &issuer_key_pair.secret_key;
     let ipk = &issuer_key_pair.public_key;
@@ -33,7 +36,7 @@ fn mybbs_benchmark_pc(c: &mut Criterion) {
             group.bench_with_input(BenchmarkId::new("Sign", len), &len, |b, &_| {
                 b.iter(|| {
                     let signature = issuer::sign(&pp, isk, &message_fr_temp);
-                    black_box(signature);
+                    let _ = black_box(signature);
                 });
             });
         }
@@ -56,7 +59,7 @@ fn mybbs_benchmark_pc(c: &mut Criterion) {
             for _ in 0..mlen {
                 message_fr_temp.push(Fr::rand(&mut rng));
             }
-            let cred_temp = issuer::sign(&pp, &issuer_key_pair.secret_key, &message_fr_temp);
+            let cred_temp = issuer::sign(&pp, &issuer_key_pair.secret_key, &message_fr_temp).unwrap();
 
             // 公開する属性の数を決定
             let open_message_6 = mlen * 3 / 5;
@@ -85,24 +88,141 @@ fn mybbs_benchmark_pc(c: &mut Criterion) {
 
                 // Verify Proof Benchmark
                 // ベンチマーク内で毎回生成すると遅いので、計測外で一度生成
-                let (pikp, pizkp) = proof::prove(&pp, &cred_temp,  &message_fr_temp, &open_temp);
+                let (pikp, pizkp) = proof::prove(&pp, &cred_temp,  &message_fr_temp, &open_temp, b"").unwrap();
                     
                 group.bench_with_input(BenchmarkId::new("Verify_Proof", &param_str), &param_str, |b, _| {
                     b.iter(|| {
-                        let result = proof::verify_proof(&pp, &ipk, &pikp, &pizkp);
-                        black_box(result);
+                        let result = proof::verify_proof(&pp, ipk, &pikp, &pizkp, b"");
+                        let _ = black_box(result);
+                    });
+                });
+
+                let context = issuer::verifier_context(&pp, ipk);
+                group.bench_with_input(BenchmarkId::new("Verify_Proof_Context", &param_str), &param_str, |b, _| {
+                    b.iter(|| {
+                        let result = proof::verify_proof_with_context(&context, &pikp, &pizkp, b"");
+                        let _ = black_box(result);
                     });
                 });
             }
         }
         group.finish();
     }
+
+    // ------------------------------------------------------------------
+    // Group 3: ペアリング検査 e(A, pk) = e(B, g2) の計算方法による比較
+    // ------------------------------------------------------------------
+    {
+        let mut group = c.benchmark_group("Pairing_Ops");
+        let a = G1Affine::from(pp.g1 * Fr::rand(&mut rng));
+        let isk_scalar = bbs::scalars_from_bytes(&bbs::secret_key_to_bytes(isk)).unwrap()[0];
+        let b_point = G1Affine::from(a * isk_scalar);
+        let pk_prepared = bbs::G2Prepared::from(ipk.0);
+        let g2_prepared = bbs::G2Prepared::from(pp.g2);
+
+        // 変更前: ペアリングを2回計算してGTで比較
+        group.bench_function("Two_Pairings", |b| {
+            b.iter(|| {
+                let result = Bls12_381::pairing(a, ipk.0) == Bls12_381::pairing(b_point, pp.g2);
+                black_box(result);
+            });
+        });
+
+        // multi-Miller loop + 最終べき1回
+        group.bench_function("Multi_Pairing", |b| {
+            b.iter(|| {
+                let result = Bls12_381::multi_pairing([a, -b_point], [ipk.0, pp.g2]).is_zero();
+                black_box(result);
+            });
+        });
+
+        // G2側を事前計算済み
+        group.bench_function("Multi_Pairing_Prepared", |b| {
+            b.iter(|| {
+                let result = Bls12_381::multi_pairing([a, -b_point], [pk_prepared.clone(), g2_prepared.clone()]).is_zero();
+                black_box(result);
+            });
+        });
+        group.finish();
+    }
+
+    // ------------------------------------------------------------------
+    // Group 4: バッチ検証 (署名数による変化, メッセージ長10)
+    // ------------------------------------------------------------------
+    {
+        let mut group = c.benchmark_group("Batch_Verify_Ops");
+        let batch_size = [10, 50, 100];
+
+        for &num in batch_size.iter() {
+            // 署名数numの入力データを準備
+            let mut messages_temp = Vec::new();
+            let mut creds_temp = Vec::new();
+            for _ in 0..num {
+                let message_fr_temp: Vec<Fr> = (0..10).map(|_| Fr::rand(&mut rng)).collect();
+                creds_temp.push(issuer::sign(&pp, isk, &message_fr_temp).unwrap());
+                messages_temp.push(message_fr_temp);
+            }
+            let items: Vec<_> = (0..num).map(|j| (ipk, &messages_temp[j], &creds_temp[j])).collect();
+
+            // 1つずつ検証
+            group.bench_with_input(BenchmarkId::new("Verify_Each", num), &num, |b, &_| {
+                b.iter(|| {
+                    for (pk, messages, signature) in items.iter() {
+                        let result = issuer::verify(&pp, pk, messages, signature);
+                        let _ = black_box(result);
+                    }
+                });
+            });
+
+            // まとめて検証
+            group.bench_with_input(BenchmarkId::new("Verify_Batch", num), &num, |b, &_| {
+                b.iter(|| {
+                    let result = issuer::verify_batch(&pp, &items);
+                    let _ = black_box(result);
+                });
+            });
+        }
+        group.finish();
+    }
+
+    // ------------------------------------------------------------------
+    // Group 5: 範囲述語付きProof (述語数による変化, メッセージ長10)
+    // ------------------------------------------------------------------
+    {
+        let mut group = c.benchmark_group("Range_Predicate_Ops");
+        let predicate_num = [1, 2, 4];
+
+        // 秘匿する属性はすべて0以上1000未満の整数
+        let messages: Vec<Fr> = (0..10).map(|i| schema::encode_integer(i * 100)).collect();
+        let cred = issuer::sign(&pp, isk, &messages).unwrap();
+        let reveal_index = vec![0];
+
+        for &num in predicate_num.iter() {
+            let predicates: Vec<_> = (1..=num).map(|i| range::integer_range(i, 0, 1000)).collect();
+
+            group.bench_with_input(BenchmarkId::new("Prove", num), &num, |b, &_| {
+                b.iter(|| {
+                    let result = proof::prove_with_predicates(&pp, &cred, &messages, &reveal_index, &predicates, b"");
+                    let _ = black_box(result);
+                });
+            });
+
+            let (pikp, pizkp) = proof::prove_with_predicates(&pp, &cred, &messages, &reveal_index, &predicates, b"").unwrap();
+            group.bench_with_input(BenchmarkId::new("Verify", num), &num, |b, &_| {
+                b.iter(|| {
+                    let result = proof::verify_proof(&pp, ipk, &pikp, &pizkp, b"");
+                    let _ = black_box(result);
+                });
+            });
+        }
+        group.finish();
+    }
 }
 
 fn mybbs_benchmark_android(c: &mut Criterion) {
     let message_len = [5, 10, 15, 20, 25, 30, 40, 50];
     let mut rng = thread_rng();
-    let pp = issuer::par_gen();
+    let pp = issuer::par_gen(*message_len.iter().max().unwrap());
     let issuer_key_pair = issuer::key_gen(&pp);
     let isk = &issuer_key_pair.secret_key;
     let ipk = &issuer_key_pair.public_key;
@@ -121,11 +241,19 @@ fn mybbs_benchmark_android(c: &mut Criterion) {
             }
 
             // Verify Credential (署名生成済みデータが必要)
-            let cred_temp = issuer::sign(&pp, isk, &message_fr_temp);
+            let cred_temp = issuer::sign(&pp, isk, &message_fr_temp).unwrap();
             group.bench_with_input(BenchmarkId::new("Verify", len), &len, |b, &_| {
                 b.iter(|| {
                     let result = issuer::verify(&pp, ipk, &message_fr_temp, &cred_temp);
-                    black_box(result);
+                    let _ = black_box(result);
+                });
+            });
+
+            let context = issuer::verifier_context(&pp, ipk);
+            group.bench_with_input(BenchmarkId::new("Verify_Context", len), &len, |b, &_| {
+                b.iter(|| {
+                    let result = issuer::verify_with_context(&context, &message_fr_temp, &cred_temp);
+                    let _ = black_box(result);
                 });
             });
         }
@@ -148,7 +276,7 @@ fn mybbs_benchmark_android(c: &mut Criterion) {
             for _ in 0..mlen {
                 message_fr_temp.push(Fr::rand(&mut rng));
             }
-            let cred_temp = issuer::sign(&pp, isk, &message_fr_temp);
+            let cred_temp = issuer::sign(&pp, isk, &message_fr_temp).unwrap();
 
             // 公開する属性の数を決定
             let open_message_6 = mlen * 3 / 5;
@@ -178,8 +306,8 @@ fn mybbs_benchmark_android(c: &mut Criterion) {
                 // Present Benchmark
                 group.bench_with_input(BenchmarkId::new("Proof", &param_str), &param_str, |b, _| {
                     b.iter(|| {
-                        let pt = proof::prove(&pp, &cred_temp, &message_fr_temp, &open_temp);
-                        black_box(pt);
+                        let pt = proof::prove(&pp, &cred_temp, &message_fr_temp, &open_temp, b"");
+                        let _ = black_box(pt);
                     });
                 });
             }
@@ -1,11 +1,18 @@
 use ark_bls12_381::{Bls12_381, G1Affine, G1Projective, G2Affine, G2Projective, g1::Config as G1Config, g2::Config as G2Config};
-use ark_ec::{hashing::{curve_maps::wb::WBMap, map_to_curve_hasher::MapToCurveBasedHasher, HashToCurve},pairing::Pairing};
+use ark_ec::{hashing::{curve_maps::wb::WBMap, map_to_curve_hasher::MapToCurveBasedHasher, HashToCurve},pairing::Pairing, AffineRepr, CurveGroup};
 use ark_ff::field_hashers::{DefaultFieldHasher, HashToField};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::Zero;
+use rand::{rngs::OsRng, Rng};
 use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt;
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 pub type Fr = <Bls12_381 as Pairing>::ScalarField;
 
+use crate::error::WireError;
 use crate::issuer;
 use crate::verifier;
 
@@ -22,8 +29,8 @@ pub struct PublicParameters {
 // g_1 = h'97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb'
 // g_2 = h'93e02b6052719f607dacd3a088274f65596bd0d09920b61ab5da61bbdc7f5049334cf11213945d57e5ac7d055d042b7e024aa2b2f08f0a91260805272dc51051c6e47ad4fa403b02b4510b647ae3d1770bac0326a805bbefd48056c8c121bdb8'
 
-pub fn par_gen() -> PublicParameters{
-    let pp_issuer = issuer::par_gen();
+pub fn par_gen(message_len: usize) -> PublicParameters{
+    let pp_issuer = issuer::par_gen(message_len);
     let pp_verifier = verifier::par_gen();
     let pp = PublicParameters{
         g1: pp_issuer.g1,
@@ -35,8 +42,75 @@ pub fn par_gen() -> PublicParameters{
     return pp
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
-pub struct SecretKey(pub Fr);
+pub fn extend_generators(pp: &mut PublicParameters, message_len: usize){
+    for i in pp.h_vec.len()..message_len{
+        pp.h_vec.push(issuer::h_generator(i));
+    }
+}
+
+// Issuer and verifier secret key. Outside this crate the scalar is only reachable through
+// secret_key_to_bytes; it is compared in constant time, redacted in Debug and wiped on drop.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct SecretKey(pub(crate) Fr);
+
+impl fmt::Debug for SecretKey{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        return f.write_str("SecretKey(<redacted>)")
+    }
+}
+
+impl PartialEq for SecretKey{
+    fn eq(&self, other: &Self) -> bool{
+        return ct_eq_scalars(&[self.0], &[other.0]).into()
+    }
+}
+
+impl Eq for SecretKey {}
+
+pub fn secret_key_to_bytes(sk: &SecretKey) -> Zeroizing<Vec<u8>>{
+    return scalars_to_bytes(&[sk.0])
+}
+
+pub fn secret_key_from_bytes(bytes: &[u8]) -> Result<SecretKey, WireError>{
+    let scalars = scalars_from_bytes(bytes)?;
+    if scalars.len() != 1 {
+        return Err(WireError::InvalidPayload);
+    }
+    return Ok(SecretKey(scalars[0]))
+}
+
+// Constant-time equality of secret scalars, through their canonical Montgomery limbs. Only
+// the lengths, which are public, are compared with an early exit.
+pub fn ct_eq_scalars(a: &[Fr], b: &[Fr]) -> Choice{
+    if a.len() != b.len() {
+        return Choice::from(0);
+    }
+    return a.iter().zip(b).fold(Choice::from(1), |acc, (x, y)| acc & x.0.0[..].ct_eq(&y.0.0[..]))
+}
+
+// The byte form of secret keys: their scalars as 32-byte little-endian field elements, in a
+// buffer allocated once so no copy is left behind by a reallocation.
+pub fn scalars_to_bytes(scalars: &[Fr]) -> Zeroizing<Vec<u8>>{
+    let mut bytes = Zeroizing::new(Vec::with_capacity(scalars.len() * Fr::zero().compressed_size()));
+    for scalar in scalars{
+        scalar.serialize_compressed(&mut *bytes).unwrap();
+    }
+    return bytes
+}
+
+// The inverse of scalars_to_bytes. Rejects non-canonical scalars and partial ones.
+pub fn scalars_from_bytes(bytes: &[u8]) -> Result<Zeroizing<Vec<Fr>>, WireError>{
+    let size = Fr::zero().compressed_size();
+    if !bytes.len().is_multiple_of(size) {
+        return Err(WireError::InvalidPayload);
+    }
+    let mut scalars = Zeroizing::new(Vec::with_capacity(bytes.len() / size));
+    let mut reader = bytes;
+    for _ in 0..bytes.len() / size{
+        scalars.push(Fr::deserialize_compressed(&mut reader).map_err(|_| WireError::InvalidPayload)?);
+    }
+    return Ok(scalars)
+}
 
 pub fn hash_to_fr(input: &[u8], dst: &[u8]) -> Fr {
     let hasher = <DefaultFieldHasher<Sha256> as HashToField<Fr>>::new(dst);
@@ -61,13 +135,112 @@ pub fn hash_to_g2(input: &[u8], dst: &[u8]) -> G2Affine {
     return hashpoint;
 }
 
+// Whether none of points is the identity and all are on the curve and in the prime-order
+// subgroup. Verifiers run it on every point a credential or presentation hands them before
+// pairing it: an identity A_bar turns e(A_bar, pk) = e(B_bar, g2) into 1 = 1. The wire module
+// already rejects off-curve and out-of-subgroup points, but a presentation built in memory
+// does not go through it.
+pub fn valid_points<P: AffineRepr>(points: &[P]) -> bool{
+    return points.iter().all(|p| !p.is_zero() && p.check().is_ok())
+}
+
+// \prod_i e(P_i, Q_i) = 1. Verifiers describe each of their pairing checks this way so the
+// checks of many credentials or presentations can be run together.
+pub type PairingEquation = Vec<(G1Projective, G2Affine)>;
+
+pub type G2Prepared = <Bls12_381 as Pairing>::G2Prepared;
+
+pub fn pairing_check(equation: &PairingEquation) -> bool{
+    let g1_pro: Vec<G1Projective> = equation.iter().map(|(p, _)| *p).collect();
+    let g1_points = G1Projective::normalize_batch(&g1_pro);
+    let g2_points: Vec<G2Affine> = equation.iter().map(|(_, q)| *q).collect();
+    return Bls12_381::multi_pairing(g1_points, g2_points).is_zero()
+}
+
+// pairing_check for G2 points that were prepared ahead of time. Keys that verify many
+// credentials (g2, an issuer's pk, ...) skip recomputing the Miller loop line coefficients.
+pub fn prepared_pairing_check(pairs: &[(G1Projective, &G2Prepared)]) -> bool{
+    let g1_pro: Vec<G1Projective> = pairs.iter().map(|(p, _)| *p).collect();
+    let g1_points = G1Projective::normalize_batch(&g1_pro);
+    let g2_points: Vec<G2Prepared> = pairs.iter().map(|(_, q)| (*q).clone()).collect();
+    return Bls12_381::multi_pairing(g1_points, g2_points).is_zero()
+}
+
+// Raises every equation to a random 64-bit exponent and multiplies them together. Pairs that
+// share a G2 point (g2, the same issuer key, ...) are merged first, so the whole batch costs
+// one multi-Miller loop over the distinct G2 points and a single final exponentiation.
+// A batch containing a false equation passes with probability at most 2^-64.
+pub fn batch_pairing_check(equations: &[PairingEquation]) -> bool{
+    let mut merged: HashMap<G2Affine, G1Projective> = HashMap::new();
+    for equation in equations{
+        let r = Fr::from(OsRng.gen::<u64>());
+        for (p, q) in equation{
+            *merged.entry(*q).or_insert(G1Projective::zero()) += *p * r;
+        }
+    }
+    let (g2_points, g1_pro): (Vec<G2Affine>, Vec<G1Projective>) = merged.into_iter().unzip();
+    let g1_points = G1Projective::normalize_batch(&g1_pro);
+    return Bls12_381::multi_pairing(g1_points, g2_points).is_zero()
+}
+
+// Shared by the verify_batch functions. items[j] is either the error item j already failed
+// with before any pairing, or the pairing equations it still has to satisfy. Returns every
+// failing item with its error, falling back to checking items one by one only when the
+// combined check fails. pairing_failed builds the error for the k-th (1-based) equation.
+pub fn verify_batch_equations<E>(
+    items: Vec<Result<Vec<PairingEquation>, E>>,
+    pairing_failed: impl Fn(usize) -> E,
+) -> Result<(), Vec<(usize, E)>>{
+    let mut failures = Vec::new();
+    let mut pending = Vec::new();
+    for (j, item) in items.into_iter().enumerate(){
+        match item{
+            Ok(equations) => pending.push((j, equations)),
+            Err(e) => failures.push((j, e)),
+        }
+    }
+
+    let all_equations: Vec<PairingEquation> = pending.iter().flat_map(|(_, eqs)| eqs.iter().cloned()).collect();
+    if !batch_pairing_check(&all_equations) {
+        for (j, equations) in &pending{
+            if let Some(k) = equations.iter().position(|eq| !pairing_check(eq)) {
+                failures.push((*j, pairing_failed(k + 1)));
+            }
+        }
+        failures.sort_by_key(|(j, _)| *j);
+    }
+
+    if failures.is_empty() {
+        return Ok(())
+    }
+    return Err(failures)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::par_gen;
+    use super::*;
+    use crate::error::WireError;
+    use crate::issuer;
 
     #[test]
     fn it_works() {
-        let pp = par_gen();
+        let pp = par_gen(10);
         println!("{:?}", pp);
+
+        // secret keys are redacted, compared by value and only leave through their bytes
+        let pp_issuer = issuer::par_gen(1);
+        let keypair = issuer::key_gen(&pp_issuer);
+        let sk = &keypair.secret_key;
+        assert_eq!(format!("{:?}", sk), "SecretKey(<redacted>)");
+        assert!(!format!("{:?}", keypair).contains(&sk.0.to_string()));
+        let bytes = secret_key_to_bytes(sk);
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(secret_key_from_bytes(&bytes), Ok(sk.clone()));
+        assert_ne!(&issuer::key_gen(&pp_issuer).secret_key, sk);
+        assert_eq!(secret_key_from_bytes(&bytes[..31]), Err(WireError::InvalidPayload));
+        assert_eq!(secret_key_from_bytes(&[0xff; 32]), Err(WireError::InvalidPayload));
+        assert_eq!(secret_key_from_bytes(&[bytes.as_slice(), bytes.as_slice()].concat()), Err(WireError::InvalidPayload));
+        assert!(bool::from(ct_eq_scalars(&[sk.0, Fr::from(1u64)], &[sk.0, Fr::from(1u64)])));
+        assert!(!bool::from(ct_eq_scalars(&[sk.0], &[sk.0, sk.0])));
     }
 }
\ No newline at end of file
@@ -0,0 +1,296 @@
+use ark_bls12_381::{G1Affine, Bls12_381, G1Projective};
+use ark_ff::Field;
+use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{fmt::{self, Debug}, UniformRand, vec::Vec};
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use crate::bbs;
+use crate::error::{ProveError, VerifyError, WireError};
+use crate::issuer;
+
+pub type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+// Blind issuance: the holder commits to some messages, the issuer signs the commitment
+// together with the messages it knows, and the holder ends up with an ordinary
+// issuer::Signature. The commitment's blinding factor becomes the message at
+// blinding_index, so the final signature covers committed.len() + 1 + known.len() messages.
+
+#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+pub struct Commitment{
+    pub c: G1Affine,                // C = \sum_{i \in committed} h_i^m_i * h_j^s
+    pub committed_index: Vec<usize>,
+    pub blinding_index: usize,      // j
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+pub struct CommitmentProof{
+    pub c: Fr,
+    pub z_s: Fr,
+    pub z_m: Vec<Fr>,
+}
+
+// Kept by the holder until the blind signature comes back, and wiped on drop.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct CommitmentSecret{
+    pub(crate) messages: Vec<Fr>,
+    pub(crate) blinding: Fr,
+}
+
+impl Debug for CommitmentSecret{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        return f.write_str("CommitmentSecret(<redacted>)")
+    }
+}
+
+impl PartialEq for CommitmentSecret{
+    fn eq(&self, other: &Self) -> bool{
+        return (bbs::ct_eq_scalars(&self.messages, &other.messages) & bbs::ct_eq_scalars(&[self.blinding], &[other.blinding])).into()
+    }
+}
+
+impl Eq for CommitmentSecret {}
+
+// The committed messages followed by the blinding.
+pub fn commitment_secret_to_bytes(secret: &CommitmentSecret) -> Zeroizing<Vec<u8>>{
+    let scalars: Zeroizing<Vec<Fr>> = Zeroizing::new(secret.messages.iter().chain(Some(&secret.blinding)).copied().collect());
+    return bbs::scalars_to_bytes(&scalars)
+}
+
+pub fn commitment_secret_from_bytes(bytes: &[u8]) -> Result<CommitmentSecret, WireError>{
+    let mut scalars = bbs::scalars_from_bytes(bytes)?;
+    let blinding = match scalars.pop() {
+        Some(blinding) => blinding,
+        None => return Err(WireError::InvalidPayload),
+    };
+    return Ok(CommitmentSecret{ messages: scalars.to_vec(), blinding })
+}
+
+fn commitment_challenge(pp: &issuer::PublicParameters, commitment: &Commitment, t: &G1Affine, nonce: &[u8]) -> Fr{
+    let dst = b"MY_BLIND_COMMITMENT_DST_V1";
+    let h_generators: Vec<G1Affine> = commitment.committed_index.iter()
+        .chain(Some(&commitment.blinding_index))
+        .map(|&i| pp.h_vec[i])
+        .collect();
+    let mut buffer = Vec::new();
+    h_generators.serialize_compressed(&mut buffer).unwrap();
+    commitment.serialize_compressed(&mut buffer).unwrap();
+    t.serialize_compressed(&mut buffer).unwrap();
+    nonce.to_vec().serialize_compressed(&mut buffer).unwrap();
+    return bbs::hash_to_fr(&buffer[..], dst)
+}
+
+// Every index in 0..total must be used exactly once across committed, blinding and known.
+fn check_layout(pp: &issuer::PublicParameters, commitment: &Commitment, known_index: &Vec<usize>) -> Result<usize, usize>{
+    let total = commitment.committed_index.len() + 1 + known_index.len();
+    let mut seen = vec![false; total];
+    let all = commitment.committed_index.iter().chain(known_index.iter()).chain(Some(&commitment.blinding_index));
+    for &idx in all{
+        if idx >= total || idx >= pp.h_vec.len() || seen[idx] {
+            return Err(idx)
+        }
+        seen[idx] = true;
+    }
+    return Ok(total)
+}
+
+pub fn commit_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    messages: &Vec<Fr>,
+    committed_index: &Vec<usize>,
+    blinding_index: usize,
+    nonce: &[u8],
+    rng: &mut R,
+) -> Result<(Commitment, CommitmentProof, CommitmentSecret), ProveError>{
+    if messages.len() != committed_index.len() {
+        return Err(ProveError::MalformedInput("committed messages do not match committed indices"));
+    }
+    if let Some(&idx) = committed_index.iter().chain(Some(&blinding_index)).find(|&&idx| idx >= pp.h_vec.len()) {
+        return Err(ProveError::BadRevealIndex(idx));
+    }
+    if committed_index.contains(&blinding_index) {
+        return Err(ProveError::BadRevealIndex(blinding_index));
+    }
+
+    let s = Fr::rand(rng);
+    let r_s = Zeroizing::new(Fr::rand(rng));
+    let r_m: Zeroizing<Vec<Fr>> = Zeroizing::new((0..messages.len()).map(|_| Fr::rand(rng)).collect());
+
+    // C = h_j^s * \prod h_i^m_i, T = h_j^r_s * \prod h_i^r_i
+    let mut c_element = pp.h_vec[blinding_index] * s;
+    let mut t_element = pp.h_vec[blinding_index] * *r_s;
+    for (k, &i) in committed_index.iter().enumerate(){
+        c_element += pp.h_vec[i] * messages[k];
+        t_element += pp.h_vec[i] * r_m[k];
+    }
+    let commitment = Commitment{
+        c: G1Affine::from(c_element),
+        committed_index: committed_index.clone(),
+        blinding_index,
+    };
+    let c = commitment_challenge(pp, &commitment, &G1Affine::from(t_element), nonce);
+
+    let z_s = *r_s + c * s;
+    let z_m: Vec<Fr> = (0..messages.len()).map(|k| r_m[k] + c * messages[k]).collect();
+    let proof = CommitmentProof{
+        c,
+        z_s,
+        z_m,
+    };
+    let secret = CommitmentSecret{
+        messages: messages.clone(),
+        blinding: s,
+    };
+    return Ok((commitment, proof, secret))
+}
+
+pub fn commit(
+    pp: &issuer::PublicParameters,
+    messages: &Vec<Fr>,
+    committed_index: &Vec<usize>,
+    blinding_index: usize,
+    nonce: &[u8],
+) -> Result<(Commitment, CommitmentProof, CommitmentSecret), ProveError>{
+    return commit_with_rng(pp, messages, committed_index, blinding_index, nonce, &mut OsRng)
+}
+
+pub fn verify_commitment(
+    pp: &issuer::PublicParameters,
+    commitment: &Commitment,
+    proof: &CommitmentProof,
+    nonce: &[u8],
+) -> Result<(), VerifyError>{
+    if proof.z_m.len() != commitment.committed_index.len() {
+        return Err(VerifyError::MalformedInput("response count does not match committed messages"));
+    }
+    if let Some(&idx) = commitment.committed_index.iter().chain(Some(&commitment.blinding_index)).find(|&&idx| idx >= pp.h_vec.len()) {
+        return Err(VerifyError::BadRevealIndex(idx));
+    }
+    if !bbs::valid_points(&[commitment.c]) {
+        return Err(VerifyError::MalformedInput("commitment is the identity or not in G1"));
+    }
+
+    // T = h_j^z_s * \prod h_i^z_i * C^{-c}
+    let mut t_element = pp.h_vec[commitment.blinding_index] * proof.z_s + commitment.c * (-proof.c);
+    for (k, &i) in commitment.committed_index.iter().enumerate(){
+        t_element += pp.h_vec[i] * proof.z_m[k];
+    }
+    let c = commitment_challenge(pp, commitment, &G1Affine::from(t_element), nonce);
+    if c != proof.c {
+        return Err(VerifyError::ChallengeMismatch)
+    }
+    return Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn blind_sign_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    sk: &bbs::SecretKey,
+    commitment: &Commitment,
+    proof: &CommitmentProof,
+    nonce: &[u8],
+    known_messages: &Vec<Fr>,
+    known_index: &Vec<usize>,
+    rng: &mut R,
+) -> Result<issuer::Signature, ProveError>{
+    if known_messages.len() != known_index.len() {
+        return Err(ProveError::MalformedInput("known messages do not match known indices"));
+    }
+    check_layout(pp, commitment, known_index).map_err(ProveError::BadRevealIndex)?;
+    verify_commitment(pp, commitment, proof, nonce).map_err(ProveError::CommitmentRejected)?;
+
+    let e = Fr::rand(rng);
+
+    // A = (g1 * C * \prod_{i \in known} h_i^m_i)^{1/(sk + e)}
+    let mut m_product = G1Projective::from(pp.g1) + commitment.c;
+    for (k, &i) in known_index.iter().enumerate(){
+        m_product += pp.h_vec[i] * known_messages[k];
+    }
+    let sk_plus_e_inv = (sk.0 + e).inverse().unwrap();
+    let signature = issuer::Signature{
+        a: G1Affine::from(m_product * sk_plus_e_inv),
+        e: e,
+    };
+    return Ok(signature)
+}
+
+pub fn blind_sign(
+    pp: &issuer::PublicParameters,
+    sk: &bbs::SecretKey,
+    commitment: &Commitment,
+    proof: &CommitmentProof,
+    nonce: &[u8],
+    known_messages: &Vec<Fr>,
+    known_index: &Vec<usize>,
+) -> Result<issuer::Signature, ProveError>{
+    return blind_sign_with_rng(pp, sk, commitment, proof, nonce, known_messages, known_index, &mut OsRng)
+}
+
+// Puts the committed, blinding and known messages back in order and checks the signature
+// over them. The returned messages are what prove/verify_proof work with afterwards.
+pub fn finalize(
+    pp: &issuer::PublicParameters,
+    pk: &issuer::PublicKey,
+    signature: &issuer::Signature,
+    commitment: &Commitment,
+    secret: &CommitmentSecret,
+    known_messages: &Vec<Fr>,
+    known_index: &Vec<usize>,
+) -> Result<(issuer::Signature, Vec<Fr>), VerifyError>{
+    if known_messages.len() != known_index.len() || secret.messages.len() != commitment.committed_index.len() {
+        return Err(VerifyError::MalformedInput("messages do not match indices"));
+    }
+    let total = check_layout(pp, commitment, known_index).map_err(VerifyError::BadRevealIndex)?;
+    let mut messages = vec![Fr::from(0u64); total];
+    for (k, &i) in commitment.committed_index.iter().enumerate(){
+        messages[i] = secret.messages[k];
+    }
+    messages[commitment.blinding_index] = secret.blinding;
+    for (k, &i) in known_index.iter().enumerate(){
+        messages[i] = known_messages[k];
+    }
+    issuer::verify(pp, pk, &messages, signature)?;
+    return Ok((signature.clone(), messages))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+    use crate::proof;
+
+    #[test]
+    fn it_works() {
+        let mut rng = thread_rng();
+        let pp = issuer::par_gen(6);
+        let keypair = issuer::key_gen(&pp);
+
+        // link secret at 0, blinding factor at 1, issuer-known attributes at 2..6
+        let link_secret = vec![Fr::rand(&mut rng)];
+        let committed_index = vec![0];
+        let known_index = vec![2, 3, 4, 5];
+        let known_messages: Vec<Fr> = (0..known_index.len()).map(|_| Fr::rand(&mut rng)).collect();
+        let nonce = b"issuer-nonce";
+
+        let (commitment, commitment_proof, secret) = commit(&pp, &link_secret, &committed_index, 1, nonce).unwrap();
+        assert_eq!(verify_commitment(&pp, &commitment, &commitment_proof, b"other-nonce"), Err(VerifyError::ChallengeMismatch));
+        assert_eq!(commitment_secret_from_bytes(&commitment_secret_to_bytes(&secret)), Ok(secret.clone()));
+        assert_eq!(format!("{:?}", secret), "CommitmentSecret(<redacted>)");
+        assert_eq!(commitment_secret_from_bytes(&[]), Err(WireError::InvalidPayload));
+        let blind_signature = blind_sign(&pp, &keypair.secret_key, &commitment, &commitment_proof, nonce, &known_messages, &known_index).unwrap();
+        let (signature, messages) = finalize(&pp, &keypair.public_key, &blind_signature, &commitment, &secret, &known_messages, &known_index).unwrap();
+        assert_eq!(messages[0], link_secret[0]);
+        assert_eq!(issuer::verify(&pp, &keypair.public_key, &messages, &signature), Ok(()));
+
+        let (pikp, pizkp) = proof::prove(&pp, &signature, &messages, &vec![2, 4], b"").unwrap();
+        assert_eq!(proof::verify_proof(&pp, &keypair.public_key, &pikp, &pizkp, b""), Ok(()));
+
+        let mut forged = commitment.clone();
+        forged.c = G1Affine::from(forged.c + pp.h_vec[0]);
+        let result = blind_sign(&pp, &keypair.secret_key, &forged, &commitment_proof, nonce, &known_messages, &known_index);
+        assert_eq!(result, Err(ProveError::CommitmentRejected(VerifyError::ChallengeMismatch)));
+        let result = blind_sign(&pp, &keypair.secret_key, &commitment, &commitment_proof, nonce, &known_messages, &vec![0, 3, 4, 5]);
+        assert_eq!(result, Err(ProveError::BadRevealIndex(0)));
+    }
+}
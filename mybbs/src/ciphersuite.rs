@@ -0,0 +1,596 @@
+// BBS_BLS12381G1_XMD:SHA-256_SSWU_RO_ ciphersuite following draft-irtf-cfrg-bbs-signatures.
+// Messages are octet strings, signatures and proofs travel as octet strings, and every
+// hash goes through expand_message_xmd so the outputs match other BBS implementations.
+use ark_bls12_381::{G1Affine, G2Affine, Bls12_381, G1Projective};
+use ark_ff::{Field, PrimeField, Zero};
+use ark_ec::{pairing::Pairing, AffineRepr};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{fmt::Debug, UniformRand, vec::Vec};
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use zeroize::Zeroizing;
+use sha2::{Digest, Sha256};
+
+use crate::bbs;
+use crate::error::{ProveError, VerifyError};
+use crate::issuer;
+use crate::proof;
+
+pub type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+pub const CIPHERSUITE_ID: &[u8] = b"BBS_BLS12381G1_XMD:SHA-256_SSWU_RO_";
+pub const API_ID: &[u8] = b"BBS_BLS12381G1_XMD:SHA-256_SSWU_RO_H2G_HM2S_";
+
+const EXPAND_LEN: usize = 48;
+const OCTET_SCALAR_LENGTH: usize = 32;
+const OCTET_POINT_LENGTH: usize = 48;
+const P1_HEX: &str = "a8ce256102840821a3e94ea9025e4662b205762f9776b3a766c872b948f1fd225e7c59698588e70d11406d161b4e28c9";
+
+#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+pub struct Proof{
+    pub a_bar: G1Affine,
+    pub b_bar: G1Affine,
+    pub d: G1Affine,
+    pub e_hat: Fr,
+    pub r1_hat: Fr,
+    pub r3_hat: Fr,
+    pub commitments: Vec<Fr>,
+    pub challenge: Fr,
+}
+
+struct InitResult{
+    a_bar: G1Affine,
+    b_bar: G1Affine,
+    d: G1Affine,
+    t1: G1Affine,
+    t2: G1Affine,
+    domain: Fr,
+}
+
+fn concat(parts: &[&[u8]]) -> Vec<u8>{
+    return parts.concat()
+}
+
+// expand_message_xmd from RFC 9380 with SHA-256 (64 byte blocks, 32 byte output).
+pub fn expand_message_xmd(msg: &[u8], dst: &[u8], len_in_bytes: usize) -> Vec<u8>{
+    let b_in_bytes = 32;
+    let s_in_bytes = 64;
+    let ell = len_in_bytes.div_ceil(b_in_bytes);
+    assert!(ell <= 255 && len_in_bytes <= 65535 && dst.len() <= 255);
+
+    let dst_prime = concat(&[dst, &[dst.len() as u8]]);
+    let z_pad = vec![0u8; s_in_bytes];
+    let l_i_b_str = (len_in_bytes as u16).to_be_bytes();
+
+    let b_0 = Sha256::new()
+        .chain_update(&z_pad)
+        .chain_update(msg)
+        .chain_update(l_i_b_str)
+        .chain_update([0u8])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut b_i = Sha256::new()
+        .chain_update(b_0)
+        .chain_update([1u8])
+        .chain_update(&dst_prime)
+        .finalize();
+
+    let mut uniform_bytes = b_i.to_vec();
+    for i in 2..=ell{
+        let xored: Vec<u8> = b_0.iter().zip(b_i.iter()).map(|(l, r)| l ^ r).collect();
+        b_i = Sha256::new()
+            .chain_update(xored)
+            .chain_update([i as u8])
+            .chain_update(&dst_prime)
+            .finalize();
+        uniform_bytes.extend_from_slice(&b_i);
+    }
+    uniform_bytes.truncate(len_in_bytes);
+    return uniform_bytes
+}
+
+pub fn hash_to_scalar(msg: &[u8], dst: &[u8]) -> Fr{
+    let uniform_bytes = expand_message_xmd(msg, dst, EXPAND_LEN);
+    return Fr::from_be_bytes_mod_order(&uniform_bytes)
+}
+
+pub fn scalar_to_octets(s: &Fr) -> Vec<u8>{
+    let mut out = Vec::new();
+    s.serialize_compressed(&mut out).unwrap();
+    out.reverse();
+    return out
+}
+
+// Rejects anything that is not a canonical scalar, as the draft's octets_to_signature does.
+pub fn octets_to_scalar(octets: &[u8]) -> Option<Fr>{
+    if octets.len() != OCTET_SCALAR_LENGTH {
+        return None
+    }
+    let mut le = octets.to_vec();
+    le.reverse();
+    return Fr::deserialize_compressed(&le[..]).ok()
+}
+
+pub fn point_to_octets_g1(p: &G1Affine) -> Vec<u8>{
+    let mut out = Vec::new();
+    p.serialize_compressed(&mut out).unwrap();
+    return out
+}
+
+pub fn point_to_octets_g2(p: &G2Affine) -> Vec<u8>{
+    let mut out = Vec::new();
+    p.serialize_compressed(&mut out).unwrap();
+    return out
+}
+
+pub fn key_gen(key_material: &[u8], key_info: &[u8], key_dst: Option<&[u8]>) -> Result<bbs::SecretKey, ProveError>{
+    if key_material.len() < 32 {
+        return Err(ProveError::MalformedInput("key material shorter than 32 bytes"));
+    }
+    if key_info.len() > 65535 {
+        return Err(ProveError::MalformedInput("key info longer than 65535 bytes"));
+    }
+    let default_dst = concat(&[API_ID, b"KEYGEN_DST_"]);
+    let key_dst = key_dst.unwrap_or(&default_dst);
+    let derive_input = concat(&[key_material, &(key_info.len() as u16).to_be_bytes(), key_info]);
+    let sk = hash_to_scalar(&derive_input, key_dst);
+    if sk.is_zero() {
+        return Err(ProveError::MalformedInput("derived secret key is zero"));
+    }
+    return Ok(bbs::SecretKey(sk))
+}
+
+pub fn sk_to_pk(sk: &bbs::SecretKey) -> issuer::PublicKey{
+    return issuer::PublicKey(G2Affine::from(G2Affine::generator() * sk.0))
+}
+
+pub fn octets_to_pubkey(octets: &[u8]) -> Result<issuer::PublicKey, VerifyError>{
+    let w = G2Affine::deserialize_compressed(octets)
+        .map_err(|_| VerifyError::MalformedInput("public key is not a G2 point"))?;
+    if w.is_zero() {
+        return Err(VerifyError::MalformedInput("public key is the identity"));
+    }
+    return Ok(issuer::PublicKey(w))
+}
+
+pub fn create_generators(count: usize, generator_seed: &[u8], api_id: &[u8]) -> Vec<G1Affine>{
+    let seed_dst = concat(&[api_id, b"SIG_GENERATOR_SEED_"]);
+    let generator_dst = concat(&[api_id, b"SIG_GENERATOR_DST_"]);
+    let mut v = expand_message_xmd(generator_seed, &seed_dst, EXPAND_LEN);
+    let mut generators = Vec::new();
+    for i in 1..=count{
+        v = expand_message_xmd(&concat(&[&v, &(i as u64).to_be_bytes()]), &seed_dst, EXPAND_LEN);
+        generators.push(bbs::hash_to_g1(&v, &generator_dst));
+    }
+    return generators
+}
+
+// Q_1 followed by H_1, ..., H_count.
+pub fn message_generators(count: usize, api_id: &[u8]) -> Vec<G1Affine>{
+    let seed = concat(&[api_id, b"MESSAGE_GENERATOR_SEED"]);
+    return create_generators(count + 1, &seed, api_id)
+}
+
+pub fn p1() -> G1Affine{
+    let bytes = hex::decode(P1_HEX).unwrap();
+    return G1Affine::deserialize_compressed(&bytes[..]).unwrap()
+}
+
+pub fn messages_to_scalars(messages: &Vec<Vec<u8>>, api_id: &[u8]) -> Vec<Fr>{
+    let dst = concat(&[api_id, b"MAP_MSG_TO_SCALAR_AS_HASH_"]);
+    return messages.iter().map(|msg| hash_to_scalar(msg, &dst)).collect()
+}
+
+pub fn calculate_domain(pk: &issuer::PublicKey, q_1: &G1Affine, h_points: &[G1Affine], header: &[u8], api_id: &[u8]) -> Fr{
+    let domain_dst = concat(&[api_id, b"H2S_"]);
+    let mut dom_input = point_to_octets_g2(&pk.0);
+    dom_input.extend_from_slice(&(h_points.len() as u64).to_be_bytes());
+    dom_input.extend(point_to_octets_g1(q_1));
+    for h in h_points{
+        dom_input.extend(point_to_octets_g1(h));
+    }
+    dom_input.extend_from_slice(api_id);
+    dom_input.extend_from_slice(&(header.len() as u64).to_be_bytes());
+    dom_input.extend_from_slice(header);
+    return hash_to_scalar(&dom_input, &domain_dst)
+}
+
+pub fn signature_to_octets(sig: &issuer::Signature) -> Vec<u8>{
+    return concat(&[&point_to_octets_g1(&sig.a), &scalar_to_octets(&sig.e)])
+}
+
+pub fn octets_to_signature(octets: &[u8]) -> Result<issuer::Signature, VerifyError>{
+    if octets.len() != OCTET_POINT_LENGTH + OCTET_SCALAR_LENGTH {
+        return Err(VerifyError::MalformedInput("signature has the wrong length"));
+    }
+    let a = G1Affine::deserialize_compressed(&octets[..OCTET_POINT_LENGTH])
+        .map_err(|_| VerifyError::MalformedInput("signature A is not a G1 point"))?;
+    if a.is_zero() {
+        return Err(VerifyError::MalformedInput("signature A is the identity"));
+    }
+    let e = octets_to_scalar(&octets[OCTET_POINT_LENGTH..])
+        .ok_or(VerifyError::MalformedInput("signature e is not a canonical scalar"))?;
+    if e.is_zero() {
+        return Err(VerifyError::MalformedInput("signature e is zero"));
+    }
+    return Ok(issuer::Signature{ a, e })
+}
+
+pub fn proof_to_octets(proof: &Proof) -> Vec<u8>{
+    let mut out = Vec::new();
+    out.extend(point_to_octets_g1(&proof.a_bar));
+    out.extend(point_to_octets_g1(&proof.b_bar));
+    out.extend(point_to_octets_g1(&proof.d));
+    out.extend(scalar_to_octets(&proof.e_hat));
+    out.extend(scalar_to_octets(&proof.r1_hat));
+    out.extend(scalar_to_octets(&proof.r3_hat));
+    for m_hat in &proof.commitments{
+        out.extend(scalar_to_octets(m_hat));
+    }
+    out.extend(scalar_to_octets(&proof.challenge));
+    return out
+}
+
+pub fn octets_to_proof(octets: &[u8]) -> Result<Proof, VerifyError>{
+    let points_len = 3 * OCTET_POINT_LENGTH;
+    if octets.len() < points_len + 4 * OCTET_SCALAR_LENGTH || !(octets.len() - points_len).is_multiple_of(OCTET_SCALAR_LENGTH) {
+        return Err(VerifyError::MalformedInput("proof has the wrong length"));
+    }
+    let mut points = Vec::new();
+    for chunk in octets[..points_len].chunks(OCTET_POINT_LENGTH){
+        let p = G1Affine::deserialize_compressed(chunk)
+            .map_err(|_| VerifyError::MalformedInput("proof point is not a G1 point"))?;
+        if p.is_zero() {
+            return Err(VerifyError::MalformedInput("proof point is the identity"));
+        }
+        points.push(p);
+    }
+    let mut scalars = Vec::new();
+    for chunk in octets[points_len..].chunks(OCTET_SCALAR_LENGTH){
+        let s = octets_to_scalar(chunk)
+            .ok_or(VerifyError::MalformedInput("proof scalar is not canonical"))?;
+        scalars.push(s);
+    }
+    let challenge = scalars.pop().unwrap();
+    let proof = Proof{
+        a_bar: points[0],
+        b_bar: points[1],
+        d: points[2],
+        e_hat: scalars[0],
+        r1_hat: scalars[1],
+        r3_hat: scalars[2],
+        commitments: scalars[3..].to_vec(),
+        challenge,
+    };
+    return Ok(proof)
+}
+
+// B = P1 + Q_1 * domain + \sum H_i * msg_i over the given (index, scalar) pairs.
+fn compute_b(q_1: &G1Affine, h_points: &[G1Affine], domain: &Fr, messages: &[(usize, Fr)]) -> G1Projective{
+    let mut b = G1Projective::from(p1()) + *q_1 * domain;
+    for (i, msg) in messages{
+        b += h_points[*i] * msg;
+    }
+    return b
+}
+
+pub fn core_sign(sk: &bbs::SecretKey, pk: &issuer::PublicKey, generators: &[G1Affine], header: &[u8], messages: &Vec<Fr>, api_id: &[u8]) -> Result<issuer::Signature, ProveError>{
+    if generators.len() != messages.len() + 1 {
+        return Err(ProveError::TooManyMessages { messages: messages.len(), generators: generators.len().saturating_sub(1) });
+    }
+    let signature_dst = concat(&[api_id, b"H2S_"]);
+    let q_1 = generators[0];
+    let h_points = &generators[1..];
+    let domain = calculate_domain(pk, &q_1, h_points, header, api_id);
+
+    let mut e_input = scalar_to_octets(&sk.0);
+    for msg in messages{
+        e_input.extend(scalar_to_octets(msg));
+    }
+    e_input.extend(scalar_to_octets(&domain));
+    let e = hash_to_scalar(&e_input, &signature_dst);
+
+    let indexed: Vec<(usize, Fr)> = messages.iter().copied().enumerate().collect();
+    let b = compute_b(&q_1, h_points, &domain, &indexed);
+    let sk_plus_e_inv = (sk.0 + e).inverse()
+        .ok_or(ProveError::MalformedInput("secret key plus e is zero"))?;
+    let a = G1Affine::from(b * sk_plus_e_inv);
+    return Ok(issuer::Signature{ a, e })
+}
+
+pub fn core_verify(pk: &issuer::PublicKey, sig: &issuer::Signature, generators: &[G1Affine], header: &[u8], messages: &Vec<Fr>, api_id: &[u8]) -> Result<(), VerifyError>{
+    if generators.len() != messages.len() + 1 {
+        return Err(VerifyError::MalformedInput("generator count does not match messages"));
+    }
+    let q_1 = generators[0];
+    let h_points = &generators[1..];
+    let domain = calculate_domain(pk, &q_1, h_points, header, api_id);
+    let indexed: Vec<(usize, Fr)> = messages.iter().copied().enumerate().collect();
+    let b = compute_b(&q_1, h_points, &domain, &indexed);
+
+    let bp2 = G2Affine::generator();
+    // e(A, W + BP2 * e) * e(B, -BP2) = Identity_GT
+    let pairing = Bls12_381::multi_pairing([sig.a, G1Affine::from(b)], [G2Affine::from(pk.0 + bp2 * sig.e), -bp2]);
+    if !pairing.is_zero() {
+        return Err(VerifyError::PairingCheckFailed(1));
+    }
+    return Ok(())
+}
+
+pub fn sign(sk: &bbs::SecretKey, pk: &issuer::PublicKey, header: &[u8], messages: &Vec<Vec<u8>>) -> Result<Vec<u8>, ProveError>{
+    let message_scalars = messages_to_scalars(messages, API_ID);
+    let generators = message_generators(messages.len(), API_ID);
+    let signature = core_sign(sk, pk, &generators, header, &message_scalars, API_ID)?;
+    return Ok(signature_to_octets(&signature))
+}
+
+pub fn verify(pk: &[u8], signature: &[u8], header: &[u8], messages: &Vec<Vec<u8>>) -> Result<(), VerifyError>{
+    let pk = octets_to_pubkey(pk)?;
+    let signature = octets_to_signature(signature)?;
+    let message_scalars = messages_to_scalars(messages, API_ID);
+    let generators = message_generators(messages.len(), API_ID);
+    return core_verify(&pk, &signature, &generators, header, &message_scalars, API_ID)
+}
+
+fn challenge_calculate(init: &InitResult, disclosed: &[(usize, Fr)], ph: &[u8], api_id: &[u8]) -> Fr{
+    let challenge_dst = concat(&[api_id, b"H2S_"]);
+    let mut c_octs = (disclosed.len() as u64).to_be_bytes().to_vec();
+    for (i, msg) in disclosed{
+        c_octs.extend_from_slice(&(*i as u64).to_be_bytes());
+        c_octs.extend(scalar_to_octets(msg));
+    }
+    for p in [&init.a_bar, &init.b_bar, &init.d, &init.t1, &init.t2]{
+        c_octs.extend(point_to_octets_g1(p));
+    }
+    c_octs.extend(scalar_to_octets(&init.domain));
+    c_octs.extend_from_slice(&(ph.len() as u64).to_be_bytes());
+    c_octs.extend_from_slice(ph);
+    return hash_to_scalar(&c_octs, &challenge_dst)
+}
+
+// Random scalars derived from a seed, used by the draft's fixtures in place of real randomness.
+pub fn seeded_random_scalars(seed: &[u8], dst: &[u8], count: usize) -> Vec<Fr>{
+    let v = expand_message_xmd(seed, dst, EXPAND_LEN * count);
+    return v.chunks(EXPAND_LEN).map(Fr::from_be_bytes_mod_order).collect()
+}
+
+// Argument order follows the draft's CoreProofGen.
+#[allow(clippy::too_many_arguments)]
+pub fn core_proof_gen(
+    pk: &issuer::PublicKey,
+    sig: &issuer::Signature,
+    generators: &[G1Affine],
+    header: &[u8],
+    ph: &[u8],
+    messages: &Vec<Fr>,
+    disclosed_indexes: &Vec<usize>,
+    random_scalars: &Vec<Fr>,
+    api_id: &[u8],
+) -> Result<Proof, ProveError>{
+    let message_len = messages.len();
+    if generators.len() != message_len + 1 {
+        return Err(ProveError::TooManyMessages { messages: message_len, generators: generators.len().saturating_sub(1) });
+    }
+    proof::check_reveal_index(message_len, disclosed_indexes).map_err(ProveError::BadRevealIndex)?;
+    let undisclosed: Vec<usize> = (0..message_len).filter(|i| !disclosed_indexes.contains(i)).collect();
+    if random_scalars.len() != 5 + undisclosed.len() {
+        return Err(ProveError::MalformedInput("wrong number of random scalars"));
+    }
+
+    let (r1, r2, e_tilde, r1_tilde, r3_tilde) = (random_scalars[0], random_scalars[1], random_scalars[2], random_scalars[3], random_scalars[4]);
+    let m_tilde = &random_scalars[5..];
+
+    let q_1 = generators[0];
+    let h_points = &generators[1..];
+    let domain = calculate_domain(pk, &q_1, h_points, header, api_id);
+    let indexed: Vec<(usize, Fr)> = messages.iter().copied().enumerate().collect();
+    let b = compute_b(&q_1, h_points, &domain, &indexed);
+
+    let d = b * r2;
+    let a_bar = sig.a * (r1 * r2);
+    let b_bar = d * r1 - a_bar * sig.e;
+    let t1 = a_bar * e_tilde + d * r1_tilde;
+    let mut t2 = d * r3_tilde;
+    for (k, &j) in undisclosed.iter().enumerate(){
+        t2 += h_points[j] * m_tilde[k];
+    }
+    let init = InitResult{
+        a_bar: G1Affine::from(a_bar),
+        b_bar: G1Affine::from(b_bar),
+        d: G1Affine::from(d),
+        t1: G1Affine::from(t1),
+        t2: G1Affine::from(t2),
+        domain,
+    };
+    let disclosed: Vec<(usize, Fr)> = disclosed_indexes.iter().map(|&i| (i, messages[i])).collect();
+    let challenge = challenge_calculate(&init, &disclosed, ph, api_id);
+
+    let r3 = r2.inverse().ok_or(ProveError::MalformedInput("random scalar r2 is zero"))?;
+    let commitments: Vec<Fr> = undisclosed.iter().enumerate()
+        .map(|(k, &j)| m_tilde[k] + messages[j] * challenge)
+        .collect();
+    let proof = Proof{
+        a_bar: init.a_bar,
+        b_bar: init.b_bar,
+        d: init.d,
+        e_hat: e_tilde + sig.e * challenge,
+        r1_hat: r1_tilde - r1 * challenge,
+        r3_hat: r3_tilde - r3 * challenge,
+        commitments,
+        challenge,
+    };
+    return Ok(proof)
+}
+
+// Argument order follows the draft's CoreProofVerify.
+#[allow(clippy::too_many_arguments)]
+pub fn core_proof_verify(
+    pk: &issuer::PublicKey,
+    proof: &Proof,
+    generators: &[G1Affine],
+    header: &[u8],
+    ph: &[u8],
+    disclosed_messages: &Vec<Fr>,
+    disclosed_indexes: &Vec<usize>,
+    api_id: &[u8],
+) -> Result<(), VerifyError>{
+    if disclosed_messages.len() != disclosed_indexes.len() {
+        return Err(VerifyError::MalformedInput("disclosed messages do not match disclosed indexes"));
+    }
+    let message_len = disclosed_indexes.len() + proof.commitments.len();
+    if generators.len() != message_len + 1 {
+        return Err(VerifyError::MalformedInput("generator count does not match messages"));
+    }
+    proof::check_reveal_index(message_len, disclosed_indexes).map_err(VerifyError::BadRevealIndex)?;
+    if !bbs::valid_points(&[proof.a_bar, proof.b_bar, proof.d]) {
+        return Err(VerifyError::MalformedInput("proof point is the identity"));
+    }
+    let undisclosed: Vec<usize> = (0..message_len).filter(|i| !disclosed_indexes.contains(i)).collect();
+
+    let q_1 = generators[0];
+    let h_points = &generators[1..];
+    let domain = calculate_domain(pk, &q_1, h_points, header, api_id);
+    let c = proof.challenge;
+
+    let t1 = proof.b_bar * c + proof.a_bar * proof.e_hat + proof.d * proof.r1_hat;
+    let disclosed: Vec<(usize, Fr)> = disclosed_indexes.iter().copied().zip(disclosed_messages.iter().copied()).collect();
+    let bv = compute_b(&q_1, h_points, &domain, &disclosed);
+    let mut t2 = bv * c + proof.d * proof.r3_hat;
+    for (k, &j) in undisclosed.iter().enumerate(){
+        t2 += h_points[j] * proof.commitments[k];
+    }
+    let init = InitResult{
+        a_bar: proof.a_bar,
+        b_bar: proof.b_bar,
+        d: proof.d,
+        t1: G1Affine::from(t1),
+        t2: G1Affine::from(t2),
+        domain,
+    };
+    if challenge_calculate(&init, &disclosed, ph, api_id) != c {
+        return Err(VerifyError::ChallengeMismatch);
+    }
+    // e(Abar, W) * e(Bbar, -BP2) = Identity_GT
+    if !Bls12_381::multi_pairing([proof.a_bar, proof.b_bar], [pk.0, -G2Affine::generator()]).is_zero() {
+        return Err(VerifyError::PairingCheckFailed(1));
+    }
+    return Ok(())
+}
+
+pub fn proof_gen_with_rng<R: RngCore + CryptoRng>(pk: &[u8], signature: &[u8], header: &[u8], ph: &[u8], messages: &Vec<Vec<u8>>, disclosed_indexes: &Vec<usize>, rng: &mut R) -> Result<Vec<u8>, ProveError>{
+    let pk = octets_to_pubkey(pk).map_err(|_| ProveError::MalformedInput("public key is invalid"))?;
+    let signature = octets_to_signature(signature).map_err(|_| ProveError::MalformedInput("signature is invalid"))?;
+    let message_scalars = messages_to_scalars(messages, API_ID);
+    let generators = message_generators(messages.len(), API_ID);
+
+    let undisclosed_len = messages.len().saturating_sub(disclosed_indexes.len());
+    let random_scalars: Zeroizing<Vec<Fr>> = Zeroizing::new((0..5 + undisclosed_len).map(|_| Fr::rand(rng)).collect());
+    let proof = core_proof_gen(&pk, &signature, &generators, header, ph, &message_scalars, disclosed_indexes, &random_scalars, API_ID)?;
+    return Ok(proof_to_octets(&proof))
+}
+
+pub fn proof_gen(pk: &[u8], signature: &[u8], header: &[u8], ph: &[u8], messages: &Vec<Vec<u8>>, disclosed_indexes: &Vec<usize>) -> Result<Vec<u8>, ProveError>{
+    return proof_gen_with_rng(pk, signature, header, ph, messages, disclosed_indexes, &mut OsRng)
+}
+
+pub fn proof_verify(pk: &[u8], proof: &[u8], header: &[u8], ph: &[u8], disclosed_messages: &Vec<Vec<u8>>, disclosed_indexes: &Vec<usize>) -> Result<(), VerifyError>{
+    let pk = octets_to_pubkey(pk)?;
+    let proof = octets_to_proof(proof)?;
+    let message_len = disclosed_indexes.len() + proof.commitments.len();
+    let message_scalars = messages_to_scalars(disclosed_messages, API_ID);
+    let generators = message_generators(message_len, API_ID);
+    return core_proof_verify(&pk, &proof, &generators, header, ph, &message_scalars, disclosed_indexes, API_ID)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fixtures from draft-irtf-cfrg-bbs-signatures, BLS12-381-SHA-256 suite.
+    const KEY_MATERIAL: &str = "746869732d49532d6a7573742d616e2d546573742d494b4d2d746f2d67656e65726174652d246528724074232d6b6579";
+    const KEY_INFO: &str = "746869732d49532d736f6d652d6b65792d6d657461646174612d746f2d62652d757365642d696e2d746573742d6b65792d67656e";
+    const SK: &str = "60e55110f76883a13d030b2f6bd11883422d5abde717569fc0731f51237169fc";
+    const PK: &str = "a820f230f6ae38503b86c70dc50b61c58a77e45c39ab25c0652bbaa8fa136f2851bd4781c9dcde39fc9d1d52c9e60268061e7d7632171d91aa8d460acee0e96f1e7c4cfb12d3ff9ab5d5dc91c277db75c845d649ef3c4f63aebc364cd55ded0c";
+    const Q_1: &str = "a9ec65b70a7fbe40c874c9eb041c2cb0a7af36ccec1bea48fa2ba4c2eb67ef7f9ecb17ed27d38d27cdeddff44c8137be";
+    const H_1: &str = "98cd5313283aaf5db1b3ba8611fe6070d19e605de4078c38df36019fbaad0bd28dd090fd24ed27f7f4d22d5ff5dea7d4";
+    const HEADER: &str = "11223344556677889900aabbccddeeff";
+    const PH: &str = "bed231d880675ed101ead304512e043ade9958dd0241ea70b4b3957fba941501";
+    const SEED: &str = "332e313431353932363533353839373933323338343632363433333833323739";
+    const MESSAGES: [&str; 10] = [
+        "9872ad089e452c7b6e283dfac2a80d58e8d0ff71cc4d5e310a1debdda4a45f02",
+        "c344136d9ab02da4dd5908bbba913ae6f58c2cc844b802a6f811f5fb075f9b80",
+        "7372e9daa5ed31e6cd5c825eac1b855e84476a1d94932aa348e07b73",
+        "77fe97eb97a1ebe2e81e4e3597a3ee740a66e9ef2412472c",
+        "496694774c5604ab1b2544eababcf0f53278ff50",
+        "515ae153e22aae04ad16f759e07237b4",
+        "d183ddc6e2665aa4e2f088af",
+        "ac55fb33a75909ed",
+        "96012096",
+        "",
+    ];
+    const M_1_SCALAR: &str = "1cb5bb86114b34dc438a911617655a1db595abafac92f47c5001799cf624b430";
+    const SIGNATURE_SINGLE: &str = "84773160b824e194073a57493dac1a20b667af70cd2352d8af241c77658da5253aa8458317cca0eae615690d55b1f27164657dcafee1d5c1973947aa70e2cfbb4c892340be5969920d0916067b4565a0";
+    const SIGNATURE_MULTI: &str = "8339b285a4acd89dec7777c09543a43e3cc60684b0a6f8ab335da4825c96e1463e28f8c5f4fd0641d19cec5920d3a8ff4bedb6c9691454597bbd298288abed3632078557b2ace7d44caed846e1a0a1e8";
+    const PROOF_SINGLE: &str = "94916292a7a6bade28456c601d3af33fcf39278d6594b467e128a3f83686a104ef2b2fcf72df0215eeaf69262ffe8194a19fab31a82ddbe06908985abc4c9825788b8a1610942d12b7f5debbea8985296361206dbace7af0cc834c80f33e0aadaeea5597befbb651827b5eed5a66f1a959bb46cfd5ca1a817a14475960f69b32c54db7587b5ee3ab665fbd37b506830a49f21d592f5e634f47cee05a025a2f8f94e73a6c15f02301d1178a92873b6e8634bafe4983c3e15a663d64080678dbf29417519b78af042be2b3e1c4d08b8d520ffab008cbaaca5671a15b22c239b38e940cfeaa5e72104576a9ec4a6fad78c532381aeaa6fb56409cef56ee5c140d455feeb04426193c57086c9b6d397d9418";
+
+    fn messages() -> Vec<Vec<u8>> {
+        return MESSAGES.iter().map(|m| hex::decode(m).unwrap()).collect()
+    }
+
+    #[test]
+    fn fixture_vectors() {
+        let sk = key_gen(&hex::decode(KEY_MATERIAL).unwrap(), &hex::decode(KEY_INFO).unwrap(), None).unwrap();
+        assert_eq!(hex::encode(scalar_to_octets(&sk.0)), SK);
+        let pk = sk_to_pk(&sk);
+        let pk_octets = point_to_octets_g2(&pk.0);
+        assert_eq!(hex::encode(&pk_octets), PK);
+
+        let p1_seed = concat(&[API_ID, b"BP_MESSAGE_GENERATOR_SEED"]);
+        assert_eq!(create_generators(1, &p1_seed, API_ID)[0], p1());
+        let generators = message_generators(1, API_ID);
+        assert_eq!(hex::encode(point_to_octets_g1(&generators[0])), Q_1);
+        assert_eq!(hex::encode(point_to_octets_g1(&generators[1])), H_1);
+
+        let messages = messages();
+        let scalars = messages_to_scalars(&messages, API_ID);
+        assert_eq!(hex::encode(scalar_to_octets(&scalars[0])), M_1_SCALAR);
+
+        let header = hex::decode(HEADER).unwrap();
+        let single = vec![messages[0].clone()];
+        let signature = sign(&sk, &pk, &header, &single).unwrap();
+        assert_eq!(hex::encode(&signature), SIGNATURE_SINGLE);
+        assert_eq!(verify(&pk_octets, &signature, &header, &single), Ok(()));
+        let signature_multi = sign(&sk, &pk, &header, &messages).unwrap();
+        assert_eq!(hex::encode(&signature_multi), SIGNATURE_MULTI);
+        assert_eq!(verify(&pk_octets, &signature_multi, &header, &messages), Ok(()));
+        assert_eq!(verify(&pk_octets, &signature_multi, b"", &messages), Err(VerifyError::PairingCheckFailed(1)));
+
+        let ph = hex::decode(PH).unwrap();
+        let mock_dst = concat(&[API_ID, b"MOCK_RANDOM_SCALARS_DST_"]);
+        let random_scalars = seeded_random_scalars(&hex::decode(SEED).unwrap(), &mock_dst, 5);
+        let sig = octets_to_signature(&signature).unwrap();
+        let proof = core_proof_gen(&pk, &sig, &generators, &header, &ph, &vec![scalars[0]], &vec![0], &random_scalars, API_ID).unwrap();
+        let proof_octets = proof_to_octets(&proof);
+        assert_eq!(hex::encode(&proof_octets), PROOF_SINGLE);
+        assert_eq!(octets_to_proof(&proof_octets).unwrap(), proof);
+        assert_eq!(proof_verify(&pk_octets, &proof_octets, &header, &ph, &single, &vec![0]), Ok(()));
+    }
+
+    #[test]
+    fn it_works() {
+        let sk = key_gen(&hex::decode(KEY_MATERIAL).unwrap(), &hex::decode(KEY_INFO).unwrap(), None).unwrap();
+        let pk = point_to_octets_g2(&sk_to_pk(&sk).0);
+        let header = hex::decode(HEADER).unwrap();
+        let ph = hex::decode(PH).unwrap();
+        let messages = messages();
+        let signature = hex::decode(SIGNATURE_MULTI).unwrap();
+
+        let disclosed_indexes = vec![0, 2, 4, 6];
+        let disclosed: Vec<Vec<u8>> = disclosed_indexes.iter().map(|&i| messages[i].clone()).collect();
+        let proof = proof_gen(&pk, &signature, &header, &ph, &messages, &disclosed_indexes).unwrap();
+        assert_eq!(proof_verify(&pk, &proof, &header, &ph, &disclosed, &disclosed_indexes), Ok(()));
+        assert_eq!(proof_verify(&pk, &proof, &header, b"", &disclosed, &disclosed_indexes), Err(VerifyError::ChallengeMismatch));
+
+        let unsorted = vec![2, 0];
+        assert_eq!(proof_gen(&pk, &signature, &header, &ph, &messages, &unsorted), Err(ProveError::BadRevealIndex(0)));
+    }
+}
@@ -0,0 +1,164 @@
+use ark_bls12_381::Bls12_381;
+use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{UniformRand, vec::Vec};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
+
+use crate::error::{ProveError, VerifyError};
+
+pub type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+// Equalities between hidden messages of the credentials in one multi-credential presentation.
+// Linked messages get the same Schnorr blinding and the presentations share one challenge,
+// so equal messages give equal responses, which is what the verifier checks.
+
+// left and right are (credential, message index) pairs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+pub struct Equality{
+    pub left: (usize, usize),
+    pub right: (usize, usize),
+}
+
+// (credential, position among its hidden indices)
+type Position = (usize, usize);
+
+// Position of every side of every equality in its credential's hidden indices.
+fn positions(equalities: &[Equality], hidden_index: &[Vec<usize>]) -> Result<Vec<(Position, Position)>, Option<usize>>{
+    // Err(None) for a missing credential, Err(Some(idx)) for a message that is not hidden
+    let position = |(cred, idx): (usize, usize)| -> Result<Position, Option<usize>> {
+        let hidden = hidden_index.get(cred).ok_or(None)?;
+        let pos = hidden.iter().position(|&i| i == idx).ok_or(Some(idx))?;
+        return Ok((cred, pos))
+    };
+    return equalities.iter().map(|eq| Ok((position(eq.left)?, position(eq.right)?))).collect()
+}
+
+// One blinding per class of linked messages, per credential and aligned with its hidden
+// indices; None where the message is not linked to anything. Wiped on drop.
+pub fn linked_blindings_with_rng<R: RngCore + CryptoRng>(
+    equalities: &[Equality],
+    messages: &[&Vec<Fr>],
+    hidden_index: &[Vec<usize>],
+    rng: &mut R,
+) -> Result<Zeroizing<Vec<Vec<Option<Fr>>>>, ProveError>{
+    let pairs = positions(equalities, hidden_index).map_err(|idx| match idx {
+        Some(idx) => ProveError::BadRevealIndex(idx),
+        None => ProveError::MalformedInput("equality refers to a missing credential"),
+    })?;
+    for k in 0..equalities.len(){
+        let ((c1, i1), (c2, i2)) = (equalities[k].left, equalities[k].right);
+        if messages[c1][i1] != messages[c2][i2] {
+            return Err(ProveError::UnequalMessages(k));
+        }
+    }
+
+    // class[cred][pos] is the class of a linked message; merging relabels one class as the other
+    let mut class: Vec<Vec<Option<usize>>> = hidden_index.iter().map(|hidden| vec![None; hidden.len()]).collect();
+    for k in 0..pairs.len(){
+        let ((c1, p1), (c2, p2)) = pairs[k];
+        match (class[c1][p1], class[c2][p2]) {
+            (None, None) => { class[c1][p1] = Some(k); class[c2][p2] = Some(k); },
+            (Some(a), None) => class[c2][p2] = Some(a),
+            (None, Some(b)) => class[c1][p1] = Some(b),
+            (Some(a), Some(b)) => {
+                for entry in class.iter_mut().flatten() {
+                    if *entry == Some(b) {
+                        *entry = Some(a);
+                    }
+                }
+            },
+        }
+    }
+    let blindings: Zeroizing<Vec<Fr>> = Zeroizing::new((0..equalities.len()).map(|_| Fr::rand(rng)).collect());
+    return Ok(Zeroizing::new(class.iter().map(|cred| cred.iter().map(|c| c.map(|k| blindings[k])).collect()).collect()))
+}
+
+// responses[cred] are the hidden-message responses of a credential, aligned with hidden_index[cred].
+pub fn check_linked_responses(equalities: &[Equality], hidden_index: &[Vec<usize>], responses: &[&Vec<Fr>]) -> Result<(), VerifyError>{
+    let pairs = positions(equalities, hidden_index).map_err(|idx| match idx {
+        Some(idx) => VerifyError::BadRevealIndex(idx),
+        None => VerifyError::MalformedInput("equality refers to a missing credential"),
+    })?;
+    for k in 0..pairs.len(){
+        let ((c1, p1), (c2, p2)) = pairs[k];
+        if responses[c1][p1] != responses[c2][p2] {
+            return Err(VerifyError::EqualityMismatch(k));
+        }
+    }
+    return Ok(())
+}
+
+// What a multi-credential presentation hashes into its challenge for its equalities.
+pub fn serialize_challenge_input(equalities: &[Equality], buffer: &mut Vec<u8>){
+    equalities.to_vec().serialize_compressed(&mut *buffer).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Equality, Fr};
+    use crate::error::{ProveError, VerifyError};
+    use crate::{issuer, proof, range, schema};
+    use ark_std::{UniformRand, vec::Vec};
+    use rand::thread_rng;
+
+    #[test]
+    fn it_works(){
+        // two credentials from two issuers bound to one link secret, a third sharing an age
+        let mut rng = thread_rng();
+        let link_secret = Fr::rand(&mut rng);
+        let pp = issuer::par_gen(4);
+        let mut messages: Vec<Vec<Fr>> = (0..3).map(|_| (0..4).map(|_| Fr::rand(&mut rng)).collect()).collect();
+        messages[0][3] = link_secret;
+        messages[1][0] = link_secret;
+        messages[1][2] = schema::encode_integer(30);
+        messages[2][1] = schema::encode_integer(30);
+        let keypairs: Vec<issuer::KeyPair> = (0..3).map(|_| issuer::key_gen(&pp)).collect();
+        let signatures: Vec<issuer::Signature> = (0..3).map(|j| issuer::sign(&pp, &keypairs[j].secret_key, &messages[j]).unwrap()).collect();
+        let reveal_index = [vec![0], vec![1], vec![]];
+        let predicates = [Vec::new(), vec![range::integer_range(2, 18, i64::MAX)], Vec::new()];
+        let items: Vec<_> = (0..3).map(|j| (&signatures[j], &messages[j], &reveal_index[j], &predicates[j])).collect();
+        let equalities = vec![
+            Equality{ left: (0, 3), right: (1, 0) },
+            Equality{ left: (2, 1), right: (1, 2) },
+        ];
+        let nonce = b"verifier-nonce";
+        let presentations = proof::prove_multi(&pp, &items, &equalities, nonce).unwrap();
+        let presented: Vec<_> = (0..3).map(|j| (&keypairs[j].public_key, &presentations[j].0, &presentations[j].1)).collect();
+        assert_eq!(proof::verify_multi(&pp, &presented, &equalities, nonce), Ok(()));
+        assert_eq!(proof::verify_multi(&pp, &presented, &equalities, b"other-nonce"), Err(VerifyError::PresentationHeaderMismatch));
+
+        // the equalities are bound to the challenge and checked against the responses
+        assert_eq!(proof::verify_multi(&pp, &presented, &equalities[..1].to_vec(), nonce), Err(VerifyError::ChallengeMismatch));
+        let unrelated = vec![equalities[0], Equality{ left: (0, 1), right: (2, 0) }];
+        assert_eq!(proof::verify_multi(&pp, &presented, &unrelated, nonce), Err(VerifyError::EqualityMismatch(1)));
+        let mut swapped = presented.clone();
+        swapped[1].0 = &keypairs[2].public_key;
+        assert_eq!(proof::verify_multi(&pp, &presented[..2], &equalities[..1].to_vec(), nonce), Err(VerifyError::ChallengeMismatch));
+        assert_eq!(proof::verify_multi(&pp, &swapped, &equalities, nonce), Err(VerifyError::PairingCheckFailed(2)));
+
+        // chained equalities put three messages in one class
+        let chained = vec![
+            Equality{ left: (0, 1), right: (1, 3) },
+            Equality{ left: (2, 0), right: (2, 2) },
+            Equality{ left: (1, 3), right: (2, 2) },
+        ];
+        messages[1][3] = messages[0][1];
+        messages[2][0] = messages[0][1];
+        messages[2][2] = messages[0][1];
+        let signatures: Vec<issuer::Signature> = (0..3).map(|j| issuer::sign(&pp, &keypairs[j].secret_key, &messages[j]).unwrap()).collect();
+        let reveal_index = [vec![], vec![], vec![]];
+        let items: Vec<_> = (0..3).map(|j| (&signatures[j], &messages[j], &reveal_index[j], &predicates[0])).collect();
+        let presentations = proof::prove_multi(&pp, &items, &chained, nonce).unwrap();
+        let presented: Vec<_> = (0..3).map(|j| (&keypairs[j].public_key, &presentations[j].0, &presentations[j].1)).collect();
+        assert_eq!(proof::verify_multi(&pp, &presented, &chained, nonce), Ok(()));
+
+        let unequal = vec![Equality{ left: (0, 0), right: (1, 1) }];
+        assert_eq!(proof::prove_multi(&pp, &items, &unequal, nonce).unwrap_err(), ProveError::UnequalMessages(0));
+        let reveal_index = [vec![1], vec![], vec![]];
+        let items: Vec<_> = (0..3).map(|j| (&signatures[j], &messages[j], &reveal_index[j], &predicates[0])).collect();
+        assert_eq!(proof::prove_multi(&pp, &items, &chained, nonce).unwrap_err(), ProveError::BadRevealIndex(1));
+        let missing = vec![Equality{ left: (0, 1), right: (3, 0) }];
+        assert_eq!(proof::prove_multi(&pp, &items[1..], &missing, nonce).unwrap_err(), ProveError::MalformedInput("equality refers to a missing credential"));
+    }
+}
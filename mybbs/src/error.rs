@@ -0,0 +1,91 @@
+use thiserror::Error;
+
+use crate::schema::AttributeType;
+
+// Reasons a verifier rejects a signature or a proof.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum VerifyError {
+    #[error("challenge mismatch")]
+    ChallengeMismatch,
+    #[error("pairing check {0} failed")]
+    PairingCheckFailed(usize),
+    #[error("bad reveal index {0}")]
+    BadRevealIndex(usize),
+    #[error("presentation header does not match the expected one")]
+    PresentationHeaderMismatch,
+    #[error("range proof failed")]
+    RangeProofFailed,
+    #[error("responses of equality {0} differ")]
+    EqualityMismatch(usize),
+    #[error("pseudonym is not for the expected scope and link secret")]
+    PseudonymMismatch,
+    #[error("message {0} is not a hidden holder secret")]
+    HolderSecretNotHidden(usize),
+    #[error("share from server {0} does not match its commitment")]
+    InvalidShare(usize),
+    #[error("malformed input: {0}")]
+    MalformedInput(&'static str),
+}
+
+// Reasons a signer or prover refuses to produce an output.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum ProveError {
+    #[error("bad reveal index {0}")]
+    BadRevealIndex(usize),
+    #[error("{messages} messages but only {generators} generators")]
+    TooManyMessages { messages: usize, generators: usize },
+    #[error("commitment proof rejected: {0}")]
+    CommitmentRejected(VerifyError),
+    #[error("holder key binding rejected: {0}")]
+    HolderKeyRejected(VerifyError),
+    #[error("message does not satisfy range predicate {0}")]
+    PredicateNotSatisfied(usize),
+    #[error("messages of equality {0} differ")]
+    UnequalMessages(usize),
+    #[error("malformed input: {0}")]
+    MalformedInput(&'static str),
+}
+
+// Reasons a schema or a set of attribute values cannot be turned into messages.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum SchemaError {
+    #[error("attribute {0} repeats an earlier attribute name")]
+    DuplicateAttribute(usize),
+    #[error("requested attribute {0} is not in the schema")]
+    UnknownAttribute(usize),
+    #[error("{values} values for {attributes} attributes")]
+    WrongValueCount { values: usize, attributes: usize },
+    #[error("value {0} does not have the attribute's type")]
+    TypeMismatch(usize),
+    #[error("attribute {0} is a link secret and cannot be revealed")]
+    RevealedLinkSecret(usize),
+    #[error("invalid date")]
+    InvalidDate,
+    #[error("{0:?} attributes are hashed and cannot be decoded")]
+    NotDecodable(AttributeType),
+    #[error("message is not an encoded {0:?}")]
+    OutOfRange(AttributeType),
+}
+
+// Reasons an envelope of the wire module is not the expected object.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum WireError {
+    #[error("bad magic")]
+    BadMagic,
+    #[error("envelope is truncated")]
+    Truncated,
+    #[error("unsupported version {0}")]
+    UnsupportedVersion(u16),
+    #[error("envelope is for another scheme")]
+    WrongScheme,
+    #[error("envelope holds another kind of object")]
+    WrongKind,
+    #[error("envelope is for another ciphersuite")]
+    WrongCiphersuite,
+    #[error("payload does not decode to a valid object")]
+    InvalidPayload,
+    #[error("trailing bytes after the payload")]
+    TrailingBytes,
+    #[error("invalid encoding: {0}")]
+    InvalidEncoding(&'static str),
+}
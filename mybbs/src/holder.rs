@@ -0,0 +1,217 @@
+use ark_bls12_381::{G1Affine, Bls12_381, G1Projective};
+use ark_ff::Field;
+use ark_ec::{pairing::Pairing, CurveGroup};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{fmt::{self, Debug}, UniformRand, vec::Vec};
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use crate::bbs;
+use crate::error::{ProveError, VerifyError, WireError};
+use crate::issuer;
+
+pub type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+// Holder key binding. The holder secret sk never leaves the holder: at issuance the holder
+// sends key = base^sk with a Schnorr proof of sk bound to the issuer's nonce, and the issuer
+// signs key in place of the message at holder_index. sk is then a hidden message of the
+// credential, so every presentation proves knowledge of it under its presentation header,
+// and the signature and the other messages alone cannot be presented.
+// base is the point the scheme multiplies that message by: h_{holder_index} for BBS and
+// Bobolz, g1 for Sanders, whose issuer raises key to y_{holder_index} itself.
+
+// Handled like bbs::SecretKey: redacted, compared in constant time and wiped on drop.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct HolderSecretKey(pub(crate) Fr);
+
+impl Debug for HolderSecretKey{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        return f.write_str("HolderSecretKey(<redacted>)")
+    }
+}
+
+impl PartialEq for HolderSecretKey{
+    fn eq(&self, other: &Self) -> bool{
+        return bbs::ct_eq_scalars(&[self.0], &[other.0]).into()
+    }
+}
+
+impl Eq for HolderSecretKey {}
+
+#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+pub struct KeyBinding{
+    pub key: G1Affine,      // base^sk
+    pub c: Fr,
+    pub z: Fr,
+}
+
+pub fn holder_key_gen_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> HolderSecretKey{
+    return HolderSecretKey(Fr::rand(rng))
+}
+
+pub fn holder_key_gen() -> HolderSecretKey{
+    return holder_key_gen_with_rng(&mut OsRng)
+}
+
+pub fn holder_secret_key_to_bytes(sk: &HolderSecretKey) -> Zeroizing<Vec<u8>>{
+    return bbs::scalars_to_bytes(&[sk.0])
+}
+
+pub fn holder_secret_key_from_bytes(bytes: &[u8]) -> Result<HolderSecretKey, WireError>{
+    let scalars = bbs::scalars_from_bytes(bytes)?;
+    if scalars.len() != 1 {
+        return Err(WireError::InvalidPayload);
+    }
+    return Ok(HolderSecretKey(scalars[0]))
+}
+
+fn binding_challenge(base: &G1Affine, key: &G1Affine, t: &G1Affine, nonce: &[u8]) -> Fr{
+    let dst = b"MY_HOLDER_KEY_BINDING_DST_V1";
+    let mut buffer = Vec::new();
+    base.serialize_compressed(&mut buffer).unwrap();
+    key.serialize_compressed(&mut buffer).unwrap();
+    t.serialize_compressed(&mut buffer).unwrap();
+    nonce.to_vec().serialize_compressed(&mut buffer).unwrap();
+    return bbs::hash_to_fr(&buffer[..], dst)
+}
+
+pub fn key_binding_with_rng<R: RngCore + CryptoRng>(base: &G1Affine, sk: &HolderSecretKey, nonce: &[u8], rng: &mut R) -> KeyBinding{
+    let r = Zeroizing::new(Fr::rand(rng));
+    let key = (*base * sk.0).into_affine();
+    let t = (*base * *r).into_affine();
+    let c = binding_challenge(base, &key, &t, nonce);
+    let binding = KeyBinding{
+        key: key,
+        c: c,
+        z: *r + c * sk.0,
+    };
+    return binding
+}
+
+pub fn key_binding(base: &G1Affine, sk: &HolderSecretKey, nonce: &[u8]) -> KeyBinding{
+    return key_binding_with_rng(base, sk, nonce, &mut OsRng)
+}
+
+pub fn verify_key_binding(base: &G1Affine, binding: &KeyBinding, nonce: &[u8]) -> Result<(), VerifyError>{
+    if !bbs::valid_points(&[binding.key]) {
+        return Err(VerifyError::MalformedInput("holder key is the identity or not in G1"));
+    }
+    // T = base^z * key^{-c}
+    let t = (*base * binding.z - binding.key * binding.c).into_affine();
+    if binding_challenge(base, &binding.key, &t, nonce) != binding.c {
+        return Err(VerifyError::ChallengeMismatch)
+    }
+    return Ok(())
+}
+
+// The messages a bound credential is signed over: messages with sk inserted at holder_index.
+pub fn with_holder_secret(messages: &Vec<Fr>, holder_index: usize, sk: &HolderSecretKey) -> Result<Vec<Fr>, ProveError>{
+    if holder_index > messages.len() {
+        return Err(ProveError::BadRevealIndex(holder_index));
+    }
+    let mut all = messages.clone();
+    all.insert(holder_index, sk.0);
+    return Ok(all)
+}
+
+// What a verifier of a bound presentation checks on top of the presentation itself.
+pub fn check_bound(len: usize, open: &[usize], holder_index: usize) -> Result<(), VerifyError>{
+    if holder_index >= len || open.contains(&holder_index) {
+        return Err(VerifyError::HolderSecretNotHidden(holder_index));
+    }
+    return Ok(())
+}
+
+// issuer::sign over messages with the holder secret at holder_index, which the issuer only
+// sees as binding.key. messages are the other messages, in order.
+#[allow(clippy::too_many_arguments)]
+pub fn sign_bound_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    sk: &bbs::SecretKey,
+    binding: &KeyBinding,
+    holder_index: usize,
+    messages: &Vec<Fr>,
+    nonce: &[u8],
+    rng: &mut R,
+) -> Result<issuer::Signature, ProveError>{
+    let message_len = messages.len() + 1;
+    if message_len > pp.h_vec.len() {
+        return Err(ProveError::TooManyMessages { messages: message_len, generators: pp.h_vec.len() });
+    }
+    if holder_index >= message_len {
+        return Err(ProveError::BadRevealIndex(holder_index));
+    }
+    verify_key_binding(&pp.h_vec[holder_index], binding, nonce).map_err(ProveError::HolderKeyRejected)?;
+
+    let e = Fr::rand(rng);
+
+    // A = (g1 * key * \prod_{i != holder_index} h_i^m_i)^{1/(sk + e)}
+    let mut m_product = G1Projective::from(pp.g1) + binding.key;
+    for (k, i) in (0..message_len).filter(|&i| i != holder_index).enumerate(){
+        m_product += pp.h_vec[i] * messages[k];
+    }
+    let sk_plus_e_inv = (sk.0 + e).inverse().unwrap();
+    let signature = issuer::Signature{
+        a: G1Affine::from(m_product * sk_plus_e_inv),
+        e: e,
+    };
+    return Ok(signature)
+}
+
+pub fn sign_bound(
+    pp: &issuer::PublicParameters,
+    sk: &bbs::SecretKey,
+    binding: &KeyBinding,
+    holder_index: usize,
+    messages: &Vec<Fr>,
+    nonce: &[u8],
+) -> Result<issuer::Signature, ProveError>{
+    return sign_bound_with_rng(pp, sk, binding, holder_index, messages, nonce, &mut OsRng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ec::AffineRepr;
+    use rand::thread_rng;
+    use crate::proof;
+
+    #[test]
+    fn it_works(){
+        let mut rng = thread_rng();
+        let pp = issuer::par_gen(5);
+        let keypair = issuer::key_gen(&pp);
+        let holder_secret = holder_key_gen();
+        let holder_index = 1;
+        let nonce = b"issuance-nonce";
+        let messages: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+
+        let binding = key_binding(&pp.h_vec[holder_index], &holder_secret, nonce);
+        let signature = sign_bound(&pp, &keypair.secret_key, &binding, holder_index, &messages, nonce).unwrap();
+        let all = with_holder_secret(&messages, holder_index, &holder_secret).unwrap();
+        assert_eq!(all[holder_index], holder_secret.0);
+        assert_eq!(issuer::verify(&pp, &keypair.public_key, &all, &signature), Ok(()));
+
+        // the binding is tied to its nonce and to the holder knowing sk
+        assert_eq!(sign_bound(&pp, &keypair.secret_key, &binding, holder_index, &messages, b"other-nonce").unwrap_err(), ProveError::HolderKeyRejected(VerifyError::ChallengeMismatch));
+        let mut stolen = binding.clone();
+        stolen.key = G1Affine::from(pp.h_vec[holder_index] * Fr::rand(&mut rng));
+        assert_eq!(verify_key_binding(&pp.h_vec[holder_index], &stolen, nonce), Err(VerifyError::ChallengeMismatch));
+        stolen.key = G1Affine::zero();
+        assert_eq!(verify_key_binding(&pp.h_vec[holder_index], &stolen, nonce), Err(VerifyError::MalformedInput("holder key is the identity or not in G1")));
+        assert_eq!(sign_bound(&pp, &keypair.secret_key, &binding, 3, &messages, nonce).unwrap_err(), ProveError::HolderKeyRejected(VerifyError::ChallengeMismatch));
+
+        let reveal_index = vec![0, 4];
+        let (pikp, pizkp) = proof::prove_bound(&pp, &signature, &holder_secret, holder_index, &messages, &reveal_index, b"presentation").unwrap();
+        assert_eq!(proof::verify_proof_bound(&pp, &keypair.public_key, &pikp, &pizkp, holder_index, b"presentation"), Ok(()));
+        assert_eq!(pikp.message_list, vec![messages[0], messages[3]]);
+
+        // without sk the credential material is useless, and sk is never shown
+        let guessed = holder_key_gen();
+        let (pikp2, pizkp2) = proof::prove_bound(&pp, &signature, &guessed, holder_index, &messages, &reveal_index, b"presentation").unwrap();
+        assert_eq!(proof::verify_proof_bound(&pp, &keypair.public_key, &pikp2, &pizkp2, holder_index, b"presentation"), Err(VerifyError::PairingCheckFailed(1)));
+        assert_eq!(proof::prove_bound(&pp, &signature, &holder_secret, holder_index, &messages, &vec![1], b"").unwrap_err(), ProveError::BadRevealIndex(holder_index));
+        let (open_pikp, open_pizkp) = proof::prove(&pp, &signature, &all, &vec![1], b"").unwrap();
+        assert_eq!(proof::verify_proof_bound(&pp, &keypair.public_key, &open_pikp, &open_pizkp, holder_index, b""), Err(VerifyError::HolderSecretNotHidden(holder_index)));
+    }
+}
@@ -1,11 +1,13 @@
-use ark_bls12_381::{G1Affine, G2Affine, Bls12_381, G1Projective};
+use ark_bls12_381::{G1Affine, G2Affine, Bls12_381, G1Projective, G2Projective};
 use ark_ff::Field;
 use ark_ec::pairing::Pairing;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::{fmt::Debug, UniformRand, vec::Vec};
-use rand::thread_rng;
+use ark_std::{fmt::Debug, UniformRand, vec::Vec, Zero};
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 use crate::bbs;
+use crate::error::{ProveError, VerifyError};
 
 pub type Fr = <Bls12_381 as Pairing>::ScalarField;
 
@@ -19,7 +21,8 @@ pub struct PublicParameters{
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
 pub struct PublicKey(pub G2Affine);
 
-#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+// Not serializable: the secret key leaves only through bbs::secret_key_to_bytes.
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct KeyPair {
     pub secret_key: bbs::SecretKey,
     pub public_key: PublicKey,
@@ -31,21 +34,30 @@ pub struct Signature {
     pub e: Fr,       // e \stackrel{\$}{\leftarrow} Z_p^*
 }
 
-pub fn par_gen() -> PublicParameters{
+// h_i is derived from its index alone, so h_vec can be grown later with extend_generators
+// and always agrees with a PublicParameters generated for a longer message vector.
+pub fn h_generator(i: usize) -> G1Affine{
+    let h_seed = "MESSAGE_GENERATOR_SEED_";
+    let h_dst = b"BLS12381G1_XMD:SHA-256_SSWU_RO_";
+    let seed = format!("{}{}", h_seed, i);
+    return bbs::hash_to_g1(seed.as_bytes(), h_dst)
+}
+
+pub fn extend_generators(pp: &mut PublicParameters, message_len: usize){
+    for i in pp.h_vec.len()..message_len{
+        pp.h_vec.push(h_generator(i));
+    }
+}
+
+pub fn par_gen(message_len: usize) -> PublicParameters{
     let g1_hex = "97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb";
     let g2_hex = "93e02b6052719f607dacd3a088274f65596bd0d09920b61ab5da61bbdc7f5049334cf11213945d57e5ac7d055d042b7e024aa2b2f08f0a91260805272dc51051c6e47ad4fa403b02b4510b647ae3d1770bac0326a805bbefd48056c8c121bdb8";
     let g1_bytes = hex::decode(g1_hex).unwrap();
     let g2_bytes = hex::decode(g2_hex).unwrap();
     let g1 = G1Affine::deserialize_compressed(&g1_bytes[..]).unwrap();
     let g2 = G2Affine::deserialize_compressed(&g2_bytes[..]).unwrap();
-    
-    let h_seed = "MESSAGE_GENERATOR_SEED_";
-    let h_dst = b"BLS12381G1_XMD:SHA-256_SSWU_RO_";
-    let h_vec:Vec<G1Affine> = (0..50).map(|i| {
-            let seed = format!("{}{}", h_seed, i);
-            bbs::hash_to_g1(seed.as_bytes(), h_dst)
-        })
-        .collect();
+
+    let h_vec:Vec<G1Affine> = (0..message_len).map(h_generator).collect();
     let pp = PublicParameters{
         g1,
         g2,
@@ -54,10 +66,10 @@ pub fn par_gen() -> PublicParameters{
     return pp
 }
 
-pub fn key_gen(pp: &PublicParameters) -> KeyPair{
+// Randomized functions take the RNG in their *_with_rng form; the plain form draws from OsRng.
+pub fn key_gen_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, rng: &mut R) -> KeyPair{
     // sk \stackrel{\$}{\leftarrow} Z_p^*
-    let mut rng = thread_rng();
-    let sk_element = Fr::rand(&mut rng);
+    let sk_element = Fr::rand(rng);
     let sk = bbs::SecretKey(
         sk_element
     );
@@ -77,10 +89,61 @@ pub fn key_gen(pp: &PublicParameters) -> KeyPair{
     return keypair
 }
 
-pub fn sign(pp: &PublicParameters, sk: &bbs::SecretKey, messages: &Vec<Fr>) -> Signature{
-    let mut rng = thread_rng();
+pub fn key_gen(pp: &PublicParameters) -> KeyPair{
+    return key_gen_with_rng(pp, &mut OsRng)
+}
+
+// Proof of possession of sk for pk = g2^sk, a Schnorr proof whose challenge covers pk. Policies
+// and lists that combine the keys of several issuers require one per key, so an issuer cannot
+// list a key made from the others' keys, whose secret it does not know, to cancel them out.
+#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+pub struct KeyProof {
+    pub c: Fr,
+    pub z: Fr,
+}
+
+fn key_challenge(pp: &PublicParameters, pk: &PublicKey, k: &G2Affine) -> Fr{
+    let dst = b"MY_KEY_POSSESSION_CHALLENGE_DST_V1";
+    let mut buffer = Vec::new();
+    pp.g2.serialize_compressed(&mut buffer).unwrap();
+    pk.0.serialize_compressed(&mut buffer).unwrap();
+    k.serialize_compressed(&mut buffer).unwrap();
+    return bbs::hash_to_fr(&buffer, dst)
+}
+
+pub fn prove_key_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, keypair: &KeyPair, rng: &mut R) -> KeyProof{
+    let r = Zeroizing::new(Fr::rand(rng));
+    let k = G2Affine::from(pp.g2 * *r);
+    let c = key_challenge(pp, &keypair.public_key, &k);
+    let proof = KeyProof {
+        c,
+        z: *r + c * keypair.secret_key.0,
+    };
+    return proof
+}
+
+pub fn prove_key(pp: &PublicParameters, keypair: &KeyPair) -> KeyProof{
+    return prove_key_with_rng(pp, keypair, &mut OsRng)
+}
+
+pub fn verify_key(pp: &PublicParameters, pk: &PublicKey, proof: &KeyProof) -> Result<(), VerifyError>{
+    if !bbs::valid_points(&[pk.0]) {
+        return Err(VerifyError::MalformedInput("public key is the identity or not in G2"));
+    }
+    // K = g2^z * pk^{-c}
+    let k = G2Affine::from(pp.g2 * proof.z - pk.0 * proof.c);
+    if key_challenge(pp, pk, &k) != proof.c {
+        return Err(VerifyError::ChallengeMismatch);
+    }
+    return Ok(())
+}
+
+pub fn sign_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, sk: &bbs::SecretKey, messages: &Vec<Fr>, rng: &mut R) -> Result<Signature, ProveError>{
+    if messages.len() > pp.h_vec.len() {
+        return Err(ProveError::TooManyMessages { messages: messages.len(), generators: pp.h_vec.len() });
+    }
     // e \stackrel{\$}{\leftarrow} Z_p^*
-    let e = Fr::rand(&mut rng);
+    let e = Fr::rand(rng);
 
     let message_len = messages.len();
     // compute h_i
@@ -105,11 +168,22 @@ pub fn sign(pp: &PublicParameters, sk: &bbs::SecretKey, messages: &Vec<Fr>) -> S
         e: e,
     };
 
-    return signature
+    return Ok(signature)
+}
+
+pub fn sign(pp: &PublicParameters, sk: &bbs::SecretKey, messages: &Vec<Fr>) -> Result<Signature, ProveError>{
+    return sign_with_rng(pp, sk, messages, &mut OsRng)
 }
 
-pub fn verify(pp: &PublicParameters, pk: &PublicKey, messages: &Vec<Fr>, signature: &Signature) -> bool{
+// Everything verify checks except the pairing itself: e(A, pk) * e(A^e / (g1 * \prod_{i=1}^{n} h_i^m_i), g2) = 1.
+fn signature_equation(pp: &PublicParameters, pk: &PublicKey, messages: &Vec<Fr>, signature: &Signature) -> Result<bbs::PairingEquation, VerifyError>{
     let message_len = messages.len();
+    if message_len > pp.h_vec.len() {
+        return Err(VerifyError::MalformedInput("more messages than generators"));
+    }
+    if !bbs::valid_points(&[signature.a]) {
+        return Err(VerifyError::MalformedInput("A is the identity or not in G1"));
+    }
 
     let h_generators : Vec<G1Affine> = pp.h_vec[0..message_len].to_vec();
 
@@ -120,17 +194,307 @@ pub fn verify(pp: &PublicParameters, pk: &PublicKey, messages: &Vec<Fr>, signatu
         let h_element = h_generators[i];
         m_product += h_element * message;
     }
-    // compute left side: e(A, pk + g2^e)
-    let left_side = Bls12_381::pairing(signature.a, G2Affine::from(pk.0 + (pp.g2 * signature.e)));
+    let equation = vec![
+        (G1Projective::from(signature.a), pk.0),
+        (signature.a * signature.e - m_product, pp.g2),
+    ];
+    return Ok(equation)
+}
+
+pub fn verify(pp: &PublicParameters, pk: &PublicKey, messages: &Vec<Fr>, signature: &Signature) -> Result<(), VerifyError>{
+    let equation = signature_equation(pp, pk, messages, signature)?;
+    if !bbs::pairing_check(&equation) {
+        return Err(VerifyError::PairingCheckFailed(1));
+    }
+    return Ok(())
+}
+
+// g2 and one issuer's key prepared for the Miller loop, for a verifier that checks many
+// credentials or proofs from the same issuer. Build it once with verifier_context.
+#[derive(Clone, Debug)]
+pub struct VerifierContext{
+    pub pp: PublicParameters,
+    pub pk: PublicKey,
+    pub g2_prepared: bbs::G2Prepared,
+    pub pk_prepared: bbs::G2Prepared,
+}
+
+pub fn verifier_context(pp: &PublicParameters, pk: &PublicKey) -> VerifierContext{
+    let context = VerifierContext{
+        pp: pp.clone(),
+        pk: pk.clone(),
+        g2_prepared: bbs::G2Prepared::from(pp.g2),
+        pk_prepared: bbs::G2Prepared::from(pk.0),
+    };
+    return context
+}
+
+pub fn verify_with_context(context: &VerifierContext, messages: &Vec<Fr>, signature: &Signature) -> Result<(), VerifyError>{
+    let equation = signature_equation(&context.pp, &context.pk, messages, signature)?;
+    let pairs = [
+        (equation[0].0, &context.pk_prepared),
+        (equation[1].0, &context.g2_prepared),
+    ];
+    if !bbs::prepared_pairing_check(&pairs) {
+        return Err(VerifyError::PairingCheckFailed(1));
+    }
+    return Ok(())
+}
+
+// Verifies many (pk, messages, signature) triples with one final exponentiation and
+// returns the index and error of every one that does not verify.
+pub fn verify_batch(pp: &PublicParameters, items: &[(&PublicKey, &Vec<Fr>, &Signature)]) -> Result<(), Vec<(usize, VerifyError)>>{
+    let equations = items.iter()
+        .map(|(pk, messages, signature)| signature_equation(pp, pk, messages, signature).map(|eq| vec![eq]))
+        .collect();
+    return bbs::verify_batch_equations(equations, VerifyError::PairingCheckFailed)
+}
+
+// Threshold issuance. sk is Shamir-shared among n servers with ids 1..=n by a polynomial of
+// degree t-1, where t is privacy_threshold: fewer than t servers learn nothing about sk and it
+// never exists in one place. This is not t-of-n signing: a signature takes a signer set of
+// exactly 2t-1 servers, so n must be at least 2t-1. Each signer multiplies its shares of r and
+// sk + e locally, which doubles the degree of the sharing, and combine interpolates
+// u = r * (sk + e) and R = M^r to get A = R^{1/u}. DKG shares are checked against the
+// dealers' commitments; the signing phases assume the servers follow the protocol, and a bad
+// partial signature is only caught by the verify at the end of combine.
+
+// What a DKG dealer publishes: g2^{a_k} for the coefficients a_0..a_{t-1} of its polynomial.
+#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+pub struct DkgCommitment(pub Vec<G2Affine>);
+
+// One server's share of sk. public_key is the joint key every combined signature verifies under.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ThresholdKeyShare {
+    pub id: usize,
+    pub privacy_threshold: usize,
+    pub secret_share: bbs::SecretKey,
+    pub public_key: PublicKey,
+}
+
+// What a dealer sends privately to one signer for one presignature: its share of the public
+// e and shares of a random r (degree t-1) and of zero (degree 2t-2).
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct PresignShare {
+    e: Fr,
+    r: Fr,
+    z: Fr,
+}
+
+// A signer's sum of the PresignShares of every dealer. partial_sign consumes it: two partial
+// signatures from the same r and e give away signatures on combinations of their messages.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct Presignature {
+    id: usize,
+    signers: Vec<usize>,
+    e: Fr,
+    r: Fr,
+    z: Fr,
+}
+
+// R_j = M^{r_j} and u_j = r_j * (sk_j + e) + z_j of signer id.
+#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+pub struct PartialSignature {
+    pub id: usize,
+    pub signers: Vec<usize>,
+    pub e: Fr,
+    pub r: G1Affine,
+    pub u: Fr,
+}
+
+fn eval_polynomial(coefficients: &[Fr], x: Fr) -> Fr{
+    return coefficients.iter().rev().fold(Fr::zero(), |acc, c| acc * x + c)
+}
+
+// Lagrange coefficient of id for interpolating at 0 over the distinct, non-zero ids.
+fn lagrange_at_zero(ids: &[usize], id: usize) -> Fr{
+    let x_j = Fr::from(id as u64);
+    let mut numerator = Fr::from(1u64);
+    let mut denominator = Fr::from(1u64);
+    for &other in ids.iter().filter(|&&other| other != id) {
+        let x_k = Fr::from(other as u64);
+        numerator *= x_k;
+        denominator *= x_k - x_j;
+    }
+    return numerator * denominator.inverse().unwrap()
+}
+
+// 2t-1 distinct server ids, none of them 0.
+fn check_signers(privacy_threshold: usize, signers: &[usize]) -> Result<(), &'static str>{
+    if privacy_threshold == 0 || signers.len() != 2 * privacy_threshold - 1 {
+        return Err("signer set must have 2t-1 servers");
+    }
+    for (i, id) in signers.iter().enumerate() {
+        if *id == 0 || signers[..i].contains(id) {
+            return Err("signer ids must be distinct and non-zero");
+        }
+    }
+    return Ok(())
+}
+
+// Run by each of the n servers. The commitment is broadcast; shares[j - 1] goes privately to server j.
+// Fails unless the n servers can form a signer set of 2 * privacy_threshold - 1.
+pub fn dkg_deal_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, privacy_threshold: usize, n: usize, rng: &mut R) -> Result<(DkgCommitment, Vec<bbs::SecretKey>), ProveError>{
+    if privacy_threshold == 0 || 2 * privacy_threshold - 1 > n {
+        return Err(ProveError::MalformedInput("signer set of 2t-1 servers does not fit in the number of servers"));
+    }
+    let coefficients: Zeroizing<Vec<Fr>> = Zeroizing::new((0..privacy_threshold).map(|_| Fr::rand(rng)).collect());
+    let commitment = DkgCommitment(coefficients.iter().map(|a| G2Affine::from(pp.g2 * a)).collect());
+    let shares = (1..=n).map(|j| bbs::SecretKey(eval_polynomial(&coefficients, Fr::from(j as u64)))).collect();
+    return Ok((commitment, shares))
+}
+
+pub fn dkg_deal(pp: &PublicParameters, privacy_threshold: usize, n: usize) -> Result<(DkgCommitment, Vec<bbs::SecretKey>), ProveError>{
+    return dkg_deal_with_rng(pp, privacy_threshold, n, &mut OsRng)
+}
 
-    // compute right side: e(g1 * \prod_{i=1}^{n} h_i^m_i, g2)
-    let right_side = Bls12_381::pairing(G1Affine::from(m_product), pp.g2);
-    
-    if left_side != right_side {
-        println!("Verification Failed!");
-        return false;
+// Run by server id with the commitments of all n dealers and the shares they sent it, both in
+// dealer order. A share that does not match its commitment names the dealer.
+pub fn dkg_finish(pp: &PublicParameters, id: usize, privacy_threshold: usize, commitments: &[DkgCommitment], shares: &[bbs::SecretKey]) -> Result<ThresholdKeyShare, VerifyError>{
+    if commitments.is_empty() || commitments.len() != shares.len() {
+        return Err(VerifyError::MalformedInput("one commitment and one share per dealer"));
+    }
+    if id == 0 || id > commitments.len() || privacy_threshold == 0 || 2 * privacy_threshold - 1 > commitments.len() {
+        return Err(VerifyError::MalformedInput("server id or privacy threshold out of range"));
     }
-    return true
+    let x = Fr::from(id as u64);
+    let mut secret_share = bbs::SecretKey(Fr::zero());
+    let mut joint_key = G2Projective::zero();
+    for (i, (commitment, share)) in commitments.iter().zip(shares).enumerate() {
+        if commitment.0.len() != privacy_threshold || !bbs::valid_points(&commitment.0) {
+            return Err(VerifyError::InvalidShare(i + 1));
+        }
+        // g2^{f_i(id)} = \prod_k C_{i,k}^{id^k}
+        let expected = commitment.0.iter().rev().fold(G2Projective::zero(), |acc, c| acc * x + c);
+        if pp.g2 * share.0 != expected {
+            return Err(VerifyError::InvalidShare(i + 1));
+        }
+        secret_share.0 += share.0;
+        joint_key += commitment.0[0];
+    }
+    let key_share = ThresholdKeyShare {
+        id,
+        privacy_threshold,
+        secret_share,
+        public_key: PublicKey(G2Affine::from(joint_key)),
+    };
+    return Ok(key_share)
+}
+
+// Run by every signer of one presignature; shares[k] goes privately to signers[k]. A signer
+// set makes as many presignatures ahead of time as it will sign credentials.
+pub fn presign_deal_with_rng<R: RngCore + CryptoRng>(privacy_threshold: usize, signers: &[usize], rng: &mut R) -> Result<Vec<PresignShare>, ProveError>{
+    check_signers(privacy_threshold, signers).map_err(ProveError::MalformedInput)?;
+    let e = Fr::rand(rng);
+    let r_coefficients: Zeroizing<Vec<Fr>> = Zeroizing::new((0..privacy_threshold).map(|_| Fr::rand(rng)).collect());
+    let mut z_coefficients: Zeroizing<Vec<Fr>> = Zeroizing::new((0..signers.len()).map(|_| Fr::rand(rng)).collect());
+    z_coefficients[0] = Fr::zero();
+    let shares = signers.iter().map(|&id| {
+        let x = Fr::from(id as u64);
+        PresignShare {
+            e,
+            r: eval_polynomial(&r_coefficients, x),
+            z: eval_polynomial(&z_coefficients, x),
+        }
+    }).collect();
+    return Ok(shares)
+}
+
+pub fn presign_deal(privacy_threshold: usize, signers: &[usize]) -> Result<Vec<PresignShare>, ProveError>{
+    return presign_deal_with_rng(privacy_threshold, signers, &mut OsRng)
+}
+
+// Run by signer key_share.id with the share every signer of the set dealt it. The shares are
+// consumed so the same r and e cannot be finished into a second Presignature.
+pub fn presign_finish(key_share: &ThresholdKeyShare, signers: &[usize], shares: Vec<PresignShare>) -> Result<Presignature, VerifyError>{
+    check_signers(key_share.privacy_threshold, signers).map_err(VerifyError::MalformedInput)?;
+    if !signers.contains(&key_share.id) {
+        return Err(VerifyError::MalformedInput("server is not in the signer set"));
+    }
+    if shares.len() != signers.len() {
+        return Err(VerifyError::MalformedInput("one presignature share per signer"));
+    }
+    let mut presignature = Presignature {
+        id: key_share.id,
+        signers: signers.to_vec(),
+        e: Fr::zero(),
+        r: Fr::zero(),
+        z: Fr::zero(),
+    };
+    for share in &shares {
+        presignature.e += share.e;
+        presignature.r += share.r;
+        presignature.z += share.z;
+    }
+    return Ok(presignature)
+}
+
+pub fn partial_sign(pp: &PublicParameters, key_share: &ThresholdKeyShare, presignature: Presignature, messages: &Vec<Fr>) -> Result<PartialSignature, ProveError>{
+    if presignature.id != key_share.id {
+        return Err(ProveError::MalformedInput("presignature belongs to another server"));
+    }
+    if messages.len() > pp.h_vec.len() {
+        return Err(ProveError::TooManyMessages { messages: messages.len(), generators: pp.h_vec.len() });
+    }
+
+    // compute M = g1 * \prod_{i=1}^{n} h_i^m_i
+    let mut m_product = G1Projective::from(pp.g1);
+    for i in 0..messages.len() {
+        m_product += pp.h_vec[i] * messages[i];
+    }
+
+    // R_j = M^{r_j}, u_j = r_j * (sk_j + e) + z_j
+    let partial = PartialSignature {
+        id: presignature.id,
+        signers: presignature.signers.clone(),
+        e: presignature.e,
+        r: G1Affine::from(m_product * presignature.r),
+        u: presignature.r * (key_share.secret_share.0 + presignature.e) + presignature.z,
+    };
+    return Ok(partial)
+}
+
+// Interpolates the partial signatures of a whole signer set into an ordinary Signature and
+// checks it under the joint public key.
+pub fn combine(pp: &PublicParameters, pk: &PublicKey, messages: &Vec<Fr>, partials: &[PartialSignature]) -> Result<Signature, VerifyError>{
+    let first = match partials.first() {
+        Some(first) => first,
+        None => return Err(VerifyError::MalformedInput("no partial signatures")),
+    };
+    let signers = &first.signers;
+    if partials.len() != signers.len() {
+        return Err(VerifyError::MalformedInput("partial signatures do not cover the signer set"));
+    }
+    for (i, partial) in partials.iter().enumerate() {
+        if partial.signers != *signers || partial.e != first.e {
+            return Err(VerifyError::MalformedInput("partial signatures are from different presignatures"));
+        }
+        if !signers.contains(&partial.id) || partials[..i].iter().any(|other| other.id == partial.id) {
+            return Err(VerifyError::MalformedInput("partial signatures do not cover the signer set"));
+        }
+        if !bbs::valid_points(&[partial.r]) {
+            return Err(VerifyError::MalformedInput("R is the identity or not in G1"));
+        }
+    }
+
+    // R = M^r and u = r * (sk + e), both interpolated at 0
+    let mut r = G1Projective::zero();
+    let mut u = Fr::zero();
+    for partial in partials {
+        let lambda = lagrange_at_zero(signers, partial.id);
+        r += partial.r * lambda;
+        u += partial.u * lambda;
+    }
+    let u_inverse = match u.inverse() {
+        Some(u_inverse) => u_inverse,
+        None => return Err(VerifyError::MalformedInput("u is zero")),
+    };
+    let signature = Signature {
+        a: G1Affine::from(r * u_inverse),
+        e: first.e,
+    };
+    verify(pp, pk, messages, &signature)?;
+    return Ok(signature)
 }
 
 #[cfg(test)]
@@ -140,14 +504,81 @@ mod tests {
 
     #[test]
     fn it_works(){
-        let pp = super::par_gen();
+        let pp = super::par_gen(1);
         let keypair = super::key_gen(&pp);
-        println!("Secret Key: {}", keypair.secret_key.0);
         let message_string = "Issuer-Hiding BBS Test Message";
         let message_fr = Fr::from(BigUint::from_bytes_be(message_string.as_bytes()));
         let messages = vec![message_fr];
-        let signature = super::sign(&pp, &keypair.secret_key, &messages);
+        let signature = super::sign(&pp, &keypair.secret_key, &messages).unwrap();
         let verify_result = super::verify(&pp, &keypair.public_key, &messages, &signature);
-        assert_eq!(verify_result, true);
+        assert_eq!(verify_result, Ok(()));
+
+        let too_long = vec![message_fr; 2];
+        assert_eq!(super::sign(&pp, &keypair.secret_key, &too_long), Err(super::ProveError::TooManyMessages { messages: 2, generators: 1 }));
+        let mut pp = pp;
+        super::extend_generators(&mut pp, 2);
+        assert_eq!(pp, super::par_gen(2));
+        let signature = super::sign(&pp, &keypair.secret_key, &too_long).unwrap();
+        assert_eq!(super::verify(&pp, &keypair.public_key, &too_long, &signature), Ok(()));
+
+        let key_proof = super::prove_key(&pp, &keypair);
+        assert_eq!(super::verify_key(&pp, &keypair.public_key, &key_proof), Ok(()));
+        assert_eq!(super::verify_key(&pp, &super::key_gen(&pp).public_key, &key_proof), Err(super::VerifyError::ChallengeMismatch));
+
+        let context = super::verifier_context(&pp, &keypair.public_key);
+        assert_eq!(super::verify_with_context(&context, &too_long, &signature), Ok(()));
+        assert_eq!(super::verify_with_context(&context, &messages, &signature), Err(super::VerifyError::PairingCheckFailed(1)));
+
+        let other = super::key_gen(&pp);
+        let other_signature = super::sign(&pp, &other.secret_key, &messages).unwrap();
+        let batch = vec![
+            (&keypair.public_key, &too_long, &signature),
+            (&other.public_key, &messages, &other_signature),
+            (&keypair.public_key, &messages, &other_signature),
+            (&other.public_key, &too_long, &other_signature),
+        ];
+        assert_eq!(super::verify_batch(&pp, &batch[0..2]), Ok(()));
+        assert_eq!(super::verify_batch(&pp, &batch), Err(vec![(2, super::VerifyError::PairingCheckFailed(1)), (3, super::VerifyError::PairingCheckFailed(1))]));
     }
-}
\ No newline at end of file
+    #[test]
+    fn threshold_issuance(){
+        // privacy threshold 2 among 5: any 3 servers sign, a single server learns nothing about sk
+        let (privacy_threshold, n) = (2, 5);
+        let pp = super::par_gen(4);
+        let dealings: Vec<_> = (0..n).map(|_| super::dkg_deal(&pp, privacy_threshold, n).unwrap()).collect();
+        let commitments: Vec<_> = dealings.iter().map(|(commitment, _)| commitment.clone()).collect();
+        let key_shares: Vec<_> = (1..=n).map(|j| {
+            let shares: Vec<_> = dealings.iter().map(|(_, shares)| shares[j - 1].clone()).collect();
+            super::dkg_finish(&pp, j, privacy_threshold, &commitments, &shares).unwrap()
+        }).collect();
+        let pk = key_shares[0].public_key.clone();
+        assert!(key_shares.iter().all(|key_share| key_share.public_key == pk));
+
+        let messages: Vec<Fr> = (0..4u64).map(Fr::from).collect();
+        let signers = vec![1, 3, 5];
+        let mut presign_dealings: Vec<_> = signers.iter().map(|_| super::presign_deal(privacy_threshold, &signers).unwrap()).collect();
+        let mut partials: Vec<_> = signers.iter().map(|&id| {
+            let shares: Vec<_> = presign_dealings.iter_mut().map(|dealing| dealing.remove(0)).collect();
+            let presignature = super::presign_finish(&key_shares[id - 1], &signers, shares).unwrap();
+            super::partial_sign(&pp, &key_shares[id - 1], presignature, &messages).unwrap()
+        }).collect();
+        partials.reverse();
+        let signature = super::combine(&pp, &pk, &messages, &partials).unwrap();
+        assert_eq!(super::verify(&pp, &pk, &messages, &signature), Ok(()));
+        let (pikp, pizkp) = crate::proof::prove(&pp, &signature, &messages, &vec![1], b"").unwrap();
+        assert_eq!(crate::proof::verify_proof(&pp, &pk, &pikp, &pizkp, b""), Ok(()));
+
+        assert_eq!(super::combine(&pp, &pk, &messages, &partials[1..]), Err(super::VerifyError::MalformedInput("partial signatures do not cover the signer set")));
+        let mut tampered = partials.clone();
+        tampered[0].u += Fr::from(1u64);
+        assert_eq!(super::combine(&pp, &pk, &messages, &tampered), Err(super::VerifyError::PairingCheckFailed(1)));
+        assert!(super::presign_deal(privacy_threshold, &[1, 2]).is_err());
+
+        let mut shares: Vec<_> = dealings.iter().map(|(_, shares)| shares[0].clone()).collect();
+        shares.swap(1, 2);
+        assert_eq!(super::dkg_finish(&pp, 1, privacy_threshold, &commitments, &shares), Err(super::VerifyError::InvalidShare(2)));
+        // with t = 4 no signer set of 7 fits among 5 servers
+        assert!(super::dkg_deal(&pp, 4, n).is_err());
+        assert_eq!(super::dkg_finish(&pp, 1, 4, &commitments, &shares), Err(super::VerifyError::MalformedInput("server id or privacy threshold out of range")));
+    }
+}
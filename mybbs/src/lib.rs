@@ -1,4 +1,13 @@
 pub mod bbs;
+pub mod blind;
+pub mod ciphersuite;
+pub mod equality;
+pub mod error;
+pub mod holder;
 pub mod issuer;
 pub mod verifier;
-pub mod proof;
\ No newline at end of file
+pub mod proof;
+pub mod pseudonym;
+pub mod range;
+pub mod schema;
+pub mod wire;
@@ -3,10 +3,16 @@ use ark_ff::Field;
 use ark_ec::{pairing::Pairing, CurveGroup};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::{fmt::Debug, UniformRand, vec::Vec};
-use rand::thread_rng;
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use zeroize::Zeroizing;
 
 use crate::bbs;
+use crate::equality::{self, Equality};
+use crate::error::{ProveError, VerifyError};
+use crate::holder::{self, HolderSecretKey};
 use crate::issuer;
+use crate::pseudonym::{self, ScopedPseudonym};
+use crate::range::{self, RangePredicate, RangeProof};
 
 pub type Fr = <Bls12_381 as Pairing>::ScalarField;
 
@@ -18,6 +24,9 @@ pub struct PiKP{
     pub open: Vec<usize>,
     pub len: usize,
     pub message_list: Vec<Fr>,
+    pub predicates: Vec<RangePredicate>,
+    pub pseudonym: Option<ScopedPseudonym>,
+    pub presentation_header: Vec<u8>,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
@@ -27,20 +36,68 @@ pub struct PiZKP{
     pub z: Fr,
     pub v: Vec<Fr>,
     pub c: Fr,
+    pub range: RangeProof,
 }
 
-pub fn prove(
+// (credential, messages, reveal_index, predicates) of one credential in prove_multi.
+pub type MultiItem<'a> = (&'a issuer::Signature, &'a Vec<Fr>, &'a Vec<usize>, &'a Vec<RangePredicate>);
+
+// The prover's first move, kept until the challenge is known. The randomizers and hidden
+// messages are wiped when it is dropped, once respond has used them or the proof failed.
+struct Commitment{
+    pikp: PiKP,
+    challenge_input: Vec<u8>,   // everything hashed into c but the presentation header
+    r1: Zeroizing<Fr>,
+    r2: Zeroizing<Fr>,
+    e: Fr,
+    alpha: Zeroizing<Fr>,
+    beta: Zeroizing<Fr>,
+    gamma: Zeroizing<Fr>,
+    delta_vec: Zeroizing<Vec<Fr>>,
+    hidden_messages: Zeroizing<Vec<Fr>>,
+    range_proof: RangeProof,
+    range_witness: range::RangeWitness,
+}
+
+pub fn hidden_index(len: usize, reveal_index: &[usize]) -> Vec<usize>{
+    return (0..len).filter(|i| !reveal_index.contains(i)).collect()
+}
+
+// Reveal indices have to be below len and strictly increasing, the order the revealed
+// messages are listed in. Returns the first index that is out of range, repeated or out of
+// order.
+pub fn check_reveal_index(len: usize, reveal_index: &[usize]) -> Result<(), usize>{
+    for (k, &idx) in reveal_index.iter().enumerate(){
+        if idx >= len || (k > 0 && idx <= reveal_index[k - 1]) {
+            return Err(idx);
+        }
+    }
+    return Ok(())
+}
+
+// linked[i], when present, is the blinding delta_i has to take because the i-th hidden
+// message is linked to a message of another credential.
+#[allow(clippy::too_many_arguments)]
+fn commit_with_rng<R: RngCore + CryptoRng>(
     pp: &issuer::PublicParameters,
     cred: &issuer::Signature,
     message_list: &Vec<Fr>,
     reveal_index: &Vec<usize>,
-) -> (PiKP, PiZKP){
-    let mut rng = thread_rng();
-    let r1 = Fr::rand(&mut rng);
-    let r2 = Fr::rand(&mut rng);
-    let r2_inv = r2.inverse().unwrap();
-
+    predicates: &Vec<RangePredicate>,
+    pseudonym: Option<(usize, &[u8])>,
+    linked: &[Option<Fr>],
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<Commitment, ProveError>{
     let message_len = message_list.len();
+    if message_len > pp.h_vec.len() {
+        return Err(ProveError::TooManyMessages { messages: message_len, generators: pp.h_vec.len() });
+    }
+    check_reveal_index(message_len, reveal_index).map_err(ProveError::BadRevealIndex)?;
+
+    let r1 = Zeroizing::new(Fr::rand(rng));
+    let r2 = Zeroizing::new(Fr::rand(rng));
+    let r2_inv = Zeroizing::new(r2.inverse().unwrap());
 
     let h_generators : Vec<G1Affine> = pp.h_vec[0..message_len].to_vec();
 
@@ -55,23 +112,36 @@ pub fn prove(
             close_index.push(i);
         }
     }
-    d_element *= r2_inv;
-    let abar_pro = (cred.a * r1) * r2_inv;
-    let bbar_pro = (d_element * r1) + (abar_pro * (-cred.e));
+    d_element *= *r2_inv;
+    let abar_pro = (cred.a * *r1) * *r2_inv;
+    let bbar_pro = (d_element * *r1) + (abar_pro * (-cred.e));
 
     let close_len = close_index.len();
 
-    let alpha = Fr::rand(&mut rng);
-    let beta = Fr::rand(&mut rng);
-    let gamma = Fr::rand(&mut rng);
-    let delta_vec : Vec<Fr> = (0..close_len).map(|_| Fr::rand(&mut rng)).collect();
+    let alpha = Zeroizing::new(Fr::rand(rng));
+    let beta = Zeroizing::new(Fr::rand(rng));
+    let gamma = Zeroizing::new(Fr::rand(rng));
+    let delta_vec : Zeroizing<Vec<Fr>> = Zeroizing::new((0..close_len).map(|i| linked.get(i).copied().flatten().unwrap_or_else(|| Fr::rand(rng))).collect());
 
-    let u1_pro = (d_element * alpha) + (abar_pro * beta);
-    let mut u2_element = d_element * gamma;
+    let u1_pro = (d_element * *alpha) + (abar_pro * *beta);
+    let mut u2_element = d_element * *gamma;
     for i in 0..close_len{
         u2_element += h_generators[close_index[i]] * delta_vec[i];
     }
-    let dst = b"MY_CHALLENGE_GENERATOR_DST_V1";
+    // the range proofs reuse delta_i of the hidden message they are about
+    let positions = range::predicate_positions(predicates, &close_index).map_err(ProveError::BadRevealIndex)?;
+    let range_messages: Zeroizing<Vec<Fr>> = Zeroizing::new(positions.iter().map(|&k| message_list[close_index[k]]).collect());
+    let range_blindings: Zeroizing<Vec<Fr>> = Zeroizing::new(positions.iter().map(|&k| delta_vec[k]).collect());
+    let (range_proof, range_witness, range_t) = range::range_commit_with_rng(predicates, &range_messages, &range_blindings, rng)?;
+    // so does the pseudonym for the link secret
+    let (pseudonym, pseudonym_t) = match pseudonym{
+        Some((index, scope)) => {
+            let k = close_index.iter().position(|&i| i == index).ok_or(ProveError::BadRevealIndex(index))?;
+            let (pseudonym, t) = pseudonym::pseudonym_commit(index, scope, &message_list[index], &delta_vec[k]);
+            (Some(pseudonym), Some(t))
+        },
+        None => (None, None),
+    };
     let c_inputs_pro = vec![
         abar_pro,
         bbar_pro,
@@ -90,7 +160,8 @@ pub fn prove(
     for open_msg in &open_messages{
         open_msg.serialize_compressed(&mut buffer).unwrap();
     }
-    let c = bbs::hash_to_fr(&buffer[..], dst);
+    range::serialize_challenge_input(predicates, &range_proof, &range_t, &mut buffer);
+    pseudonym::serialize_challenge_input(&pseudonym, &pseudonym_t, &mut buffer);
     let pikp = PiKP{
         a_bar: c_inputs[0],
         b_bar: c_inputs[1],
@@ -98,42 +169,181 @@ pub fn prove(
         open: reveal_index.clone(),
         len: message_len,
         message_list: open_messages,
+        predicates: predicates.clone(),
+        pseudonym,
+        presentation_header: presentation_header.to_vec(),
     };
-    let s = alpha + c * r1;
-    let t = beta - c * cred.e;
-    let z = gamma + c * r2;
+    return Ok(Commitment{
+        pikp,
+        challenge_input: buffer,
+        r1,
+        r2,
+        e: cred.e,
+        alpha,
+        beta,
+        gamma,
+        delta_vec,
+        hidden_messages: Zeroizing::new(close_index.iter().map(|&i| message_list[i]).collect()),
+        range_proof,
+        range_witness,
+    })
+}
+
+fn respond(commitment: Commitment, c: Fr) -> (PiKP, PiZKP){
+    let s = *commitment.alpha + c * *commitment.r1;
+    let t = *commitment.beta - c * commitment.e;
+    let z = *commitment.gamma + c * *commitment.r2;
     let mut v_vec = Vec::new();
-    for i in 0..close_len{
-        let v_i = delta_vec[i] - c * message_list[close_index[i]];
+    for i in 0..commitment.delta_vec.len(){
+        let v_i = commitment.delta_vec[i] - c * commitment.hidden_messages[i];
         v_vec.push(v_i);
     }
+    let mut range_proof = commitment.range_proof;
+    range::range_respond(&mut range_proof, &commitment.range_witness, &(-c));
     let pizkp = PiZKP{
         s,
         t,
         z,
         v: v_vec,
-        c
+        c,
+        range: range_proof,
     };
-    (pikp, pizkp)
+    return (commitment.pikp, pizkp)
 }
 
-pub fn verify_proof(
+#[allow(clippy::too_many_arguments)]
+fn prove_single_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    pseudonym: Option<(usize, &[u8])>,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    let commitment = commit_with_rng(pp, cred, message_list, reveal_index, predicates, pseudonym, &[], presentation_header, rng)?;
+    let dst = b"MY_CHALLENGE_GENERATOR_DST_V1";
+    let mut buffer = commitment.challenge_input.clone();
+    presentation_header.to_vec().serialize_compressed(&mut buffer).unwrap();
+    let c = bbs::hash_to_fr(&buffer[..], dst);
+    Ok(respond(commitment, c))
+}
+
+// prove that also shows min <= m_i < max for every predicate on a hidden message m_i.
+#[allow(clippy::too_many_arguments)]
+pub fn prove_with_predicates_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return prove_single_with_rng(pp, cred, message_list, reveal_index, predicates, None, presentation_header, rng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn prove_with_predicates(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    presentation_header: &[u8],
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return prove_with_predicates_with_rng(pp, cred, message_list, reveal_index, predicates, presentation_header, &mut OsRng)
+}
+
+// prove_with_predicates that also shows the pseudonym H_G1(scope)^m for the hidden message m
+// at index link_secret.
+#[allow(clippy::too_many_arguments)]
+pub fn prove_with_pseudonym_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    link_secret: usize,
+    scope: &[u8],
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return prove_single_with_rng(pp, cred, message_list, reveal_index, predicates, Some((link_secret, scope)), presentation_header, rng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn prove_with_pseudonym(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    predicates: &Vec<RangePredicate>,
+    link_secret: usize,
+    scope: &[u8],
+    presentation_header: &[u8],
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return prove_with_pseudonym_with_rng(pp, cred, message_list, reveal_index, predicates, link_secret, scope, presentation_header, &mut OsRng)
+}
+
+pub fn prove_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return prove_with_predicates_with_rng(pp, cred, message_list, reveal_index, &Vec::new(), presentation_header, rng)
+}
+
+pub fn prove(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    presentation_header: &[u8],
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return prove_with_rng(pp, cred, message_list, reveal_index, presentation_header, &mut OsRng)
+}
+
+// The challenge input the responses of a presentation imply, but the presentation header,
+// and the pairing e(A_bar, pk) = e(B_bar, g2) left to check.
+fn challenge_input(
     pp: &issuer::PublicParameters,
     pk: &issuer::PublicKey,
     pikp: &PiKP,
     pizkp: &PiZKP,
-) -> bool{
+) -> Result<(Vec<u8>, bbs::PairingEquation), VerifyError>{
+    if pikp.len > pp.h_vec.len() {
+        return Err(VerifyError::MalformedInput("message length exceeds generators"));
+    }
+    check_reveal_index(pikp.len, &pikp.open).map_err(VerifyError::BadRevealIndex)?;
+    if pikp.message_list.len() != pikp.open.len() {
+        return Err(VerifyError::MalformedInput("revealed messages do not match reveal indices"));
+    }
+    if !bbs::valid_points(&[pikp.a_bar, pikp.b_bar, pikp.d]) {
+        return Err(VerifyError::MalformedInput("A_bar, B_bar or D is the identity or not in G1"));
+    }
     let h_generators : Vec<G1Affine> = pp.h_vec[0..pikp.len].to_vec();
-    let dst = b"MY_CHALLENGE_GENERATOR_DST_V1";
 
     let mut lhs_u2_element = pikp.d * pizkp.z + pp.g1 * (-pizkp.c);
-    let close_len = pikp.len - pikp.open.len();
-    let mut close_idx = Vec::new();
-    for i in 0..pikp.len{
-        if !pikp.open.contains(&i){
-            close_idx.push(i);
-        }
+    let close_idx = hidden_index(pikp.len, &pikp.open);
+    let close_len = close_idx.len();
+    if pizkp.v.len() != close_len {
+        return Err(VerifyError::MalformedInput("response count does not match hidden messages"));
     }
+    let positions = range::predicate_positions(&pikp.predicates, &close_idx).map_err(VerifyError::BadRevealIndex)?;
+    let range_responses: Vec<Fr> = positions.iter().map(|&k| pizkp.v[k]).collect();
+    let range_t = range::range_verify(&pikp.predicates, &pizkp.range, &range_responses, &(-pizkp.c))?;
+    let pseudonym_t = match &pikp.pseudonym{
+        Some(pseudonym) => {
+            let k = close_idx.iter().position(|&i| i == pseudonym.index).ok_or(VerifyError::BadRevealIndex(pseudonym.index))?;
+            Some(pseudonym::pseudonym_verify(pseudonym, &pizkp.v[k], &(-pizkp.c))?)
+        },
+        None => None,
+    };
     for i in 0..pikp.open.len(){
         let h_i = h_generators[pikp.open[i]];
         lhs_u2_element += (h_i * (pikp.message_list[i])) * (-pizkp.c);
@@ -166,28 +376,231 @@ pub fn verify_proof(
     for open_msg in &pikp.message_list{
         open_msg.serialize_compressed(&mut buffer).unwrap();
     }
+    range::serialize_challenge_input(&pikp.predicates, &pizkp.range, &range_t, &mut buffer);
+    pseudonym::serialize_challenge_input(&pikp.pseudonym, &pseudonym_t, &mut buffer);
+
+    let equation = vec![
+        (G1Projective::from(pikp.a_bar), pk.0),
+        (-G1Projective::from(pikp.b_bar), pp.g2),
+    ];
+    return Ok((buffer, equation))
+}
+
+// prove for a credential bound to a holder key. message_list are the messages without the
+// holder secret, which goes in at holder_index; reveal_index counts it.
+#[allow(clippy::too_many_arguments)]
+pub fn prove_bound_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    holder_secret: &HolderSecretKey,
+    holder_index: usize,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<(PiKP, PiZKP), ProveError>{
+    if reveal_index.contains(&holder_index) {
+        return Err(ProveError::BadRevealIndex(holder_index));
+    }
+    let messages = holder::with_holder_secret(message_list, holder_index, holder_secret)?;
+    return prove_with_rng(pp, cred, &messages, reveal_index, presentation_header, rng)
+}
+
+pub fn prove_bound(
+    pp: &issuer::PublicParameters,
+    cred: &issuer::Signature,
+    holder_secret: &HolderSecretKey,
+    holder_index: usize,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    presentation_header: &[u8],
+) -> Result<(PiKP, PiZKP), ProveError>{
+    return prove_bound_with_rng(pp, cred, holder_secret, holder_index, message_list, reveal_index, presentation_header, &mut OsRng)
+}
+
+// Everything verify_proof checks except the pairing e(A_bar, pk) = e(B_bar, g2).
+fn proof_equation(
+    pp: &issuer::PublicParameters,
+    pk: &issuer::PublicKey,
+    pikp: &PiKP,
+    pizkp: &PiZKP,
+    presentation_header: &[u8],
+) -> Result<bbs::PairingEquation, VerifyError>{
+    if pikp.presentation_header != presentation_header {
+        return Err(VerifyError::PresentationHeaderMismatch);
+    }
+    let dst = b"MY_CHALLENGE_GENERATOR_DST_V1";
+    let (mut buffer, equation) = challenge_input(pp, pk, pikp, pizkp)?;
+    pikp.presentation_header.serialize_compressed(&mut buffer).unwrap();
     let c_calculated = bbs::hash_to_fr(&buffer[..], dst);
 
     if c_calculated != pizkp.c{
-        println!("Challenge hash check failed");
-        return false
+        return Err(VerifyError::ChallengeMismatch)
+    }
+    return Ok(equation)
+}
+
+pub fn verify_proof(
+    pp: &issuer::PublicParameters,
+    pk: &issuer::PublicKey,
+    pikp: &PiKP,
+    pizkp: &PiZKP,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    let equation = proof_equation(pp, pk, pikp, pizkp, presentation_header)?;
+    if !bbs::pairing_check(&equation) {
+        return Err(VerifyError::PairingCheckFailed(1))
+    }
+    return Ok(())
+}
+
+// verify_proof for a presentation that has to carry a pseudonym for scope derived from the
+// message at index link_secret. Returns the pseudonym.
+pub fn verify_proof_with_pseudonym(
+    pp: &issuer::PublicParameters,
+    pk: &issuer::PublicKey,
+    pikp: &PiKP,
+    pizkp: &PiZKP,
+    link_secret: usize,
+    scope: &[u8],
+    presentation_header: &[u8],
+) -> Result<G1Affine, VerifyError>{
+    let nym = pseudonym::expect_pseudonym(&pikp.pseudonym, link_secret, scope)?;
+    verify_proof(pp, pk, pikp, pizkp, presentation_header)?;
+    return Ok(nym)
+}
+
+// verify_proof for a presentation of a credential bound to a holder key at holder_index.
+pub fn verify_proof_bound(
+    pp: &issuer::PublicParameters,
+    pk: &issuer::PublicKey,
+    pikp: &PiKP,
+    pizkp: &PiZKP,
+    holder_index: usize,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    holder::check_bound(pikp.len, &pikp.open, holder_index)?;
+    return verify_proof(pp, pk, pikp, pizkp, presentation_header)
+}
+
+// verify_proof with g2 and the issuer key taken prepared from the context.
+pub fn verify_proof_with_context(
+    context: &issuer::VerifierContext,
+    pikp: &PiKP,
+    pizkp: &PiZKP,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    let equation = proof_equation(&context.pp, &context.pk, pikp, pizkp, presentation_header)?;
+    let pairs = [
+        (equation[0].0, &context.pk_prepared),
+        (equation[1].0, &context.g2_prepared),
+    ];
+    if !bbs::prepared_pairing_check(&pairs) {
+        return Err(VerifyError::PairingCheckFailed(1))
+    }
+    return Ok(())
+}
+
+// Verifies many (pk, pikp, pizkp, presentation_header) tuples with one final exponentiation
+// and returns the index and error of every one that does not verify.
+pub fn verify_batch(
+    pp: &issuer::PublicParameters,
+    items: &[(&issuer::PublicKey, &PiKP, &PiZKP, &[u8])],
+) -> Result<(), Vec<(usize, VerifyError)>>{
+    let equations = items.iter()
+        .map(|(pk, pikp, pizkp, presentation_header)| proof_equation(pp, pk, pikp, pizkp, presentation_header).map(|eq| vec![eq]))
+        .collect();
+    return bbs::verify_batch_equations(equations, VerifyError::PairingCheckFailed)
+}
+
+// Presents several credentials at once under a single challenge. Each equality asserts that two hidden
+// messages, named by (item, message index), are equal without revealing them.
+pub fn prove_multi_with_rng<R: RngCore + CryptoRng>(
+    pp: &issuer::PublicParameters,
+    items: &[MultiItem],
+    equalities: &Vec<Equality>,
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<Vec<(PiKP, PiZKP)>, ProveError>{
+    let messages: Vec<&Vec<Fr>> = items.iter().map(|item| item.1).collect();
+    let hidden: Vec<Vec<usize>> = items.iter().map(|item| hidden_index(item.1.len(), item.2)).collect();
+    let linked = equality::linked_blindings_with_rng(equalities, &messages, &hidden, rng)?;
+    let mut commitments = Vec::new();
+    for j in 0..items.len(){
+        let (cred, message_list, reveal_index, predicates) = items[j];
+        commitments.push(commit_with_rng(pp, cred, message_list, reveal_index, predicates, None, &linked[j], presentation_header, rng)?);
     }
 
-    if Bls12_381::pairing(pikp.a_bar, pk.0) != Bls12_381::pairing(pikp.b_bar, pp.g2) {
-        println!("Pairing check failed");
-        return false
+    let dst = b"MY_MULTI_CHALLENGE_DST_V1";
+    let mut buffer = Vec::new();
+    for commitment in &commitments{
+        commitment.challenge_input.serialize_compressed(&mut buffer).unwrap();
     }
+    equality::serialize_challenge_input(equalities, &mut buffer);
+    presentation_header.to_vec().serialize_compressed(&mut buffer).unwrap();
+    let c = bbs::hash_to_fr(&buffer[..], dst);
+    return Ok(commitments.into_iter().map(|commitment| respond(commitment, c)).collect())
+}
 
-    return true
+pub fn prove_multi(
+    pp: &issuer::PublicParameters,
+    items: &[MultiItem],
+    equalities: &Vec<Equality>,
+    presentation_header: &[u8],
+) -> Result<Vec<(PiKP, PiZKP)>, ProveError>{
+    return prove_multi_with_rng(pp, items, equalities, presentation_header, &mut OsRng)
+}
+
+// Verifies the output of prove_multi. A failing pairing is reported as PairingCheckFailed(j + 1)
+// for the j-th presentation.
+pub fn verify_multi(
+    pp: &issuer::PublicParameters,
+    items: &[(&issuer::PublicKey, &PiKP, &PiZKP)],
+    equalities: &Vec<Equality>,
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    if items.is_empty() {
+        return Err(VerifyError::MalformedInput("no presentations"));
+    }
+    let dst = b"MY_MULTI_CHALLENGE_DST_V1";
+    let c = items[0].2.c;
+    let mut buffer = Vec::new();
+    let mut equations = Vec::new();
+    for (pk, pikp, pizkp) in items{
+        if pikp.presentation_header != presentation_header {
+            return Err(VerifyError::PresentationHeaderMismatch);
+        }
+        if pizkp.c != c {
+            return Err(VerifyError::ChallengeMismatch);
+        }
+        let (input, equation) = challenge_input(pp, pk, pikp, pizkp)?;
+        input.serialize_compressed(&mut buffer).unwrap();
+        equations.push(equation);
+    }
+    let hidden: Vec<Vec<usize>> = items.iter().map(|(_, pikp, _)| hidden_index(pikp.len, &pikp.open)).collect();
+    let responses: Vec<&Vec<Fr>> = items.iter().map(|(_, _, pizkp)| &pizkp.v).collect();
+    equality::check_linked_responses(equalities, &hidden, &responses)?;
+    equality::serialize_challenge_input(equalities, &mut buffer);
+    presentation_header.to_vec().serialize_compressed(&mut buffer).unwrap();
+    if bbs::hash_to_fr(&buffer[..], dst) != c {
+        return Err(VerifyError::ChallengeMismatch);
+    }
+
+    if !bbs::batch_pairing_check(&equations) {
+        let j = equations.iter().position(|eq| !bbs::pairing_check(eq)).unwrap_or(0);
+        return Err(VerifyError::PairingCheckFailed(j + 1));
+    }
+    return Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use ark_bls12_381::Bls12_381;
-    use ark_ec::pairing::Pairing;
+    use ark_bls12_381::{Bls12_381, Fq, G1Affine};
+    use ark_ec::{pairing::Pairing, AffineRepr};
     use ark_std::{UniformRand, vec::Vec};
-    use rand::thread_rng;
-    use crate::issuer;
+    use rand::{rngs::StdRng, thread_rng, SeedableRng};
+    use crate::error::{ProveError, VerifyError};
+    use crate::{issuer, range, schema};
 
     pub type Fr = <Bls12_381 as Pairing>::ScalarField;
 
@@ -197,12 +610,143 @@ mod tests {
         let message_len = 10;
         let mut rng = thread_rng();
         let messages: Vec<Fr> = (0..message_len).map(|_| Fr::rand(&mut rng)).collect();
-        let pp = issuer::par_gen();
+        let pp = issuer::par_gen(message_len);
+        let keypair = issuer::key_gen(&pp);
+        let signature = issuer::sign(&pp, &keypair.secret_key, &messages).unwrap();
+        let reveal_index = vec![0, 3, 5];
+        let nonce = b"verifier-nonce-1";
+        let (pikp, pizkp) = super::prove(&pp, &signature, &messages, &reveal_index, nonce).unwrap();
+        let result = super::verify_proof(&pp, &keypair.public_key, &pikp, &pizkp, nonce);
+        assert_eq!(result, Ok(()));
+
+        // the same seed gives the same proof
+        let seeded = super::prove_with_rng(&pp, &signature, &messages, &reveal_index, nonce, &mut StdRng::seed_from_u64(7)).unwrap();
+        assert_eq!(super::prove_with_rng(&pp, &signature, &messages, &reveal_index, nonce, &mut StdRng::seed_from_u64(7)).unwrap(), seeded);
+        assert_eq!(super::verify_proof(&pp, &keypair.public_key, &seeded.0, &seeded.1, nonce), Ok(()));
+        let context = issuer::verifier_context(&pp, &keypair.public_key);
+        assert_eq!(super::verify_proof_with_context(&context, &seeded.0, &seeded.1, nonce), Ok(()));
+
+        let result = super::verify_proof(&pp, &keypair.public_key, &pikp, &pizkp, b"verifier-nonce-2");
+        assert_eq!(result, Err(VerifyError::PresentationHeaderMismatch));
+        let mut replayed = pikp.clone();
+        replayed.presentation_header = b"verifier-nonce-2".to_vec();
+        let result = super::verify_proof(&pp, &keypair.public_key, &replayed, &pizkp, b"verifier-nonce-2");
+        assert_eq!(result, Err(VerifyError::ChallengeMismatch));
+    }
+
+    #[test]
+    fn rejects_tampered_proof() {
+        let message_len = 10;
+        let mut rng = thread_rng();
+        let messages: Vec<Fr> = (0..message_len).map(|_| Fr::rand(&mut rng)).collect();
+        let pp = issuer::par_gen(message_len);
         let keypair = issuer::key_gen(&pp);
-        let signature = issuer::sign(&pp, &keypair.secret_key, &messages);
+        let signature = issuer::sign(&pp, &keypair.secret_key, &messages).unwrap();
         let reveal_index = vec![0, 3, 5];
-        let (pikp, pizkp) = super::prove(&pp, &signature, &messages, &reveal_index);
-        let bool = super::verify_proof(&pp, &keypair.public_key, &pikp, &pizkp);
-        assert_eq!(bool,true);
+        let (mut pikp, pizkp) = super::prove(&pp, &signature, &messages, &reveal_index, b"").unwrap();
+        pikp.message_list[0] += Fr::from(1u64);
+        let result = super::verify_proof(&pp, &keypair.public_key, &pikp, &pizkp, b"");
+        assert_eq!(result, Err(VerifyError::ChallengeMismatch));
+
+        let result = super::prove(&pp, &signature, &messages, &vec![0, message_len], b"");
+        assert_eq!(result.unwrap_err(), ProveError::BadRevealIndex(message_len));
+
+        let (good_pikp, good_pizkp) = super::prove(&pp, &signature, &messages, &reveal_index, b"").unwrap();
+        let stranger = issuer::key_gen(&pp);
+        let batch: Vec<(&issuer::PublicKey, &super::PiKP, &super::PiZKP, &[u8])> = vec![
+            (&keypair.public_key, &good_pikp, &good_pizkp, b""),
+            (&keypair.public_key, &pikp, &pizkp, b""),
+            (&stranger.public_key, &good_pikp, &good_pizkp, b""),
+            (&keypair.public_key, &good_pikp, &good_pizkp, b""),
+        ];
+        assert_eq!(super::verify_batch(&pp, &batch[3..]), Ok(()));
+        assert_eq!(super::verify_batch(&pp, &batch), Err(vec![(1, VerifyError::ChallengeMismatch), (2, VerifyError::PairingCheckFailed(1))]));
+
+        // age >= 18 and 2020 <= year < 2030 on hidden integers
+        let mut messages = messages;
+        messages[2] = schema::encode_integer(30);
+        messages[4] = schema::encode_integer(2025);
+        let signature = issuer::sign(&pp, &keypair.secret_key, &messages).unwrap();
+        let predicates = vec![range::integer_range(2, 18, i64::MAX), range::integer_range(4, 2020, 2030)];
+        let (pikp, pizkp) = super::prove_with_predicates(&pp, &signature, &messages, &reveal_index, &predicates, b"").unwrap();
+        assert_eq!(super::verify_proof(&pp, &keypair.public_key, &pikp, &pizkp, b""), Ok(()));
+        assert_eq!(pikp.predicates, predicates);
+
+        let mut widened = pikp.clone();
+        widened.predicates[0] = range::integer_range(2, 0, i64::MAX);
+        assert_eq!(super::verify_proof(&pp, &keypair.public_key, &widened, &pizkp, b""), Err(VerifyError::ChallengeMismatch));
+        let mut stripped = pikp.clone();
+        stripped.predicates.clear();
+        assert_eq!(super::verify_proof(&pp, &keypair.public_key, &stripped, &pizkp, b""), Err(VerifyError::MalformedInput("range proof does not match its predicates")));
+
+        let too_old = vec![range::integer_range(2, 40, 65)];
+        let result = super::prove_with_predicates(&pp, &signature, &messages, &reveal_index, &too_old, b"");
+        assert_eq!(result.unwrap_err(), ProveError::PredicateNotSatisfied(0));
+        let revealed = vec![range::integer_range(3, 0, 1)];
+        let result = super::prove_with_predicates(&pp, &signature, &messages, &reveal_index, &revealed, b"");
+        assert_eq!(result.unwrap_err(), ProveError::BadRevealIndex(3));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let message_len = 10;
+        let mut rng = thread_rng();
+        let messages: Vec<Fr> = (0..message_len).map(|_| Fr::rand(&mut rng)).collect();
+        let pp = issuer::par_gen(message_len);
+        let keypair = issuer::key_gen(&pp);
+        let pk = &keypair.public_key;
+        let signature = issuer::sign(&pp, &keypair.secret_key, &messages).unwrap();
+        let (pikp, pizkp) = super::prove(&pp, &signature, &messages, &vec![0, 3, 5], b"").unwrap();
+        let points = VerifyError::MalformedInput("A_bar, B_bar or D is the identity or not in G1");
+
+        // A_bar = B_bar = 1 would satisfy e(A_bar, pk) = e(B_bar, g2) for any key
+        let mut bad = pikp.clone();
+        bad.a_bar = G1Affine::zero();
+        bad.b_bar = G1Affine::zero();
+        assert_eq!(super::verify_proof(&pp, pk, &bad, &pizkp, b""), Err(points));
+        let mut bad = pikp.clone();
+        bad.d = G1Affine::zero();
+        assert_eq!(super::verify_proof(&pp, pk, &bad, &pizkp, b""), Err(points));
+        let mut bad = pikp.clone();
+        bad.a_bar = G1Affine::new_unchecked(pikp.a_bar.x, pikp.a_bar.y + Fq::from(1u64));
+        assert_eq!(super::verify_proof(&pp, pk, &bad, &pizkp, b""), Err(points));
+        let context = issuer::verifier_context(&pp, pk);
+        assert_eq!(super::verify_proof_with_context(&context, &bad, &pizkp, b""), Err(points));
+
+        // reveal indices out of range, repeated or out of order
+        for (open, idx) in [(vec![0, 3, 10], 10), (vec![0, 3, 3], 3), (vec![3, 0, 5], 0), (vec![0, usize::MAX, 5], usize::MAX)]{
+            let mut bad = pikp.clone();
+            bad.open = open;
+            assert_eq!(super::verify_proof(&pp, pk, &bad, &pizkp, b""), Err(VerifyError::BadRevealIndex(idx)));
+        }
+        let mut bad = pikp.clone();
+        bad.open = vec![0, 0, 3, 5];
+        bad.message_list.insert(0, messages[0]);
+        assert_eq!(super::verify_proof(&pp, pk, &bad, &pizkp, b""), Err(VerifyError::BadRevealIndex(0)));
+        assert_eq!(super::prove(&pp, &signature, &messages, &vec![3, 0], b"").unwrap_err(), ProveError::BadRevealIndex(0));
+        assert_eq!(super::prove(&pp, &signature, &messages, &vec![3, 3], b"").unwrap_err(), ProveError::BadRevealIndex(3));
+
+        // lengths that do not add up
+        let mut bad = pikp.clone();
+        bad.len = usize::MAX;
+        assert_eq!(super::verify_proof(&pp, pk, &bad, &pizkp, b""), Err(VerifyError::MalformedInput("message length exceeds generators")));
+        let mut bad = pikp.clone();
+        bad.message_list.pop();
+        assert_eq!(super::verify_proof(&pp, pk, &bad, &pizkp, b""), Err(VerifyError::MalformedInput("revealed messages do not match reveal indices")));
+        let response_count = VerifyError::MalformedInput("response count does not match hidden messages");
+        let mut bad = pizkp.clone();
+        bad.v.pop();
+        assert_eq!(super::verify_proof(&pp, pk, &pikp, &bad, b""), Err(response_count));
+        bad.v.extend([Fr::from(0u64), Fr::from(0u64)]);
+        assert_eq!(super::verify_proof(&pp, pk, &pikp, &bad, b""), Err(response_count));
+        let mut bad = pizkp.clone();
+        bad.v.clear();
+        let batch: Vec<(&issuer::PublicKey, &super::PiKP, &super::PiZKP, &[u8])> = vec![(pk, &pikp, &bad, b"")];
+        assert_eq!(super::verify_batch(&pp, &batch), Err(vec![(0, response_count)]));
+        assert_eq!(super::verify_multi(&pp, &[(pk, &pikp, &bad)], &Vec::new(), b""), Err(response_count));
+
+        // the same for credentials
+        let forged = issuer::Signature{ a: G1Affine::zero(), e: signature.e };
+        assert_eq!(issuer::verify(&pp, pk, &messages, &forged), Err(VerifyError::MalformedInput("A is the identity or not in G1")));
     }
 }
\ No newline at end of file
@@ -0,0 +1,125 @@
+use ark_bls12_381::{Bls12_381, G1Affine};
+use ark_ec::{pairing::Pairing, CurveGroup};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::vec::Vec;
+
+use crate::bbs;
+use crate::error::VerifyError;
+
+pub type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+// Scope-exclusive pseudonyms nym = H_G1(scope)^s, s the hidden link secret message. The same
+// holder always shows the same nym to one scope, and nyms of different scopes are unlinkable
+// under DDH. The proof that s is the hidden message is a Schnorr proof T = H_G1(scope)^delta
+// that reuses the caller's blinding and response for that message, like the range proofs.
+// (crypto_docknet/syra derives its pseudonyms with a VRF under an issuer key instead; here
+// the credential signature already binds s to the holder.)
+//
+// Sign convention: responses here are blinding + c * witness. Callers whose responses are
+// blinding - c * witness pass -c.
+
+#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+pub struct ScopedPseudonym{
+    pub index: usize,       // the link secret message
+    pub scope: Vec<u8>,
+    pub nym: G1Affine,
+}
+
+pub fn scope_base(scope: &[u8]) -> G1Affine{
+    return bbs::hash_to_g1(scope, b"MY_PSEUDONYM_SCOPE_DST_V1")
+}
+
+// What a holder with link secret s is known as in scope.
+pub fn pseudonym(scope: &[u8], link_secret: &Fr) -> G1Affine{
+    return (scope_base(scope) * link_secret).into_affine()
+}
+
+// First move. blinding is the one the caller's proof uses for the link secret. Returns the
+// pseudonym and the T value the caller hashes into its challenge.
+pub fn pseudonym_commit(index: usize, scope: &[u8], link_secret: &Fr, blinding: &Fr) -> (ScopedPseudonym, G1Affine){
+    let base = scope_base(scope);
+    let pseudonym = ScopedPseudonym{
+        index: index,
+        scope: scope.to_vec(),
+        nym: (base * link_secret).into_affine(),
+    };
+    return (pseudonym, (base * blinding).into_affine())
+}
+
+// Recomputes T from the caller's response for the link secret. An identity nym, from a zero
+// link secret, would be the same in every scope and is rejected.
+pub fn pseudonym_verify(pseudonym: &ScopedPseudonym, response: &Fr, c: &Fr) -> Result<G1Affine, VerifyError>{
+    if !bbs::valid_points(&[pseudonym.nym]) {
+        return Err(VerifyError::MalformedInput("pseudonym is the identity or not in G1"));
+    }
+    return Ok((scope_base(&pseudonym.scope) * response - pseudonym.nym * c).into_affine())
+}
+
+// The nym of a presentation, once it is known to be for scope and derived from the message
+// the verifier takes as the link secret. Without the index check a holder could derive a
+// fresh nym for the same scope from any other hidden message.
+pub fn expect_pseudonym(pseudonym: &Option<ScopedPseudonym>, index: usize, scope: &[u8]) -> Result<G1Affine, VerifyError>{
+    return match pseudonym{
+        Some(pseudonym) if pseudonym.index == index && pseudonym.scope == scope => Ok(pseudonym.nym),
+        _ => Err(VerifyError::PseudonymMismatch),
+    }
+}
+
+// What a presentation hashes into its challenge for its pseudonym. Nothing when it has none,
+// so presentations without a pseudonym hash what they did before.
+pub fn serialize_challenge_input(pseudonym: &Option<ScopedPseudonym>, t: &Option<G1Affine>, buffer: &mut Vec<u8>){
+    if let (Some(pseudonym), Some(t)) = (pseudonym, t) {
+        pseudonym.serialize_compressed(&mut *buffer).unwrap();
+        t.serialize_compressed(&mut *buffer).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fr;
+    use ark_bls12_381::G1Affine;
+    use ark_ec::AffineRepr;
+    use crate::error::{ProveError, VerifyError};
+    use crate::{issuer, proof};
+    use ark_std::{UniformRand, vec::Vec};
+    use rand::thread_rng;
+
+    #[test]
+    fn it_works(){
+        // two credentials from two issuers, both carrying the holder's link secret at index 0
+        let mut rng = thread_rng();
+        let link_secret = Fr::rand(&mut rng);
+        let pp = issuer::par_gen(3);
+        let keypairs: Vec<issuer::KeyPair> = (0..2).map(|_| issuer::key_gen(&pp)).collect();
+        let messages: Vec<Vec<Fr>> = (0..2).map(|_| vec![link_secret, Fr::rand(&mut rng), Fr::rand(&mut rng)]).collect();
+        let signatures: Vec<issuer::Signature> = (0..2).map(|j| issuer::sign(&pp, &keypairs[j].secret_key, &messages[j]).unwrap()).collect();
+        let reveal_index = vec![2];
+        let nym = |j: usize, scope: &[u8]| {
+            let (pikp, pizkp) = proof::prove_with_pseudonym(&pp, &signatures[j], &messages[j], &reveal_index, &Vec::new(), 0, scope, b"").unwrap();
+            return proof::verify_proof_with_pseudonym(&pp, &keypairs[j].public_key, &pikp, &pizkp, 0, scope, b"").unwrap()
+        };
+        // stable within a scope, across presentations and credentials, and different across scopes
+        assert_eq!(nym(0, b"shop.example"), super::pseudonym(b"shop.example", &link_secret));
+        assert_eq!(nym(0, b"shop.example"), nym(1, b"shop.example"));
+        assert_ne!(nym(0, b"shop.example"), nym(0, b"bank.example"));
+
+        let (pikp, pizkp) = proof::prove_with_pseudonym(&pp, &signatures[0], &messages[0], &reveal_index, &Vec::new(), 0, b"shop.example", b"").unwrap();
+        assert_eq!(proof::verify_proof(&pp, &keypairs[0].public_key, &pikp, &pizkp, b""), Ok(()));
+        assert_eq!(proof::verify_proof_with_pseudonym(&pp, &keypairs[0].public_key, &pikp, &pizkp, 0, b"bank.example", b""), Err(VerifyError::PseudonymMismatch));
+        assert_eq!(proof::verify_proof_with_pseudonym(&pp, &keypairs[0].public_key, &pikp, &pizkp, 1, b"shop.example", b""), Err(VerifyError::PseudonymMismatch));
+        let (plain_pikp, plain_pizkp) = proof::prove(&pp, &signatures[0], &messages[0], &reveal_index, b"").unwrap();
+        assert_eq!(proof::verify_proof_with_pseudonym(&pp, &keypairs[0].public_key, &plain_pikp, &plain_pizkp, 0, b"shop.example", b""), Err(VerifyError::PseudonymMismatch));
+
+        // the nym is bound to the hidden link secret
+        let mut swapped = pikp.clone();
+        swapped.pseudonym.as_mut().unwrap().nym = super::pseudonym(b"shop.example", &Fr::rand(&mut rng));
+        assert_eq!(proof::verify_proof(&pp, &keypairs[0].public_key, &swapped, &pizkp, b""), Err(VerifyError::ChallengeMismatch));
+        swapped.pseudonym.as_mut().unwrap().nym = G1Affine::zero();
+        assert_eq!(proof::verify_proof(&pp, &keypairs[0].public_key, &swapped, &pizkp, b""), Err(VerifyError::MalformedInput("pseudonym is the identity or not in G1")));
+        let mut moved = pikp.clone();
+        moved.pseudonym.as_mut().unwrap().index = 1;
+        assert_eq!(proof::verify_proof(&pp, &keypairs[0].public_key, &moved, &pizkp, b""), Err(VerifyError::ChallengeMismatch));
+        let result = proof::prove_with_pseudonym(&pp, &signatures[0], &messages[0], &vec![0], &Vec::new(), 0, b"shop.example", b"");
+        assert_eq!(result.unwrap_err(), ProveError::BadRevealIndex(0));
+    }
+}
@@ -0,0 +1,240 @@
+use ark_bls12_381::{Bls12_381, G1Affine, G1Projective};
+use ark_bls12_381_04 as bls04;
+use ark_ec::{pairing::Pairing, CurveGroup};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_serialize_04 as serialize04;
+use ark_std::{UniformRand, vec::Vec};
+use blake2::Blake2b512;
+use bulletproofs_plus_plus::prelude::{ProofArbitraryRange, SetupParams};
+use dock_crypto_utils::transcript::new_merlin_transcript;
+use rand::{CryptoRng, RngCore};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use crate::error::{ProveError, SchemaError, VerifyError};
+use crate::schema::{self, Date};
+
+pub type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+// Range predicates min <= m < max on hidden messages. Each predicate is a Bulletproofs++
+// arbitrary range proof over two Pedersen commitments, G^{m - min} H^{r_1} and
+// G^{max - 1 - m} H^{r_2}, plus a Schnorr proof that the m in both is the hidden message.
+// The Schnorr part reuses the caller's blinding and response for that message under the
+// caller's challenge, so a presentation and its predicates share one Fiat-Shamir challenge.
+//
+// The Bulletproofs++ crate is on arkworks 0.4. Points and scalars cross over through their
+// compressed encodings, which are the same in both versions.
+//
+// Sign convention: responses here are blinding + c * witness. Callers whose responses are
+// blinding - c * witness pass -c.
+
+pub const RANGE_BITS: u16 = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+pub struct RangePredicate{
+    pub index: usize,
+    pub min: u64,
+    pub max: u64,   // exclusive
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default, CanonicalDeserialize, CanonicalSerialize)]
+pub struct RangeProof{
+    pub commitments: Vec<G1Affine>,  // V_{2k} = G^{m - min_k} H^{r_2k}, V_{2k+1} = G^{max_k - 1 - m} H^{r_2k+1}
+    pub bulletproof: Vec<u8>,        // arkworks 0.4 encoding of the Bulletproofs++ proof
+    pub z_r: Vec<Fr>,
+}
+
+// Kept by the prover between range_commit and range_respond, and wiped on drop.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct RangeWitness{
+    randomness: Vec<Fr>,    // r_{2k}, -r_{2k+1}: the randomness of the commitments to m itself
+    blindings: Vec<Fr>,
+}
+
+// min <= value < max on a schema Integer attribute.
+pub fn integer_range(index: usize, min: i64, max: i64) -> RangePredicate{
+    return RangePredicate{ index: index, min: schema::encoded_integer(min), max: schema::encoded_integer(max) }
+}
+
+// min <= value < max on a schema Date attribute.
+pub fn date_range(index: usize, min: &Date, max: &Date) -> Result<RangePredicate, SchemaError>{
+    let min = schema::days_from_date(min)?;
+    let max = schema::days_from_date(max)?;
+    return Ok(integer_range(index, min, max))
+}
+
+// Position of every predicate's message among the hidden indices, or the first predicate
+// index that is not hidden.
+pub fn predicate_positions(predicates: &[RangePredicate], hidden_index: &[usize]) -> Result<Vec<usize>, usize>{
+    return predicates.iter()
+        .map(|p| hidden_index.iter().position(|&i| i == p.index).ok_or(p.index))
+        .collect()
+}
+
+fn fr_to_04(x: &Fr) -> bls04::Fr{
+    let mut buffer = Vec::new();
+    x.serialize_compressed(&mut buffer).unwrap();
+    return serialize04::CanonicalDeserialize::deserialize_compressed(&buffer[..]).unwrap()
+}
+
+fn g1_to_04(p: &G1Affine) -> bls04::G1Affine{
+    let mut buffer = Vec::new();
+    p.serialize_compressed(&mut buffer).unwrap();
+    return serialize04::CanonicalDeserialize::deserialize_compressed_unchecked(&buffer[..]).unwrap()
+}
+
+fn g1_from_04(p: &bls04::G1Affine) -> G1Affine{
+    let mut buffer = Vec::new();
+    serialize04::CanonicalSerialize::serialize_compressed(p, &mut buffer).unwrap();
+    return G1Affine::deserialize_compressed_unchecked(&buffer[..]).unwrap()
+}
+
+fn setup(num_predicates: usize) -> SetupParams<bls04::G1Affine>{
+    return SetupParams::new_for_arbitrary_range_proof::<Blake2b512>(b"MY_RANGE_PROOF_SETUP_V1", 2, RANGE_BITS, num_predicates as u32)
+}
+
+// G and H of the commitments, in arkworks 0.5.
+fn pedersen_key(setup: &SetupParams<bls04::G1Affine>) -> (G1Affine, G1Affine){
+    let (g, h) = setup.get_pedersen_commitment_key();
+    return (g1_from_04(&g), g1_from_04(&h))
+}
+
+fn message_to_u64(message: &Fr) -> Option<u64>{
+    let limbs = message.into_bigint().0;
+    if limbs[1..].iter().any(|&limb| limb != 0){
+        return None;
+    }
+    return Some(limbs[0])
+}
+
+// First move. messages[k] and blindings[k] are the message predicates[k] is about and the
+// blinding the caller's proof uses for it. Returns the proof without responses, the witness
+// for range_respond and the T values the caller hashes into its challenge.
+pub fn range_commit_with_rng<R: RngCore + CryptoRng>(
+    predicates: &[RangePredicate],
+    messages: &[Fr],
+    blindings: &[Fr],
+    rng: &mut R,
+) -> Result<(RangeProof, RangeWitness, Vec<G1Affine>), ProveError>{
+    let mut witness = RangeWitness{ randomness: Vec::new(), blindings: Vec::new() };
+    if predicates.is_empty() {
+        return Ok((RangeProof::default(), witness, Vec::new()));
+    }
+    let mut values_and_bounds = Vec::new();
+    for k in 0..predicates.len(){
+        let p = &predicates[k];
+        if p.min >= p.max {
+            return Err(ProveError::MalformedInput("range predicate with min >= max"));
+        }
+        let value = message_to_u64(&messages[k]).filter(|v| p.min <= *v && *v < p.max).ok_or(ProveError::PredicateNotSatisfied(k))?;
+        values_and_bounds.push((value, p.min, p.max));
+    }
+
+    let setup = setup(predicates.len());
+    let (g, h) = pedersen_key(&setup);
+    let r: Zeroizing<Vec<Fr>> = Zeroizing::new((0..2 * predicates.len()).map(|_| Fr::rand(rng)).collect());
+    let r_04 = r.iter().map(fr_to_04).collect();
+    let mut transcript = new_merlin_transcript(b"MY_RANGE_PROOF_V1");
+    let bulletproof = ProofArbitraryRange::new(rng, RANGE_BITS, values_and_bounds, r_04, setup, &mut transcript)
+        .map_err(|_| ProveError::MalformedInput("range proof failed"))?;
+
+    let mut t_pro = Vec::new();
+    for j in 0..r.len(){
+        // V_{2k} + G^min commits to m with r_{2k}, G^{max-1} - V_{2k+1} commits to m with -r_{2k+1}
+        witness.randomness.push(if j % 2 == 0 { r[j] } else { -r[j] });
+        witness.blindings.push(Fr::rand(rng));
+        t_pro.push(g * blindings[j / 2] + h * witness.blindings[j]);
+    }
+    let mut buffer = Vec::new();
+    serialize04::CanonicalSerialize::serialize_compressed(&bulletproof.proof, &mut buffer).unwrap();
+    let proof = RangeProof{
+        commitments: bulletproof.V.iter().map(g1_from_04).collect(),
+        bulletproof: buffer,
+        z_r: Vec::new(),
+    };
+    return Ok((proof, witness, G1Projective::normalize_batch(&t_pro)))
+}
+
+pub fn range_respond(proof: &mut RangeProof, witness: &RangeWitness, c: &Fr){
+    proof.z_r = (0..witness.randomness.len()).map(|j| witness.blindings[j] + *c * witness.randomness[j]).collect();
+}
+
+// Checks the Bulletproofs++ proof and recomputes the T values from the responses.
+// responses[k] is the caller's response for the message of predicates[k].
+pub fn range_verify(
+    predicates: &[RangePredicate],
+    proof: &RangeProof,
+    responses: &[Fr],
+    c: &Fr,
+) -> Result<Vec<G1Affine>, VerifyError>{
+    if proof.commitments.len() != 2 * predicates.len() || proof.z_r.len() != 2 * predicates.len() {
+        return Err(VerifyError::MalformedInput("range proof does not match its predicates"));
+    }
+    if responses.len() != predicates.len() {
+        return Err(VerifyError::MalformedInput("one response per range predicate"));
+    }
+    if predicates.is_empty() {
+        return Ok(Vec::new());
+    }
+    if predicates.iter().any(|p| p.min >= p.max) {
+        return Err(VerifyError::MalformedInput("range predicate with min >= max"));
+    }
+
+    let setup = setup(predicates.len());
+    let (g, h) = pedersen_key(&setup);
+    let bulletproof = ProofArbitraryRange{
+        V: proof.commitments.iter().map(g1_to_04).collect(),
+        proof: serialize04::CanonicalDeserialize::deserialize_compressed(&proof.bulletproof[..]).map_err(|_| VerifyError::MalformedInput("range proof encoding"))?,
+    };
+    let mut transcript = new_merlin_transcript(b"MY_RANGE_PROOF_V1");
+    if bulletproof.verify(RANGE_BITS, &setup, &mut transcript).is_err() {
+        return Err(VerifyError::RangeProofFailed);
+    }
+
+    let mut t_pro = Vec::new();
+    for k in 0..predicates.len(){
+        let commitment_min = proof.commitments[2 * k] + g * Fr::from(predicates[k].min);
+        let commitment_max = g * Fr::from(predicates[k].max - 1) - proof.commitments[2 * k + 1];
+        t_pro.push(g * responses[k] + h * proof.z_r[2 * k] - commitment_min * *c);
+        t_pro.push(g * responses[k] + h * proof.z_r[2 * k + 1] - commitment_max * *c);
+    }
+    return Ok(G1Projective::normalize_batch(&t_pro))
+}
+
+// What a presentation hashes into its challenge for its predicates.
+pub fn serialize_challenge_input(predicates: &[RangePredicate], proof: &RangeProof, t: &[G1Affine], buffer: &mut Vec<u8>){
+    predicates.to_vec().serialize_compressed(&mut *buffer).unwrap();
+    proof.commitments.serialize_compressed(&mut *buffer).unwrap();
+    proof.bulletproof.serialize_compressed(&mut *buffer).unwrap();
+    t.to_vec().serialize_compressed(&mut *buffer).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::thread_rng;
+
+    use super::*;
+
+    #[test]
+    fn rejects_malformed_range_proofs() {
+        let mut rng = thread_rng();
+        let predicates = vec![RangePredicate{ index: 0, min: 10, max: 20 }];
+        let message = Fr::from(15u64);
+        let blinding = Fr::rand(&mut rng);
+        let c = Fr::rand(&mut rng);
+        let (mut proof, witness, t) = range_commit_with_rng(&predicates, &[message], &[blinding], &mut rng).unwrap();
+        range_respond(&mut proof, &witness, &c);
+        let responses = vec![blinding + c * message];
+        assert_eq!(range_verify(&predicates, &proof, &responses, &c), Ok(t));
+
+        let mut tampered = proof.clone();
+        let middle = tampered.bulletproof.len() / 2;
+        tampered.bulletproof[middle] ^= 1;
+        assert!(range_verify(&predicates, &tampered, &responses, &c).is_err());
+        assert_eq!(range_verify(&predicates, &proof, &[], &c), Err(VerifyError::MalformedInput("one response per range predicate")));
+
+        let empty = vec![RangePredicate{ index: 0, min: 20, max: 20 }];
+        assert_eq!(range_commit_with_rng(&empty, &[message], &[blinding], &mut rng).map(|_| ()), Err(ProveError::MalformedInput("range predicate with min >= max")));
+        assert_eq!(range_verify(&empty, &proof, &responses, &c), Err(VerifyError::MalformedInput("range predicate with min >= max")));
+    }
+}
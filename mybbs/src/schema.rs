@@ -0,0 +1,266 @@
+use ark_bls12_381::Bls12_381;
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_std::vec::Vec;
+
+use crate::bbs;
+use crate::error::SchemaError;
+
+pub type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+// Typed attributes and their encoding as messages. Integers and dates are encoded as
+// value + 2^63, an order-preserving map onto [0, 2^INTEGER_BITS), so a range statement on
+// the value is the same range statement on the message. Strings and bytes are hashed
+// to Fr, so they can be compared against an expected value but not decoded.
+
+pub const INTEGER_BITS: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AttributeType{
+    Utf8String,
+    Integer,
+    Date,
+    Boolean,
+    Bytes,
+    LinkSecret,     // random per holder, never revealed
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Attribute{
+    pub name: String,
+    pub attribute_type: AttributeType,
+}
+
+// The message at index i of a credential is attributes[i].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CredentialSchema{
+    pub id: String,
+    pub attributes: Vec<Attribute>,
+}
+
+// A proleptic Gregorian calendar date.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Date{
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum AttributeValue{
+    Utf8String(String),
+    Integer(i64),
+    Date(Date),
+    Boolean(bool),
+    Bytes(Vec<u8>),
+    LinkSecret(Fr),
+}
+
+pub fn value_type(value: &AttributeValue) -> AttributeType{
+    return match value{
+        AttributeValue::Utf8String(_) => AttributeType::Utf8String,
+        AttributeValue::Integer(_) => AttributeType::Integer,
+        AttributeValue::Date(_) => AttributeType::Date,
+        AttributeValue::Boolean(_) => AttributeType::Boolean,
+        AttributeValue::Bytes(_) => AttributeType::Bytes,
+        AttributeValue::LinkSecret(_) => AttributeType::LinkSecret,
+    }
+}
+
+// The encoded integer as a u64, the form range predicates take their bounds in.
+pub fn encoded_integer(value: i64) -> u64{
+    // flipping the sign bit is value + 2^63 mod 2^64
+    return (value as u64) ^ (1u64 << 63)
+}
+
+pub fn encode_integer(value: i64) -> Fr{
+    return Fr::from(encoded_integer(value))
+}
+
+pub fn decode_integer(message: &Fr) -> Result<i64, SchemaError>{
+    let limbs = message.into_bigint().0;
+    if limbs[1..].iter().any(|&limb| limb != 0){
+        return Err(SchemaError::OutOfRange(AttributeType::Integer));
+    }
+    return Ok((limbs[0] ^ (1u64 << 63)) as i64)
+}
+
+// Days since 1970-01-01, H. Hinnant's days_from_civil.
+pub fn days_from_date(date: &Date) -> Result<i64, SchemaError>{
+    if date.month < 1 || date.month > 12 || date.day < 1 || date.day > 31 {
+        return Err(SchemaError::InvalidDate);
+    }
+    let (month, day) = (date.month as i64, date.day as i64);
+    let year = date.year as i64 - if month <= 2 { 1 } else { 0 };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    // rejects the 31st of a 30-day month, February 29th of a common year and so on
+    if date_from_days(days) != Some(*date) {
+        return Err(SchemaError::InvalidDate);
+    }
+    return Ok(days)
+}
+
+pub fn date_from_days(days: i64) -> Option<Date>{
+    let z = days.checked_add(719468)?;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    let year = i32::try_from(year).ok()?;
+    return Some(Date{ year: year, month: month as u8, day: day as u8 })
+}
+
+pub fn encode_value(value: &AttributeValue) -> Result<Fr, SchemaError>{
+    let message = match value{
+        AttributeValue::Utf8String(s) => bbs::hash_to_fr(s.as_bytes(), b"MY_SCHEMA_UTF8_STRING_DST_V1"),
+        AttributeValue::Integer(i) => encode_integer(*i),
+        AttributeValue::Date(date) => encode_integer(days_from_date(date)?),
+        AttributeValue::Boolean(b) => Fr::from(*b as u64),
+        AttributeValue::Bytes(bytes) => bbs::hash_to_fr(bytes, b"MY_SCHEMA_BYTES_DST_V1"),
+        AttributeValue::LinkSecret(secret) => *secret,
+    };
+    return Ok(message)
+}
+
+pub fn decode_value(attribute_type: AttributeType, message: &Fr) -> Result<AttributeValue, SchemaError>{
+    let value = match attribute_type{
+        AttributeType::Integer => AttributeValue::Integer(decode_integer(message)?),
+        AttributeType::Date => {
+            let days = decode_integer(message).map_err(|_| SchemaError::OutOfRange(attribute_type))?;
+            AttributeValue::Date(date_from_days(days).ok_or(SchemaError::OutOfRange(attribute_type))?)
+        },
+        AttributeType::Boolean => {
+            if *message == Fr::from(0u64) {
+                AttributeValue::Boolean(false)
+            } else if *message == Fr::from(1u64) {
+                AttributeValue::Boolean(true)
+            } else {
+                return Err(SchemaError::OutOfRange(attribute_type));
+            }
+        },
+        AttributeType::LinkSecret => AttributeValue::LinkSecret(*message),
+        AttributeType::Utf8String | AttributeType::Bytes => return Err(SchemaError::NotDecodable(attribute_type)),
+    };
+    return Ok(value)
+}
+
+pub fn check_schema(schema: &CredentialSchema) -> Result<(), SchemaError>{
+    for i in 0..schema.attributes.len(){
+        if schema.attributes[..i].iter().any(|attr| attr.name == schema.attributes[i].name){
+            return Err(SchemaError::DuplicateAttribute(i));
+        }
+    }
+    return Ok(())
+}
+
+pub fn attribute_index(schema: &CredentialSchema, name: &str) -> Option<usize>{
+    return schema.attributes.iter().position(|attr| attr.name == name)
+}
+
+// The messages of a credential, one value per attribute in schema order.
+pub fn encode_messages(schema: &CredentialSchema, values: &Vec<AttributeValue>) -> Result<Vec<Fr>, SchemaError>{
+    check_schema(schema)?;
+    if values.len() != schema.attributes.len() {
+        return Err(SchemaError::WrongValueCount { values: values.len(), attributes: schema.attributes.len() });
+    }
+    let mut messages = Vec::new();
+    for i in 0..values.len(){
+        if value_type(&values[i]) != schema.attributes[i].attribute_type {
+            return Err(SchemaError::TypeMismatch(i));
+        }
+        messages.push(encode_value(&values[i])?);
+    }
+    return Ok(messages)
+}
+
+// Sorted message indices of the named attributes, for the reveal_index of a presentation.
+// An unknown name is reported by its position in names.
+pub fn reveal_index(schema: &CredentialSchema, names: &[&str]) -> Result<Vec<usize>, SchemaError>{
+    check_schema(schema)?;
+    let mut index = Vec::new();
+    for i in 0..names.len(){
+        let idx = attribute_index(schema, names[i]).ok_or(SchemaError::UnknownAttribute(i))?;
+        if schema.attributes[idx].attribute_type == AttributeType::LinkSecret {
+            return Err(SchemaError::RevealedLinkSecret(idx));
+        }
+        index.push(idx);
+    }
+    index.sort();
+    index.dedup();
+    return Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Attribute, AttributeType, AttributeValue, CredentialSchema, Date, Fr};
+    use crate::error::SchemaError;
+    use crate::{issuer, proof};
+    use ark_std::UniformRand;
+    use rand::thread_rng;
+
+    #[test]
+    fn it_works(){
+        let attribute = |name: &str, attribute_type| Attribute{ name: name.to_string(), attribute_type: attribute_type };
+        let schema = CredentialSchema{
+            id: "employee".to_string(),
+            attributes: vec![
+                attribute("name", AttributeType::Utf8String),
+                attribute("age", AttributeType::Integer),
+                attribute("expiry", AttributeType::Date),
+                attribute("manager", AttributeType::Boolean),
+                attribute("photo", AttributeType::Bytes),
+                attribute("holder", AttributeType::LinkSecret),
+            ],
+        };
+        let values = vec![
+            AttributeValue::Utf8String("a name far longer than thirty-two bytes, which used to wrap mod p".to_string()),
+            AttributeValue::Integer(-42),
+            AttributeValue::Date(Date{ year: 2030, month: 2, day: 28 }),
+            AttributeValue::Boolean(true),
+            AttributeValue::Bytes(vec![0xff; 100]),
+            AttributeValue::LinkSecret(Fr::rand(&mut thread_rng())),
+        ];
+        let messages = super::encode_messages(&schema, &values).unwrap();
+        for i in 1..4{
+            assert_eq!(super::decode_value(schema.attributes[i].attribute_type, &messages[i]), Ok(values[i].clone()));
+        }
+        assert_eq!(super::decode_value(AttributeType::Utf8String, &messages[0]), Err(SchemaError::NotDecodable(AttributeType::Utf8String)));
+
+        // integers and dates keep their order
+        assert!(super::encode_integer(i64::MIN) < super::encode_integer(-1));
+        assert!(super::encode_integer(-1) < super::encode_integer(0));
+        assert!(super::encode_integer(0) < super::encode_integer(i64::MAX));
+        assert_eq!(super::decode_integer(&super::encode_integer(i64::MIN)), Ok(i64::MIN));
+        assert_eq!(super::days_from_date(&Date{ year: 1970, month: 1, day: 1 }), Ok(0));
+        assert_eq!(super::days_from_date(&Date{ year: 2000, month: 3, day: 1 }), Ok(11017));
+        assert_eq!(super::days_from_date(&Date{ year: 2023, month: 2, day: 29 }), Err(SchemaError::InvalidDate));
+        assert_eq!(super::decode_integer(&-Fr::from(1u64)), Err(SchemaError::OutOfRange(AttributeType::Integer)));
+
+        let mut wrong = values.clone();
+        wrong[1] = AttributeValue::Boolean(false);
+        assert_eq!(super::encode_messages(&schema, &wrong), Err(SchemaError::TypeMismatch(1)));
+        assert_eq!(super::encode_messages(&schema, &values[..2].to_vec()), Err(SchemaError::WrongValueCount { values: 2, attributes: 6 }));
+        assert_eq!(super::reveal_index(&schema, &["expiry", "name"]), Ok(vec![0, 2]));
+        assert_eq!(super::reveal_index(&schema, &["name", "salary"]), Err(SchemaError::UnknownAttribute(1)));
+        assert_eq!(super::reveal_index(&schema, &["holder"]), Err(SchemaError::RevealedLinkSecret(5)));
+        let mut duplicated = schema.clone();
+        duplicated.attributes.push(attribute("age", AttributeType::Integer));
+        assert_eq!(super::check_schema(&duplicated), Err(SchemaError::DuplicateAttribute(6)));
+
+        // the encoded messages sign and present like any other
+        let pp = issuer::par_gen(messages.len());
+        let keypair = issuer::key_gen(&pp);
+        let signature = issuer::sign(&pp, &keypair.secret_key, &messages).unwrap();
+        let reveal_index = super::reveal_index(&schema, &["age", "manager"]).unwrap();
+        let (pikp, pizkp) = proof::prove(&pp, &signature, &messages, &reveal_index, b"").unwrap();
+        assert_eq!(proof::verify_proof(&pp, &keypair.public_key, &pikp, &pizkp, b""), Ok(()));
+    }
+}
@@ -2,10 +2,11 @@ use ark_bls12_381::{G1Affine, G2Affine, Bls12_381};
 use ark_ff::Field;
 use ark_ec::pairing::Pairing;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::{fmt::Debug, UniformRand, vec::Vec};
-use rand::thread_rng;
+use ark_std::{fmt::Debug, UniformRand, vec::Vec, Zero};
+use rand::{rngs::OsRng, CryptoRng, RngCore};
 
 use crate::bbs;
+use crate::error::VerifyError;
 
 pub type Fr = <Bls12_381 as Pairing>::ScalarField;
 
@@ -18,7 +19,8 @@ pub struct PublicParameters{
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
 pub struct PublicKey(pub G1Affine, pub G2Affine);
 
-#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+// Not serializable: the secret key leaves only through bbs::secret_key_to_bytes.
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct KeyPair {
     pub secret_key: bbs::SecretKey,
     pub public_key: PublicKey,
@@ -45,10 +47,9 @@ pub fn par_gen() -> PublicParameters{
     return pp
 }
 
-pub fn key_gen(pp: &PublicParameters) -> KeyPair{
+pub fn key_gen_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, rng: &mut R) -> KeyPair{
     // sk \stackrel{\$}{\leftarrow} Z_p^*
-    let mut rng = thread_rng();
-    let sk_element = Fr::rand(&mut rng);
+    let sk_element = Fr::rand(rng);
     let sk = bbs::SecretKey(
         sk_element
     );
@@ -71,10 +72,13 @@ pub fn key_gen(pp: &PublicParameters) -> KeyPair{
     return keypair
 }
 
-pub fn sign(pp: &PublicParameters, sk: &bbs::SecretKey, messages: &G2Affine) -> Signature{
-    let mut rng = thread_rng();
+pub fn key_gen(pp: &PublicParameters) -> KeyPair{
+    return key_gen_with_rng(pp, &mut OsRng)
+}
+
+pub fn sign_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, sk: &bbs::SecretKey, messages: &G2Affine, rng: &mut R) -> Signature{
     // e \stackrel{\$}{\leftarrow} Z_p^*
-    let e = Fr::rand(&mut rng);
+    let e = Fr::rand(rng);
 
     // compute A = (g2 * ipk)^{1/(sk + e)}
     let sk_plus_e = sk.0 + e;
@@ -89,7 +93,14 @@ pub fn sign(pp: &PublicParameters, sk: &bbs::SecretKey, messages: &G2Affine) ->
     return signature
 }
 
-pub fn verify(pp: &PublicParameters, pk: &PublicKey, message: &G2Affine, signature: &Signature) -> bool{
+pub fn sign(pp: &PublicParameters, sk: &bbs::SecretKey, messages: &G2Affine) -> Signature{
+    return sign_with_rng(pp, sk, messages, &mut OsRng)
+}
+
+pub fn verify(pp: &PublicParameters, pk: &PublicKey, message: &G2Affine, signature: &Signature) -> Result<(), VerifyError>{
+    if !bbs::valid_points(&[signature.a]) {
+        return Err(VerifyError::MalformedInput("A is the identity or not in G2"));
+    }
 
     // verify e(gbar1^e * vpk, a) = e(gbar1, gbar2 * M)
     let pk_1: G1Affine = pk.0;
@@ -97,13 +108,10 @@ pub fn verify(pp: &PublicParameters, pk: &PublicKey, message: &G2Affine, signatu
     let left_q = signature.a;
     let right_p = pp.gbar1;
     let right_q = G2Affine::from(pp.gbar2 + message);
-    let left = Bls12_381::pairing(left_p, left_q);
-    let right = Bls12_381::pairing(right_p, right_q);
-    if left != right {
-        println!("Verification failed");
-        return false;
+    if !Bls12_381::multi_pairing([left_p, -right_p], [left_q, right_q]).is_zero() {
+        return Err(VerifyError::PairingCheckFailed(1));
     }
-    return true;
+    return Ok(());
 }
 
 #[cfg(test)]
@@ -113,11 +121,11 @@ mod tests {
     fn it_works(){
         use crate::issuer;
         let pp = super::par_gen();
-        let pp_issuer = issuer::par_gen();
+        let pp_issuer = issuer::par_gen(0);
         let issuerkeypair = issuer::key_gen(&pp_issuer);
         let verifierkeypair = super::key_gen(&pp);
         let signature = super::sign(&pp, &verifierkeypair.secret_key, &issuerkeypair.public_key.0);
         let verify_result = super::verify(&pp, &verifierkeypair.public_key, &issuerkeypair.public_key.0, &signature);
-        assert_eq!(verify_result, true);
+        assert_eq!(verify_result, Ok(()));
     }
 }
\ No newline at end of file
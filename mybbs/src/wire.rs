@@ -0,0 +1,271 @@
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::vec::Vec;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::value::Value as Cbor;
+use serde_json::Value as Json;
+
+use crate::error::WireError;
+
+// Versioned envelope for the keys, credentials and presentations of every scheme crate:
+//
+//   magic "MYCR" | scheme u8 | kind u8 | version u16 big-endian | ciphersuite u8 | payload
+//
+// The payload is the compressed arkworks encoding of the object, validated on decode. That
+// encoding writes usize fields and vector lengths as u64 little-endian, so it is the same on
+// every platform; version 1 fixes it as the payload format. JSON and CBOR carry the same
+// fields by name, the payload as unpadded base64url in JSON and as a byte string in CBOR.
+// Each scheme crate names the headers of its objects in its own wire module. Secret keys have
+// no envelope: they leave their crate only through the explicit *_to_bytes functions.
+
+pub const MAGIC: [u8; 4] = *b"MYCR";
+pub const VERSION: u16 = 1;
+
+const HEADER_LEN: usize = 9;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Scheme{
+    Bbs = 1,
+    Shigeo = 2,
+    Katz = 3,
+    Sanders = 4,
+    Bobolz = 5,
+    Ps = 6,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind{
+    PublicParameters = 1,
+    PublicKey = 2,
+    Signature = 3,
+    TrustedIssuerList = 4,
+    PolicyPublicKey = 5,
+    PolicyProof = 6,
+    Presentation = 7,
+    PolicyUpdate = 8,
+}
+
+// BLS12-381 with SHA-256 under this repository's DSTs. Objects of the IETF ciphersuite
+// module travel as the draft's octet strings instead.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Ciphersuite{
+    Bls12381Sha256 = 1,
+}
+
+const SCHEMES: [(Scheme, &str); 6] = [
+    (Scheme::Bbs, "bbs"),
+    (Scheme::Shigeo, "shigeo"),
+    (Scheme::Katz, "katz"),
+    (Scheme::Sanders, "sanders"),
+    (Scheme::Bobolz, "bobolz"),
+    (Scheme::Ps, "ps"),
+];
+
+const KINDS: [(Kind, &str); 8] = [
+    (Kind::PublicParameters, "public-parameters"),
+    (Kind::PublicKey, "public-key"),
+    (Kind::Signature, "signature"),
+    (Kind::TrustedIssuerList, "trusted-issuer-list"),
+    (Kind::PolicyPublicKey, "policy-public-key"),
+    (Kind::PolicyProof, "policy-proof"),
+    (Kind::Presentation, "presentation"),
+    (Kind::PolicyUpdate, "policy-update"),
+];
+
+const CIPHERSUITES: [(Ciphersuite, &str); 1] = [
+    (Ciphersuite::Bls12381Sha256, "BLS12381_XMD:SHA-256"),
+];
+
+// What an object is: the decoder only accepts envelopes with exactly this header.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Header{
+    pub scheme: Scheme,
+    pub kind: Kind,
+    pub ciphersuite: Ciphersuite,
+}
+
+fn name_of<T: Copy + PartialEq>(table: &[(T, &'static str)], id: T) -> &'static str{
+    return table.iter().find(|(t, _)| *t == id).map(|(_, name)| *name).unwrap()
+}
+
+fn id_of<T: Copy>(table: &[(T, &'static str)], name: &str) -> Option<T>{
+    return table.iter().find(|(_, n)| *n == name).map(|(t, _)| *t)
+}
+
+pub fn encode<T: CanonicalSerialize>(header: &Header, value: &T) -> Vec<u8>{
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(header.scheme as u8);
+    bytes.push(header.kind as u8);
+    bytes.extend_from_slice(&VERSION.to_be_bytes());
+    bytes.push(header.ciphersuite as u8);
+    value.serialize_compressed(&mut bytes).unwrap();
+    return bytes
+}
+
+pub fn decode<T: CanonicalDeserialize>(header: &Header, bytes: &[u8]) -> Result<T, WireError>{
+    if bytes.len() < HEADER_LEN {
+        return Err(WireError::Truncated);
+    }
+    if bytes[0..4] != MAGIC {
+        return Err(WireError::BadMagic);
+    }
+    let version = u16::from_be_bytes([bytes[6], bytes[7]]);
+    if version != VERSION {
+        return Err(WireError::UnsupportedVersion(version));
+    }
+    if bytes[4] != header.scheme as u8 {
+        return Err(WireError::WrongScheme);
+    }
+    if bytes[5] != header.kind as u8 {
+        return Err(WireError::WrongKind);
+    }
+    if bytes[8] != header.ciphersuite as u8 {
+        return Err(WireError::WrongCiphersuite);
+    }
+    let mut payload = &bytes[HEADER_LEN..];
+    let value = T::deserialize_compressed(&mut payload).map_err(|_| WireError::InvalidPayload)?;
+    if !payload.is_empty() {
+        return Err(WireError::TrailingBytes);
+    }
+    return Ok(value)
+}
+
+// The binary envelope the named fields of a JSON or CBOR envelope stand for.
+fn envelope(magic: &str, scheme: &str, kind: &str, version: u64, ciphersuite: &str, payload: &[u8]) -> Result<Vec<u8>, WireError>{
+    if magic.as_bytes() != MAGIC {
+        return Err(WireError::BadMagic);
+    }
+    let version = u16::try_from(version).map_err(|_| WireError::UnsupportedVersion(u16::MAX))?;
+    if version != VERSION {
+        return Err(WireError::UnsupportedVersion(version));
+    }
+    let scheme = id_of(&SCHEMES, scheme).ok_or(WireError::WrongScheme)?;
+    let kind = id_of(&KINDS, kind).ok_or(WireError::WrongKind)?;
+    let ciphersuite = id_of(&CIPHERSUITES, ciphersuite).ok_or(WireError::WrongCiphersuite)?;
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(scheme as u8);
+    bytes.push(kind as u8);
+    bytes.extend_from_slice(&version.to_be_bytes());
+    bytes.push(ciphersuite as u8);
+    bytes.extend_from_slice(payload);
+    return Ok(bytes)
+}
+
+pub fn to_json<T: CanonicalSerialize>(header: &Header, value: &T) -> String{
+    let bytes = encode(header, value);
+    let json = serde_json::json!({
+        "magic": "MYCR",
+        "scheme": name_of(&SCHEMES, header.scheme),
+        "kind": name_of(&KINDS, header.kind),
+        "version": VERSION,
+        "ciphersuite": name_of(&CIPHERSUITES, header.ciphersuite),
+        "payload": URL_SAFE_NO_PAD.encode(&bytes[HEADER_LEN..]),
+    });
+    return json.to_string()
+}
+
+pub fn from_json<T: CanonicalDeserialize>(header: &Header, text: &str) -> Result<T, WireError>{
+    let json: Json = serde_json::from_str(text).map_err(|_| WireError::InvalidEncoding("not JSON"))?;
+    let field = |key: &str| json.get(key).and_then(Json::as_str).ok_or(WireError::InvalidEncoding("missing or non-string field"));
+    let version = json.get("version").and_then(Json::as_u64).ok_or(WireError::InvalidEncoding("missing or non-integer version"))?;
+    let payload = URL_SAFE_NO_PAD.decode(field("payload")?).map_err(|_| WireError::InvalidEncoding("payload is not base64url"))?;
+    let bytes = envelope(field("magic")?, field("scheme")?, field("kind")?, version, field("ciphersuite")?, &payload)?;
+    return decode(header, &bytes)
+}
+
+pub fn to_cbor<T: CanonicalSerialize>(header: &Header, value: &T) -> Vec<u8>{
+    let bytes = encode(header, value);
+    let cbor = Cbor::Map(vec![
+        (Cbor::Text("magic".into()), Cbor::Text("MYCR".into())),
+        (Cbor::Text("scheme".into()), Cbor::Text(name_of(&SCHEMES, header.scheme).into())),
+        (Cbor::Text("kind".into()), Cbor::Text(name_of(&KINDS, header.kind).into())),
+        (Cbor::Text("version".into()), Cbor::Integer(VERSION.into())),
+        (Cbor::Text("ciphersuite".into()), Cbor::Text(name_of(&CIPHERSUITES, header.ciphersuite).into())),
+        (Cbor::Text("payload".into()), Cbor::Bytes(bytes[HEADER_LEN..].to_vec())),
+    ]);
+    let mut out = Vec::new();
+    ciborium::ser::into_writer(&cbor, &mut out).unwrap();
+    return out
+}
+
+pub fn from_cbor<T: CanonicalDeserialize>(header: &Header, bytes: &[u8]) -> Result<T, WireError>{
+    let cbor: Cbor = ciborium::de::from_reader(bytes).map_err(|_| WireError::InvalidEncoding("not CBOR"))?;
+    let map = cbor.as_map().ok_or(WireError::InvalidEncoding("not a CBOR map"))?;
+    let get = |key: &str| map.iter().find(|(k, _)| k.as_text() == Some(key)).map(|(_, v)| v);
+    let field = |key: &str| get(key).and_then(Cbor::as_text).ok_or(WireError::InvalidEncoding("missing or non-string field"));
+    let version = get("version").and_then(Cbor::as_integer).and_then(|v| u64::try_from(v).ok()).ok_or(WireError::InvalidEncoding("missing or non-integer version"))?;
+    let payload = get("payload").and_then(Cbor::as_bytes).ok_or(WireError::InvalidEncoding("missing or non-bytes payload"))?;
+    let bytes = envelope(field("magic")?, field("scheme")?, field("kind")?, version, field("ciphersuite")?, payload)?;
+    return decode(header, &bytes)
+}
+
+// mybbs's own objects. The presentation is the (PiKP, PiZKP) pair of proof::prove.
+pub const PUBLIC_PARAMETERS: Header = header(Kind::PublicParameters);
+pub const PUBLIC_KEY: Header = header(Kind::PublicKey);
+pub const SIGNATURE: Header = header(Kind::Signature);
+pub const PRESENTATION: Header = header(Kind::Presentation);
+
+const fn header(kind: Kind) -> Header{
+    return Header{ scheme: Scheme::Bbs, kind: kind, ciphersuite: Ciphersuite::Bls12381Sha256 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{issuer, proof};
+    use ark_bls12_381::Bls12_381;
+    use ark_ec::pairing::Pairing;
+    use ark_std::UniformRand;
+    use rand::thread_rng;
+
+    type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+    #[test]
+    fn it_works(){
+        let mut rng = thread_rng();
+        let pp = issuer::par_gen(4);
+        let keypair = issuer::key_gen(&pp);
+        let messages: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+        let signature = issuer::sign(&pp, &keypair.secret_key, &messages).unwrap();
+        let pt = proof::prove(&pp, &signature, &messages, &vec![1, 3], b"nonce").unwrap();
+
+        // round trips in every encoding
+        let bytes = encode(&PRESENTATION, &pt);
+        assert_eq!(&bytes[..9], &[b'M', b'Y', b'C', b'R', 1, 7, 0, 1, 1]);
+        assert_eq!(decode::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, &bytes), Ok(pt.clone()));
+        let json = to_json(&PRESENTATION, &pt);
+        assert_eq!(from_json::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, &json), Ok(pt.clone()));
+        assert_eq!(from_cbor::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, &to_cbor(&PRESENTATION, &pt)), Ok(pt.clone()));
+        assert_eq!(decode::<issuer::PublicParameters>(&PUBLIC_PARAMETERS, &encode(&PUBLIC_PARAMETERS, &pp)), Ok(pp.clone()));
+        assert_eq!(decode::<issuer::PublicKey>(&PUBLIC_KEY, &encode(&PUBLIC_KEY, &keypair.public_key)), Ok(keypair.public_key.clone()));
+        assert_eq!(from_json::<issuer::Signature>(&SIGNATURE, &to_json(&SIGNATURE, &signature)), Ok(signature.clone()));
+
+        // rejections
+        let katz = Header{ scheme: Scheme::Katz, ..PRESENTATION };
+        assert_eq!(decode::<(proof::PiKP, proof::PiZKP)>(&katz, &bytes), Err(WireError::WrongScheme));
+        assert_eq!(decode::<issuer::PublicKey>(&PUBLIC_KEY, &bytes), Err(WireError::WrongKind));
+        let mut other = bytes.clone();
+        other[0] = b'X';
+        assert_eq!(decode::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, &other), Err(WireError::BadMagic));
+        let mut other = bytes.clone();
+        other[7] = 2;
+        assert_eq!(decode::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, &other), Err(WireError::UnsupportedVersion(2)));
+        let mut other = bytes.clone();
+        other[8] = 7;
+        assert_eq!(decode::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, &other), Err(WireError::WrongCiphersuite));
+        let mut other = bytes.clone();
+        other.push(0);
+        assert_eq!(decode::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, &other), Err(WireError::TrailingBytes));
+        assert_eq!(decode::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, &bytes[..bytes.len() - 1]), Err(WireError::InvalidPayload));
+        assert_eq!(decode::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, &bytes[..5]), Err(WireError::Truncated));
+        let mut other = bytes.clone();
+        other[9] ^= 0x01;   // a_bar off the curve
+        assert_eq!(decode::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, &other), Err(WireError::InvalidPayload));
+
+        assert_eq!(from_json::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, &json.replace("\"bbs\"", "\"shigeo\"")), Err(WireError::WrongScheme));
+        assert_eq!(from_json::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, &json.replace("\"version\":1", "\"version\":70000")), Err(WireError::UnsupportedVersion(u16::MAX)));
+        assert_eq!(from_json::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, "[]"), Err(WireError::InvalidEncoding("missing or non-integer version")));
+        assert_eq!(from_json::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, &json.replace("\"payload\":\"", "\"payload\":\"+")), Err(WireError::InvalidEncoding("payload is not base64url")));
+        assert_eq!(from_cbor::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, json.as_bytes()), Err(WireError::InvalidEncoding("not CBOR")));
+        assert_eq!(from_cbor::<(proof::PiKP, proof::PiZKP)>(&PRESENTATION, &to_cbor(&SIGNATURE, &signature)), Err(WireError::WrongKind));
+    }
+}
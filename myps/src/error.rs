@@ -0,0 +1,25 @@
+use thiserror::Error;
+
+// Reasons a verifier rejects a PS signature or a presentation token.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum VerifyError {
+    #[error("challenge mismatch")]
+    ChallengeMismatch,
+    #[error("pairing check {0} failed")]
+    PairingCheckFailed(usize),
+    #[error("bad reveal index {0}")]
+    BadRevealIndex(usize),
+    #[error("presentation header does not match the expected one")]
+    PresentationHeaderMismatch,
+    #[error("malformed input: {0}")]
+    MalformedInput(&'static str),
+}
+
+// Reasons a signer or holder refuses to produce an output.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum ProveError {
+    #[error("bad reveal index {0}")]
+    BadRevealIndex(usize),
+    #[error("{messages} messages but only {key_len} key elements")]
+    TooManyMessages { messages: usize, key_len: usize },
+}
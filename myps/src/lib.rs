@@ -1 +1,3 @@
-pub mod ps;
\ No newline at end of file
+pub mod error;
+pub mod ps;
+pub mod wire;
\ No newline at end of file
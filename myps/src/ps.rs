@@ -1,10 +1,17 @@
 use ark_bls12_381::{Bls12_381, G1Affine, G1Projective, G2Affine, G2Projective, g1::Config as G1Config, g2::Config as G2Config};
 use ark_ec::{hashing::{curve_maps::wb::WBMap, map_to_curve_hasher::MapToCurveBasedHasher, HashToCurve},pairing::Pairing, CurveGroup};
 use ark_ff::field_hashers::{DefaultFieldHasher, HashToField};
-use ark_std::{fmt::Debug, UniformRand, vec::Vec};
+use ark_std::{fmt::{self, Debug}, UniformRand, Zero, vec::Vec};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use sha2::Sha256;
-use rand::thread_rng;
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use mybbs::bbs;
+use mybbs::error::WireError;
+use mybbs::proof;
+
+use crate::error::{ProveError, VerifyError};
 
 pub type Fr = <Bls12_381 as Pairing>::ScalarField;
 
@@ -14,19 +21,35 @@ pub struct PublicParameters {
     pub g2: G2Affine,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+// Redacted in Debug, compared in constant time and wiped on drop. Outside this crate it is
+// only reachable through secret_key_to_bytes.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
 pub struct SecretKey {
-    pub x: Fr,
-    pub y: Vec<Fr>,
+    pub(crate) x: Fr,
+    pub(crate) y: Vec<Fr>,
+}
+
+impl Debug for SecretKey{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        return f.write_str("SecretKey(<redacted>)")
+    }
 }
 
+impl PartialEq for SecretKey{
+    fn eq(&self, other: &Self) -> bool{
+        return (bbs::ct_eq_scalars(&[self.x], &[other.x]) & bbs::ct_eq_scalars(&self.y, &other.y)).into()
+    }
+}
+
+impl Eq for SecretKey {}
+
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
 pub struct PublicKey {
     pub pk_x: G2Affine,
     pub pk_y: Vec<G2Affine>,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, CanonicalDeserialize, CanonicalSerialize)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct KeyPair {
     pub sk: SecretKey,
     pub pk: PublicKey,
@@ -53,6 +76,7 @@ pub struct PresentationToken{
     pub len: usize,
     pub open_messages: Vec<Fr>,
     pub reveal_index: Vec<usize>,
+    pub presentation_header: Vec<u8>,
 }
 
 pub fn hash_to_fr(input: &[u8], dst: &[u8]) -> Fr {
@@ -91,14 +115,28 @@ pub fn par_gen() -> PublicParameters{
     return pp
 }
 
-pub fn key_gen(pp: &PublicParameters) -> KeyPair{
-    let mut rng = thread_rng();
-    let x = Fr::rand(&mut rng);
-    let y_vec : Vec<Fr> = (0..50).map(|_| Fr::rand(&mut rng)).collect();
+// x || y_1 || ... || y_n as 32-byte little-endian scalars.
+pub fn secret_key_to_bytes(sk: &SecretKey) -> Zeroizing<Vec<u8>>{
+    let mut scalars = Zeroizing::new(vec![sk.x]);
+    scalars.extend_from_slice(&sk.y);
+    return bbs::scalars_to_bytes(&scalars)
+}
+
+pub fn secret_key_from_bytes(bytes: &[u8]) -> Result<SecretKey, WireError>{
+    let scalars = bbs::scalars_from_bytes(bytes)?;
+    if scalars.is_empty() {
+        return Err(WireError::InvalidPayload);
+    }
+    return Ok(SecretKey{ x: scalars[0], y: scalars[1..].to_vec() })
+}
+
+pub fn key_gen_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, message_len: usize, rng: &mut R) -> KeyPair{
+    let x = Fr::rand(rng);
+    let y_vec : Vec<Fr> = (0..message_len).map(|_| Fr::rand(rng)).collect();
 
     let pk_x = G2Affine::from(pp.g2 * x);
     let mut pk_y_pro : Vec<G2Projective> = Vec::new();
-    for i in 0..50{
+    for i in 0..message_len{
         let pk_y_i = pp.g2 * y_vec[i];
         pk_y_pro.push(pk_y_i);
     }
@@ -120,15 +158,35 @@ pub fn key_gen(pp: &PublicParameters) -> KeyPair{
     return keypair
 }
 
-pub fn sign(pp: &PublicParameters, sk: &SecretKey, messages: &Vec<Fr>) -> Signature{
-    let mut rng = thread_rng();
-    let r = Fr::rand(&mut rng);
+pub fn key_gen(pp: &PublicParameters, message_len: usize) -> KeyPair{
+    return key_gen_with_rng(pp, message_len, &mut OsRng)
+}
+
+// Appends fresh y_i to an existing key so it covers message_len messages. Signatures
+// made before stay valid, but the new public key has to be published again.
+pub fn extend_key_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, keypair: &mut KeyPair, message_len: usize, rng: &mut R){
+    while keypair.sk.y.len() < message_len{
+        let y_i = Fr::rand(rng);
+        keypair.sk.y.push(y_i);
+        keypair.pk.pk_y.push(G2Affine::from(pp.g2 * y_i));
+    }
+}
+
+pub fn extend_key(pp: &PublicParameters, keypair: &mut KeyPair, message_len: usize){
+    return extend_key_with_rng(pp, keypair, message_len, &mut OsRng)
+}
+
+pub fn sign_with_rng<R: RngCore + CryptoRng>(pp: &PublicParameters, sk: &SecretKey, messages: &Vec<Fr>, rng: &mut R) -> Result<Signature, ProveError>{
+    let r = Fr::rand(rng);
 
     let message_len = messages.len();
+    if message_len > sk.y.len() {
+        return Err(ProveError::TooManyMessages { messages: message_len, key_len: sk.y.len() });
+    }
 
     let sigma1 = G1Affine::from(pp.g1 * r);
-    let mut temp_element = sk.y[0] * messages[0];
-    for i in 1..message_len{
+    let mut temp_element = Fr::from(0u64);
+    for i in 0..message_len{
         temp_element += sk.y[i] * messages[i];
     }
     let sigma2 = G1Affine::from(pp.g1 * (r * (sk.x + temp_element)));
@@ -137,31 +195,53 @@ pub fn sign(pp: &PublicParameters, sk: &SecretKey, messages: &Vec<Fr>) -> Signat
         sigma1,
         sigma2,
     };
-    return signature
+    return Ok(signature)
+}
+
+pub fn sign(pp: &PublicParameters, sk: &SecretKey, messages: &Vec<Fr>) -> Result<Signature, ProveError>{
+    return sign_with_rng(pp, sk, messages, &mut OsRng)
 }
 
-pub fn verify_sign(pp: &PublicParameters, pk: &PublicKey, signature: &Signature, messages: &Vec<Fr>) -> bool{
+pub fn verify_sign(pp: &PublicParameters, pk: &PublicKey, signature: &Signature, messages: &Vec<Fr>) -> Result<(), VerifyError>{
     let message_len = messages.len();
+    if message_len == 0 || message_len > pk.pk_y.len() {
+        return Err(VerifyError::MalformedInput("message length out of range"));
+    }
+    // sigma1 = sigma2 = 1 would pass for any messages
+    if !bbs::valid_points(&[signature.sigma1, signature.sigma2]) {
+        return Err(VerifyError::MalformedInput("sigma1 or sigma2 is the identity or not in G1"));
+    }
 
     let mut temp_element = pk.pk_y[0] * messages[0];
     for i in 1..message_len{
         temp_element += pk.pk_y[i] * messages[i];
     }
-    let left = Bls12_381::pairing(signature.sigma2, G2Affine::from(pp.g2));
-    let right = Bls12_381::pairing(signature.sigma1, G2Affine::from(pk.pk_x + temp_element));
-
-    return left == right
+    // e(sigma2, g2) = e(sigma1, X + sum Y_i^m_i), checked as one multi-pairing
+    let check = Bls12_381::multi_pairing(
+        [-signature.sigma2, signature.sigma1],
+        [G2Affine::from(pp.g2), G2Affine::from(pk.pk_x + temp_element)],
+    );
+
+    if !check.is_zero(){
+        return Err(VerifyError::PairingCheckFailed(1))
+    }
+    return Ok(())
 }
 
-pub fn create_proof(
+pub fn create_proof_with_rng<R: RngCore + CryptoRng>(
     pp: &PublicParameters,
     pk: &PublicKey,
     cred: &Signature,
     message_list: &Vec<Fr>,
     reveal_index: &Vec<usize>,
-) -> PresentationToken{
-    let mut rng = thread_rng();
+    presentation_header: &[u8],
+    rng: &mut R,
+) -> Result<PresentationToken, ProveError>{
     let message_len = message_list.len();
+    if message_len > pk.pk_y.len() {
+        return Err(ProveError::TooManyMessages { messages: message_len, key_len: pk.pk_y.len() });
+    }
+    proof::check_reveal_index(message_len, reveal_index).map_err(ProveError::BadRevealIndex)?;
     let close_len = message_len - reveal_index.len();
 
     let mut close_index : Vec<usize> = Vec::new();
@@ -175,19 +255,20 @@ pub fn create_proof(
         }
     }
 
-    let r = Fr::rand(&mut rng);
-    let t = Fr::rand(&mut rng);
-    let mut k_i = Vec::new();
+    // wiped once the proof is built
+    let r = Zeroizing::new(Fr::rand(rng));
+    let t = Zeroizing::new(Fr::rand(rng));
+    let mut k_i = Zeroizing::new(Vec::with_capacity(close_len));
     for _ in 0..close_len{
-        let k_i_i = Fr::rand(&mut rng);
+        let k_i_i = Fr::rand(rng);
         k_i.push(k_i_i);
     }
-    let k_t = Fr::rand(&mut rng);
+    let k_t = Zeroizing::new(Fr::rand(rng));
 
-    let new_sigma1 = G1Affine::from(cred.sigma1 * r);
-    let new_sigma2 = G1Affine::from((cred.sigma2 + (cred.sigma1 * t)) * r);
+    let new_sigma1 = G1Affine::from(cred.sigma1 * *r);
+    let new_sigma2 = G1Affine::from((cred.sigma2 + (cred.sigma1 * *t)) * *r);
 
-    let mut k_input = pp.g2 * k_t;
+    let mut k_input = pp.g2 * *k_t;
     for i in 0..close_len{
         k_input += pk.pk_y[close_index[i]] * k_i[i];
     }
@@ -206,6 +287,8 @@ pub fn create_proof(
         open_msg.serialize_compressed(&mut buffer).unwrap();
     }
     k.serialize_compressed(&mut buffer).unwrap();
+    let presentation_header = presentation_header.to_vec();
+    presentation_header.serialize_compressed(&mut buffer).unwrap();
     let c = hash_to_fr(&buffer, dst);
 
     let mut z_i : Vec<Fr> = Vec::new();
@@ -213,7 +296,7 @@ pub fn create_proof(
         let z_i_i = k_i[i] + c * message_list[close_index[i]];
         z_i.push(z_i_i);
     }
-    let z_t = k_t + c * t;
+    let z_t = *k_t + c * *t;
     let proof = Proof{
         z_i,
         z_t,
@@ -226,17 +309,43 @@ pub fn create_proof(
         len: message_len,
         open_messages,
         reveal_index: reveal_index.clone(),
+        presentation_header,
     };
-    return pt
+    return Ok(pt)
+}
+
+pub fn create_proof(
+    pp: &PublicParameters,
+    pk: &PublicKey,
+    cred: &Signature,
+    message_list: &Vec<Fr>,
+    reveal_index: &Vec<usize>,
+    presentation_header: &[u8],
+) -> Result<PresentationToken, ProveError>{
+    return create_proof_with_rng(pp, pk, cred, message_list, reveal_index, presentation_header, &mut OsRng)
 }
 
 pub fn verify_proof(
     pp: &PublicParameters,
     pk: &PublicKey,
     pt: &PresentationToken,
-) -> bool{
+    presentation_header: &[u8],
+) -> Result<(), VerifyError>{
+    if pt.presentation_header != presentation_header {
+        return Err(VerifyError::PresentationHeaderMismatch);
+    }
     let message_len = pt.len;
-    let close_len = message_len - pt.reveal_index.len();
+    if message_len > pk.pk_y.len() {
+        return Err(VerifyError::MalformedInput("message length exceeds key"));
+    }
+    proof::check_reveal_index(message_len, &pt.reveal_index).map_err(VerifyError::BadRevealIndex)?;
+    if pt.open_messages.len() != pt.reveal_index.len() {
+        return Err(VerifyError::MalformedInput("revealed messages do not match reveal indices"));
+    }
+    // with sigma1 = sigma2 = 1 the commitment k is 1 whatever the responses, and any c verifies
+    if !bbs::valid_points(&[pt.sigma1, pt.sigma2]) {
+        return Err(VerifyError::MalformedInput("sigma1 or sigma2 is the identity or not in G1"));
+    }
 
     let mut close_index : Vec<usize> = Vec::new();
     for i in 0..message_len{
@@ -244,6 +353,10 @@ pub fn verify_proof(
             close_index.push(i);
         }
     }
+    let close_len = close_index.len();
+    if pt.proof.z_i.len() != close_len {
+        return Err(VerifyError::MalformedInput("response count does not match hidden messages"));
+    }
 
     let mut t_input = -G2Projective::from(pk.pk_x);
     for i in 0..pt.reveal_index.len(){
@@ -252,12 +365,15 @@ pub fn verify_proof(
     }
     t_input *= -pt.proof.c;
 
-    let t = Bls12_381::pairing(pt.sigma2, pp.g2 * (-pt.proof.c)) + Bls12_381::pairing(pt.sigma1, G2Affine::from(t_input));
     let mut k_input = pp.g2 * pt.proof.z_t;
     for i in 0..close_len{
         k_input += pk.pk_y[close_index[i]] * pt.proof.z_i[i];
     }
-    let k = t + Bls12_381::pairing(pt.sigma1, G2Affine::from(k_input));
+    // t + e(sigma1, k_input) folded into a single multi-pairing
+    let k = Bls12_381::multi_pairing(
+        [pt.sigma2 * (-pt.proof.c), pt.sigma1.into()],
+        [G2Affine::from(pp.g2), G2Affine::from(t_input + k_input)],
+    );
 
     let dst = b"MY_CHALLENGE_GENERATOR_DST_V1";
     let c_inputs = vec![
@@ -272,14 +388,14 @@ pub fn verify_proof(
         open_msg.serialize_compressed(&mut buffer).unwrap();
     }
     k.serialize_compressed(&mut buffer).unwrap();
+    pt.presentation_header.serialize_compressed(&mut buffer).unwrap();
     let c_calculated = hash_to_fr(&buffer, dst);
 
     if c_calculated != pt.proof.c{
-        println!("Proof verification failed: challenge mismatch");
-        return false
+        return Err(VerifyError::ChallengeMismatch)
     }
 
-    return true
+    return Ok(())
 }
 
 #[cfg(test)]
@@ -299,13 +415,72 @@ mod tests {
         let mut rng = thread_rng();
         let messages: Vec<Fr> = (0..message_len).map(|_| Fr::rand(&mut rng)).collect();
         let pp = par_gen();
-        let keypair = key_gen(&pp);
-        let signature = sign(&pp, &keypair.sk, &messages);
+        let mut keypair = key_gen(&pp, message_len - 1);
+        assert_eq!(sign(&pp, &keypair.sk, &messages), Err(ProveError::TooManyMessages { messages: message_len, key_len: message_len - 1 }));
+        extend_key(&pp, &mut keypair, message_len);
+        let signature = sign(&pp, &keypair.sk, &messages).unwrap();
         let bool1 = verify_sign(&pp, &keypair.pk, &signature, &messages);
-        assert_eq!(bool1,true);
+        assert_eq!(bool1, Ok(()));
         let reveal_index = vec![0, 3, 5];
-        let pt = super::create_proof(&pp, &keypair.pk, &signature, &messages, &reveal_index);
-        let bool2 = super::verify_proof(&pp, &keypair.pk, &pt);
-        assert_eq!(bool2,true);
+        let pt = super::create_proof(&pp, &keypair.pk, &signature, &messages, &reveal_index, b"verifier-nonce-1").unwrap();
+        let bool2 = super::verify_proof(&pp, &keypair.pk, &pt, b"verifier-nonce-1");
+        assert_eq!(bool2, Ok(()));
+
+        let mut tampered = pt.clone();
+        tampered.open_messages[0] += Fr::from(1u64);
+        assert_eq!(super::verify_proof(&pp, &keypair.pk, &tampered, b"verifier-nonce-1"), Err(VerifyError::ChallengeMismatch));
+        assert_eq!(super::verify_proof(&pp, &keypair.pk, &pt, b"verifier-nonce-2"), Err(VerifyError::PresentationHeaderMismatch));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let message_len = 10;
+        let mut rng = thread_rng();
+        let messages: Vec<Fr> = (0..message_len).map(|_| Fr::rand(&mut rng)).collect();
+        let pp = par_gen();
+        let keypair = key_gen(&pp, message_len);
+        let signature = sign(&pp, &keypair.sk, &messages).unwrap();
+        let pt = create_proof(&pp, &keypair.pk, &signature, &messages, &vec![0, 3, 5], b"").unwrap();
+        let points = VerifyError::MalformedInput("sigma1 or sigma2 is the identity or not in G1");
+
+        // sigma1 = sigma2 = 1 verifies for any messages, and a token with them for any challenge
+        let forged = Signature{ sigma1: G1Affine::identity(), sigma2: G1Affine::identity() };
+        assert_eq!(verify_sign(&pp, &keypair.pk, &forged, &messages), Err(points));
+        let mut bad = pt.clone();
+        bad.sigma1 = G1Affine::identity();
+        bad.sigma2 = G1Affine::identity();
+        let mut buffer = Vec::new();
+        for c_input in [bad.sigma1, bad.sigma2]{
+            c_input.serialize_compressed(&mut buffer).unwrap();
+        }
+        for open_msg in &bad.open_messages{
+            open_msg.serialize_compressed(&mut buffer).unwrap();
+        }
+        <Bls12_381 as Pairing>::TargetField::from(1u64).serialize_compressed(&mut buffer).unwrap();
+        bad.presentation_header.serialize_compressed(&mut buffer).unwrap();
+        bad.proof.c = hash_to_fr(&buffer, b"MY_CHALLENGE_GENERATOR_DST_V1");
+        assert_eq!(verify_proof(&pp, &keypair.pk, &bad, b""), Err(points));
+        let mut bad = pt.clone();
+        bad.sigma1 = G1Affine::new_unchecked(pt.sigma1.x, -pt.sigma1.x);
+        assert_eq!(verify_proof(&pp, &keypair.pk, &bad, b""), Err(points));
+
+        // reveal indices out of range, repeated or out of order
+        for (reveal_index, idx) in [(vec![0, 3, 10], 10), (vec![0, 3, 3], 3), (vec![3, 0, 5], 0)]{
+            let mut bad = pt.clone();
+            bad.reveal_index = reveal_index;
+            assert_eq!(verify_proof(&pp, &keypair.pk, &bad, b""), Err(VerifyError::BadRevealIndex(idx)));
+        }
+        assert_eq!(create_proof(&pp, &keypair.pk, &signature, &messages, &vec![5, 3], b"").unwrap_err(), ProveError::BadRevealIndex(3));
+
+        // lengths that do not add up
+        let mut bad = pt.clone();
+        bad.len = usize::MAX;
+        assert_eq!(verify_proof(&pp, &keypair.pk, &bad, b""), Err(VerifyError::MalformedInput("message length exceeds key")));
+        let mut bad = pt.clone();
+        bad.open_messages.pop();
+        assert_eq!(verify_proof(&pp, &keypair.pk, &bad, b""), Err(VerifyError::MalformedInput("revealed messages do not match reveal indices")));
+        let mut bad = pt.clone();
+        bad.proof.z_i.push(Fr::from(0u64));
+        assert_eq!(verify_proof(&pp, &keypair.pk, &bad, b""), Err(VerifyError::MalformedInput("response count does not match hidden messages")));
     }
 }
\ No newline at end of file
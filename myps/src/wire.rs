@@ -0,0 +1,47 @@
+use mybbs::wire::{Ciphersuite, Header, Kind, Scheme};
+
+pub use mybbs::wire::{decode, encode, from_cbor, from_json, to_cbor, to_json};
+
+// Headers of this scheme's objects for the envelopes of mybbs::wire.
+pub const PUBLIC_PARAMETERS: Header = header(Kind::PublicParameters);
+pub const PUBLIC_KEY: Header = header(Kind::PublicKey);
+pub const SIGNATURE: Header = header(Kind::Signature);
+pub const PRESENTATION: Header = header(Kind::Presentation);      // PresentationToken
+
+const fn header(kind: Kind) -> Header{
+    return Header{ scheme: Scheme::Ps, kind: kind, ciphersuite: Ciphersuite::Bls12381Sha256 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ps::*;
+    use ark_std::{UniformRand, vec::Vec};
+    use mybbs::error::WireError;
+    use rand::thread_rng;
+
+    #[test]
+    fn it_works(){
+        let mut rng = thread_rng();
+        let messages: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+        let pp = par_gen();
+        let keypair = key_gen(&pp, 4);
+        let signature = sign(&pp, &keypair.sk, &messages).unwrap();
+        let pt = create_proof(&pp, &keypair.pk, &signature, &messages, &vec![0, 2], b"nonce").unwrap();
+
+        let pp2: PublicParameters = decode(&PUBLIC_PARAMETERS, &encode(&PUBLIC_PARAMETERS, &pp)).unwrap();
+        let pk2: PublicKey = from_cbor(&PUBLIC_KEY, &to_cbor(&PUBLIC_KEY, &keypair.pk)).unwrap();
+        let pt2: PresentationToken = from_json(&PRESENTATION, &to_json(&PRESENTATION, &pt)).unwrap();
+        let sk2 = secret_key_from_bytes(&secret_key_to_bytes(&keypair.sk)).unwrap();
+        assert_eq!(KeyPair{ sk: sk2, pk: pk2.clone() }, keypair);
+        assert_eq!(format!("{:?}", keypair.sk), "SecretKey(<redacted>)");
+        assert_eq!(decode::<Signature>(&SIGNATURE, &encode(&SIGNATURE, &signature)), Ok(signature.clone()));
+        assert_eq!(verify_proof(&pp2, &pk2, &pt2, b"nonce"), Ok(()));
+
+        // envelopes of other schemes and kinds are rejected before the payload is read
+        let bytes = encode(&SIGNATURE, &signature);
+        assert_eq!(decode::<mybbs::issuer::Signature>(&mybbs::wire::SIGNATURE, &bytes), Err(WireError::WrongScheme));
+        assert_eq!(decode::<PublicKey>(&PUBLIC_KEY, &bytes), Err(WireError::WrongKind));
+        assert_eq!(decode::<Signature>(&SIGNATURE, &bytes[..bytes.len() - 1]), Err(WireError::InvalidPayload));
+    }
+}